@@ -0,0 +1,59 @@
+//! Measures the instructions per second of the interpreter: `cargo bench --bench throughput`
+//!
+//! The ROM is a compute-heavy loop, mostly arithmetic with a skip, a jump and a subroutine
+//! call per iteration, and a BCD and a sprite draw every 256 iterations. It runs at 100000
+//! instructions per second, the turbo speed of the frontends, on the profiles that do not
+//! wait for the vertical blank after a draw, so every instruction of a frame runs.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use r8::{emulator::Emulator, quirks::Profile};
+
+/// The frames of an iteration, a second.
+const FRAMES: u32 = 60;
+
+/// The instructions per frame, 100000 per second.
+const INSTRUCTIONS_PER_FRAME: u32 = 1667;
+
+/// The benchmark ROM, see the module documentation.
+const ROM: [u16; 18] = [
+    // 0x200: V0 and V1 count, I points at the BCD digits
+    0x6000, 0x6100, 0xA300,
+    // 0x206: the loop, ADD V0, 1 then a mix of the 8XYN instructions
+    0x7001, 0x8104, 0x8213, 0x8326, 0x8431,
+    // 0x210: CALL 0x220, then loop until V0 wraps around to 0
+    0x2220, 0x3000, 0x1206,
+    // 0x216: BCD of V5 and draw its first digit, then loop again
+    0xF533, 0xD011, 0x1206, 0x0000, 0x0000,
+    // 0x220: SUB V5, V4 and RET
+    0x8545, 0x00EE,
+];
+
+/// Returns an emulator running the benchmark ROM.
+fn emulator(profile: Profile) -> Emulator {
+    let rom: Vec<u8> = ROM.iter().flat_map(|word| word.to_be_bytes()).collect();
+    let mut emulator = Emulator::with_profile(profile);
+    emulator.load_rom_bytes(&rom).unwrap();
+    emulator
+}
+
+fn throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("throughput");
+    group.throughput(Throughput::Elements((FRAMES * INSTRUCTIONS_PER_FRAME) as u64));
+    for profile in [Profile::SuperChipModern, Profile::XoChip] {
+        group.bench_function(BenchmarkId::from_parameter(format!("{profile:?}")), |b| {
+            b.iter_batched_ref(
+                || emulator(profile),
+                |emulator| {
+                    for _ in 0..FRAMES {
+                        emulator.run_frame(INSTRUCTIONS_PER_FRAME).unwrap();
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
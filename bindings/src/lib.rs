@@ -0,0 +1,12 @@
+//! The shared library of R8, built as a `cdylib` apart from the `r8` crate.
+//!
+//! Each feature exports the bindings of the `r8` feature of the same name: `wasm` for
+//! wasm-pack, `ffi` for the C ABI and `libretro` for RetroArch. Several can be enabled at
+//! once.
+
+#[cfg(feature = "ffi")]
+pub use r8::ffi::*;
+#[cfg(feature = "libretro")]
+pub use r8::libretro::*;
+#[cfg(feature = "wasm")]
+pub use r8::wasm::*;
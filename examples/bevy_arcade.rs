@@ -0,0 +1,83 @@
+//! Two arcade cabinets running CHIP-8 games: `cargo run --example bevy_arcade --features bevy`
+//!
+//! Tab moves the keyboard from one cabinet to the other, the keypad is the left 4x4 block of
+//! a QWERTY keyboard.
+use bevy::prelude::*;
+use r8::{
+    bevy::{EmulatorComponent, InputFocus, R8Plugin},
+    emulator::Emulator,
+};
+
+/// The games of the cabinets, left to right.
+const GAMES: [&[u8]; 2] = [
+    include_bytes!("../roms/BRIX.ch8"),
+    include_bytes!("../roms/MAZE.ch8"),
+];
+
+/// The size of a screen in world units, 6 times a low resolution display.
+const SCREEN: Vec2 = Vec2::new(384.0, 192.0);
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(R8Plugin)
+        .add_systems(Startup, spawn_cabinets)
+        .add_systems(Update, switch_focus)
+        .run();
+}
+
+fn spawn_cabinets(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.spawn(Camera2dBundle::default());
+    for (index, game) in GAMES.iter().enumerate() {
+        let mut emulator = Emulator::new();
+        if let Err(err) = emulator.load_rom_bytes(game) {
+            error!("Failed to load the game of cabinet {index}: {err}");
+            continue;
+        }
+        let screen = EmulatorComponent::new(emulator, &mut images);
+        let x = (index as f32 - 0.5) * (SCREEN.x + 64.0);
+
+        // The cabinet, the screen in front of it
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.2, 0.1, 0.3),
+                custom_size: Some(SCREEN + Vec2::new(48.0, 160.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, -40.0, 0.0),
+            ..default()
+        });
+        commands.spawn(SpriteBundle {
+            texture: screen.image.clone(),
+            sprite: Sprite {
+                custom_size: Some(SCREEN),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, 0.0, 1.0),
+            ..default()
+        });
+
+        let mut cabinet = commands.spawn(screen);
+        if index == 0 {
+            cabinet.insert(InputFocus);
+        }
+    }
+}
+
+/// Gives the keyboard to the next cabinet when Tab is pressed.
+fn switch_focus(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    cabinets: Query<(Entity, Has<InputFocus>), With<EmulatorComponent>>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let cabinets: Vec<_> = cabinets.iter().collect();
+    let Some(focused) = cabinets.iter().position(|&(_, focus)| focus) else {
+        return;
+    };
+    let next = (focused + 1) % cabinets.len();
+    commands.entity(cabinets[focused].0).remove::<InputFocus>();
+    commands.entity(cabinets[next].0).insert(InputFocus);
+}
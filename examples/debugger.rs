@@ -0,0 +1,145 @@
+//! Debugs a ROM in a window: `cargo run --example debugger --features debugger -- rom.ch8`
+//!
+//! The ROM starts paused. Click the left column of the disassembly to set breakpoints, the
+//! registers can be edited while paused. The keypad is the left 4x4 block of a QWERTY
+//! keyboard.
+use std::{fs, path::PathBuf, process};
+
+use eframe::egui::{self, ColorImage, Key, TextureHandle, TextureOptions};
+use r8::{
+    constants::{Palette, MONOCHROME_PALETTE},
+    emulator::Emulator,
+    keymap::KeyMap,
+};
+
+/// The instructions per frame, about 700 per second at 60 frames per second.
+const INSTRUCTIONS_PER_FRAME: u32 = 12;
+
+/// The instructions of the disassembly.
+const DISASSEMBLY_LINES: u16 = 24;
+
+/// The size of a pixel of a low resolution display.
+const SCALE: f32 = 8.0;
+
+struct Debugger {
+    emulator: Emulator,
+    keymap: KeyMap,
+    palette: Palette,
+    paused: bool,
+    memory_start: u16,
+    screen: Option<TextureHandle>,
+    /// The last error of the emulator, it stays paused until the user resumes it
+    error: Option<String>,
+}
+
+impl Debugger {
+    /// Sets the keys held on the keyboard.
+    fn poll_keys(&mut self, ctx: &egui::Context) {
+        let keys = ctx.input(|input| {
+            (0..16u8).fold(0u16, |mask, pad| {
+                let held = self
+                    .keymap
+                    .host_key(pad)
+                    .and_then(|host| Key::from_name(&host.to_ascii_uppercase().to_string()))
+                    .is_some_and(|key| input.key_down(key));
+                if held {
+                    mask | 1 << pad
+                } else {
+                    mask
+                }
+            })
+        });
+        self.emulator.set_key_mask(keys);
+    }
+
+    /// Copies the display into the texture of the screen.
+    fn draw_screen(&mut self, ctx: &egui::Context) -> &TextureHandle {
+        let display = self.emulator.display();
+        let (width, height) = display.dimensions();
+        let image =
+            ColorImage::from_rgba_unmultiplied([width, height], &display.to_rgba8(&self.palette));
+        match &mut self.screen {
+            Some(screen) => screen.set(image, TextureOptions::NEAREST),
+            None => self.screen = Some(ctx.load_texture("screen", image, TextureOptions::NEAREST)),
+        }
+        self.screen.as_ref().unwrap()
+    }
+}
+
+impl eframe::App for Debugger {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_keys(ctx);
+        if !self.paused {
+            // A frame per repaint, eframe repaints at the refresh rate of the monitor
+            match self.emulator.run_until_breakpoint(INSTRUCTIONS_PER_FRAME) {
+                Ok(stopped) => self.paused = stopped,
+                Err(err) => {
+                    self.error = Some(err.to_string());
+                    self.paused = true;
+                }
+            }
+            ctx.request_repaint();
+        }
+
+        egui::SidePanel::right("debugger").show(ctx, |ui| {
+            if let Err(err) = r8::egui::controls(
+                &mut self.emulator,
+                ui,
+                &mut self.paused,
+                INSTRUCTIONS_PER_FRAME,
+            ) {
+                self.error = Some(err.to_string());
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.separator();
+            r8::egui::registers(&mut self.emulator, ui);
+            ui.separator();
+            r8::egui::stack(&mut self.emulator, ui);
+            ui.separator();
+            r8::egui::disassembly(&mut self.emulator, ui, DISASSEMBLY_LINES);
+        });
+        egui::TopBottomPanel::bottom("memory").show(ctx, |ui| {
+            r8::egui::memory(&mut self.emulator, ui, &mut self.memory_start);
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let (width, height) = self.emulator.display().dimensions();
+            // A high resolution display has the size of a low resolution one
+            let scale = SCALE * 64.0 / width as f32;
+            let size = egui::vec2(width as f32 * scale, height as f32 * scale);
+            let screen = self.draw_screen(ctx);
+            ui.add(egui::Image::new(screen).fit_to_exact_size(size));
+        });
+    }
+}
+
+fn main() -> eframe::Result<()> {
+    let Some(path) = std::env::args().nth(1).map(PathBuf::from) else {
+        eprintln!("Usage: debugger <rom>");
+        process::exit(2);
+    };
+    let mut emulator = Emulator::new();
+    let loaded = fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|rom| emulator.load_rom_bytes(&rom).map_err(|err| err.to_string()));
+    if let Err(err) = loaded {
+        eprintln!("Failed to load {}: {err}", path.display());
+        process::exit(1);
+    }
+
+    let debugger = Debugger {
+        emulator,
+        keymap: KeyMap::default(),
+        palette: MONOCHROME_PALETTE,
+        paused: true,
+        memory_start: 0x200,
+        screen: None,
+        error: None,
+    };
+    eframe::run_native(
+        "R8 debugger",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Box::new(debugger)),
+    )
+}
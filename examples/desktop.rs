@@ -0,0 +1,207 @@
+//! Runs a ROM in a window: `cargo run --example desktop --features desktop -- rom.ch8 [ips]`
+//!
+//! The keypad is the left 4x4 block of a QWERTY keyboard. F5 resets, F1 saves the state next
+//! to the ROM, F2 loads it and Esc quits. The title shows a note while the beep plays.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant},
+};
+
+use minifb::{Key, KeyRepeat, ScaleMode, Window, WindowOptions};
+use r8::{
+    constants::{Palette, MONOCHROME_PALETTE},
+    display::Display,
+    emulator::Emulator,
+    frontend::{Frontend, InputState},
+    keymap::KeyMap,
+};
+
+/// The window is 10 times a low resolution display, 5 times a high resolution one.
+const WIDTH: usize = 640;
+const HEIGHT: usize = 320;
+
+/// The instructions per second without the `ips` argument.
+const DEFAULT_IPS: u32 = 700;
+
+const TITLE: &str = "R8";
+
+/// The host keys a `KeyMap` can use.
+const HOST_KEYS: [(Key, char); 36] = [
+    (Key::Key0, '0'),
+    (Key::Key1, '1'),
+    (Key::Key2, '2'),
+    (Key::Key3, '3'),
+    (Key::Key4, '4'),
+    (Key::Key5, '5'),
+    (Key::Key6, '6'),
+    (Key::Key7, '7'),
+    (Key::Key8, '8'),
+    (Key::Key9, '9'),
+    (Key::A, 'a'),
+    (Key::B, 'b'),
+    (Key::C, 'c'),
+    (Key::D, 'd'),
+    (Key::E, 'e'),
+    (Key::F, 'f'),
+    (Key::G, 'g'),
+    (Key::H, 'h'),
+    (Key::I, 'i'),
+    (Key::J, 'j'),
+    (Key::K, 'k'),
+    (Key::L, 'l'),
+    (Key::M, 'm'),
+    (Key::N, 'n'),
+    (Key::O, 'o'),
+    (Key::P, 'p'),
+    (Key::Q, 'q'),
+    (Key::R, 'r'),
+    (Key::S, 's'),
+    (Key::T, 't'),
+    (Key::U, 'u'),
+    (Key::V, 'v'),
+    (Key::W, 'w'),
+    (Key::X, 'x'),
+    (Key::Y, 'y'),
+    (Key::Z, 'z'),
+];
+
+/// A window as a [`Frontend`], with the hotkeys read by the main loop.
+struct Desktop {
+    window: Window,
+    keymap: KeyMap,
+    palette: Palette,
+    /// The pixels of the last frame, `0RGB` as minifb expects
+    buffer: Vec<u32>,
+}
+
+impl Frontend for Desktop {
+    fn poll_input(&mut self) -> InputState {
+        self.window.update();
+        let mut keys = 0;
+        for (key, host) in HOST_KEYS {
+            match self.keymap.map(host) {
+                Some(pad) if self.window.is_key_down(key) => keys |= 1 << pad,
+                _ => {}
+            }
+        }
+        InputState { keys }
+    }
+
+    fn present(&mut self, display: &Display) {
+        let (width, height) = display.dimensions();
+        self.buffer = display
+            .to_rgba8(&self.palette)
+            .chunks_exact(4)
+            .map(|rgba| u32::from_be_bytes([0, rgba[0], rgba[1], rgba[2]]))
+            .collect();
+        if let Err(err) = self.window.update_with_buffer(&self.buffer, width, height) {
+            eprintln!("Failed to draw the frame: {err}");
+        }
+    }
+
+    fn set_beep(&mut self, on: bool) {
+        self.window.set_title(if on { "R8 ♪" } else { TITLE });
+    }
+
+    fn should_exit(&self) -> bool {
+        !self.window.is_open() || self.window.is_key_down(Key::Escape)
+    }
+}
+
+/// Prints the error and exits.
+fn fail(message: String) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
+
+/// Returns the path of the save state of a ROM.
+fn state_path(rom: &Path) -> PathBuf {
+    rom.with_extension("state")
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next().map(PathBuf::from) else {
+        eprintln!("Usage: desktop <rom> [ips]");
+        process::exit(2);
+    };
+    let ips = match args.next().map(|ips| ips.parse::<u32>()) {
+        None => DEFAULT_IPS,
+        Some(Ok(ips)) if ips > 0 => ips,
+        Some(_) => fail("The instructions per second must be a positive number".to_string()),
+    };
+    let rom = fs::read(&path)
+        .unwrap_or_else(|err| fail(format!("Failed to read {}: {err}", path.display())));
+
+    let mut emulator = Emulator::new();
+    if let Err(err) = emulator.load_rom_bytes(&rom) {
+        fail(format!("Failed to load {}: {err}", path.display()));
+    }
+
+    let options = WindowOptions {
+        scale_mode: ScaleMode::Stretch,
+        ..WindowOptions::default()
+    };
+    let window = Window::new(TITLE, WIDTH, HEIGHT, options)
+        .unwrap_or_else(|err| fail(format!("Failed to open the window: {err}")));
+    let mut desktop = Desktop {
+        window,
+        keymap: KeyMap::qwerty(),
+        palette: MONOCHROME_PALETTE,
+        buffer: Vec::new(),
+    };
+
+    // The run loop of `r8::frontend::run`, with the hotkeys between frames
+    let frame_duration = Duration::from_secs(1) / 60;
+    let instructions = ips.div_ceil(60);
+    let mut drawn = None;
+    let mut beeping = false;
+    while !desktop.should_exit() {
+        let frame_start = Instant::now();
+
+        let keys = desktop.poll_input().keys;
+        if desktop.window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            if let Err(err) = emulator.load_rom_bytes(&rom) {
+                fail(format!("Failed to reset: {err}"));
+            }
+            drawn = None;
+        }
+        if desktop.window.is_key_pressed(Key::F1, KeyRepeat::No) {
+            if let Err(err) = fs::write(state_path(&path), emulator.save_state()) {
+                eprintln!("Failed to save the state: {err}");
+            }
+        }
+        if desktop.window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            let loaded = fs::read(state_path(&path))
+                .map_err(|err| err.to_string())
+                .and_then(|state| emulator.load_state(&state).map_err(|err| err.to_string()));
+            match loaded {
+                Ok(()) => drawn = None,
+                Err(err) => eprintln!("Failed to load the state: {err}"),
+            }
+        }
+
+        emulator.set_key_mask(keys);
+        if let Err(err) = emulator.run_frame(instructions) {
+            fail(format!("Emulator error: {err}"));
+        }
+
+        let version = emulator.display().version();
+        if drawn != Some(version) {
+            drawn = Some(version);
+            desktop.present(emulator.display());
+        }
+        let beep = emulator.sound_timer() > 0;
+        if beep != beeping {
+            beeping = beep;
+            desktop.set_beep(beep);
+        }
+
+        let elapsed = frame_start.elapsed();
+        if elapsed < frame_duration {
+            thread::sleep(frame_duration - elapsed);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Runs a ROM in the terminal: `cargo run --example terminal --features terminal -- rom.ch8`
+//!
+//! The keypad is the left 4x4 block of a QWERTY keyboard, Esc or Ctrl+C quits.
+use std::{fs, process};
+
+use r8::{
+    emulator::Emulator,
+    frontend::{run, RunConfig},
+    terminal::TerminalFrontend,
+};
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: terminal <rom>");
+        process::exit(2);
+    };
+    let rom = match fs::read(&path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Failed to read {path}: {err}");
+            process::exit(1);
+        }
+    };
+
+    let mut emulator = Emulator::new();
+    if let Err(err) = emulator.load_rom_bytes(&rom) {
+        eprintln!("Failed to load {path}: {err}");
+        process::exit(1);
+    }
+
+    let result = match TerminalFrontend::new() {
+        // The frontend restores the terminal when dropped, before the error is printed
+        Ok(mut frontend) => run(&mut emulator, &mut frontend, &RunConfig::default()),
+        Err(err) => {
+            eprintln!("Failed to set up the terminal: {err}");
+            process::exit(1);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("Emulator error: {err}");
+        process::exit(1);
+    }
+}
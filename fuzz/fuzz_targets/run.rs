@@ -0,0 +1,10 @@
+//! Runs random programs, see `r8::fuzz`: `cargo +nightly fuzz run run`
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use r8::fuzz::FuzzInput;
+
+fuzz_target!(|input: FuzzInput| {
+    // The errors of the emulator are expected, the invariants panic
+    let _ = input.run();
+});
@@ -0,0 +1,207 @@
+/* R8, a CHIP-8 emulator. Generated by cbindgen from src/emulator/ffi.rs, do not edit. */
+
+#ifndef R8_H
+#define R8_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded.
+#define R8_OK 0
+
+// A pointer argument is null.
+#define R8_ERROR_NULL -1
+
+// The emulator panicked, it should be freed.
+#define R8_ERROR_PANIC -2
+
+// An emulator behind the C ABI, opaque to the caller.
+//
+// # Fields
+//
+// * `emulator` - The emulator.
+// * `palette` - The colors of the framebuffer.
+// * `ticks_per_frame` - The instructions run by `r8_run_frame`.
+// * `framebuffer` - The RGBA pixels returned by `r8_framebuffer`.
+typedef struct R8 R8;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates an emulator running one instruction per frame.
+//
+// # Returns
+//
+// * `*mut R8` - The emulator, free it with `r8_free`. Null if it can not be created.
+R8 *r8_new(void);
+
+// Frees an emulator created by `r8_new`.
+//
+// # Arguments
+//
+// * `r8` - The emulator, nothing is done if it is null.
+//
+// # Safety
+//
+// `r8` must be null or point to an emulator created by `r8_new` and not freed, that no
+// other thread uses. It must not be used after.
+void r8_free(R8 *r8);
+
+// Loads a ROM, see `Emulator::load_rom_bytes`.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+// * `data` - The content of the ROM.
+// * `len` - The length of the ROM in bytes.
+//
+// # Returns
+//
+// * `i32` - `R8_OK`, or the code of the error, e.g. 402 for an empty ROM.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`, and `data` must point to `len` bytes.
+int32_t r8_load_rom(R8 *r8, const uint8_t *data, size_t len);
+
+// Sets the instructions run by each `r8_run_frame`.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+// * `ticks` - The instructions per frame, at least 1.
+//
+// # Returns
+//
+// * `i32` - `R8_OK`, or `R8_ERROR_NULL`.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`.
+int32_t r8_set_ticks_per_frame(R8 *r8, uint32_t ticks);
+
+// Runs a frame, call it 60 times per second.
+//
+// The timers are decremented once per frame whatever the instructions per frame, see
+// `Emulator::run_frame`.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+//
+// # Returns
+//
+// * `i32` - `R8_OK`, or the code of the error, e.g. 202 when the program returns with an
+//   empty stack.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`.
+int32_t r8_run_frame(R8 *r8);
+
+// Presses or releases a key.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+// * `key` - The key, only its low nibble is used.
+// * `down` - True when the key is pressed, false when it is released.
+//
+// # Returns
+//
+// * `i32` - `R8_OK`, or `R8_ERROR_NULL`.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`.
+int32_t r8_key_event(R8 *r8, uint8_t key, bool down);
+
+// Returns true while the sound timer runs.
+//
+// # Arguments
+//
+// * `r8` - The emulator, false is returned if it is null.
+//
+// # Safety
+//
+// `r8` must be null or a valid emulator, see `r8_free`.
+bool r8_beep_active(const R8 *r8);
+
+// Renders the display as RGBA pixels, 4 bytes per pixel.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+// * `width` - Set to the width of the image in pixels, if not null.
+// * `height` - Set to the height of the image in pixels, if not null.
+// * `stride` - Set to the bytes between the start of two rows, if not null.
+//
+// # Returns
+//
+// * `*const u8` - The pixels, row by row. They belong to the emulator and are valid until
+//   the next call with it. Null on error.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`, and the other pointers must be null or
+// writable.
+const uint8_t *r8_framebuffer(R8 *r8, size_t *width, size_t *height, size_t *stride);
+
+// Saves the state of the emulator, see `Emulator::save_state`.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+// * `buffer` - Where the state is written, it may be null to query the size.
+// * `capacity` - The size of `buffer` in bytes.
+// * `len` - Set to the size of the state, if not null.
+//
+// # Returns
+//
+// * `i32` - `R8_OK`, or 501 if `buffer` is too small for the state. `len` is set then,
+//   call again with a buffer of that size.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`, `buffer` must be null or point to
+// `capacity` writable bytes, and `len` must be null or writable.
+int32_t r8_save_state(R8 *r8, uint8_t *buffer, size_t capacity, size_t *len);
+
+// Restores a state saved by `r8_save_state`, see `Emulator::load_state`.
+//
+// # Arguments
+//
+// * `r8` - The emulator.
+// * `data` - The state.
+// * `len` - The size of the state in bytes.
+//
+// # Returns
+//
+// * `i32` - `R8_OK`, or the code of the error, e.g. 705 for an invalid state. The
+//   emulator is left untouched then.
+//
+// # Safety
+//
+// `r8` must be a valid emulator, see `r8_free`, and `data` must point to `len` bytes.
+int32_t r8_load_state(R8 *r8, const uint8_t *data, size_t len);
+
+// Returns the code of the last error of the calling thread, `R8_OK` if there was none.
+int32_t r8_last_error_code(void);
+
+// Returns the message of the last error of the calling thread.
+//
+// # Returns
+//
+// * `*const c_char` - A NUL terminated UTF-8 string, valid until the next error of the
+//   thread. Null if there was no error.
+const char *r8_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* R8_H */
@@ -0,0 +1,12 @@
+#!/bin/sh
+# Checks that the emulator core builds without the standard library, on the host and on a
+# Cortex-M0 target that has no `std`, and runs the unit tests without the `std` feature.
+set -e
+
+cd "$(dirname "$0")/.."
+cargo check --lib --no-default-features
+cargo clippy --lib --no-default-features -- -D warnings
+rustup target add thumbv6m-none-eabi
+cargo check --lib --no-default-features --target thumbv6m-none-eabi
+cargo check --lib --no-default-features --features rand,embedded-graphics --target thumbv6m-none-eabi
+cargo test --lib --no-default-features
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Downloads the conformance ROMs run by tests/suite.rs, with their licenses, into the cache
+# the tests read: $R8_TEST_ROMS, or target/test-roms/ by default. Then run
+# `R8_UPDATE_SNAPSHOTS=1 cargo test --test suite -- --ignored` once to write the snapshots
+# into tests/roms/, check them against the screenshots of the upstream READMEs and commit
+# them. See tests/roms/README.md.
+set -e
+
+ROOT="$(cd "$(dirname "$0")/.." && pwd)"
+CACHE="${R8_TEST_ROMS:-$ROOT/target/test-roms}"
+mkdir -p "$CACHE"
+cd "$CACHE"
+
+CORAX=https://raw.githubusercontent.com/corax89/chip8-test-rom/master
+TIMENDUS=https://raw.githubusercontent.com/Timendus/chip8-test-suite/v4.1
+
+curl -fsSL -o test_opcode.ch8 "$CORAX/test_opcode.ch8"
+curl -fsSL -o LICENSE.corax89 "$CORAX/LICENSE" ||
+    echo "corax89/chip8-test-rom has no LICENSE file, check its README" >&2
+
+for rom in 1-chip8-logo 3-corax+ 4-flags 5-quirks; do
+    curl -fsSL -o "$rom.ch8" "$TIMENDUS/bin/$rom.ch8"
+done
+curl -fsSL -o LICENSE.timendus "$TIMENDUS/LICENSE"
+
+echo "Fetched the ROMs into $CACHE"
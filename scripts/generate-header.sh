@@ -0,0 +1,7 @@
+#!/bin/sh
+# Regenerates include/r8.h from the C ABI of src/emulator/ffi.rs, run it after changing the
+# ABI. Needs the cbindgen command, `cargo install cbindgen`.
+set -e
+
+cd "$(dirname "$0")/.."
+cbindgen --config cbindgen.toml --crate r8 --output include/r8.h
@@ -185,6 +185,16 @@ fn cast_line<'src>(
         &[id!("CLS")] => op_sxyn!(0x0, 0x0, 0xE, 0x0),
         // 00EE - RET
         &[id!("RET")] => op_sxyn!(0x0, 0x0, 0xE, 0xE),
+        // 00CN - SCD N
+        &[id!("SCD"), num!(n)] => op_sxyn!(0x0, 0x0, 0xC, n),
+        // 00FB - SCR
+        &[id!("SCR")] => op_sxyn!(0x0, 0x0, 0xF, 0xB),
+        // 00FC - SCL
+        &[id!("SCL")] => op_sxyn!(0x0, 0x0, 0xF, 0xC),
+        // 00FE - LOW
+        &[id!("LOW")] => op_sxyn!(0x0, 0x0, 0xF, 0xE),
+        // 00FF - HIGH
+        &[id!("HIGH")] => op_sxyn!(0x0, 0x0, 0xF, 0xF),
         // 0NNN - SYS NNN
         &[id!("SYS"), num!(addr)] => op_snnn!(0x00, addr),
         // 0NNN - SYS :label
@@ -221,12 +231,16 @@ fn cast_line<'src>(
         &[id!("SUB"), register!(x), comma!(), register!(y)] => op_sxyn!(0x8, x, y, 0x05),
         // 8XY6 - SHR VX, VY
         &[id!("SHR"), register!(x)] => op_sxyn!(0x8, x, 0x1, 0x06),
+        // 8XY6 - SHR VX, VY
+        &[id!("SHR"), register!(x), comma!(), register!(y)] => op_sxyn!(0x8, x, y, 0x06),
         // 8XY7 - SUBN VX, VY
         &[id!("SUBN"), register!(x), comma!(), register!(y)] => {
             op_sxyn!(0x8, x, y, 0x07)
         }
         // 8XYE - SHL VX, VY
         &[id!("SHL"), register!(x)] => op_sxyn!(0x8, x, 0x0, 0x0E),
+        // 8XYE - SHL VX, VY
+        &[id!("SHL"), register!(x), comma!(), register!(y)] => op_sxyn!(0x8, x, y, 0x0E),
         // 9XY0 - SNE VX, VY
         &[id!("SNE"), register!(x), comma!(), register!(y)] => op_sxyn!(0x9, x, y, 0x00),
         // ANNN - LD I, NNN
@@ -243,10 +257,20 @@ fn cast_line<'src>(
         &[id!("DRW"), register!(x), comma!(), register!(y), comma!(), num!(n)] => {
             op_sxyn!(0xD, x, y, n)
         }
+        // BXYN - COL VX, VY, N
+        &[id!("COL"), register!(x), comma!(), register!(y), comma!(), num!(n)] => {
+            op_sxyn!(0xB, x, y, n)
+        }
         // EX9E - SKP VX
         &[id!("SKP"), register!(x)] => op_sxyn!(0xE, x, 0x9, 0xE),
         // EXA1 - SKNP VX
         &[id!("SKNP"), register!(x)] => op_sxyn!(0xE, x, 0xA, 0x1),
+        // EXF2 - SKP2 VX
+        &[id!("SKP2"), register!(x)] => op_sxyn!(0xE, x, 0xF, 0x2),
+        // EXF5 - SKNP2 VX
+        &[id!("SKNP2"), register!(x)] => op_sxyn!(0xE, x, 0xF, 0x5),
+        // 02A0 - BGC
+        &[id!("BGC")] => op_sxyn!(0x0, 0x2, 0xA, 0x0),
         // FX07 - LD VX, DT
         &[id!("LD"), register!(x), comma!(), id!("DT")] => op_sxyn!(0xF, x, 0x0, 0x7),
         // FX0A - LD VX, K
@@ -259,12 +283,24 @@ fn cast_line<'src>(
         &[id!("ADD"), id!("I"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x1, 0xE),
         // FX29 - LD F, VX
         &[id!("LD"), id!("F"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x2, 0x9),
+        // FX30 - LD HF, VX
+        &[id!("LD"), id!("HF"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x3, 0x0),
         // FX33 - LD B, VX
         &[id!("LD"), id!("B"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x3, 0x3),
         // FX55 - LD [I], VX
         &[id!("LD"), id!("[I]"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x5, 0x5),
         // FX65 - LD VX, [I]
         &[id!("LD"), register!(x), comma!(), id!("[I]")] => op_sxyn!(0xF, x, 0x6, 0x5),
+        // FX75 - LD R, VX
+        &[id!("LD"), id!("R"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x7, 0x5),
+        // FX85 - LD VX, R
+        &[id!("LD"), register!(x), comma!(), id!("R")] => op_sxyn!(0xF, x, 0x8, 0x5),
+        // F002 - LD AUDIO, [I]
+        &[id!("LD"), id!("AUDIO"), comma!(), id!("[I]")] => op_sxyn!(0xF, 0x0, 0x0, 0x2),
+        // FX3A - LD PITCH, VX
+        &[id!("LD"), id!("PITCH"), comma!(), register!(x)] => op_sxyn!(0xF, x, 0x3, 0xA),
+        // FN01 - PLANE N
+        &[id!("PLANE"), num!(n)] => op_sxyn!(0xF, n, 0x0, 0x1),
         &[id!("DB"), num!(n)] => {
             *address += 1;
             Ok(MemorySlices::Byte(byte!(n)))
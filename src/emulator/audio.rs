@@ -0,0 +1,198 @@
+use crate::{error::EmulatorError, prelude::*};
+
+/// Size of the XO-CHIP audio pattern buffer in bytes, 128 1-bit samples.
+pub const PATTERN_SIZE: usize = 16;
+
+/// Pitch that plays the pattern at the base rate of 4000 samples per second.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// Frequency in Hz of the classic beep, played when no pattern has been loaded.
+pub const BEEP_FREQUENCY: f64 = 440.0;
+
+/// Amplitude of the generated samples.
+const AMPLITUDE: f32 = 0.25;
+
+/// The sound generator of the emulator.
+///
+/// The classic CHIP-8 only beeps while the sound timer is nonzero, XO-CHIP programs can load
+/// a 1-bit sample pattern with `F002` and set its playback rate with `FX3A`.
+///
+/// # Fields
+///
+/// * `pattern_buffer` - The 128 1-bit samples, most significant bit first.
+/// * `pitch` - The playback pitch, the pattern is played at `4000 * 2 ^ ((pitch - 64) / 48)` samples per second.
+/// * `pattern_loaded` - Whether a program has loaded a pattern, otherwise the classic beep is played.
+/// * `phase` - The position in the pattern (or the beep period) of the next generated sample.
+pub struct Audio {
+    pub(crate) pattern_buffer: [u8; PATTERN_SIZE],
+    pub(crate) pitch: u8,
+    pattern_loaded: bool,
+    phase: f64,
+}
+
+impl Audio {
+    /// Creates a new sound generator that plays the classic beep.
+    ///
+    /// # Returns
+    ///
+    /// * `Audio` - The sound generator created.
+    pub(crate) fn new() -> Self {
+        Self {
+            pattern_buffer: [0; PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            pattern_loaded: false,
+            phase: 0.0,
+        }
+    }
+
+    /// Loads the sample pattern, from now on it is played instead of the classic beep.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The 128 1-bit samples, most significant bit first.
+    pub(crate) fn load_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) {
+        self.pattern_buffer = pattern;
+        self.pattern_loaded = true;
+    }
+
+    /// Packs the pattern and the pitch, for save states.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - Whether a pattern was loaded (1 byte), the pitch, then the pattern.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.pattern_loaded as u8, self.pitch];
+        bytes.extend(self.pattern_buffer);
+        bytes
+    }
+
+    /// Unpacks a sound generator packed with [`Audio::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The packed pattern and pitch.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Audio, EmulatorError>` - The sound generator, from the start of its
+    ///   waveform, or `InvalidSaveState` if the data is not a packed one.
+    pub(crate) fn from_bytes(data: &[u8]) -> Result<Self, EmulatorError> {
+        let &[loaded, pitch, ref pattern @ ..] = data else {
+            return Err(EmulatorError::InvalidSaveState(
+                "The audio is truncated".to_string(),
+            ));
+        };
+        let pattern_buffer = pattern.try_into().map_err(|_| {
+            EmulatorError::InvalidSaveState(format!(
+                "The audio pattern is {} bytes, not {PATTERN_SIZE}",
+                pattern.len()
+            ))
+        })?;
+        Ok(Self {
+            pattern_buffer,
+            pitch,
+            pattern_loaded: loaded != 0,
+            phase: 0.0,
+        })
+    }
+
+    /// Returns the playback rate of the pattern in samples per second.
+    ///
+    /// # Notes
+    ///
+    /// The pitch 64 plays the pattern at 4000 samples per second, each 48 steps double the rate.
+    pub fn playback_rate(&self) -> f64 {
+        #[cfg(feature = "std")]
+        return 4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0);
+        // `powf` needs `std`, whole octaves are exact and the rest is applied step by step
+        #[cfg(not(feature = "std"))]
+        {
+            const STEP: f64 = 1.0145453349375237;
+            let steps = self.pitch as i32 - DEFAULT_PITCH as i32;
+            let rate = (0..steps.rem_euclid(48)).fold(4000.0, |rate, _| rate * STEP);
+            match steps.div_euclid(48) {
+                octaves @ 0.. => rate * (1 << octaves) as f64,
+                octaves => rate / (1 << -octaves) as f64,
+            }
+        }
+    }
+
+    /// Fills a buffer with mono samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to fill, samples are in the range `[-1.0, 1.0]`.
+    /// * `sample_rate` - The sample rate of the output in Hz.
+    /// * `playing` - Whether the sound timer is nonzero, otherwise the buffer is filled with silence.
+    pub(crate) fn fill(&mut self, buffer: &mut [f32], sample_rate: u32, playing: bool) {
+        if !playing {
+            buffer.fill(0.0);
+            return;
+        }
+        // The phase is counted in pattern samples, or in beep periods
+        let (period, step) = if self.pattern_loaded {
+            ((PATTERN_SIZE * 8) as f64, self.playback_rate())
+        } else {
+            (1.0, BEEP_FREQUENCY)
+        };
+        let step = step / sample_rate as f64;
+        for sample in buffer.iter_mut() {
+            let high = if self.pattern_loaded {
+                let bit = self.phase as usize;
+                self.pattern_buffer[bit / 8] & (0x80 >> (bit % 8)) != 0
+            } else {
+                self.phase < 0.5
+            };
+            *sample = if high { AMPLITUDE } else { -AMPLITUDE };
+            self.phase = (self.phase + step) % period;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the indexes of the samples where the waveform goes from low to high.
+    fn rising_edges(buffer: &[f32]) -> Vec<usize> {
+        (1..buffer.len())
+            .filter(|&i| buffer[i - 1] < 0.0 && buffer[i] > 0.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_pattern_period() {
+        let mut audio = Audio::new();
+        // 8 high samples followed by 8 low samples, 8 times
+        audio.load_pattern([0xFF, 0x00].repeat(8).try_into().unwrap());
+        for (pitch, samples) in [(64, 32), (112, 16), (16, 64)] {
+            audio.pitch = pitch;
+            audio.phase = 0.0;
+            let mut buffer = [0.0; 256];
+            audio.fill(&mut buffer, 8000, true);
+            let edges = rising_edges(&buffer);
+            assert!(edges.len() > 2, "pitch {}", pitch);
+            assert!(
+                edges.windows(2).all(|edge| edge[1] - edge[0] == samples),
+                "pitch {}: {:?}",
+                pitch,
+                edges
+            );
+            // The period follows 4000 * 2 ^ ((pitch - 64) / 48) samples per second
+            let rate = 4000.0 * 2f64.powf((pitch as f64 - 64.0) / 48.0);
+            assert_eq!(samples as f64, 16.0 * 8000.0 / rate);
+        }
+    }
+
+    #[test]
+    fn test_beep() {
+        let mut audio = Audio::new();
+        let mut buffer = [1.0; 256];
+        audio.fill(&mut buffer, 28160, false);
+        assert!(buffer.iter().all(|&sample| sample == 0.0));
+
+        // 28160 / 440 = 64 samples per period
+        audio.fill(&mut buffer, 28160, true);
+        assert_eq!(rising_edges(&buffer), [64, 128, 192]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Emulators as Bevy components, built with the `bevy` feature.
+//!
+//! [`R8Plugin`] runs every entity with an [`EmulatorComponent`] at 60 frames per second on
+//! the `FixedUpdate` schedule, and copies their displays into `Image` assets, to show on a
+//! sprite or a material. The entities with [`InputFocus`] get the keyboard, through the
+//! [`EmulatorKeyMap`] resource.
+//!
+//! ```ignore
+//! fn spawn(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+//!     let mut emulator = Emulator::new();
+//!     emulator.load_rom_bytes(include_bytes!("rom.ch8")).unwrap();
+//!     let screen = EmulatorComponent::new(emulator, &mut images);
+//!     commands.spawn(SpriteBundle {
+//!         texture: screen.image.clone(),
+//!         ..default()
+//!     });
+//!     commands.spawn((screen, InputFocus));
+//! }
+//! ```
+
+use ::bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+use log::error;
+
+use crate::{
+    constants::{Palette, MONOCHROME_PALETTE},
+    emulator::Emulator,
+    keymap::{HostKey, KeyMap},
+};
+
+/// The instructions per frame of a new [`EmulatorComponent`], 720 per second.
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 12;
+
+/// The host keys a [`KeyMap`] can use.
+const HOST_KEYS: [(KeyCode, HostKey); 36] = [
+    (KeyCode::Digit0, '0'),
+    (KeyCode::Digit1, '1'),
+    (KeyCode::Digit2, '2'),
+    (KeyCode::Digit3, '3'),
+    (KeyCode::Digit4, '4'),
+    (KeyCode::Digit5, '5'),
+    (KeyCode::Digit6, '6'),
+    (KeyCode::Digit7, '7'),
+    (KeyCode::Digit8, '8'),
+    (KeyCode::Digit9, '9'),
+    (KeyCode::KeyA, 'a'),
+    (KeyCode::KeyB, 'b'),
+    (KeyCode::KeyC, 'c'),
+    (KeyCode::KeyD, 'd'),
+    (KeyCode::KeyE, 'e'),
+    (KeyCode::KeyF, 'f'),
+    (KeyCode::KeyG, 'g'),
+    (KeyCode::KeyH, 'h'),
+    (KeyCode::KeyI, 'i'),
+    (KeyCode::KeyJ, 'j'),
+    (KeyCode::KeyK, 'k'),
+    (KeyCode::KeyL, 'l'),
+    (KeyCode::KeyM, 'm'),
+    (KeyCode::KeyN, 'n'),
+    (KeyCode::KeyO, 'o'),
+    (KeyCode::KeyP, 'p'),
+    (KeyCode::KeyQ, 'q'),
+    (KeyCode::KeyR, 'r'),
+    (KeyCode::KeyS, 's'),
+    (KeyCode::KeyT, 't'),
+    (KeyCode::KeyU, 'u'),
+    (KeyCode::KeyV, 'v'),
+    (KeyCode::KeyW, 'w'),
+    (KeyCode::KeyX, 'x'),
+    (KeyCode::KeyY, 'y'),
+    (KeyCode::KeyZ, 'z'),
+];
+
+/// Runs the emulator entities, see the module documentation.
+///
+/// # Notes
+///
+/// The plugin sets the fixed timestep to 60Hz, the rate of the CHIP-8 timers. It needs the
+/// `Image` assets, from `DefaultPlugins` or `AssetPlugin` and `init_asset::<Image>` in
+/// headless apps. Without `ButtonInput<KeyCode>` the keys are left to the app.
+pub struct R8Plugin;
+
+impl Plugin for R8Plugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(60.0))
+            .init_resource::<EmulatorKeyMap>()
+            .add_systems(FixedUpdate, (map_keys, run_frames).chain())
+            .add_systems(Update, copy_displays);
+    }
+}
+
+/// An emulator and the image its display is copied to.
+///
+/// # Fields
+///
+/// * `emulator` - The emulator.
+/// * `instructions_per_frame` - The instructions run by each fixed update, see
+///   `Emulator::run_frame`.
+/// * `palette` - The colors of the image.
+/// * `image` - The image, resized when the resolution changes.
+/// * `halted` - Set by an error of the emulator, the frames do not run anymore.
+#[derive(Component)]
+pub struct EmulatorComponent {
+    pub emulator: Emulator,
+    pub instructions_per_frame: u32,
+    pub palette: Palette,
+    pub image: Handle<Image>,
+    pub halted: bool,
+}
+
+impl EmulatorComponent {
+    /// Creates the component and its image, black until the first frame is copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `emulator` - The emulator, with a ROM loaded.
+    /// * `images` - The assets the image is added to.
+    pub fn new(emulator: Emulator, images: &mut Assets<Image>) -> Self {
+        let (width, height) = emulator.display().dimensions();
+        let mut image = Image::new_fill(
+            extent(width, height),
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        // Sharp pixels when the image is scaled up
+        image.sampler = ImageSampler::nearest();
+        Self {
+            emulator,
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            palette: MONOCHROME_PALETTE,
+            image: images.add(image),
+            halted: false,
+        }
+    }
+}
+
+/// Marks the emulators the keyboard plays, several can share it.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct InputFocus;
+
+/// The keys of the keypad on the keyboard, QWERTY by default.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct EmulatorKeyMap(pub KeyMap);
+
+impl EmulatorKeyMap {
+    /// Returns the keys held, bit n is the key n.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The state of the keyboard.
+    pub fn mask(&self, input: &ButtonInput<KeyCode>) -> u16 {
+        HOST_KEYS
+            .iter()
+            .filter(|(code, _)| input.pressed(*code))
+            .filter_map(|&(_, host)| self.0.map(host))
+            .fold(0, |mask, key| mask | 1 << key)
+    }
+}
+
+/// Returns the size of an image of a display.
+fn extent(width: usize, height: usize) -> Extent3d {
+    Extent3d {
+        width: width as u32,
+        height: height as u32,
+        depth_or_array_layers: 1,
+    }
+}
+
+/// Sets the keys of the focused emulators.
+fn map_keys(
+    input: Option<Res<ButtonInput<KeyCode>>>,
+    keymap: Res<EmulatorKeyMap>,
+    mut emulators: Query<&mut EmulatorComponent, With<InputFocus>>,
+) {
+    let Some(input) = input else {
+        return;
+    };
+    let mask = keymap.mask(&input);
+    for mut screen in &mut emulators {
+        screen.emulator.set_key_mask(mask);
+    }
+}
+
+/// Runs a frame of every emulator.
+fn run_frames(mut emulators: Query<(Entity, &mut EmulatorComponent)>) {
+    for (entity, mut screen) in &mut emulators {
+        if screen.halted {
+            continue;
+        }
+        let instructions = screen.instructions_per_frame;
+        if let Err(err) = screen.emulator.run_frame(instructions) {
+            error!("Fatal emulator error in {entity:?}: {err}");
+            screen.halted = true;
+        }
+    }
+}
+
+/// Copies the displays that changed into their images.
+fn copy_displays(mut emulators: Query<&mut EmulatorComponent>, mut images: ResMut<Assets<Image>>) {
+    for mut screen in &mut emulators {
+        // Taking the rows also clears them for the next frame
+        if screen.emulator.take_dirty_rows().is_empty() {
+            continue;
+        }
+        let Some(image) = images.get_mut(&screen.image) else {
+            continue;
+        };
+        let display = screen.emulator.display();
+        let (width, height) = display.dimensions();
+        if image.texture_descriptor.size != extent(width, height) {
+            image.resize(extent(width, height));
+        }
+        if let Err(err) = display.write_rgba8(&mut image.data, &screen.palette) {
+            error!("Failed to copy the display: {err}");
+        }
+    }
+}
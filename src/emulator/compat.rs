@@ -0,0 +1,407 @@
+use crate::{
+    memory::Address,
+    opcode::Opcode,
+    prelude::*,
+    quirks::{Profile, Quirks},
+    rom::{self, Variant, MAX_ROM_SIZE},
+};
+
+/// A ROM dump whose profile is known.
+///
+/// # Fields
+///
+/// * `name` - The name of the program.
+/// * `crc32` - The CRC-32 of the dump, see [`crc32`].
+/// * `profile` - The profile the program needs to run correctly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownRom {
+    pub name: &'static str,
+    pub crc32: u32,
+    pub profile: Profile,
+}
+
+/// Well-known ROM dumps and the profile they need, add new dumps here.
+pub const KNOWN_ROMS: &[KnownRom] = &[
+    KnownRom {
+        name: "IBM Logo",
+        crc32: 0xC46CA868,
+        profile: Profile::Chip8,
+    },
+    // Draws sprites past the bottom edge and relies on them being clipped
+    KnownRom {
+        name: "Blitz",
+        crc32: 0xD106C808,
+        profile: Profile::Chip8,
+    },
+    // Runs at the intended speed only with the display wait
+    KnownRom {
+        name: "Vertical Brix",
+        crc32: 0x608C6AB0,
+        profile: Profile::Chip8,
+    },
+    // Written for CHIP-48, shifts VX in place
+    KnownRom {
+        name: "Blinky",
+        crc32: 0x9D307E90,
+        profile: Profile::Chip48,
+    },
+    KnownRom {
+        name: "Space Invaders",
+        crc32: 0xEAD625B8,
+        profile: Profile::Chip48,
+    },
+];
+
+/// How likely a recommendation is to be right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Nothing hints at an interpreter, the original CHIP-8 is assumed.
+    Low,
+    /// The instructions of the ROM hint at an interpreter.
+    Medium,
+    /// The ROM is a known dump.
+    High,
+}
+
+/// A fact about a ROM that a recommendation is based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evidence {
+    /// The ROM is a known dump.
+    KnownRom(&'static KnownRom),
+    /// A reachable instruction requires an extension.
+    Extension(rom::Evidence),
+    /// A reachable `8XY6` or `8XYE` names two different registers, so it likely expects VY
+    /// to be shifted into VX.
+    ShiftFromVy { address: Address, word: u16 },
+    /// The ROM does not fit in 4KB of memory, only XO-CHIP has more.
+    Size(usize),
+}
+
+impl core::fmt::Display for Evidence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::KnownRom(known) => write!(f, "The ROM is {}", known.name),
+            Self::Extension(evidence) => write!(
+                f,
+                "0x{:03X}: {:04X} is a {} instruction",
+                evidence.address.inner(),
+                evidence.word,
+                evidence.variant
+            ),
+            Self::ShiftFromVy { address, word } => write!(
+                f,
+                "0x{:03X}: {:04X} shifts between different registers",
+                address.inner(),
+                word
+            ),
+            Self::Size(size) => write!(f, "The ROM is {} bytes long", size),
+        }
+    }
+}
+
+/// The configuration a ROM should be run with.
+///
+/// # Fields
+///
+/// * `profile` - The recommended profile.
+/// * `quirks` - The recommended quirks, the preset of the profile with the changes the
+///   evidence asks for.
+/// * `confidence` - How likely the recommendation is to be right.
+/// * `evidence` - The facts the recommendation is based on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recommendation {
+    pub profile: Profile,
+    pub quirks: Quirks,
+    pub confidence: Confidence,
+    pub evidence: Vec<Evidence>,
+}
+
+/// Computes the CRC-32 (IEEE 802.3) of a ROM, the checksum used by [`KNOWN_ROMS`].
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Computes the SHA-1 of a ROM, as used by ROM databases to identify dumps.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM.
+pub fn sha1(bytes: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    // The message, a 1 bit, zeros up to 8 bytes before a 64 byte boundary, and its bit length
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    message.resize((bytes.len() + 8) / 64 * 64 + 64, 0);
+    let length = message.len();
+    message[length - 8..].copy_from_slice(&(bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, chunk) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, temp);
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    let mut digest = [0; 20];
+    for (chunk, value) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// The identity of a ROM, to recognize it across sessions.
+///
+/// # Fields
+///
+/// * `crc32` - The CRC-32 of the ROM, see [`crc32`].
+/// * `sha1` - The SHA-1 of the ROM, see [`sha1`].
+/// * `len` - The length of the ROM in bytes.
+///
+/// # Notes
+///
+/// The hashes cover the content of the ROM file, not the memory it is loaded into, so
+/// they do not change with the padding of the memory or when the program modifies itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RomHash {
+    pub crc32: u32,
+    pub sha1: [u8; 20],
+    pub len: usize,
+}
+
+impl RomHash {
+    /// Hashes a ROM.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the ROM.
+    pub fn new(bytes: &[u8]) -> Self {
+        Self {
+            crc32: crc32(bytes),
+            sha1: sha1(bytes),
+            len: bytes.len(),
+        }
+    }
+}
+
+impl core::fmt::Display for RomHash {
+    /// Writes the SHA-1 as 40 lowercase hex digits.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.sha1
+            .iter()
+            .try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Recommends the profile and quirks to run a ROM with.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM.
+///
+/// # Returns
+///
+/// * `Recommendation` - The profile and quirks, with the evidence they are based on.
+///
+/// # Notes
+///
+/// * Known dumps are looked up in [`KNOWN_ROMS`] by their CRC-32.
+/// * Otherwise the reachable instructions decide: XO-CHIP instructions or a ROM bigger than
+///   3.5KB select XO-CHIP, SUPER-CHIP instructions select the modern SUPER-CHIP and
+///   CHIP-8X instructions select CHIP-8X. Shifts between different registers enable
+///   `shift_uses_vy`.
+pub fn recommend(bytes: &[u8]) -> Recommendation {
+    let checksum = crc32(bytes);
+    if let Some(known) = KNOWN_ROMS.iter().find(|known| known.crc32 == checksum) {
+        return Recommendation {
+            profile: known.profile,
+            quirks: known.profile.quirks(),
+            confidence: Confidence::High,
+            evidence: vec![Evidence::KnownRom(known)],
+        };
+    }
+
+    let mut evidence = Vec::new();
+    let report = rom::detect_variant(bytes);
+    let profile = if report.uses(Variant::XoChip) {
+        Profile::XoChip
+    } else if bytes.len() > MAX_ROM_SIZE {
+        evidence.push(Evidence::Size(bytes.len()));
+        Profile::XoChip
+    } else if report.uses(Variant::SuperChip) {
+        Profile::SuperChipModern
+    } else if report.uses(Variant::Chip8X) {
+        Profile::Chip8X
+    } else {
+        Profile::Chip8
+    };
+    // One instruction of each extension is enough evidence
+    let mut variants = report.variants();
+    for found in report.evidence {
+        if variants.remove(&found.variant) {
+            evidence.push(Evidence::Extension(found));
+        }
+    }
+
+    let mut quirks = profile.quirks();
+    let shift = rom::reachable(bytes).into_iter().find_map(|address| {
+        match rom::fetch(bytes, Address::ENTRY_POINT, address.inner())? {
+            opcode @ (Opcode::Shr { x, y } | Opcode::Shl { x, y }) if x != y => {
+                Some(Evidence::ShiftFromVy {
+                    address,
+                    word: opcode.encode(),
+                })
+            }
+            _ => None,
+        }
+    });
+    if let Some(shift) = shift {
+        quirks.shift_uses_vy = true;
+        evidence.push(shift);
+    }
+
+    let confidence = if evidence.is_empty() {
+        Confidence::Low
+    } else {
+        Confidence::Medium
+    };
+    Recommendation {
+        profile,
+        quirks,
+        confidence,
+        evidence,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_sha1() {
+        let hex = |bytes: &[u8]| RomHash::new(bytes).to_string();
+        let cases: [(&[u8], &str); 4] = [
+            (b"", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+            (
+                &[b'a'; 1_000_000],
+                "34aa973cd4c4daa4f61eeb2bdbad27316534016f",
+            ),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(hex(bytes), expected);
+        }
+        // Lengths around the padding boundary
+        let zeros = [
+            (55, "8e8832c642a6a38c74c17fc92ccedc266c108e6c"),
+            (56, "9438e360f578e12c0e0e8ed28e2c125c1cefee16"),
+            (63, "0b8bf9fc37ad802cefa6733ec62b09d5f43a1b75"),
+            (64, "c8d7d0ef0eedfa82d2ea1aa592845b9a6d4b02b7"),
+        ];
+        for (len, expected) in zeros {
+            assert_eq!(hex(&vec![0; len]), expected);
+        }
+    }
+
+    #[test]
+    fn test_known_roms() {
+        let ibm: &[u8] = include_bytes!("../../roms/IBM Logo.ch8");
+        let blitz: &[u8] = include_bytes!("../../roms/BLITZ.ch8");
+        let blinky: &[u8] = include_bytes!("../../roms/BLINKY.ch8");
+        let roms = [
+            (ibm, "IBM Logo", Profile::Chip8),
+            (blitz, "Blitz", Profile::Chip8),
+            (blinky, "Blinky", Profile::Chip48),
+        ];
+        for (bytes, name, profile) in roms {
+            let recommendation = recommend(bytes);
+            assert_eq!(recommendation.profile, profile, "{}", name);
+            assert_eq!(recommendation.quirks, profile.quirks());
+            assert_eq!(recommendation.confidence, Confidence::High);
+            assert!(matches!(
+                recommendation.evidence[..],
+                [Evidence::KnownRom(known)] if known.name == name
+            ));
+        }
+    }
+
+    #[test]
+    fn test_heuristics() {
+        // CLS, JP 0x200
+        let recommendation = recommend(&[0x00, 0xE0, 0x12, 0x00]);
+        assert_eq!(recommendation.profile, Profile::Chip8);
+        assert_eq!(recommendation.confidence, Confidence::Low);
+        assert!(recommendation.evidence.is_empty());
+
+        // HIGH, SCR, SCL, JP 0x200
+        let recommendation = recommend(&[0x00, 0xFF, 0x00, 0xFB, 0x00, 0xFC, 0x12, 0x00]);
+        assert_eq!(recommendation.profile, Profile::SuperChipModern);
+        assert_eq!(recommendation.confidence, Confidence::Medium);
+        assert!(matches!(
+            recommendation.evidence[..],
+            [Evidence::Extension(rom::Evidence {
+                variant: Variant::SuperChip,
+                word: 0x00FF,
+                ..
+            })]
+        ));
+
+        // SHR V1, V2 on a SUPER-CHIP program
+        let recommendation = recommend(&[0x00, 0xFF, 0x81, 0x26, 0x12, 0x00]);
+        assert_eq!(recommendation.profile, Profile::SuperChipModern);
+        assert!(recommendation.quirks.shift_uses_vy);
+        assert!(recommendation.evidence.contains(&Evidence::ShiftFromVy {
+            address: Address::new(0x202),
+            word: 0x8126,
+        }));
+
+        // A program too big for 4KB of memory
+        let mut bytes = vec![0; MAX_ROM_SIZE + 2];
+        bytes[..2].copy_from_slice(&[0x12, 0x00]);
+        let recommendation = recommend(&bytes);
+        assert_eq!(recommendation.profile, Profile::XoChip);
+        assert_eq!(recommendation.evidence, [Evidence::Size(MAX_ROM_SIZE + 2)]);
+    }
+}
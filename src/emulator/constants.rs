@@ -9,6 +9,62 @@ pub const WIDTH: usize = 64;
 /// Height of the display.
 pub const HEIGHT: usize = 32;
 
+/// Height of the ETI-660 display, its width is the standard `WIDTH`.
+pub const ETI_660_HEIGHT: usize = 48;
+
+/// Width of the display in SUPER-CHIP high resolution mode.
+pub const HIRES_WIDTH: usize = 128;
+
+/// Height of the display in SUPER-CHIP high resolution mode.
+pub const HIRES_HEIGHT: usize = 64;
+
+/// Number of bit planes of the XO-CHIP display, each pixel has a 2-bit color index.
+pub const PLANES: usize = 2;
+
+/// Width in pixels of a CHIP-8X color zone.
+pub const ZONE_WIDTH: usize = 8;
+
+/// Height in pixels of a CHIP-8X color zone.
+pub const ZONE_HEIGHT: usize = 4;
+
+/// Foreground colors of the CHIP-8X VP-590 color board as RGB, indexed by `COL`.
+///
+/// Black, red, blue, violet, green, yellow, aqua and white.
+pub const CHIP8X_FOREGROUND: [[u8; 3]; 8] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+
+/// Background colors of the CHIP-8X VP-590 color board as RGB, in the order `BGC` steps them.
+///
+/// Dark blue, black, dark green and dark red.
+pub const CHIP8X_BACKGROUND: [[u8; 3]; 4] = [
+    [0x00, 0x00, 0x80],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x80, 0x00],
+    [0x80, 0x00, 0x00],
+];
+
+/// RGBA colors of the four color indexes of the display, see `Display::to_rgba8`.
+pub type Palette = [[u8; 4]; 4];
+
+/// Black unlit pixels and white lit pixels, the second XO-CHIP plane is drawn in gray.
+pub const MONOCHROME_PALETTE: Palette = [
+    [0x00, 0x00, 0x00, 0xFF],
+    [0xFF, 0xFF, 0xFF, 0xFF],
+    [0x80, 0x80, 0x80, 0xFF],
+    [0xFF, 0xFF, 0xFF, 0xFF],
+];
+
 /// http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#2.2
 /// The chip-8 stack size is traditionally 16 (`0x10`).
-pub const STACK_SIZE: usize = 0x10;
\ No newline at end of file
+pub const STACK_SIZE: usize = 0x10;
+
+/// Number of RPL user flags of the HP-48, used by the SUPER-CHIP `FX75` and `FX85` instructions.
+pub const RPL_FLAG_COUNT: usize = 8;
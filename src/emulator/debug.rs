@@ -1,9 +1,21 @@
+use core::ops::Range;
+
 use crate::{
+    audio::PATTERN_SIZE,
+    constants::RPL_FLAG_COUNT,
     emulator::{Emulator, State},
+    error::EmulatorError,
     memory::Address,
-    register::VRegisters,
+    opcode::Opcode,
+    prelude::*,
+    register::{RegisterIndex, VRegisters},
     stack::Stack,
 };
+#[cfg(feature = "std")]
+use crate::{
+    memory::{MemReader, MemWriter},
+    source_map::SourceLocation,
+};
 
 // Impl getters for debugging
 impl Emulator {
@@ -34,8 +46,315 @@ impl Emulator {
     pub fn stack(&self) -> &Stack<Address> {
         &self.stack
     }
+    /// Returns the SUPER-CHIP RPL user flags
+    pub fn rpl_flags(&self) -> &[u8; RPL_FLAG_COUNT] {
+        &self.rpl_flags
+    }
+    /// Returns the XO-CHIP audio pattern buffer
+    pub fn pattern_buffer(&self) -> &[u8; PATTERN_SIZE] {
+        &self.audio.pattern_buffer
+    }
+    /// Returns the XO-CHIP audio playback pitch
+    pub fn pitch(&self) -> u8 {
+        self.audio.pitch
+    }
     /// Return the current state of the emulator
     pub fn state(&self) -> &State {
         &self.state
     }
+    /// Returns the source location of the next instruction, if a source map is loaded and covers it
+    #[cfg(feature = "std")]
+    pub fn current_source_line(&self) -> Option<&SourceLocation> {
+        self.source_map.as_ref()?.location(self.pc)
+    }
+
+    /// Reads a byte of memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address, up to `0xFFFF` in a 64KB memory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u8, EmulatorError>` - The byte, or `AddressOutOfRange` if the address is
+    ///   beyond the memory.
+    pub fn peek(&self, address: u16) -> Result<u8, EmulatorError> {
+        let address = address as usize;
+        Ok(self.memory.view(address..address + 1)?[0])
+    }
+
+    /// Writes a byte of memory, e.g. to edit a value from a debugger.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address, up to `0xFFFF` in a 64KB memory.
+    /// * `value` - The byte to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `AddressOutOfRange` if the address is beyond the
+    ///   memory, or `ProtectedWrite` if it is below `0x200` with the write protection on,
+    ///   see `Emulator::set_write_protection`. Nothing is written then.
+    pub fn poke(&mut self, address: u16, value: u8) -> Result<(), EmulatorError> {
+        let start = address as usize;
+        self.memory.view(start..start + 1)?;
+        self.write_memory(Address::new_long(address), &[value], self.pc)
+    }
+
+    /// Returns a range of memory without copying it, e.g. to render a hex dump.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to return.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<&[u8], EmulatorError>` - The bytes, or `AddressOutOfRange` if the range is
+    ///   not inside the memory.
+    pub fn memory_view(&self, range: Range<u16>) -> Result<&[u8], EmulatorError> {
+        self.memory.view(range.start as usize..range.end as usize)
+    }
+
+    /// Returns a reader over a range of memory, see `Memory::reader`.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to read, e.g. `loaded_range` to save the program.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MemReader, EmulatorError>` - The reader, or `AddressOutOfRange` if the range
+    ///   is not inside the memory.
+    #[cfg(feature = "std")]
+    pub fn memory_reader(&self, range: Range<u16>) -> Result<MemReader<'_>, EmulatorError> {
+        self.memory.reader(range.start as usize..range.end as usize)
+    }
+
+    /// Returns a writer filling memory from an address, see `Memory::writer_at`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The first address written, up to `0xFFFF` in a 64KB memory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MemWriter, EmulatorError>` - The writer, it stops at the end of the memory.
+    ///   `ProtectedWrite` if the address is below `0x200` with the write protection on, see
+    ///   `Emulator::set_write_protection`.
+    ///
+    /// # Notes
+    ///
+    /// Like the loads, the writer does not call the memory hooks.
+    #[cfg(feature = "std")]
+    pub fn memory_writer(&mut self, address: u16) -> Result<MemWriter<'_>, EmulatorError> {
+        let address = Address::new_long(address);
+        if self.write_protection && address < Address::ENTRY_POINT {
+            return Err(EmulatorError::ProtectedWrite {
+                addr: address,
+                pc: self.pc,
+                word: None,
+            });
+        }
+        Ok(self.memory.writer_at(address))
+    }
+
+    /// Formats a range of memory as a classic hex dump.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to dump.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String, EmulatorError>` - One line per 16 bytes, or `AddressOutOfRange` if
+    ///   the range is not inside the memory. Empty for an empty range.
+    ///
+    /// # Notes
+    ///
+    /// Lines start at multiples of 16, with the address, the bytes in pairs and the
+    /// printable ASCII characters, `.` for the others. Bytes outside the range are blank:
+    ///
+    /// ```text
+    /// 01F0                           0000 0000 0000  |          ......|
+    /// 0200  6000 A21E D015 1206 4142 4344            |`.......ABCD    |
+    /// ```
+    pub fn hexdump(&self, range: Range<u16>) -> Result<String, EmulatorError> {
+        let bytes = self.memory_view(range.clone())?;
+        let start = range.start as usize;
+        let mut dump = String::new();
+        for line in (start & !0xF..start + bytes.len()).step_by(16) {
+            let (mut hex, mut ascii) = (String::new(), String::new());
+            for address in line..line + 16 {
+                if address != line && address % 2 == 0 {
+                    hex.push(' ');
+                }
+                match address.checked_sub(start).and_then(|i| bytes.get(i)) {
+                    Some(&byte) => {
+                        hex.push_str(&format!("{byte:02X}"));
+                        let printable = byte.is_ascii_graphic() || byte == b' ';
+                        ascii.push(if printable { byte as char } else { '.' });
+                    }
+                    None => {
+                        hex.push_str("  ");
+                        ascii.push(' ');
+                    }
+                }
+            }
+            dump.push_str(&format!("{line:04X}  {hex}  |{ascii}|\n"));
+        }
+        Ok(dump)
+    }
+}
+
+// Impl setters and breakpoints for debuggers
+impl Emulator {
+    /// Moves the program counter, e.g. to skip an instruction from a debugger.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The next instruction.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `InvalidAddress` if the address is beyond the memory.
+    pub fn set_pc(&mut self, address: u16) -> Result<(), EmulatorError> {
+        self.pc = self.memory.address(address)?;
+        Ok(())
+    }
+
+    /// Sets the index register, up to `0xFFFF` like the 16-bit I of XO-CHIP.
+    pub fn set_i(&mut self, address: u16) {
+        self.i = Address::new_long(address);
+    }
+
+    /// Sets a V register.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The register, `0x0` to `0xF`.
+    /// * `value` - The value.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `InvalidRegister` if the index is above `0xF`.
+    pub fn set_register(&mut self, index: u8, value: u8) -> Result<(), EmulatorError> {
+        self.registers[RegisterIndex::try_new(index)?] = value;
+        Ok(())
+    }
+
+    /// Sets the delay timer
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer.set(value);
+    }
+
+    /// Sets the sound timer, the tone plays while it is not zero
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer.set(value);
+    }
+
+    /// Adds a breakpoint, or removes it if the address already has one.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the address has a breakpoint now.
+    ///
+    /// # Notes
+    ///
+    /// The breakpoints are kept when a ROM is loaded.
+    pub fn toggle_breakpoint(&mut self, address: Address) -> bool {
+        !self.breakpoints.remove(&address) && self.breakpoints.insert(address)
+    }
+
+    /// Returns true if the address has a breakpoint
+    pub fn has_breakpoint(&self, address: Address) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    /// Returns the addresses with a breakpoint, in order
+    pub fn breakpoints(&self) -> impl Iterator<Item = Address> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Removes all the breakpoints
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Returns true if the next instruction runs now and has a breakpoint.
+    fn at_breakpoint(&self) -> bool {
+        matches!(self.state, State::Running) && self.breakpoints.contains(&self.pc)
+    }
+
+    /// Runs a frame like `run_frame`, stopping before an instruction with a breakpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - The instructions run in the frame, at least 1.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, EmulatorError>` - True if the emulator stopped at a breakpoint, the
+    ///   rest of the frame does not run.
+    ///
+    /// # Notes
+    ///
+    /// The instruction at the program counter is checked too, an emulator stopped at a
+    /// breakpoint stays there: `step` past it to resume.
+    pub fn run_until_breakpoint(&mut self, instructions: u32) -> Result<bool, EmulatorError> {
+        self.run_frame_until(instructions, |_| false)
+    }
+
+    /// Runs the next instruction, or a whole subroutine if it is a call.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - The instructions per frame, see `run_frame`.
+    /// * `frames` - The frames the subroutine can run at most, it may never return.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, EmulatorError>` - True once the emulator is at the instruction after
+    ///   the call, false if it stopped at a breakpoint or ran out of frames.
+    ///
+    /// # Notes
+    ///
+    /// The subroutine runs in frames so the timers count down, e.g. for a delay loop. A
+    /// recursive call to the same subroutine does not stop the step, the stack must be back
+    /// to its depth.
+    pub fn step_over(&mut self, instructions: u32, frames: u32) -> Result<bool, EmulatorError> {
+        let call = matches!(self.state, State::Running)
+            && matches!(self.fetch_opcode()?, Opcode::Call { .. });
+        let (next, depth) = (self.pc.inner().wrapping_add(2), self.stack.len());
+        self.step()?;
+        if !call {
+            return Ok(true);
+        }
+        let returned =
+            |emulator: &Self| emulator.pc.inner() == next && emulator.stack.len() == depth;
+        for _ in 0..frames {
+            if self.run_frame_until(instructions, returned)? {
+                return Ok(returned(self));
+            }
+        }
+        Ok(false)
+    }
+
+    /// Runs a frame, stopping before a breakpoint or an instruction `done` is true for.
+    fn run_frame_until(
+        &mut self,
+        instructions: u32,
+        done: impl Fn(&Self) -> bool,
+    ) -> Result<bool, EmulatorError> {
+        for instruction in 0..instructions.max(1) {
+            if done(self) || self.at_breakpoint() {
+                return Ok(true);
+            }
+            if instruction == 0 {
+                self.tick()?;
+            } else {
+                self.step()?;
+            }
+        }
+        Ok(false)
+    }
 }
@@ -0,0 +1,353 @@
+use alloc::collections::BTreeSet;
+use core::fmt::Display;
+
+use crate::{
+    memory::Address,
+    opcode::{decode_iter, Opcode},
+    prelude::*,
+    rom,
+    symbols::Symbols,
+};
+
+/// Disassembles a ROM into a listing, one instruction per line.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM.
+/// * `base` - The address where the ROM is loaded.
+/// * `symbols` - Optional symbols, used to label lines and annotate address operands.
+///
+/// # Returns
+///
+/// * `String` - The listing, each line formatted by [`Opcode::format_at`].
+pub fn disassemble(bytes: &[u8], base: Address, symbols: Option<&Symbols>) -> String {
+    let mut text = String::new();
+    for (address, opcode) in decode_iter(bytes, base) {
+        let Some(symbols) = symbols else {
+            text.push_str(&opcode.format_at(address));
+            text.push('\n');
+            continue;
+        };
+        if let Some(name) = symbols.name_at(address) {
+            text.push_str(&format!("{name}:\n"));
+        }
+        text.push_str(&opcode.format_at(address));
+        if let Some(target) = opcode.operands().nnn {
+            if symbols.lookup(target).is_some() {
+                text.push_str(&format!("  ; {}", symbols.format(target)));
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// A difference between two ROMs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry {
+    /// An instruction present in both ROMs that changed.
+    Changed {
+        address: Address,
+        old: Opcode,
+        new: Opcode,
+    },
+    /// A run of data bytes present in both ROMs that changed.
+    Data {
+        address: Address,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+    /// Bytes at the end of the first ROM missing from the second one.
+    Removed { address: Address, bytes: Vec<u8> },
+    /// Bytes at the end of the second ROM missing from the first one.
+    Inserted { address: Address, bytes: Vec<u8> },
+}
+
+/// Counts of the differences between two ROMs.
+///
+/// # Fields
+///
+/// * `instructions` - The number of changed instructions.
+/// * `data_bytes` - The number of changed data bytes.
+/// * `removed` - The number of bytes only present in the first ROM.
+/// * `inserted` - The number of bytes only present in the second ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiffSummary {
+    pub instructions: usize,
+    pub data_bytes: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+impl DiffSummary {
+    /// Counts the differences in a list of entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries returned by [`diff`].
+    pub fn new(entries: &[DiffEntry]) -> Self {
+        let mut summary = Self::default();
+        for entry in entries {
+            match entry {
+                DiffEntry::Changed { .. } => summary.instructions += 1,
+                DiffEntry::Data { old, .. } => summary.data_bytes += old.len(),
+                DiffEntry::Removed { bytes, .. } => summary.removed += bytes.len(),
+                DiffEntry::Inserted { bytes, .. } => summary.inserted += bytes.len(),
+            }
+        }
+        summary
+    }
+}
+
+impl Display for DiffSummary {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} instructions changed, {} data bytes changed, {} bytes removed, {} bytes inserted",
+            self.instructions, self.data_bytes, self.removed, self.inserted
+        )
+    }
+}
+
+/// Formats bytes as space separated hex pairs.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the address of the byte at `offset` in a ROM loaded at `base`.
+fn address_at(base: Address, offset: usize) -> Address {
+    Address::new(base.inner().wrapping_add(offset as u16))
+}
+
+/// Compares two ROMs instruction by instruction.
+///
+/// # Arguments
+///
+/// * `a` - The original ROM.
+/// * `b` - The modified ROM.
+/// * `base` - The address where both ROMs are loaded.
+///
+/// # Returns
+///
+/// * `Vec<DiffEntry>` - The differences, sorted by address.
+///
+/// # Notes
+///
+/// * The ROMs are aligned by address, a size change is reported as a removed or inserted tail.
+/// * When both ROMs have the same length and are loaded at the entry point, the bytes that
+///   are not reachable code in either ROM are compared as data runs. Otherwise every word
+///   is compared as an instruction.
+pub fn diff(a: &[u8], b: &[u8], base: Address) -> Vec<DiffEntry> {
+    let common = a.len().min(b.len());
+    let code: Option<BTreeSet<Address>> = (a.len() == b.len() && base == Address::ENTRY_POINT)
+        .then(|| {
+            rom::reachable(a)
+                .union(&rom::reachable(b))
+                .copied()
+                .collect()
+        });
+    let is_code = |offset: usize| {
+        offset + 1 < common
+            && code
+                .as_ref()
+                .is_none_or(|code| code.contains(&address_at(base, offset)))
+    };
+
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset < common {
+        if is_code(offset) {
+            let old = u16::from_be_bytes([a[offset], a[offset + 1]]);
+            let new = u16::from_be_bytes([b[offset], b[offset + 1]]);
+            if old != new {
+                entries.push(DiffEntry::Changed {
+                    address: address_at(base, offset),
+                    old: Opcode::try_from(old).unwrap_or(Opcode::Invalid(old)),
+                    new: Opcode::try_from(new).unwrap_or(Opcode::Invalid(new)),
+                });
+            }
+            offset += 2;
+        } else if a[offset] != b[offset] {
+            // Group the consecutive changed data bytes
+            let start = offset;
+            while offset < common && !is_code(offset) && a[offset] != b[offset] {
+                offset += 1;
+            }
+            entries.push(DiffEntry::Data {
+                address: address_at(base, start),
+                old: a[start..offset].to_vec(),
+                new: b[start..offset].to_vec(),
+            });
+        } else {
+            offset += 1;
+        }
+    }
+
+    if a.len() > common {
+        entries.push(DiffEntry::Removed {
+            address: address_at(base, common),
+            bytes: a[common..].to_vec(),
+        });
+    }
+    if b.len() > common {
+        entries.push(DiffEntry::Inserted {
+            address: address_at(base, common),
+            bytes: b[common..].to_vec(),
+        });
+    }
+    entries
+}
+
+/// Formats the differences between two ROMs as a unified-style text block.
+///
+/// # Arguments
+///
+/// * `entries` - The entries returned by [`diff`].
+///
+/// # Returns
+///
+/// * `String` - One hunk per entry followed by the summary line.
+pub fn format_diff(entries: &[DiffEntry]) -> String {
+    let mut text = String::new();
+    for entry in entries {
+        let hunk = match entry {
+            DiffEntry::Changed { address, old, new } => format!(
+                "@@ 0x{:04X} @@\n- {}\n+ {}\n",
+                address.inner(),
+                old.format_at(*address),
+                new.format_at(*address)
+            ),
+            DiffEntry::Data { address, old, new } => format!(
+                "@@ 0x{:04X} data @@\n- 0x{:04X}  {}\n+ 0x{:04X}  {}\n",
+                address.inner(),
+                address.inner(),
+                hex(old),
+                address.inner(),
+                hex(new)
+            ),
+            DiffEntry::Removed { address, bytes } => format!(
+                "@@ 0x{:04X} removed {} bytes @@\n- 0x{:04X}  {}\n",
+                address.inner(),
+                bytes.len(),
+                address.inner(),
+                hex(bytes)
+            ),
+            DiffEntry::Inserted { address, bytes } => format!(
+                "@@ 0x{:04X} inserted {} bytes @@\n+ 0x{:04X}  {}\n",
+                address.inner(),
+                bytes.len(),
+                address.inner(),
+                hex(bytes)
+            ),
+        };
+        text.push_str(&hunk);
+    }
+    text.push_str(&DiffSummary::new(entries).to_string());
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let rom = [0xA2, 0x06, 0x12, 0x02, 0x00, 0xE0];
+        assert_eq!(
+            disassemble(&rom, Address::ENTRY_POINT, None),
+            "0x0200  A2 06        LD I, #206\n\
+             0x0202  12 02        JP #202\n\
+             0x0204  00 E0        CLS\n"
+        );
+
+        let symbols = Symbols::parse("main = 0x200\nloop = 0x202").unwrap();
+        assert_eq!(
+            disassemble(&rom, Address::ENTRY_POINT, Some(&symbols)),
+            "main:\n\
+             0x0200  A2 06        LD I, #206  ; loop+0x4\n\
+             loop:\n\
+             0x0202  12 02        JP #202  ; loop\n\
+             0x0204  00 E0        CLS\n"
+        );
+    }
+
+    #[test]
+    fn test_identical() {
+        let rom = [0xA2, 0x1E, 0x12, 0x00];
+        assert!(diff(&rom, &rom, Address::ENTRY_POINT).is_empty());
+    }
+
+    #[test]
+    fn test_changed_instruction_and_data() {
+        let a = [
+            0xA2, 0x06, // LD I, 0x206
+            0x12, 0x02, // JP 0x202
+            0x00, 0x00, // Padding
+            0x01, 0x02, 0x03, // Data
+            0x00,
+        ];
+        let mut b = a;
+        b[1] = 0x07;
+        b[7] = 0xFF;
+        b[8] = 0xFE;
+
+        let entries = diff(&a, &b, Address::ENTRY_POINT);
+        assert_eq!(
+            entries,
+            [
+                DiffEntry::Changed {
+                    address: Address::new(0x200),
+                    old: Opcode::LdI {
+                        address: Address::new(0x206)
+                    },
+                    new: Opcode::LdI {
+                        address: Address::new(0x207)
+                    },
+                },
+                DiffEntry::Data {
+                    address: Address::new(0x207),
+                    old: vec![0x02, 0x03],
+                    new: vec![0xFF, 0xFE],
+                },
+            ]
+        );
+        assert_eq!(
+            format_diff(&entries),
+            "@@ 0x0200 @@\n\
+             - 0x0200  A2 06        LD I, #206\n\
+             + 0x0200  A2 07        LD I, #207\n\
+             @@ 0x0207 data @@\n\
+             - 0x0207  02 03\n\
+             + 0x0207  FF FE\n\
+             1 instructions changed, 2 data bytes changed, 0 bytes removed, 0 bytes inserted"
+        );
+    }
+
+    #[test]
+    fn test_different_lengths() {
+        let a = [0x00, 0xE0, 0x12, 0x00];
+        let b = [0x00, 0xEE, 0x12, 0x00, 0xAA];
+
+        let entries = diff(&a, &b, Address::new(0x600));
+        assert_eq!(
+            entries,
+            [
+                DiffEntry::Changed {
+                    address: Address::new(0x600),
+                    old: Opcode::Cls,
+                    new: Opcode::Ret,
+                },
+                DiffEntry::Inserted {
+                    address: Address::new(0x604),
+                    bytes: vec![0xAA],
+                },
+            ]
+        );
+        let summary = DiffSummary::new(&diff(&b, &a, Address::new(0x600)));
+        assert_eq!((summary.instructions, summary.removed), (1, 1));
+    }
+}
@@ -1,15 +1,215 @@
+use core::ops::RangeInclusive;
+
+#[cfg(feature = "embedded-graphics")]
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, Point, Size},
+    primitives::Rectangle,
+};
+
+use crate::{
+    constants::{
+        Palette, CHIP8X_BACKGROUND, CHIP8X_FOREGROUND, ETI_660_HEIGHT, HEIGHT, HIRES_HEIGHT,
+        HIRES_WIDTH, PLANES, WIDTH, ZONE_HEIGHT, ZONE_WIDTH,
+    },
+    error::EmulatorError,
+    prelude::*,
+    testing,
+};
+
+/// Number of CHIP-8X color zone columns.
+const ZONE_COLUMNS: usize = WIDTH / ZONE_WIDTH;
+
+/// Number of CHIP-8X color zone rows.
+const ZONE_ROWS: usize = HEIGHT / ZONE_HEIGHT;
+
+/// Foreground color of the CHIP-8X zones until a program changes them, red.
+const DEFAULT_ZONE_COLOR: u8 = 1;
+
+/// The pixels of one bit plane, one row per `u128` indexed by y.
+/// The pixel at x is the bit `HIRES_WIDTH - 1 - x`, so the leftmost pixel is the most significant bit.
+type Plane = [u128; HIRES_HEIGHT];
+
+/// An empty bit plane.
+const EMPTY_PLANE: Plane = [0; HIRES_HEIGHT];
+
+/// Returns the mask of the bits of the pixels inside a display `width` pixels wide.
+fn width_mask(width: usize) -> u128 {
+    !0 << (HIRES_WIDTH - width)
+}
+
+/// A set of display rows, bit N is row N.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RowMask(pub u64);
+
+impl RowMask {
+    /// Every row of the high resolution display.
+    pub const ALL: Self = Self(!0);
+
+    /// Returns true if the row is in the set.
+    pub fn contains(&self, row: usize) -> bool {
+        row < HIRES_HEIGHT && self.0 & (1 << row) != 0
+    }
+
+    /// Returns true if no row is in the set.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the rows in the set, top to bottom.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let mask = self.0;
+        (0..HIRES_HEIGHT).filter(move |row| mask & (1 << row) != 0)
+    }
+}
+
+/// Bits of the braille dots of a 2x4 cell, indexed `[x][y]`.
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// How `Display::render_to_string` draws pixels with characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    /// One character per pixel, `#` for lit pixels and `.` for unlit ones.
+    Plain,
+    /// Two vertical pixels per character, with `▀`, `▄`, `█` and spaces.
+    HalfBlock,
+    /// 2x4 pixels per character, with the Unicode braille patterns.
+    Braille,
+}
+
+/// How the exported images are rotated or flipped, for screens mounted sideways.
+///
+/// Only the output changes, the video RAM and the emulation are untouched.
+/// Rotations are clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transform {
+    /// The image as it is drawn.
+    #[default]
+    None,
+    /// Rotated by 90 degrees, the top row becomes the right column.
+    Rot90,
+    /// Rotated by 180 degrees.
+    Rot180,
+    /// Rotated by 270 degrees, the top row becomes the left column.
+    Rot270,
+    /// Mirrored left to right.
+    FlipH,
+    /// Mirrored top to bottom.
+    FlipV,
+}
+
+impl Transform {
+    /// Returns the dimensions of a transformed image.
+    ///
+    /// # Arguments
+    ///
+    /// * `(width, height)` - The dimensions of the display.
+    ///
+    /// # Returns
+    ///
+    /// * `(usize, usize)` - The width and height of the output, swapped by `Rot90` and `Rot270`.
+    pub fn dimensions(self, (width, height): (usize, usize)) -> (usize, usize) {
+        match self {
+            Self::Rot90 | Self::Rot270 => (height, width),
+            _ => (width, height),
+        }
+    }
+
+    /// Returns the display pixel shown at a position of the output.
+    ///
+    /// # Arguments
+    ///
+    /// * `(x, y)` - The coordinates in the output.
+    /// * `(width, height)` - The dimensions of the display.
+    ///
+    /// # Returns
+    ///
+    /// * `(usize, usize)` - The coordinates of the pixel on the display.
+    fn source(self, (x, y): (usize, usize), (width, height): (usize, usize)) -> (usize, usize) {
+        match self {
+            Self::None => (x, y),
+            Self::Rot90 => (y, height - 1 - x),
+            Self::Rot180 => (width - 1 - x, height - 1 - y),
+            Self::Rot270 => (width - 1 - y, x),
+            Self::FlipH => (width - 1 - x, y),
+            Self::FlipV => (x, height - 1 - y),
+        }
+    }
+}
+
+/// The pixel layout of the buffers written by `Display::render_scaled`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel, the RGBA color of the color index in the palette.
+    Rgba8(Palette),
+    /// 1 byte per pixel, the color index times `0x55`: unlit pixels are `0x00` and pixels
+    /// lit on the first plane `0x55`, up to `0xFF` on both XO-CHIP planes.
+    Gray8,
+}
+
+impl PixelFormat {
+    /// Returns the number of bytes of a pixel.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Rgba8(_) => 4,
+            Self::Gray8 => 1,
+        }
+    }
+}
+
 /// Represents the display of the Chip8 system.
-/// The display is a 64x32 monochrome display.
+/// The display is a 64x32 monochrome display, or 128x64 in SUPER-CHIP high resolution mode.
+/// XO-CHIP adds a second bit plane, so each pixel has one of four colors.
+/// CHIP-8X colors the lit pixels of each 8x4 zone, and the unlit pixels with a background color.
 ///
 /// # Fields
 ///
-/// * `vram` - A 2D array of booleans representing the video RAM of the display.
-/// * `updated` - Indicates whether the display has been updated. (to avoid redrawing the display when it hasn't changed)
+/// * `planes` - The bit planes, the video RAM of the display packed as one integer per row.
+/// * `selected` - The mask of the planes drawn, cleared and scrolled, bit 0 is the first plane.
+/// * `zones` - The CHIP-8X foreground color of each zone, indexed `[column][row]`.
+/// * `background` - The CHIP-8X background color.
+/// * `hires` - Whether the display is in high resolution mode.
+/// * `lores_height` - The height of the low resolution mode, 32 or 48 for ETI-660.
+/// * `version` - Bumped on every change, to avoid redrawing the display when it hasn't changed.
+/// * `taken` - The version as of the last call to `take_updated`.
+/// * `dirty` - The rows changed since the last call to `take_dirty_rows`.
+/// * `presented` - The lit pixels as of the last call to `take_diff`.
+/// * `diff` - The pixels that changed, reused by every call to `take_diff`.
+/// * `front` - The planes as of the last call to `present`, only when double buffered.
+///
+/// # Notes
+///
+/// * The video RAM is always 128x64, in low resolution mode only the top-left 64x32
+///   (or 64x48) pixels are used. Packing each row in an integer turns drawing a sprite row
+///   into a shift and a XOR.
+/// * No public method panics, whatever the coordinates. Pixels outside the display read as
+///   unlit, use `try_get` to tell them apart.
+#[derive(Clone)]
 pub struct Display {
-    /// The video RAM of the display.
-    vram: [[bool; crate::constants::HEIGHT]; crate::constants::WIDTH],
-    /// Indicates whether the display has been updated.
-    pub updated: bool,
+    /// The bit planes of the display.
+    planes: [Plane; PLANES],
+    /// The mask of the selected planes.
+    selected: u8,
+    /// The CHIP-8X foreground color of each zone.
+    zones: [[u8; ZONE_ROWS]; ZONE_COLUMNS],
+    /// The CHIP-8X background color.
+    background: u8,
+    /// Whether the display is in high resolution mode.
+    hires: bool,
+    /// The height of the low resolution mode.
+    lores_height: usize,
+    /// Bumped on every change.
+    version: u64,
+    /// The version as of the last call to `take_updated`.
+    taken: u64,
+    /// The rows changed since they were last taken.
+    dirty: RowMask,
+    /// The lit pixels as of the last diff.
+    presented: Plane,
+    /// The pixels that changed in the last diff.
+    diff: Vec<(u8, u8, bool)>,
+    /// The planes read by frontends when double buffered.
+    front: Option<[Plane; PLANES]>,
 }
 
 impl Display {
@@ -20,20 +220,404 @@ impl Display {
     /// * `Display` - The display created.
     pub(super) fn new() -> Self {
         Self {
-            vram: [[false; crate::constants::HEIGHT]; crate::constants::WIDTH],
-            updated: false,
+            planes: [EMPTY_PLANE; PLANES],
+            selected: 1,
+            zones: [[DEFAULT_ZONE_COLOR; ZONE_ROWS]; ZONE_COLUMNS],
+            background: 0,
+            hires: false,
+            lores_height: HEIGHT,
+            // Nothing has been presented yet
+            version: 1,
+            taken: 0,
+            dirty: RowMask::ALL,
+            presented: EMPTY_PLANE,
+            diff: Vec::new(),
+            front: None,
+        }
+    }
+
+    /// Creates a display showing an ASCII art frame, to build expected frames in tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `art` - The frame, one line per row with `#` for lit pixels and `.` for unlit ones.
+    ///   Blank lines and the whitespace around rows are ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Display, EmulatorError>` - The display, in the mode of the dimensions of
+    ///   the frame: 64x32, 64x48 (ETI-660) or 128x64. An error if the frame is invalid or
+    ///   has other dimensions.
+    pub fn from_ascii_art(art: &str) -> Result<Self, EmulatorError> {
+        let rows = testing::parse_ascii_art(art).map_err(EmulatorError::InvalidDisplayData)?;
+        let (width, height) = (rows[0].len(), rows.len());
+        let mut display = Self::new();
+        match (width, height) {
+            (WIDTH, HEIGHT) => {}
+            (WIDTH, ETI_660_HEIGHT) => display.set_lores_height(height),
+            (HIRES_WIDTH, HIRES_HEIGHT) => display.set_hires(true),
+            _ => {
+                return Err(EmulatorError::InvalidDisplayData(format!(
+                    "The frame is {width}x{height}, a display is 64x32, 64x48 or 128x64"
+                )))
+            }
         }
+        for (y, row) in rows.iter().enumerate() {
+            let bits = row.iter().enumerate();
+            let packed = bits.fold(0, |packed, (x, &lit)| packed | (lit as u128) << (127 - x));
+            display.set_row(y, packed)?;
+        }
+        Ok(display)
+    }
+
+    /// Resets the display to its initial state, low resolution with only the first plane selected.
+    ///
+    /// Every plane is cleared.
+    pub(super) fn reset(&mut self) {
+        // What the frontend presented is still on its screen
+        *self = Self {
+            lores_height: self.lores_height,
+            version: self.version,
+            taken: self.taken,
+            presented: self.presented,
+            diff: core::mem::take(&mut self.diff),
+            front: self.front,
+            ..Self::new()
+        };
+        self.touch_all();
     }
 
     /// Clears the display.
     ///
-    /// Sets all pixels to false.
+    /// Sets all pixels of the selected planes to false.
     pub(super) fn clear(&mut self) {
-        self.updated = true;
-        self.vram = [[false; crate::constants::HEIGHT]; crate::constants::WIDTH];
+        self.touch_all();
+        for plane in self.selected_planes() {
+            self.planes[plane] = EMPTY_PLANE;
+        }
+    }
+
+    /// Sets whether the display is double buffered.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - If true, instructions draw to a back buffer and the pixels read by
+    ///   frontends only change when `present` is called. Otherwise they are read directly
+    ///   (default).
+    pub(super) fn set_double_buffered(&mut self, enabled: bool) {
+        self.front = enabled.then_some(self.planes);
+    }
+
+    /// Returns whether the display is double buffered.
+    pub fn is_double_buffered(&self) -> bool {
+        self.front.is_some()
+    }
+
+    /// Copies the back buffer to the front buffer, the pixels frontends read.
+    ///
+    /// # Notes
+    ///
+    /// Does nothing unless the display is double buffered. The emulator presents on every
+    /// vertical blank, so frontends never see a half-drawn frame.
+    pub fn present(&mut self) {
+        if let Some(front) = &mut self.front {
+            if *front != self.planes {
+                *front = self.planes;
+                self.version += 1;
+            }
+        }
+    }
+
+    /// Returns the planes frontends read, the front buffer when double buffered.
+    fn visible(&self) -> &[Plane; PLANES] {
+        self.front.as_ref().unwrap_or(&self.planes)
+    }
+
+    /// Marks the whole display as changed.
+    fn touch_all(&mut self) {
+        self.version += 1;
+        self.dirty = RowMask::ALL;
+    }
+
+    /// Returns the version of the display, bumped every time it changes.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The version, it only ever grows. Each consumer remembers the version it
+    ///   last drew and redraws when it differs.
+    ///
+    /// # Notes
+    ///
+    /// A double buffered display is bumped when a changed frame is presented, but also when
+    /// the back buffer changes.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns whether the display changed since the last call, for a single consumer.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the display changed since the last call, and on the first call.
+    ///   Use `version` when several consumers watch the display.
+    pub fn take_updated(&mut self) -> bool {
+        let updated = self.version != self.taken;
+        self.taken = self.version;
+        updated
+    }
+
+    /// Returns whether the display changed since the last `take_updated`, without
+    /// forgetting it.
+    ///
+    /// # Notes
+    ///
+    /// It replaces the `updated` field removed in 0.3.0, which frontends had to reset
+    /// themselves. Reset it with `take_updated` instead.
+    #[deprecated(
+        since = "0.3.0",
+        note = "use `take_updated` for one consumer or `version` for several"
+    )]
+    pub fn updated(&self) -> bool {
+        self.version != self.taken
+    }
+
+    /// Returns the rows changed since the last call and forgets them.
+    ///
+    /// # Returns
+    ///
+    /// * `RowMask` - The rows drawn to since the last call, every row after a clear, a scroll,
+    ///   a resolution change or a color change.
+    ///
+    /// # Notes
+    ///
+    /// Every row is dirty on a new display, so the first frame is presented whole.
+    pub fn take_dirty_rows(&mut self) -> RowMask {
+        core::mem::take(&mut self.dirty)
+    }
+
+    /// Returns the pixels that changed since the last call, and remembers the current ones.
+    ///
+    /// # Returns
+    ///
+    /// * `&[(u8, u8, bool)]` - The x-coordinate, the y-coordinate and the new value of each
+    ///   changed pixel, in row-major order. A pixel turned off and on again between two calls
+    ///   is not reported.
+    ///
+    /// # Notes
+    ///
+    /// * The first call reports every lit pixel, as if the screen was blank.
+    /// * Only the pixels inside the current resolution are reported, after a resolution
+    ///   change frontends should redraw everything.
+    /// * The buffer is reused, it never holds more than one entry per pixel.
+    pub fn take_diff(&mut self) -> &[(u8, u8, bool)] {
+        self.diff.clear();
+        let (width, height) = self.dimensions();
+        let mask = width_mask(width);
+        for y in 0..HIRES_HEIGHT {
+            let lit = self.visible().iter().fold(0, |lit, plane| lit | plane[y]);
+            let mut changed = (lit ^ self.presented[y]) & mask;
+            self.presented[y] = lit;
+            if y >= height {
+                continue;
+            }
+            while changed != 0 {
+                let x = changed.leading_zeros() as usize;
+                changed &= !(1 << (HIRES_WIDTH - 1 - x));
+                self.diff.push((x as u8, y as u8, self.get(x, y)));
+            }
+        }
+        &self.diff
+    }
+
+    /// Switches between low (64x32) and high (128x64) resolution modes.
+    ///
+    /// Every plane is cleared when switching modes.
+    ///
+    /// # Arguments
+    ///
+    /// * `hires` - True to switch to high resolution mode.
+    pub(super) fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.touch_all();
+        self.planes = [EMPTY_PLANE; PLANES];
+    }
+
+    /// Selects the planes drawn, cleared and scrolled by the next instructions.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - The mask of the planes, bit 0 is the first plane. Only the low 2 bits are used.
+    pub(super) fn select_planes(&mut self, mask: u8) {
+        self.selected = mask & ((1 << PLANES) - 1);
     }
 
-    /// Sets 8 pixels on the display.
+    /// Returns the mask of the selected planes, bit 0 is the first plane.
+    pub fn selected(&self) -> u8 {
+        self.selected
+    }
+
+    /// Sets the height of the low resolution mode and clears the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `height` - The height in pixels, `HEIGHT` or `ETI_660_HEIGHT`, at most `HIRES_HEIGHT`.
+    pub(super) fn set_lores_height(&mut self, height: usize) {
+        self.lores_height = height.min(HIRES_HEIGHT);
+        self.touch_all();
+        self.planes = [EMPTY_PLANE; PLANES];
+    }
+
+    /// Returns the indexes of the selected planes, in the order the sprites are drawn.
+    pub(super) fn selected_planes(&self) -> impl Iterator<Item = usize> {
+        let selected = self.selected;
+        (0..PLANES).filter(move |plane| selected & (1 << plane) != 0)
+    }
+
+    /// Returns whether the display is in high resolution mode.
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Returns the dimensions of the display in the current mode.
+    ///
+    /// # Returns
+    ///
+    /// * `(usize, usize)` - The width and height, `(64, 32)`, `(64, 48)` for ETI-660 or `(128, 64)`.
+    pub fn dimensions(&self) -> (usize, usize) {
+        if self.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (WIDTH, self.lores_height)
+        }
+    }
+
+    /// Sets the CHIP-8X foreground color of a block of zones.
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - The zone columns, those past the right edge are ignored.
+    /// * `rows` - The zone rows, those past the bottom edge are ignored.
+    /// * `color` - The index of the color in `CHIP8X_FOREGROUND`, only the low 3 bits are used.
+    pub(super) fn set_zone_color(
+        &mut self,
+        columns: RangeInclusive<usize>,
+        rows: RangeInclusive<usize>,
+        color: u8,
+    ) {
+        self.touch_all();
+        let (first, last) = (*columns.start(), *columns.end());
+        for column in self.zones.iter_mut().take(last + 1).skip(first) {
+            for zone in column.iter_mut().take(rows.end() + 1).skip(*rows.start()) {
+                *zone = color & 0x7;
+            }
+        }
+    }
+
+    /// Steps the CHIP-8X background color to the next one of `CHIP8X_BACKGROUND`.
+    pub(super) fn step_background(&mut self) {
+        self.touch_all();
+        self.background = (self.background + 1) % CHIP8X_BACKGROUND.len() as u8;
+    }
+
+    /// Returns the CHIP-8X foreground color index of the zone that contains a pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The index of the color in `CHIP8X_FOREGROUND`, red unless a program changed it.
+    pub fn zone_color(&self, x: usize, y: usize) -> u8 {
+        let column = (x / ZONE_WIDTH).min(ZONE_COLUMNS - 1);
+        let row = (y / ZONE_HEIGHT).min(ZONE_ROWS - 1);
+        self.zones[column][row]
+    }
+
+    /// Returns the CHIP-8X background color index, in `CHIP8X_BACKGROUND`.
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    /// Returns the RGB color of a pixel on a CHIP-8X display.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    ///
+    /// # Returns
+    ///
+    /// * `[u8; 3]` - The color of the zone if the pixel is lit, otherwise the background color.
+    ///
+    /// # Notes
+    ///
+    /// Frontends for other profiles should keep using `get`, the zones are only changed by CHIP-8X programs.
+    pub fn chip8x_rgb(&self, x: usize, y: usize) -> [u8; 3] {
+        if self.get(x, y) {
+            CHIP8X_FOREGROUND[self.zone_color(x, y) as usize]
+        } else {
+            CHIP8X_BACKGROUND[self.background as usize]
+        }
+    }
+
+    /// Scrolls the selected planes down, the vacated rows at the top are cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of rows to scroll.
+    pub(super) fn scroll_down(&mut self, n: u8) {
+        self.touch_all();
+        let height = self.dimensions().1;
+        let n = (n as usize).min(height);
+        for plane in self.selected_planes() {
+            self.planes[plane].copy_within(..height - n, n);
+            self.planes[plane][..n].fill(0);
+        }
+    }
+
+    /// Scrolls the selected planes right by 4 pixels.
+    pub(super) fn scroll_right4(&mut self) {
+        self.scroll_right(4);
+    }
+
+    /// Scrolls the selected planes left by 4 pixels.
+    pub(super) fn scroll_left4(&mut self) {
+        self.scroll_left(4);
+    }
+
+    /// Scrolls the selected planes right, the vacated columns on the left are cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of columns to scroll.
+    pub(super) fn scroll_right(&mut self, n: usize) {
+        self.touch_all();
+        let mask = width_mask(self.dimensions().0);
+        for plane in self.selected_planes() {
+            for row in self.planes[plane].iter_mut() {
+                *row = row.checked_shr(n as u32).unwrap_or(0) & mask;
+            }
+        }
+    }
+
+    /// Scrolls the selected planes left, the vacated columns on the right are cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of columns to scroll.
+    pub(super) fn scroll_left(&mut self, n: usize) {
+        self.touch_all();
+        let mask = width_mask(self.dimensions().0);
+        for plane in self.selected_planes() {
+            for row in self.planes[plane].iter_mut() {
+                *row = row.checked_shl(n as u32).unwrap_or(0) & mask;
+            }
+        }
+    }
+
+    /// Sets 8 pixels on every selected plane.
     ///
     /// # Arguments
     ///
@@ -43,23 +627,64 @@ impl Display {
     ///
     /// # Returns
     ///
-    /// * `u8` - Returns 1 if a pixel was erased, otherwise returns 0.
-    pub fn set(&mut self, x: u8, mut y: u8, value: u8) -> u8 {
-        self.updated = true;
+    /// * `u8` - Returns 1 if a pixel was erased on any plane, otherwise returns 0.
+    pub fn set(&mut self, x: u8, y: u8, value: u8) -> u8 {
         let mut result = 0;
-        y %= crate::constants::HEIGHT as u8;
-        let y_usize = y as usize;
-        for bit_index in 0..u8::BITS as u8 {
-            let x_usize = (x + bit_index) as usize % crate::constants::WIDTH;
-            let pixel = (value & (0x80 >> bit_index)) != 0;
-            if !(self.vram[x_usize][y_usize] ^ pixel) && !pixel {
-                result = 1
-            }
-            self.vram[x_usize][y_usize] ^= pixel;
+        for plane in self.selected_planes() {
+            result |= self.set_plane(plane, x, y, value, false);
         }
         result
     }
 
+    /// Sets 8 pixels on one plane.
+    ///
+    /// # Arguments
+    ///
+    /// * `plane` - The index of the plane, 0 or 1.
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    /// * `value` - The value to set the pixels to, represented as 8 bit-encoded pixels.
+    /// * `clip` - Whether pixels outside the display are dropped instead of wrapped around.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - Returns 1 if a pixel was erased, otherwise returns 0.
+    ///
+    /// # Notes
+    ///
+    /// The version is only bumped when a pixel is drawn, an empty sprite row leaves it as is.
+    /// The row is dirty still, as every row a sprite covers, see `take_dirty_rows`.
+    #[inline]
+    pub(super) fn set_plane(&mut self, plane: usize, x: u8, y: u8, value: u8, clip: bool) -> u8 {
+        let (width, height) = self.dimensions();
+        let y = y as usize;
+        if clip && y >= height {
+            return 0;
+        }
+        let mask = width_mask(width);
+        // The sprite at x = 0, then shifted into place
+        let sprite = (value as u128) << (HIRES_WIDTH - 8);
+        let x = if clip { x as usize } else { x as usize % width };
+        let mut bits = sprite.checked_shr(x as u32).unwrap_or(0) & mask;
+        if !clip {
+            // The pixels past the right edge wrap around to the left edge
+            bits |= sprite.checked_shl((width - x) as u32).unwrap_or(0) & mask;
+        }
+        let y = y % height;
+        let Some(row) = self.planes.get_mut(plane).and_then(|rows| rows.get_mut(y)) else {
+            return 0;
+        };
+        self.dirty.0 |= 1 << y;
+        if bits == 0 {
+            return 0;
+        }
+        self.version += 1;
+        // A lit pixel turned off by a lit sprite bit is a collision
+        let collision = *row & bits != 0;
+        *row ^= bits;
+        collision as u8
+    }
+
     /// Returns the value of a pixel.
     ///
     /// # Arguments
@@ -69,36 +694,1319 @@ impl Display {
     ///
     /// # Returns
     ///
-    /// * `bool` - The value of the pixel.
+    /// * `bool` - The value of the pixel, true if it is lit on any plane. False outside the
+    ///   display.
     pub fn get(&self, x: usize, y: usize) -> bool {
-        self.vram[x][y]
+        self.try_get(x, y).unwrap_or(false)
     }
 
-    /// Returns a reference to the video RAM of the display.
-    /// 
+    /// Returns the value of a pixel, if it is on the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    ///
     /// # Returns
-    /// 
-    /// * `&[[bool; crate::constants::HEIGHT]; crate::constants::WIDTH]` - The video RAM of the display.
-    pub fn get_vram(&self) -> &[[bool; crate::constants::HEIGHT]; crate::constants::WIDTH] {
-        &self.vram
+    ///
+    /// * `Option<bool>` - The value of the pixel, or `None` if the coordinates are outside
+    ///   the display in the current mode.
+    pub fn try_get(&self, x: usize, y: usize) -> Option<bool> {
+        self.try_color(x, y).map(|color| color != 0)
     }
-}
 
-impl std::ops::Index<(usize, usize)> for Display {
-    type Output = bool;
-    
-    /// Returns the value of the pixel at the given coordinates.
-    /// 
+    /// Returns the color index of a pixel.
+    ///
     /// # Arguments
-    /// 
+    ///
     /// * `x` - The x-coordinate of the pixel.
     /// * `y` - The y-coordinate of the pixel.
-    /// 
+    ///
     /// # Returns
-    /// 
-    /// * `&bool` - The value of the pixel.
-    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
-        &self.vram[x][y]
+    ///
+    /// * `u8` - The 2-bit color index, bit 0 is the first plane and bit 1 the second one.
+    ///   Frontends map the four indexes to a palette, monochrome programs only use 0 and 1.
+    ///   0 outside the display.
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        self.try_color(x, y).unwrap_or(0)
     }
-    
-}
\ No newline at end of file
+
+    /// Returns the color index of a pixel, if it is on the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u8>` - The 2-bit color index, or `None` if the coordinates are outside the
+    ///   display in the current mode.
+    pub fn try_color(&self, x: usize, y: usize) -> Option<u8> {
+        let (width, height) = self.dimensions();
+        if x >= width || y >= height {
+            return None;
+        }
+        let planes = self.visible().iter().enumerate();
+        let color = planes.fold(0, |color, (plane, rows)| {
+            let pixel = (rows[y] >> (HIRES_WIDTH - 1 - x)) & 1;
+            color | (pixel as u8) << plane
+        });
+        Some(color)
+    }
+
+    /// Returns the visible pixels in row-major order, left to right and top to bottom.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = (usize, usize, bool)>` - The x-coordinate, the y-coordinate
+    ///   and the value of each pixel, as [`Display::get`] returns it.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        let (width, height) = self.dimensions();
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y, self.get(x, y))))
+    }
+
+    /// Returns a whole row, packed in an integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - The y-coordinate of the row.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u128>` - The pixels lit on any plane, bit 127 is the leftmost pixel (x = 0)
+    ///   and bit `127 - x` the pixel at x. Bits past the right edge are always 0. `None` if
+    ///   the row is outside the display in the current mode.
+    ///
+    /// # Notes
+    ///
+    /// Rows are 128 bits wide, a `u64` can not hold a high resolution row.
+    pub fn get_row(&self, y: usize) -> Option<u128> {
+        if y >= self.dimensions().1 {
+            return None;
+        }
+        Some(self.visible().iter().fold(0, |lit, rows| lit | rows[y]))
+    }
+
+    /// Sets a whole row of the selected planes, mostly to set up tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - The y-coordinate of the row.
+    /// * `row` - The pixels, with the layout of [`Display::get_row`]. Bits past the right
+    ///   edge are ignored.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the row is outside the display in the
+    ///   current mode.
+    pub fn set_row(&mut self, y: usize, row: u128) -> Result<(), EmulatorError> {
+        let (width, height) = self.dimensions();
+        if y >= height {
+            return Err(EmulatorError::InvalidDisplayData(format!(
+                "The row {y} is outside the display"
+            )));
+        }
+        for plane in self.selected_planes() {
+            self.planes[plane][y] = row & width_mask(width);
+        }
+        self.version += 1;
+        self.dirty.0 |= 1 << y;
+        Ok(())
+    }
+
+    /// Returns the coordinates of the lit pixels only, in row-major order.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = (u8, u8)>` - The x-coordinate and the y-coordinate of each
+    ///   pixel lit on any plane, top to bottom and left to right within a row.
+    ///
+    /// # Notes
+    ///
+    /// Unlit pixels cost nothing, each row is scanned with `leading_zeros`.
+    pub fn lit_pixels(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        let (width, height) = self.dimensions();
+        let mask = width_mask(width);
+        (0..height).flat_map(move |y| {
+            let mut row = self.visible().iter().fold(0, |lit, rows| lit | rows[y]) & mask;
+            core::iter::from_fn(move || {
+                if row == 0 {
+                    return None;
+                }
+                let x = row.leading_zeros();
+                row &= !(1 << (HIRES_WIDTH as u32 - 1 - x));
+                Some((x as u8, y as u8))
+            })
+        })
+    }
+
+    /// Returns the visible rows, top to bottom.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = impl Iterator<Item = bool>>` - The pixels of each row,
+    ///   left to right.
+    ///
+    /// # Notes
+    ///
+    /// Each row is packed in a `u128` of every plane, see [`Display::packed_rows`] to read
+    /// them without unpacking the pixels.
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        let (width, height) = self.dimensions();
+        (0..height).map(move |y| (0..width).map(move |x| self.get(x, y)))
+    }
+
+    /// Returns the visible rows packed, top to bottom.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = u128>` - The pixels lit on any plane of each row, with the
+    ///   layout of [`Display::get_row`].
+    pub fn packed_rows(&self) -> impl Iterator<Item = u128> + '_ {
+        (0..self.dimensions().1).filter_map(|y| self.get_row(y))
+    }
+
+    /// Converts the visible pixels to an RGBA image.
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The color of each color index, see [`Display::color`].
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - 4 bytes per pixel in row-major order, `width * height * 4` bytes long.
+    pub fn to_rgba8(&self, palette: &Palette) -> Vec<u8> {
+        self.to_rgba8_transformed(palette, Transform::None)
+    }
+
+    /// Converts the visible pixels to a rotated or flipped RGBA image.
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The color of each color index, see [`Display::color`].
+    /// * `transform` - How the image is rotated or flipped.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - 4 bytes per pixel in row-major order, with the dimensions given by
+    ///   [`Transform::dimensions`].
+    pub fn to_rgba8_transformed(&self, palette: &Palette, transform: Transform) -> Vec<u8> {
+        let (width, height) = self.dimensions();
+        let mut buffer = vec![0; width * height * 4];
+        self.fill_rgba8(&mut buffer, palette, transform);
+        buffer
+    }
+
+    /// Writes the visible pixels to an RGBA buffer, without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer, `width * height * 4` bytes long for the current resolution.
+    /// * `palette` - The color of each color index, see [`Display::color`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the buffer does not have the size of the image.
+    pub fn write_rgba8(&self, buffer: &mut [u8], palette: &Palette) -> Result<(), EmulatorError> {
+        self.write_rgba8_transformed(buffer, palette, Transform::None)
+    }
+
+    /// Writes the visible pixels to an RGBA buffer, rotated or flipped, without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer, `width * height * 4` bytes long for the current resolution.
+    /// * `palette` - The color of each color index, see [`Display::color`].
+    /// * `transform` - How the image is rotated or flipped.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the buffer does not have the size of the image.
+    pub fn write_rgba8_transformed(
+        &self,
+        buffer: &mut [u8],
+        palette: &Palette,
+        transform: Transform,
+    ) -> Result<(), EmulatorError> {
+        let (width, height) = self.dimensions();
+        let expected = width * height * 4;
+        if buffer.len() != expected {
+            return Err(EmulatorError::InvalidBufferSize {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+        self.fill_rgba8(buffer, palette, transform);
+        Ok(())
+    }
+
+    /// Writes the visible pixels to an RGBA buffer of the right size.
+    fn fill_rgba8(&self, buffer: &mut [u8], palette: &Palette, transform: Transform) {
+        let dimensions = self.dimensions();
+        let width = transform.dimensions(dimensions).0;
+        for (y, row) in buffer.chunks_exact_mut(width * 4).enumerate() {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let (x, y) = transform.source((x, y), dimensions);
+                pixel.copy_from_slice(&palette[self.color(x, y) as usize]);
+            }
+        }
+    }
+
+    /// Upscales the visible pixels by an integer factor into a buffer, without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer, `width * scale * height * scale` pixels long for the
+    ///   current resolution.
+    /// * `scale` - The width and height in the buffer of each pixel.
+    /// * `format` - The layout of the pixels in the buffer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the scale is 0 or the buffer does not
+    ///   have the size of the image.
+    ///
+    /// # Notes
+    ///
+    /// Only the first row of each scaled row is built pixel by pixel, the other rows are
+    /// copies of it. With `PixelFormat::Rgba8` and a scale of 1 this is `write_rgba8`.
+    pub fn render_scaled(
+        &self,
+        buffer: &mut [u8],
+        scale: usize,
+        format: PixelFormat,
+    ) -> Result<(), EmulatorError> {
+        if scale == 0 {
+            return Err(EmulatorError::InvalidScale(scale));
+        }
+        let (width, height) = self.dimensions();
+        let row_length = width * scale * format.bytes_per_pixel();
+        let expected = row_length * height * scale;
+        if buffer.len() != expected {
+            return Err(EmulatorError::InvalidBufferSize {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+        for (y, rows) in buffer.chunks_exact_mut(row_length * scale).enumerate() {
+            let (first, copies) = rows.split_at_mut(row_length);
+            let pixels = first.chunks_exact_mut(format.bytes_per_pixel() * scale);
+            for (x, pixel) in pixels.enumerate() {
+                let color = self.color(x, y);
+                match format {
+                    PixelFormat::Rgba8(palette) => pixel
+                        .chunks_exact_mut(4)
+                        .for_each(|bytes| bytes.copy_from_slice(&palette[color as usize])),
+                    PixelFormat::Gray8 => pixel.fill(color * 0x55),
+                }
+            }
+            for copy in copies.chunks_exact_mut(row_length) {
+                copy.copy_from_slice(first);
+            }
+        }
+        Ok(())
+    }
+
+    /// Packs the visible pixels at 1 bit per pixel, for save states and network sync.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - A 3-byte header with the width, the height and the number of planes,
+    ///   then each plane row by row, top to bottom. Each row is `width / 8` bytes, the most
+    ///   significant bit of the first byte is the leftmost pixel.
+    ///
+    /// # Notes
+    ///
+    /// The second plane is only written when it has a lit pixel, so a 64x32 monochrome
+    /// display is 3 + 256 bytes long.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_transformed(Transform::None)
+    }
+
+    /// Packs the visible pixels at 1 bit per pixel, rotated or flipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `transform` - How the image is rotated or flipped.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The format of [`Display::to_bytes`], the header has the dimensions
+    ///   given by [`Transform::dimensions`].
+    ///
+    /// # Notes
+    ///
+    /// [`Display::from_bytes`] only loads untransformed data.
+    pub fn to_bytes_transformed(&self, transform: Transform) -> Vec<u8> {
+        let dimensions = self.dimensions();
+        let height = dimensions.1;
+        let lit = |rows: &Plane| rows[..height].iter().any(|&row| row != 0);
+        let last = self.planes.iter().rposition(lit);
+        let count = last.map_or(1, |plane| plane + 1);
+        let (width, height) = transform.dimensions(dimensions);
+        let mut bytes = vec![width as u8, height as u8, count as u8];
+        for rows in &self.planes[..count] {
+            for y in 0..height {
+                let row = (0..width).fold(0u128, |row, x| {
+                    let (source_x, source_y) = transform.source((x, y), dimensions);
+                    let pixel = (rows[source_y] >> (HIRES_WIDTH - 1 - source_x)) & 1;
+                    row | pixel << (HIRES_WIDTH - 1 - x)
+                });
+                bytes.extend_from_slice(&row.to_be_bytes()[..width / 8]);
+            }
+        }
+        bytes
+    }
+
+    /// Loads pixels packed by [`Display::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The packed pixels, with the dimensions of the current mode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the header does not match the current
+    ///   mode or the data does not have the size the header announces. The display is left
+    ///   unchanged on error.
+    ///
+    /// # Notes
+    ///
+    /// Planes missing from the data are cleared. The whole display is marked as changed, and
+    /// a double buffered display is presented.
+    pub fn from_bytes(&mut self, data: &[u8]) -> Result<(), EmulatorError> {
+        let (width, height) = self.dimensions();
+        let [data_width, data_height, count, pixels @ ..] = data else {
+            return Err(EmulatorError::InvalidDisplayData(
+                "The header is missing".to_string(),
+            ));
+        };
+        if (*data_width as usize, *data_height as usize) != (width, height) {
+            return Err(EmulatorError::InvalidDisplayData(format!(
+                "The data is {data_width}x{data_height}, the display is {width}x{height}"
+            )));
+        }
+        let count = *count as usize;
+        if !(1..=PLANES).contains(&count) {
+            return Err(EmulatorError::InvalidDisplayData(format!(
+                "The data has {count} planes, the display has {PLANES}"
+            )));
+        }
+        let expected = 3 + count * height * width / 8;
+        if data.len() != expected {
+            return Err(EmulatorError::InvalidBufferSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        self.planes = [EMPTY_PLANE; PLANES];
+        let mut rows = pixels.chunks_exact(width / 8);
+        for plane in &mut self.planes[..count] {
+            for (row, bytes) in plane.iter_mut().zip(rows.by_ref().take(height)) {
+                let mut packed = [0; 16];
+                packed[..bytes.len()].copy_from_slice(bytes);
+                *row = u128::from_be_bytes(packed);
+            }
+        }
+        self.touch_all();
+        self.present();
+        Ok(())
+    }
+
+    /// Converts the visible pixels to a plain (`P1`) PBM image, without any dependency.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The `P1` magic number and the dimensions on their own lines, then the
+    ///   pixels row by row, `1` for lit pixels and `0` for unlit ones.
+    ///
+    /// # Notes
+    ///
+    /// The output only depends on the pixels, so it can be compared to golden files. PBM
+    /// lines are at most 70 characters long, so rows are written 64 pixels per line
+    /// without separators, a high resolution row takes two lines.
+    pub fn to_pbm(&self) -> String {
+        let (width, height) = self.dimensions();
+        let mut pbm = format!("P1\n{width} {height}\n");
+        for row in self.rows() {
+            let digits: Vec<char> = row.map(|pixel| if pixel { '1' } else { '0' }).collect();
+            for line in digits.chunks(WIDTH) {
+                pbm.extend(line);
+                pbm.push('\n');
+            }
+        }
+        pbm
+    }
+
+    /// Saves the visible pixels as a PNG image.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the image, it is overwritten if it exists.
+    /// * `palette` - The color of each color index, see [`Display::color`].
+    /// * `scale` - The width and height in the image of each pixel, see
+    ///   [`Display::render_scaled`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error with the path if the image can not be
+    ///   written, or if the scale is 0.
+    #[cfg(feature = "screenshot")]
+    pub fn save_png(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        palette: &Palette,
+        scale: usize,
+    ) -> Result<(), EmulatorError> {
+        let path = path.as_ref();
+        let error = |reason: String| EmulatorError::ScreenshotError {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let (width, height) = self.dimensions();
+        let mut image = vec![0; width * scale * height * scale * 4];
+        self.render_scaled(&mut image, scale, PixelFormat::Rgba8(*palette))?;
+
+        let file = std::fs::File::create(path).map_err(|e| error(e.to_string()))?;
+        let (width, height) = ((width * scale) as u32, (height * scale) as u32);
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| error(e.to_string()))?;
+        writer
+            .write_image_data(&image)
+            .map_err(|e| error(e.to_string()))
+    }
+
+    /// Draws the visible pixels on an `embedded-graphics` target, like a small OLED screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The target, pixels lit on any plane are `On` and the others `Off`.
+    /// * `offset` - The position on the target of the top-left pixel.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), D::Error>` - The first error of the target, if any.
+    ///
+    /// # Notes
+    ///
+    /// Each row is drawn as runs of pixels of the same value, one `fill_solid` per run.
+    /// Unlit pixels are drawn too, so the target does not need to be cleared first.
+    #[cfg(feature = "embedded-graphics")]
+    pub fn draw_to<D>(&self, target: &mut D, offset: Point) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let (width, height) = self.dimensions();
+        for y in 0..height {
+            let row = self.get_row(y).unwrap_or(0);
+            let mut x = 0;
+            while x < width {
+                // The pixel at x is the most significant bit
+                let rest = row << x;
+                let lit = rest.leading_zeros() == 0;
+                let run = if lit {
+                    rest.leading_ones()
+                } else {
+                    rest.leading_zeros()
+                };
+                let run = (run as usize).min(width - x);
+                let area = Rectangle::new(
+                    offset + Point::new(x as i32, y as i32),
+                    Size::new(run as u32, 1),
+                );
+                target.fill_solid(&area, BinaryColor::from(lit))?;
+                x += run;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the visible pixels as text.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - How pixels are drawn with characters.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - One line per character row, each ending with `\n`.
+    ///
+    /// # Notes
+    ///
+    /// The size only depends on the style and the resolution. A 64x32 display is 64x32
+    /// characters in `Plain`, 64x16 in `HalfBlock` and 32x8 in `Braille`.
+    pub fn render_to_string(&self, style: TextStyle) -> String {
+        let (width, height) = self.dimensions();
+        let (cell_width, cell_height) = match style {
+            TextStyle::Plain => (1, 1),
+            TextStyle::HalfBlock => (1, 2),
+            TextStyle::Braille => (2, 4),
+        };
+        let lit = |x: usize, y: usize| x < width && y < height && self.get(x, y);
+        let mut text = String::new();
+        for top in (0..height).step_by(cell_height) {
+            for left in (0..width).step_by(cell_width) {
+                let character = match style {
+                    TextStyle::Plain if lit(left, top) => '#',
+                    TextStyle::Plain => '.',
+                    TextStyle::HalfBlock => match (lit(left, top), lit(left, top + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                    TextStyle::Braille => {
+                        let mut pattern = 0;
+                        for (x, dots) in BRAILLE_DOTS.iter().enumerate() {
+                            for (y, dot) in dots.iter().enumerate() {
+                                if lit(left + x, top + y) {
+                                    pattern |= dot;
+                                }
+                            }
+                        }
+                        char::from_u32(0x2800 + pattern).unwrap_or(' ')
+                    }
+                };
+                text.push(character);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Returns the video RAM of the first plane of the display, unpacked and indexed `[x][y]`.
+    ///
+    /// In low resolution mode only the top-left 64x32 pixels are used.
+    ///
+    /// # Returns
+    ///
+    /// * `[[bool; HIRES_HEIGHT]; HIRES_WIDTH]` - The video RAM of the first plane.
+    pub fn get_vram(&self) -> [[bool; HIRES_HEIGHT]; HIRES_WIDTH] {
+        let mut vram = [[false; HIRES_HEIGHT]; HIRES_WIDTH];
+        for (x, column) in vram.iter_mut().enumerate() {
+            for (y, pixel) in column.iter_mut().enumerate() {
+                *pixel = (self.visible()[0][y] >> (HIRES_WIDTH - 1 - x)) & 1 != 0;
+            }
+        }
+        vram
+    }
+}
+
+impl core::ops::Index<(usize, usize)> for Display {
+    type Output = bool;
+    
+    /// Returns the value of the pixel at the given coordinates.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    /// 
+    /// # Returns
+    /// 
+    /// * `&bool` - The value of the pixel.
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        if self.get(x, y) {
+            &true
+        } else {
+            &false
+        }
+    }
+    
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_display_eq;
+
+    /// Formats the visible pixels as rows of `#` and `.`.
+    fn grid(display: &Display) -> Vec<String> {
+        display
+            .rows()
+            .map(|row| row.map(|pixel| if pixel { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    /// Returns a low resolution display with a 8x2 pattern at (4, 1).
+    fn pattern() -> Display {
+        let mut display = Display::new();
+        display.set(4, 1, 0b1100_0011);
+        display.set(4, 2, 0b0011_1100);
+        display
+    }
+
+    #[test]
+    fn test_scroll_down() {
+        let mut display = pattern();
+        display.scroll_down(3);
+        assert_display_eq!(
+            display,
+            (0, 0),
+            "
+            ............
+            ............
+            ............
+            ............
+            ....##....##
+            ......####..
+            ............
+            "
+        );
+        assert_eq!(display.lit_pixels().count(), 8);
+
+        // Rows scrolled past the bottom edge are lost
+        display.scroll_down(27);
+        assert_display_eq!(display, (0, 31), "....##....##");
+        display.scroll_down(1);
+        assert!(display.get_vram().iter().flatten().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn test_scroll_horizontal() {
+        let mut display = pattern();
+        display.scroll_right4();
+        assert_display_eq!(
+            display,
+            (0, 1),
+            "
+            ........##....##
+            ..........####..
+            "
+        );
+
+        display.scroll_left4();
+        display.scroll_left4();
+        assert_display_eq!(
+            display,
+            (0, 1),
+            "
+            ##....##....
+            ..####......
+            "
+        );
+
+        // Columns scrolled past the left edge are lost
+        display.scroll_left4();
+        assert_display_eq!(display, (0, 1), "..##....");
+        assert!(!display.get(WIDTH - 1, 1));
+    }
+
+    #[test]
+    fn test_scroll_uses_current_resolution() {
+        let mut display = Display::new();
+        display.set_hires(true);
+        display.set(120, 0, 0xFF);
+        display.scroll_right4();
+        assert!(display.get(127, 0) && !display.get(128 - 9, 0));
+        display.scroll_down(60);
+        assert!(display.get(127, 60));
+    }
+
+    #[test]
+    fn test_lores_height() {
+        let mut display = Display::new();
+        display.set_lores_height(48);
+        display.reset();
+        assert_eq!(display.dimensions(), (64, 48));
+        display.set(0, 40, 0x80);
+        display.set(0, 48, 0x80);
+        assert!(display.get(0, 40) && display.get(0, 0));
+        display.scroll_down(8);
+        assert!(display.get(0, 8) && !display.get(0, 48));
+    }
+
+    #[test]
+    fn test_zone_colors() {
+        let mut display = Display::new();
+        display.set(8, 4, 0x80);
+        assert_eq!(display.chip8x_rgb(8, 4), CHIP8X_FOREGROUND[1]);
+        assert_eq!(display.chip8x_rgb(9, 4), CHIP8X_BACKGROUND[0]);
+
+        // Zones past the edges are ignored
+        display.set_zone_color(1..=9, 1..=1, 0xC);
+        assert_eq!(display.zone_color(8, 4), 4);
+        assert_eq!(display.zone_color(63, 7), 4);
+        assert_eq!(display.zone_color(7, 4), 1);
+        assert_eq!(display.zone_color(8, 8), 1);
+        assert_eq!(display.chip8x_rgb(8, 4), CHIP8X_FOREGROUND[4]);
+
+        for background in [1, 2, 3, 0] {
+            display.step_background();
+            assert_eq!(display.background(), background);
+        }
+    }
+
+    #[test]
+    fn test_planes() {
+        let mut display = Display::new();
+        display.set(0, 0, 0b1100_0000);
+        display.select_planes(2);
+        display.set(0, 0, 0b1010_0000);
+        assert_eq!([0, 1, 2, 3].map(|x| display.color(x, 0)), [3, 1, 2, 0]);
+        assert!(display.get(2, 0) && !display.get(3, 0));
+
+        // Sprites are drawn to every selected plane
+        display.select_planes(3);
+        display.set(0, 1, 0x80);
+        assert_eq!(display.color(0, 1), 3);
+        display.set(0, 1, 0x80);
+        assert_eq!(display.color(0, 1), 0);
+        display.set(0, 1, 0x80);
+
+        // Scrolling and clearing only affect the selected planes
+        display.select_planes(2);
+        display.scroll_down(1);
+        assert_eq!([0, 1, 2].map(|y| display.color(0, y)), [1, 3, 2]);
+        display.clear();
+        assert_eq!([0, 1, 2].map(|y| display.color(0, y)), [1, 1, 0]);
+        assert_eq!(display.color(2, 0), 0);
+
+        display.reset();
+        assert_eq!(display.selected(), 1);
+        assert!(!display.get(0, 0));
+    }
+
+    #[test]
+    fn test_clip() {
+        let mut display = Display::new();
+        assert_eq!(display.set_plane(0, 60, 0, 0xFF, true), 0);
+        assert!(display.get(60, 0) && display.get(63, 0));
+        assert!(!display.get(0, 0));
+        // Rows below the display are not drawn at all
+        display.set_plane(0, 0, 32, 0xFF, true);
+        assert!(!display.get(0, 0));
+
+        display.set_plane(0, 60, 1, 0xFF, false);
+        assert!(display.get(63, 1) && display.get(3, 1));
+    }
+
+    #[test]
+    fn test_pixel_order() {
+        let display = pattern();
+        // Row-major: the pixels of row 1 come before those of row 2
+        let lit: Vec<_> = display
+            .pixels()
+            .filter(|&(_, _, pixel)| pixel)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert_eq!(
+            lit,
+            [
+                (4, 1),
+                (5, 1),
+                (10, 1),
+                (11, 1),
+                (6, 2),
+                (7, 2),
+                (8, 2),
+                (9, 2)
+            ]
+        );
+        assert_eq!(display.pixels().count(), 64 * 32);
+        let (x, y, _) = display.pixels().last().unwrap();
+        assert_eq!((x, y), (63, 31));
+
+        let rows = grid(&display);
+        assert_eq!(rows.len(), 32);
+        assert!(rows.iter().all(|row| row.len() == 64));
+        assert_eq!(&rows[1][..12], "....##....##");
+        assert_eq!(&rows[2][..12], "......####..");
+        // Both iterators agree
+        let flattened: Vec<_> = display.rows().flatten().collect();
+        let pixels: Vec<_> = display.pixels().map(|(_, _, pixel)| pixel).collect();
+        assert_eq!(flattened, pixels);
+        // And the packed rows
+        let packed: Vec<_> = display.packed_rows().collect();
+        assert_eq!(packed.len(), 32);
+        assert_eq!(packed[1], 0b1100_0011 << 116);
+        assert_eq!(packed[2], 0b0011_1100 << 116);
+        for (packed, row) in packed.iter().zip(display.rows()) {
+            assert!(row
+                .enumerate()
+                .all(|(x, lit)| lit == (packed >> (127 - x) & 1 == 1)));
+        }
+    }
+
+    #[test]
+    fn test_collision() {
+        let mut display = Display::new();
+        // Drawing onto an empty screen is not a collision
+        assert_eq!(display.set(10, 5, 0b1010_0101), 0);
+        // Unlit sprite bits never collide
+        assert_eq!(display.set(10, 5, 0x00), 0);
+        assert_eq!(display.set(10, 5, 0b0101_1010), 0);
+        // Drawing the same sprite twice collides and leaves the screen blank
+        let mut display = Display::new();
+        assert_eq!(display.set(10, 5, 0xFF), 0);
+        assert_eq!(display.set(10, 5, 0xFF), 1);
+        assert!(display.pixels().all(|(_, _, pixel)| !pixel));
+        // One overlapping pixel is enough
+        display.set(10, 5, 0x01);
+        assert_eq!(display.set(17, 5, 0x80), 1);
+    }
+
+    #[test]
+    fn test_wrap_rows() {
+        // The wrapped part of a sprite lands at the left edge of the current resolution
+        for (hires, width) in [(false, 64), (true, 128)] {
+            let mut display = Display::new();
+            display.set_hires(hires);
+            display.set(width as u8 - 3, 2, 0b1011_0001);
+            let lit: Vec<_> = display
+                .pixels()
+                .filter(|&(_, _, pixel)| pixel)
+                .map(|(x, _, _)| x)
+                .collect();
+            assert_eq!(lit, [0, 4, width - 3, width - 1]);
+        }
+    }
+
+    #[test]
+    fn test_rgba8() {
+        use crate::constants::MONOCHROME_PALETTE;
+        let display = pattern();
+        let buffer = display.to_rgba8(&MONOCHROME_PALETTE);
+        assert_eq!(buffer.len(), WIDTH * HEIGHT * 4);
+        let offset = |x: usize, y: usize| (y * WIDTH + x) * 4;
+        assert_eq!(buffer[offset(4, 1)..][..4], [0xFF; 4]);
+        assert_eq!(buffer[offset(6, 1)..][..4], [0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(buffer[offset(6, 2)..][..4], [0xFF; 4]);
+
+        // The second plane takes the third color
+        let mut display = pattern();
+        display.select_planes(2);
+        display.set(4, 1, 0x80);
+        let palette = [[0; 4], [1; 4], [2; 4], [3; 4]];
+        let buffer = display.to_rgba8(&palette);
+        assert_eq!(buffer[offset(4, 1)..][..4], [3; 4]);
+        assert_eq!(buffer[offset(5, 1)..][..4], [1; 4]);
+
+        let mut buffer = vec![0; WIDTH * HEIGHT * 4];
+        assert!(display.write_rgba8(&mut buffer, &palette).is_ok());
+        assert_eq!(buffer, display.to_rgba8(&palette));
+        assert!(matches!(
+            display.write_rgba8(&mut buffer[1..], &palette),
+            Err(EmulatorError::InvalidBufferSize {
+                expected: 8192,
+                actual: 8191
+            })
+        ));
+
+        display.set_hires(true);
+        assert_eq!(display.to_rgba8(&palette).len(), 128 * 64 * 4);
+    }
+
+    #[test]
+    fn test_dirty_rows() {
+        let mut display = Display::new();
+        assert_eq!(display.take_dirty_rows(), RowMask::ALL);
+        assert!(display.take_dirty_rows().is_empty());
+
+        // A 3 rows sprite at row 30 wraps to row 0
+        for (y, value) in [(30, 0x80), (31, 0x80), (32, 0x80)] {
+            display.set(0, y, value);
+        }
+        let dirty = display.take_dirty_rows();
+        assert_eq!(dirty.iter().collect::<Vec<_>>(), [0, 30, 31]);
+        assert!(dirty.contains(30) && !dirty.contains(29));
+
+        // Erasing is a change too, even without lit sprite bits the row was drawn to
+        display.set(0, 5, 0x00);
+        assert_eq!(display.take_dirty_rows(), RowMask(1 << 5));
+
+        display.clear();
+        assert_eq!(display.take_dirty_rows(), RowMask::ALL);
+        display.scroll_left4();
+        assert_eq!(display.take_dirty_rows(), RowMask::ALL);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut display = pattern();
+        assert_eq!(display.take_diff().len(), 8);
+        assert!(display.take_diff().is_empty());
+
+        // Pixels turned off and on again are not changes
+        display.set(4, 1, 0x80);
+        display.set(4, 1, 0x80);
+        display.set(20, 3, 0x80);
+        display.set(4, 2, 0x20);
+        assert_eq!(display.take_diff(), [(6, 2, false), (20, 3, true)]);
+
+        display.clear();
+        assert_eq!(display.take_diff().len(), 8);
+        assert!(display.pixels().all(|(_, _, pixel)| !pixel));
+    }
+
+    #[test]
+    fn test_render_to_string() {
+        let display = pattern();
+        let plain = display.render_to_string(TextStyle::Plain);
+        let lines: Vec<_> = plain.lines().collect();
+        assert_eq!(lines.len(), 32);
+        assert!(lines.iter().all(|line| line.len() == 64));
+        assert_eq!(&lines[1][..12], "....##....##");
+        assert_eq!(&lines[2][..12], "......####..");
+        assert!(plain.ends_with(".\n"));
+
+        // Rows 0 and 1, then rows 2 and 3
+        let half = display.render_to_string(TextStyle::HalfBlock);
+        let lines: Vec<_> = half.lines().collect();
+        assert_eq!(lines.len(), 16);
+        assert!(lines.iter().all(|line| line.chars().count() == 64));
+        assert!(lines[0].starts_with("    ▄▄    ▄▄  "));
+        assert!(lines[1].starts_with("      ▀▀▀▀    "));
+
+        // Dots 2 and 5 are the second row of a cell, dots 3 and 6 the third one
+        let braille = display.render_to_string(TextStyle::Braille);
+        let lines: Vec<_> = braille.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines.iter().all(|line| line.chars().count() == 32));
+        assert!(lines[0].starts_with("\u{2800}\u{2800}\u{2812}\u{2824}\u{2824}\u{2812}\u{2800}"));
+        let blank = |line: &&str| line.chars().all(|c| c == '\u{2800}');
+        assert!(lines[1..].iter().all(blank));
+
+        let mut display = Display::new();
+        display.set_hires(true);
+        let braille = display.render_to_string(TextStyle::Braille);
+        assert_eq!(braille.lines().count(), 16);
+    }
+
+    #[test]
+    fn test_double_buffering() {
+        let mut display = Display::new();
+        display.set(4, 1, 0xFF);
+        display.set_double_buffered(true);
+        assert!(display.is_double_buffered());
+        assert!(display.get(4, 1));
+
+        // Draws only show up once presented, collisions still see the back buffer
+        assert_eq!(display.set(4, 1, 0xF0), 1);
+        display.set(4, 2, 0xFF);
+        assert!(display.get(4, 1) && !display.get(4, 2));
+        display.present();
+        assert!(!display.get(4, 1) && display.get(8, 1) && display.get(4, 2));
+
+        display.clear();
+        assert!(display.get(4, 2));
+        display.present();
+        assert!(display.pixels().all(|(_, _, pixel)| !pixel));
+
+        display.set_double_buffered(false);
+        display.set(4, 1, 0xFF);
+        assert!(display.get(4, 1));
+        display.present();
+        assert!(display.get(4, 1));
+    }
+
+    #[test]
+    fn test_try_get() {
+        let mut display = pattern();
+        assert_eq!(display.try_get(4, 1), Some(true));
+        assert_eq!(display.try_get(63, 31), Some(false));
+        for (x, y) in [(64, 0), (0, 32), (127, 63), (usize::MAX, usize::MAX)] {
+            assert_eq!(display.try_get(x, y), None);
+            assert_eq!(display.try_color(x, y), None);
+            assert!(!display.get(x, y) && !display[(x, y)]);
+            assert_eq!(display.color(x, y), 0);
+            display.chip8x_rgb(x, y);
+        }
+        display.set_hires(true);
+        assert_eq!(display.try_get(127, 63), Some(false));
+        assert_eq!(display.try_get(128, 63), None);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let display = pattern();
+        let bytes = display.to_bytes();
+        assert_eq!(bytes.len(), 3 + 256);
+        assert_eq!(bytes[..3], [64, 32, 1]);
+        // Row 1 starts with pixels 4 and 5, then 10 and 11
+        assert_eq!(bytes[3 + 8..3 + 10], [0b0000_1100, 0b0011_0000]);
+        let mut copy = Display::new();
+        copy.take_dirty_rows();
+        assert!(copy.from_bytes(&bytes).is_ok());
+        assert_eq!(copy.take_dirty_rows(), RowMask::ALL);
+        assert_eq!(copy.to_pbm(), display.to_pbm());
+
+        // Both planes in high resolution mode
+        let mut display = Display::new();
+        display.set_hires(true);
+        display.select_planes(0b10);
+        display.set(120, 63, 0xFF);
+        display.select_planes(0b01);
+        display.set(0, 0, 0x80);
+        let bytes = display.to_bytes();
+        assert_eq!(bytes.len(), 3 + 2 * 1024);
+        assert_eq!(bytes[..3], [128, 64, 2]);
+        let mut copy = Display::new();
+        copy.set_hires(true);
+        assert!(copy.from_bytes(&bytes).is_ok());
+        assert_eq!(copy.color(0, 0), 1);
+        assert_eq!(copy.color(127, 63), 2);
+        assert_eq!(copy.to_bytes(), bytes);
+
+        // ETI-660
+        let mut display = Display::new();
+        display.set_lores_height(48);
+        display.set(60, 47, 0xFF);
+        let mut copy = Display::new();
+        copy.set_lores_height(48);
+        assert!(copy.from_bytes(&display.to_bytes()).is_ok());
+        assert!(copy.get(63, 47) && copy.get(0, 47) && !copy.get(4, 47));
+    }
+
+    #[test]
+    fn test_bytes_invalid() {
+        let mut display = pattern();
+        let bytes = display.to_bytes();
+        let mut hires = Display::new();
+        hires.set_hires(true);
+        let invalid = [&bytes[..2], &bytes[..bytes.len() - 1], &[64, 32, 3]];
+        for data in invalid {
+            assert!(display.from_bytes(data).is_err());
+        }
+        assert!(matches!(
+            hires.from_bytes(&bytes),
+            Err(EmulatorError::InvalidDisplayData(_))
+        ));
+        assert!(matches!(
+            display.from_bytes(&bytes[..bytes.len() - 1]),
+            Err(EmulatorError::InvalidBufferSize { expected: 259, .. })
+        ));
+        // Nothing changed
+        assert_eq!(display.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_lit_pixels() {
+        let mut display = pattern();
+        let lit: Vec<_> = display.lit_pixels().collect();
+        assert_eq!(lit[..4], [(4, 1), (5, 1), (10, 1), (11, 1)]);
+        assert_eq!(lit[4..], [(6, 2), (7, 2), (8, 2), (9, 2)]);
+
+        // The corners, on both planes
+        display.set_hires(true);
+        display.set(0, 0, 0x80);
+        display.set(120, 0, 0x01);
+        display.select_planes(0b10);
+        display.set(0, 63, 0x80);
+        display.set(120, 63, 0x01);
+        let lit: Vec<_> = display.lit_pixels().collect();
+        assert_eq!(lit, [(0, 0), (127, 0), (0, 63), (127, 63)]);
+        let all = display.pixels().filter(|pixel| pixel.2);
+        assert!(all.map(|(x, y, _)| (x as u8, y as u8)).eq(lit));
+    }
+
+    #[test]
+    fn test_rows() {
+        let mut display = pattern();
+        assert_eq!(display.get_row(0), Some(0));
+        assert_eq!(display.get_row(1), Some(0b1100_0011 << 116));
+        assert_eq!(display.get_row(32), None);
+        for x in 0..WIDTH {
+            let bit = display.get_row(2).unwrap() >> (127 - x) & 1 != 0;
+            assert_eq!(bit, display.get(x, 2));
+        }
+
+        // Bits past the right edge are dropped
+        display.take_dirty_rows();
+        assert!(display.set_row(31, !0).is_ok());
+        assert_eq!(display.get_row(31), Some(width_mask(WIDTH)));
+        assert!(display.get(0, 31) && display.get(63, 31) && !display.get(64, 31));
+        assert_eq!(display.take_dirty_rows(), RowMask(1 << 31));
+        assert!(display.set_row(32, 1).is_err());
+
+        display.set_hires(true);
+        assert!(display.set_row(63, 1).is_ok());
+        assert!(display.get(127, 63));
+        assert_eq!(display.get_row(63), Some(1));
+    }
+
+    #[test]
+    fn test_transforms() {
+        // Pixels at (0, 0) and (1, 0), and (0, 31) on the second plane
+        let mut display = Display::new();
+        display.set(0, 0, 0xC0);
+        display.select_planes(0b10);
+        display.set(0, 31, 0x80);
+        let palette = [[0; 4], [1; 4], [2; 4], [3; 4]];
+        let transforms = [
+            (Transform::None, (64, 32), [(0, 0), (1, 0), (0, 31)]),
+            (Transform::Rot90, (32, 64), [(31, 0), (31, 1), (0, 0)]),
+            (Transform::Rot180, (64, 32), [(63, 31), (62, 31), (63, 0)]),
+            (Transform::Rot270, (32, 64), [(0, 63), (0, 62), (31, 63)]),
+            (Transform::FlipH, (64, 32), [(63, 0), (62, 0), (63, 31)]),
+            (Transform::FlipV, (64, 32), [(0, 31), (1, 31), (0, 0)]),
+        ];
+        for (transform, (width, height), lit) in transforms {
+            assert_eq!(transform.dimensions(display.dimensions()), (width, height));
+            let image = display.to_rgba8_transformed(&palette, transform);
+            let mut buffer = vec![0; width * height * 4];
+            assert!(display
+                .write_rgba8_transformed(&mut buffer, &palette, transform)
+                .is_ok());
+            assert_eq!(buffer, image);
+            let color = |(x, y): (usize, usize)| image[(y * width + x) * 4];
+            assert_eq!(lit.map(color), [1, 1, 2], "{:?}", transform);
+            assert_eq!(image.iter().filter(|&&byte| byte != 0).count(), 3 * 4);
+
+            let bytes = display.to_bytes_transformed(transform);
+            assert_eq!(bytes[..3], [width as u8, height as u8, 2]);
+            let planes = bytes[3..].chunks_exact(width * height / 8);
+            for (plane, (data, expected)) in planes.zip([&lit[..2], &lit[2..]]).enumerate() {
+                let bit = |&(x, y): &(usize, usize)| data[(y * width + x) / 8] << (x % 8) & 0x80;
+                assert!(expected.iter().all(|pixel| bit(pixel) != 0), "{plane}");
+                let count: u32 = data.iter().map(|byte| byte.count_ones()).sum();
+                assert_eq!(count as usize, expected.len());
+            }
+        }
+        let bytes = display.to_bytes();
+        assert_eq!(display.to_bytes_transformed(Transform::None), bytes);
+    }
+
+    #[test]
+    fn test_render_scaled() {
+        let mut display = pattern();
+        display.select_planes(0b10);
+        display.set(0, 0, 0x80);
+        let palette = [[0; 4], [1; 4], [2; 4], [3; 4]];
+
+        // Scale 1 is the plain export
+        let mut buffer = vec![0; 64 * 32 * 4];
+        let (rgba8, gray8) = (PixelFormat::Rgba8(palette), PixelFormat::Gray8);
+        assert!(display.render_scaled(&mut buffer, 1, rgba8).is_ok());
+        assert_eq!(buffer, display.to_rgba8(&palette));
+
+        for scale in [2, 3] {
+            let width = 64 * scale;
+            let mut gray = vec![0x11; width * 32 * scale];
+            assert!(display.render_scaled(&mut gray, scale, gray8).is_ok());
+            let mut rgba = vec![0; gray.len() * 4];
+            assert!(display.render_scaled(&mut rgba, scale, rgba8).is_ok());
+            for (index, &byte) in gray.iter().enumerate() {
+                let (x, y) = (index % width / scale, index / width / scale);
+                assert_eq!(byte, display.color(x, y) * 0x55, "{x} {y}");
+                assert_eq!(rgba[index * 4..index * 4 + 4], [display.color(x, y); 4]);
+            }
+            // The pixel at (0, 0) is lit on the second plane only
+            assert!(gray[..scale].iter().all(|&byte| byte == 0xAA));
+            assert_eq!(gray[scale], 0);
+        }
+
+        let mut buffer = vec![0; 128 * 64];
+        assert!(matches!(
+            display.render_scaled(&mut buffer, 0, gray8),
+            Err(EmulatorError::InvalidScale(0))
+        ));
+        assert!(matches!(
+            display.render_scaled(&mut buffer[1..], 2, gray8),
+            Err(EmulatorError::InvalidBufferSize { expected, .. }) if expected == 128 * 64
+        ));
+    }
+
+    #[test]
+    fn test_version() {
+        let mut display = Display::new();
+        #[allow(deprecated)]
+        let updated = display.updated();
+        assert!(updated);
+        assert!(display.take_updated());
+        assert!(!display.take_updated());
+        #[allow(deprecated)]
+        let updated = display.updated();
+        assert!(!updated);
+
+        // Two consumers polling the version both see every change
+        let (mut renderer, mut recorder) = (display.version(), display.version());
+        display.set(0, 0, 0x80);
+        assert!(display.take_updated());
+        assert_ne!(display.version(), renderer);
+        renderer = display.version();
+        display.clear();
+        assert_ne!(display.version(), renderer);
+        assert_ne!(display.version(), recorder);
+        recorder = display.version();
+        assert_eq!(display.version(), recorder);
+
+        // An empty sprite row does not change the version
+        assert_eq!(display.set(0, 5, 0), 0);
+        assert_eq!(display.version(), recorder);
+
+        // Versions only grow, even through a reset
+        display.reset();
+        assert!(display.version() > recorder);
+        assert!(display.take_updated());
+
+        // A double buffered display changes when a new frame is presented
+        display.set_double_buffered(true);
+        display.present();
+        let version = display.version();
+        display.present();
+        assert_eq!(display.version(), version);
+    }
+
+    #[test]
+    fn test_to_pbm() {
+        let display = pattern();
+        let pbm = display.to_pbm();
+        let lines: Vec<_> = pbm.lines().collect();
+        assert_eq!(lines[..2], ["P1", "64 32"]);
+        assert_eq!(lines.len(), 2 + 32);
+        assert_eq!(lines[3], format!("000011000011{}", "0".repeat(52)));
+        assert_eq!(lines[4], format!("000000111100{}", "0".repeat(52)));
+        assert!(pbm.ends_with("0\n"));
+        assert_eq!(pbm.len(), "P1\n64 32\n".len() + 32 * 65);
+
+        // High resolution rows are split in two lines of 64 pixels
+        let mut display = Display::new();
+        display.set_hires(true);
+        display.set(120, 0, 0x01);
+        let pbm = display.to_pbm();
+        let lines: Vec<_> = pbm.lines().collect();
+        assert_eq!(lines[1], "128 64");
+        assert_eq!(lines.len(), 2 + 128);
+        assert!(lines[2..].iter().all(|line| line.len() == 64));
+        assert!(lines[3].ends_with("01"));
+    }
+
+    #[cfg(feature = "screenshot")]
+    #[test]
+    fn test_save_png() {
+        use crate::constants::MONOCHROME_PALETTE;
+        let display = pattern();
+        let path = std::env::temp_dir().join("r8_test_save_png.png");
+        assert!(display.save_png(&path, &MONOCHROME_PALETTE, 2).is_ok());
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        std::fs::remove_file(&path).unwrap();
+
+        let path = std::env::temp_dir().join("r8_missing/shot.png");
+        let error = display.save_png(&path, &MONOCHROME_PALETTE, 1).unwrap_err();
+        assert!(error.to_string().contains("shot.png"));
+    }
+
+    #[cfg(feature = "embedded-graphics")]
+    #[test]
+    fn test_draw_to() {
+        use embedded_graphics::mock_display::MockDisplay;
+
+        let mut display = pattern();
+        display.set(56, 31, 0x01);
+        let mut target: MockDisplay<BinaryColor> = MockDisplay::new();
+        assert!(display.draw_to(&mut target, Point::new(0, 32)).is_ok());
+        for (x, y, lit) in display.pixels() {
+            let point = Point::new(x as i32, y as i32 + 32);
+            assert_eq!(target.get_pixel(point), Some(lit.into()), "{x} {y}");
+        }
+        assert_eq!(target.get_pixel(Point::new(63, 63)), Some(BinaryColor::On));
+        // Nothing is drawn above the offset
+        assert_eq!(target.get_pixel(Point::new(0, 31)), None);
+    }
+}
@@ -0,0 +1,239 @@
+//! Debugger widgets for egui, built with the `egui` feature.
+//!
+//! Each widget is a function drawing into a `Ui`, to put in any window or panel of an
+//! eframe app, see `examples/debugger.rs`:
+//!
+//! ```ignore
+//! egui::SidePanel::right("debugger").show(ctx, |ui| {
+//!     r8::egui::controls(&mut emulator, ui, &mut paused, 12)?;
+//!     r8::egui::registers(&mut emulator, ui);
+//!     r8::egui::disassembly(&mut emulator, ui, 16);
+//! });
+//! ```
+//!
+//! The widgets edit the emulator through the setters of the `debug` module, the app runs
+//! the frames with `Emulator::run_until_breakpoint` while it is not paused.
+
+use ::egui::{Button, Color32, DragValue, Grid, RichText, Ui};
+
+use crate::{
+    constants::REGISTER_COUNT,
+    emulator::Emulator,
+    error::EmulatorError,
+    memory::Address,
+    opcode::{decode_iter, Opcode},
+    register::RegisterIndex,
+};
+
+/// The frames a step over runs before giving up on the subroutine, 10 seconds.
+pub const STEP_OVER_FRAMES: u32 = 600;
+
+/// The rows of 16 bytes shown by [`memory`].
+pub const MEMORY_ROWS: u16 = 16;
+
+/// The marker of the lines with a breakpoint.
+const BREAKPOINT: &str = "●";
+
+/// The color of the breakpoint markers.
+const BREAKPOINT_COLOR: Color32 = Color32::from_rgb(0xE0, 0x40, 0x40);
+
+/// Draws the play, pause, step and step over buttons.
+///
+/// # Arguments
+///
+/// * `emulator` - The emulator.
+/// * `ui` - The UI to draw in.
+/// * `paused` - Whether the app runs the frames, toggled by the buttons.
+/// * `instructions` - The instructions per frame, see `Emulator::run_frame`.
+///
+/// # Returns
+///
+/// * `Result<(), EmulatorError>` - The error of a step, the emulator is paused then.
+///
+/// # Notes
+///
+/// Play steps past a breakpoint at the program counter first, so `run_until_breakpoint`
+/// does not stop there again. The steps only run while paused.
+pub fn controls(
+    emulator: &mut Emulator,
+    ui: &mut Ui,
+    paused: &mut bool,
+    instructions: u32,
+) -> Result<(), EmulatorError> {
+    let mut result = Ok(());
+    ui.horizontal(|ui| {
+        if *paused {
+            if ui.button("▶ Play").clicked() {
+                *paused = false;
+                if emulator.has_breakpoint(emulator.pc()) {
+                    result = emulator.step();
+                }
+            }
+        } else if ui.button("⏸ Pause").clicked() {
+            *paused = true;
+        }
+        if ui.add_enabled(*paused, Button::new("Step")).clicked() {
+            result = emulator.step();
+        }
+        if ui.add_enabled(*paused, Button::new("Step over")).clicked() {
+            result = emulator
+                .step_over(instructions, STEP_OVER_FRAMES)
+                .map(|_| ());
+        }
+        ui.label(format!("Frame {}", emulator.frame()));
+    });
+    if result.is_err() {
+        *paused = true;
+    }
+    result
+}
+
+/// Draws the registers and timers, each one editable with a drag or a double click.
+///
+/// # Arguments
+///
+/// * `emulator` - The emulator.
+/// * `ui` - The UI to draw in.
+pub fn registers(emulator: &mut Emulator, ui: &mut Ui) {
+    let last = (emulator.memory.size() - 1) as u16;
+    Grid::new("r8_registers")
+        .num_columns(8)
+        .striped(true)
+        .show(ui, |ui| {
+            let mut pc = emulator.pc().inner();
+            ui.label("PC");
+            if ui.add(hex(&mut pc, 4).clamp_range(0..=last)).changed() {
+                // In range of the memory
+                let _ = emulator.set_pc(pc);
+            }
+            let mut i = emulator.i().inner();
+            ui.label("I");
+            if ui.add(hex(&mut i, 4)).changed() {
+                emulator.set_i(i);
+            }
+            let mut delay = emulator.delay_timer();
+            ui.label("DT");
+            if ui.add(DragValue::new(&mut delay)).changed() {
+                emulator.set_delay_timer(delay);
+            }
+            let mut sound = emulator.sound_timer();
+            ui.label("ST");
+            if ui.add(DragValue::new(&mut sound)).changed() {
+                emulator.set_sound_timer(sound);
+            }
+            ui.end_row();
+
+            for index in 0..REGISTER_COUNT as u8 {
+                let mut value = emulator.v_registers()[RegisterIndex::new(index)];
+                ui.label(format!("V{index:X}"));
+                if ui.add(hex(&mut value, 2)).changed() {
+                    // The index is below REGISTER_COUNT
+                    let _ = emulator.set_register(index, value);
+                }
+                if index % 4 == 3 {
+                    ui.end_row();
+                }
+            }
+        });
+    ui.label(format!("State: {:?}", emulator.state()));
+}
+
+/// Draws the instructions around the program counter, a click on the left column toggles
+/// a breakpoint.
+///
+/// # Arguments
+///
+/// * `emulator` - The emulator.
+/// * `ui` - The UI to draw in.
+/// * `lines` - The instructions shown, about half of them before the program counter.
+///
+/// # Notes
+///
+/// The instructions before the program counter are decoded from an even number of bytes
+/// before it, data mixed with the code may shift them.
+pub fn disassembly(emulator: &mut Emulator, ui: &mut Ui, lines: u16) {
+    let pc = emulator.pc();
+    let start = pc.inner().saturating_sub(lines / 2 * 2);
+    // The long load is 4 bytes, room for it on every line
+    let end = (start as usize + lines as usize * 4)
+        .min(emulator.memory.size())
+        .min(u16::MAX as usize) as u16;
+    let Ok(bytes) = emulator.memory_view(start..end) else {
+        return;
+    };
+    let listing: Vec<(Address, Opcode)> = decode_iter(bytes, Address::new_long(start))
+        .take(lines as usize)
+        .collect();
+    for (address, opcode) in listing {
+        ui.horizontal(|ui| {
+            let marker = match emulator.has_breakpoint(address) {
+                true => RichText::new(BREAKPOINT).color(BREAKPOINT_COLOR),
+                false => RichText::new(" "),
+            };
+            if ui.selectable_label(false, marker.monospace()).clicked() {
+                emulator.toggle_breakpoint(address);
+            }
+            let line = RichText::new(opcode.format_at(address)).monospace();
+            ui.label(match address == pc {
+                true => line.background_color(ui.visuals().selection.bg_fill),
+                false => line,
+            });
+        });
+    }
+}
+
+/// Draws a hex dump of [`MEMORY_ROWS`] rows of memory.
+///
+/// # Arguments
+///
+/// * `emulator` - The emulator.
+/// * `ui` - The UI to draw in.
+/// * `start` - The first address shown, edited by the address field above the dump.
+pub fn memory(emulator: &mut Emulator, ui: &mut Ui, start: &mut u16) {
+    let size = emulator.memory.size();
+    ui.horizontal(|ui| {
+        ui.label("Address");
+        ui.add(hex(start, 4).clamp_range(0..=size - 1));
+        if ui.button("PC").clicked() {
+            *start = emulator.pc().inner();
+        }
+        if ui.button("I").clicked() {
+            *start = emulator.i().inner();
+        }
+    });
+    let first = (*start as usize).min(size - 1) & !0xF;
+    // The last byte of a 64KB memory is beyond a range of u16
+    let end = (first + MEMORY_ROWS as usize * 16)
+        .min(size)
+        .min(u16::MAX as usize);
+    if let Ok(dump) = emulator.hexdump(first as u16..end as u16) {
+        ui.label(RichText::new(dump.trim_end()).monospace());
+    }
+}
+
+/// Draws the backtrace, the program counter then the calls on the stack, innermost first.
+///
+/// # Arguments
+///
+/// * `emulator` - The emulator.
+/// * `ui` - The UI to draw in.
+pub fn stack(emulator: &mut Emulator, ui: &mut Ui) {
+    let stack = emulator.stack();
+    ui.label(format!("Depth {}/{}", stack.len(), stack.depth()));
+    ui.label(RichText::new(format!("#0  0x{:04X}", emulator.pc().inner())).monospace());
+    for (frame, address) in stack.iter().rev().enumerate() {
+        // The return address follows the call
+        let call = address.inner().wrapping_sub(2);
+        let line = format!(
+            "#{}  0x{call:04X}  returns to 0x{:04X}",
+            frame + 1,
+            address.inner()
+        );
+        ui.label(RichText::new(line).monospace());
+    }
+}
+
+/// Returns an uppercase hexadecimal drag value of `width` digits.
+fn hex<T: ::egui::emath::Numeric>(value: &mut T, width: usize) -> DragValue<'_> {
+    DragValue::new(value).hexadecimal(width, false, true)
+}
@@ -1,24 +1,77 @@
+use alloc::collections::BTreeSet;
+use core::ops::Range;
+#[cfg(feature = "std")]
 use std::io::Read;
 
-use log::{debug, error};
+use log::{debug, error, log_enabled, warn, Level};
 
 use crate::{
-    display::Display,
-    error::EmulatorError,
-    keyboard::{self, KeyBoard},
-    memory::{Address, Memory},
+    audio::{Audio, PATTERN_SIZE},
+    compat::RomHash,
+    constants::{RPL_FLAG_COUNT, STACK_SIZE, ZONE_HEIGHT, ZONE_WIDTH},
+    display::{Display, RowMask},
+    error::{EmulatorError, Fault},
+    hooks::{HookId, MemoryHooks, MemoryWrite},
+    keyboard::{self, KeyBoard, KeyEvent},
+    memory::{Address, FontSet, Memory, MEMORY_SIZE},
     opcode::Opcode,
-    rand::RandGen,
+    persistence::PersistenceHook,
+    prelude::*,
+    quirks::{IndexIncrement, Profile, Quirks, PRESETS},
+    rand::{RandGen, RngSource},
     register::{RegisterIndex, VRegisters},
+    replay::{Recording, Replay},
+    rom::{self, Variant},
     stack::Stack, timer::Timer,
 };
+#[cfg(feature = "std")]
+use crate::{
+    compat::{self, Recommendation},
+    keyboard::SharedKeyboard,
+    memory,
+    source_map::SourceMap,
+};
+
+/// The version of the save state format, bumped on incompatible changes.
+const SAVE_STATE_VERSION: u8 = 7;
 
 /// Represents the state of the emulator.
+///
+/// `WaitingKey` follows `FX0A`, `held` are the keys already pressed when the wait began
+/// (bit n is key n), they only complete the wait once released and pressed again.
+///
+/// `WaitingVblank` follows a sprite drawn with the `display_wait` quirk, no instruction runs
+/// until the next vertical blank, see [`Emulator::tick_timers`].
 #[derive(Debug)]
 pub enum State {
     New,
     Running,
-    WaitingKey { x: RegisterIndex },
+    WaitingKey { x: RegisterIndex, held: u16 },
+    WaitingVblank,
+}
+
+/// The settings an `Emulator` is created with, see [`Emulator::with_config`].
+///
+/// # Fields
+///
+/// * `profile` - The interpreter the program targets, its preset sets the quirks, the
+///   memory and the display.
+/// * `stack_depth` - The number of nested calls the stack holds, at least 1. The COSMAC VIP
+///   held 12, `usize::MAX` has no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmulatorConfig {
+    pub profile: Profile,
+    pub stack_depth: usize,
+}
+
+impl Default for EmulatorConfig {
+    /// The default profile with a stack of 16 calls.
+    fn default() -> Self {
+        Self {
+            profile: Profile::default(),
+            stack_depth: STACK_SIZE,
+        }
+    }
 }
 
 /// The `Emulator` struct represents the CHIP-8 emulator.
@@ -34,8 +87,26 @@ pub enum State {
 /// * `memory` - The memory.
 /// * `display` - The display.
 /// * `keyboard` - The keyboard.
+/// * `keyboard2` - The second keypad of CHIP-8X.
+/// * `shared_keyboard` - The queue other threads push key events to, if any.
+/// * `audio` - The sound generator, with the XO-CHIP pattern buffer and pitch.
+/// * `rpl_flags` - The SUPER-CHIP RPL user flags.
+/// * `persistence` - The hook that keeps the RPL user flags between sessions, if any.
+/// * `memory_hooks` - The callbacks notified of memory writes.
 /// * `rand` - The random number generator.
 /// * `state` - The state of the emulator.
+/// * `frame` - The number of vertical blanks run so far.
+/// * `instructions` - The number of instructions run so far.
+/// * `strict` - Whether invalid opcodes stop the emulator.
+/// * `write_protection` - Whether the program can not write below `0x200`.
+/// * `normalize_roms` - Whether `load_rom` removes the zero padding of ROMs.
+/// * `profile` - The interpreter the program targets.
+/// * `quirks` - The behaviors that differ between interpreters.
+/// * `source_map` - The source line map of the loaded ROM, if any.
+/// * `breakpoints` - The addresses `run_until_breakpoint` stops at.
+/// * `rom_hash` - The hashes of the loaded ROM.
+/// * `recording` - The input recording in progress, if any.
+/// * `replay` - The input recording being played back, if any.
 pub struct Emulator {
     // Registers
     pub(crate) pc: Address,
@@ -49,9 +120,32 @@ pub struct Emulator {
     // Devices
     pub(crate) display: Display,
     pub(crate) keyboard: KeyBoard,
+    pub(crate) keyboard2: KeyBoard,
+    #[cfg(feature = "std")]
+    pub(crate) shared_keyboard: Option<SharedKeyboard>,
+    pub(crate) audio: Audio,
+    pub(crate) rpl_flags: [u8; RPL_FLAG_COUNT],
+    pub(crate) persistence: Option<Box<dyn PersistenceHook>>,
+    pub(crate) memory_hooks: MemoryHooks,
     // Helper Structs
-    pub(crate) rand: RandGen,
+    pub(crate) rand: Box<dyn RngSource>,
     pub(crate) state: State,
+    pub(crate) frame: u64,
+    pub(crate) instructions: u64,
+    // Settings
+    pub(crate) strict: bool,
+    pub(crate) write_protection: bool,
+    pub(crate) normalize_roms: bool,
+    pub(crate) profile: Profile,
+    pub(crate) quirks: Quirks,
+    // Debugging
+    #[cfg(feature = "std")]
+    pub(crate) source_map: Option<SourceMap>,
+    pub(crate) breakpoints: BTreeSet<Address>,
+    // Input Recording
+    pub(crate) rom_hash: RomHash,
+    pub(crate) recording: Option<Recording>,
+    pub(crate) replay: Option<Replay>,
 }
 
 impl Emulator {
@@ -71,11 +165,61 @@ impl Emulator {
             memory: Memory::new(),
             display: Display::new(),
             keyboard: KeyBoard::default(),
-            rand: RandGen::new(),
+            keyboard2: KeyBoard::default(),
+            #[cfg(feature = "std")]
+            shared_keyboard: None,
+            audio: Audio::new(),
+            rpl_flags: [0; RPL_FLAG_COUNT],
+            persistence: None,
+            memory_hooks: MemoryHooks::default(),
+            rand: Box::new(RandGen::new()),
             state: State::New,
+            frame: 0,
+            instructions: 0,
+            strict: false,
+            write_protection: false,
+            normalize_roms: false,
+            profile: Profile::default(),
+            quirks: Quirks::default(),
+            #[cfg(feature = "std")]
+            source_map: None,
+            breakpoints: BTreeSet::new(),
+            rom_hash: RomHash::new(&[]),
+            recording: None,
+            replay: None,
         }
     }
 
+    /// Creates a new `Emulator` for the interpreter a program targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The profile, its preset sets the quirks, the memory and the display.
+    ///
+    /// # Returns
+    ///
+    /// * `Emulator` - The newly created emulator.
+    pub fn with_profile(profile: Profile) -> Self {
+        let mut emulator = Self::new();
+        emulator.set_profile(profile);
+        emulator
+    }
+
+    /// Creates a new `Emulator` with its settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The profile and the depth of the stack.
+    ///
+    /// # Returns
+    ///
+    /// * `Emulator` - The newly created emulator.
+    pub fn with_config(config: EmulatorConfig) -> Self {
+        let mut emulator = Self::with_profile(config.profile);
+        emulator.stack = Stack::with_depth(config.stack_depth);
+        emulator
+    }
+
     /// Loads a ROM into the emulator.
     ///
     /// # Arguments
@@ -84,24 +228,243 @@ impl Emulator {
     ///
     /// # Returns
     ///
-    /// * `Result<(), RuntimeError>` - The result of the operation.
+    /// * `Result<(), RuntimeError>` - `EmptyRom` if the reader has no data, `RomTooLarge` if
+    ///   the ROM does not fit between the entry point and the end of the memory, or an error
+    ///   if it can not be read. The emulator is left untouched then.
+    ///
+    /// # Notes
+    ///
+    /// Only the bytes that fit are read, then the ROM is loaded as with
+    /// [`Emulator::load_rom_bytes`].
+    #[cfg(feature = "std")]
+    pub fn load_rom<R: Read>(&mut self, mut reader: R) -> Result<(), EmulatorError> {
+        let max = self.memory.size() - self.profile.entry_point().inner() as usize;
+        // One byte more than fits, to tell ROMs that do not fit apart
+        let limit = match self.normalize_roms {
+            true => u64::MAX,
+            false => max as u64 + 1,
+        };
+        let mut bytes = Vec::new();
+        reader
+            .by_ref()
+            .take(limit)
+            .read_to_end(&mut bytes)
+            .map_err(EmulatorError::LoadError)?;
+        match self.load_rom_bytes(&bytes) {
+            Err(EmulatorError::RomTooLarge { len, max }) => {
+                let len = len + memory::remaining(&mut reader)?;
+                Err(EmulatorError::RomTooLarge { len, max })
+            }
+            result => result,
+        }
+    }
+
+    /// Loads a ROM into the emulator from memory, e.g. a ROM embedded with `include_bytes!`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the ROM.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RuntimeError>` - `EmptyRom` if the ROM is empty, or `RomTooLarge` if it
+    ///   does not fit between the entry point and the end of the memory. The emulator is left
+    ///   untouched then.
     ///
     /// # Notes
     ///
-    /// * The emulator is reset to its initial state.
-    pub fn load_rom<R: Read>(&mut self, reader: R) -> Result<(), EmulatorError> {
-        self.pc = Address::ENTRY_POINT;
+    /// * The emulator is reset to its initial state, except for the RPL user flags.
+    /// * The ROM is loaded at the entry point of the profile, `0x200` or `0x600` for ETI-660.
+    /// * ROMs with an odd length are common and load normally, a warning is logged.
+    /// * With `set_normalize_roms`, the zero padding is removed first, see
+    ///   [`rom::normalize`]. Padding past the end of the memory is accepted then.
+    pub fn load_rom_bytes(&mut self, bytes: &[u8]) -> Result<(), EmulatorError> {
+        if bytes.is_empty() {
+            return Err(EmulatorError::EmptyRom);
+        }
+        let normalized;
+        let bytes = match self.normalize_roms {
+            true => {
+                normalized = rom::normalize(bytes);
+                let removed = normalized.removed;
+                if removed > 0 {
+                    debug!("Removed {removed} bytes of padding from the ROM");
+                }
+                normalized.bytes.as_slice()
+            }
+            false => bytes,
+        };
+        let max = self.memory.size() - self.profile.entry_point().inner() as usize;
+        if bytes.len() > max {
+            let len = bytes.len();
+            return Err(EmulatorError::RomTooLarge { len, max });
+        }
+        if !bytes.len().is_multiple_of(2) {
+            warn!("The ROM has an odd length of {} bytes", bytes.len());
+        }
+        if !self.profile.supports(Variant::MegaChip)
+            && rom::detect_variant(bytes).uses(Variant::MegaChip)
+        {
+            warn!("The ROM enters MEGA-CHIP mode, which is not supported, MEGAON runs as SYS");
+        }
+        self.reset(bytes)
+    }
+
+    /// Resets the emulator and loads a ROM that fits at the entry point.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the ROM, it may be empty.
+    fn reset(&mut self, bytes: &[u8]) -> Result<(), EmulatorError> {
+        self.pc = self.profile.entry_point();
         self.i = Address::new(0);
         self.delay_timer = Timer::new();
         self.sound_timer = Timer::new();
+        self.audio = Audio::new();
         self.registers = VRegisters::default();
         self.stack.clear();
-        self.display.clear();
-        self.memory.load_rom(reader)?;
+        self.display.reset();
+        self.memory.load_rom_bytes_at(bytes, self.pc)?;
+        self.rom_hash = RomHash::new(bytes);
         self.state = State::Running;
         Ok(())
     }
 
+    /// Loads a ROM listed as hex bytes, see [`rom::from_hex_text`].
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The listing, e.g. pasted from a tutorial.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the listing is invalid or does not fit in
+    ///   memory.
+    pub fn load_rom_hex_text(&mut self, text: &str) -> Result<(), EmulatorError> {
+        self.load_rom_bytes(&rom::from_hex_text(text)?)
+    }
+
+    /// Loads a ROM from an Intel HEX image, see [`rom::from_ihex`].
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The image.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the image is invalid, empty or does not
+    ///   fit in memory.
+    ///
+    /// # Notes
+    ///
+    /// The emulator is reset as with `load_rom`, then the image is loaded at its origin,
+    /// which may differ from the entry point. The program still starts at the entry point.
+    pub fn load_rom_ihex(&mut self, text: &str) -> Result<(), EmulatorError> {
+        let (bytes, origin) = rom::from_ihex(text)?;
+        if bytes.is_empty() {
+            return Err(EmulatorError::EmptyRom);
+        }
+        self.reset(&[])?;
+        self.memory.load_at(self.memory.address(origin)?, &bytes)?;
+        self.rom_hash = RomHash::new(&bytes);
+        Ok(())
+    }
+
+    /// Returns the identity of the loaded ROM.
+    ///
+    /// # Notes
+    ///
+    /// The hashes cover the bytes read by `load_rom`, the decoded image for
+    /// `load_rom_ihex`. They do not change when the program modifies itself or with
+    /// `load_at`.
+    pub fn rom_hash(&self) -> RomHash {
+        self.rom_hash
+    }
+
+    /// Loads data at a given address without resetting the emulator, see [`Memory::load_at`].
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the first byte.
+    /// * `data` - The data to load, e.g. the second part of a ROM or a patch.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `LoadOutOfBounds` if the data does not fit, in which
+    ///   case the memory is unchanged.
+    pub fn load_at(&mut self, address: Address, data: &[u8]) -> Result<(), EmulatorError> {
+        self.memory.load_at(address, data)
+    }
+
+    /// Loads a font for `FX29`, see [`Memory::load_font`].
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The font, e.g. [`FontSet::rounded`].
+    /// * `base` - The address of the font.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `FontCollision` if the fonts would overlap the ROM.
+    ///
+    /// # Notes
+    ///
+    /// The font is kept for the next ROMs and across profile changes.
+    pub fn set_font(&mut self, font: FontSet, base: Address) -> Result<(), EmulatorError> {
+        self.memory.load_font(&font, base)
+    }
+
+    /// Returns the address of the font `FX29` points into.
+    pub fn font_base(&self) -> Address {
+        self.memory.font_base()
+    }
+
+    /// Returns where the ROM lives, from the first to the last byte loaded by
+    /// [`Emulator::load_rom`] and [`Emulator::load_at`] since.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Range<usize>>` - The range, or `None` if nothing was loaded.
+    ///
+    /// # Notes
+    ///
+    /// The range covers the gaps between separate loads, see [`Memory::loaded_ranges`] for
+    /// the individual ranges.
+    pub fn loaded_range(&self) -> Option<Range<usize>> {
+        let ranges = self.memory.loaded_ranges();
+        Some(ranges.first()?.start..ranges.last()?.end)
+    }
+
+    /// Loads a ROM into the emulator with the profile and quirks recommended for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the ROM from.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Recommendation, EmulatorError>` - The recommendation applied, or an error if
+    ///   the ROM could not be read or loaded.
+    ///
+    /// # Notes
+    ///
+    /// * The profile and quirks are replaced, see [`compat::recommend`].
+    #[cfg(feature = "std")]
+    pub fn load_rom_with_autodetect<R: Read>(
+        &mut self,
+        mut reader: R,
+    ) -> Result<Recommendation, EmulatorError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(EmulatorError::LoadError)?;
+        let recommendation = compat::recommend(&bytes);
+        self.set_profile(recommendation.profile);
+        self.set_quirks(recommendation.quirks);
+        self.load_rom_bytes(&bytes)?;
+        Ok(recommendation)
+    }
+
     /// Executes a single tick of the emulator.
     ///
     /// # Returns
@@ -113,33 +476,162 @@ impl Emulator {
     /// * If the emulator is in the `State::New` state, this function does nothing.
     /// * If the emulator is in the `State::WaitingKey` state and the keyboard is not pressed, this function does nothing.
     /// * If the emulator is in the `State::WaitingKey` state and the keyboard is pressed, the state is changed to `State::Running`.
+    /// * Each tick is also a vertical blank, the timers are decremented. If the emulator is in the
+    ///   `State::WaitingVblank` state, the tick only does that and no instruction runs.
+    /// * The key events pushed to the shared keyboard are queued, and the key events pushed
+    ///   up to the current frame are applied first, then the keyboard
+    ///   state is recorded or replaced by the replay, see [`Emulator::start_recording`].
+    #[inline]
     pub fn tick(&mut self) -> Result<(), EmulatorError> {
+        self.cycle(true)
+    }
+
+    /// Executes a single instruction without a vertical blank, the timers are left as is.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RuntimeError>` - The result of the operation.
+    ///
+    /// # Notes
+    ///
+    /// The queued key events are not applied, they wait for the next `tick`, a key tapped
+    /// between two frames is seen by every instruction of the next frame. In the
+    /// `State::WaitingVblank` state nothing runs until the next `tick`. See [`Emulator::run_frame`] to run more than 60
+    /// instructions per second with 60Hz timers.
+    #[inline]
+    pub fn step(&mut self) -> Result<(), EmulatorError> {
+        self.cycle(false)
+    }
+
+    /// Runs a 60Hz frame: one tick, then the remaining instructions as steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - The instructions run in the frame, at least 1. At 60 frames per
+    ///   second, 700 instructions per second are 12 per frame.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RuntimeError>` - The first error, the rest of the frame does not run.
+    ///
+    /// # Notes
+    ///
+    /// `run_frame(1)` is a `tick`. The timers are decremented once whatever the number of
+    /// instructions, as a frontend drawing at 60Hz expects.
+    pub fn run_frame(&mut self, instructions: u32) -> Result<(), EmulatorError> {
+        self.tick()?;
+        for _ in 1..instructions {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Runs an instruction, after a vertical blank if `vblank`, see [`Emulator::tick`].
+    #[inline(always)]
+    fn cycle(&mut self, vblank: bool) -> Result<(), EmulatorError> {
+        // The keys change once per frame, so every instruction of a frame sees the same keys
+        if vblank {
+            #[cfg(feature = "std")]
+            if let Some(shared) = &self.shared_keyboard {
+                for event in shared.drain() {
+                    self.keyboard.push(self.frame, event);
+                }
+            }
+            self.keyboard.update(self.frame);
+            self.sync_recording();
+        }
+        // Nearly every cycle is running, the waits are out of the hot path
+        if !matches!(self.state, State::Running) && !self.resume(vblank) {
+            return Ok(());
+        }
+
+        if vblank {
+            self.tick_timers();
+        }
+
+        // Fetch the opcode, the fetched word is the one reported by the errors
+        let (opcode, fault) = self.fetch()?;
+
+        // Skip the source map lookup when the trace is off, this is the hottest loop
+        if log_enabled!(Level::Debug) {
+            let pc = self.pc.inner();
+            #[cfg(feature = "std")]
+            let location = self.current_source_line();
+            #[cfg(not(feature = "std"))]
+            let location: Option<&str> = None;
+            match location {
+                Some(location) => debug!("| 0x{pc:X} | {opcode} | {location}"),
+                None => debug!("| 0x{pc:X} | {opcode}"),
+            }
+        }
+
+        self.instructions += 1;
+        self.execute(opcode, fault).map_err(|e| e.with_fault(fault))
+    }
+
+    /// Handles the states other than `State::Running` at the start of a cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `vblank` - Whether the cycle is a vertical blank.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the emulator runs again and the cycle executes an instruction.
+    #[cold]
+    fn resume(&mut self, vblank: bool) -> bool {
         match self.state {
-            State::New => return Ok(()),
-            State::WaitingKey { x } => {
-                let Some(key) = (0..=0xF).find(|&key| self.keyboard.is_set(key)) else {
-                    return Ok(());
-                };
-                self.registers[x] = key;
+            State::WaitingKey { x, held } => {
+                // Released keys can complete the wait once pressed again
+                let held = held & self.keyboard.as_mask();
+                let pressed = self.keyboard.as_mask() & !held;
+                if pressed == 0 {
+                    self.state = State::WaitingKey { x, held };
+                    return false;
+                }
+                self.registers[x] = pressed.trailing_zeros() as u8;
                 self.state = State::Running;
+                true
+            }
+            State::WaitingVblank => {
+                if vblank {
+                    self.tick_timers();
+                }
+                false
             }
-            _ => {}
+            State::New => false,
+            State::Running => true,
         }
+    }
 
-        // reset the updated flag
-        self.display.updated = false;
-
+    /// Runs the 60Hz vertical blank: decrements the timers, presents a double buffered
+    /// display and ends the wait of the `display_wait` quirk.
+    ///
+    /// # Notes
+    ///
+    /// * `tick` calls this on every instruction, treating each tick as a frame.
+    pub fn tick_timers(&mut self) {
         self.sound_timer.decrement();
         self.delay_timer.decrement();
+        self.display.present();
+        self.frame += 1;
+        if matches!(self.state, State::WaitingVblank) {
+            self.state = State::Running;
+        }
+    }
 
-        // Fetch the opcode
-        let opcode = self.fetch_opcode()?;
-
-        debug!("| 0x{PC:X} | {opcode}", PC = self.pc.inner());
-
-        self.execute_opcode(opcode)?;
+    /// Returns the number of vertical blanks run so far, see [`Emulator::tick_timers`].
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
 
-        Ok(())
+    /// Returns the number of instructions run so far, the failed ones included.
+    ///
+    /// # Notes
+    ///
+    /// The count is not part of the save states, loading one leaves it as is.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
     }
 
     /// Fetches the next opcode from memory.
@@ -148,9 +640,35 @@ impl Emulator {
     ///
     /// * `Result<Opcode, RuntimeError>` - The next opcode or an error if the opcode could not be fetched.
     pub fn fetch_opcode(&self) -> Result<Opcode, EmulatorError> {
-        let mut opcode = [0, 0];
-        self.memory.write_range(self.pc, &mut opcode)?;
-        opcode.try_into()
+        self.fetch().map(|(opcode, _)| opcode)
+    }
+
+    /// Fetches the next opcode with a single read of its word, see [`Emulator::fetch_opcode`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(Opcode, Fault), EmulatorError>` - The opcode, and the address and word its
+    ///   errors are reported with.
+    #[inline(always)]
+    fn fetch(&self) -> Result<(Opcode, Fault), EmulatorError> {
+        let word = self.memory.read_u16(self.pc)?;
+        let fault = Fault { pc: self.pc, word };
+        match Opcode::try_from(word)? {
+            // The 16-bit address is the next word, the other profiles run F000 as an
+            // invalid 2 bytes instruction
+            Opcode::LdILong { .. } if self.profile.supports(Variant::XoChip) => {
+                let address = self
+                    .memory
+                    .address(self.pc.inner().saturating_add(2))
+                    .and_then(|next| self.memory.read_u16(next))
+                    .map_err(|e| e.with_fault(fault))?;
+                let opcode = Opcode::LdILong {
+                    address: Address::new_long(address),
+                };
+                Ok((opcode, fault))
+            }
+            opcode => Ok((opcode, fault)),
+        }
     }
 
     /// Executes an opcode (instruction) on the emulator.
@@ -162,11 +680,24 @@ impl Emulator {
     /// # Returns
     ///
     /// * `Result<(), RuntimeError>` - () if the opcode was executed successfully or an error if the cpu encountered any problems.
+    ///
+    /// # Notes
+    ///
+    /// The errors carry the address of the instruction and its opcode word, see [`Fault`].
     pub fn execute_opcode(&mut self, opcode: Opcode) -> Result<(), EmulatorError> {
+        let fault = Fault {
+            pc: self.pc,
+            word: opcode.encode(),
+        };
+        self.execute(opcode, fault).map_err(|e| e.with_fault(fault))
+    }
+
+    /// Executes an opcode, the errors of the memory do not say which instruction failed yet.
+    fn execute(&mut self, opcode: Opcode, fault: Fault) -> Result<(), EmulatorError> {
         // Macro to jump if a condition is met
         macro_rules! jump_if {
             ($op:tt, $x:expr, $y:expr) => {
-                if $x $op $y { self.pc.add_assign(2)?; }
+                if $x $op $y { self.skip_next()?; }
             };
         }
         // Macro to facilitate access to the V registers
@@ -187,15 +718,67 @@ impl Emulator {
             };
         }
 
-        // Increment the program counter by 2
-        self.pc.add_assign(2)?;
+        // Address the opcode was fetched from, and its word
+        let Fault { pc, word } = fault;
+
+        // CHIP-8X gives new meanings to some CHIP-8 words
+        let opcode = match self.profile {
+            Profile::Chip8X => opcode.chip8x(),
+            _ => opcode,
+        };
+
+        // Extension instructions are only valid when the profile supports them,
+        // DXY0 is still a (empty) sprite draw on plain CHIP-8. The CHIP-8X opcodes keep
+        // their word. MEGAOFF and MEGAON are SYS calls for the other interpreters.
+        let opcode = match rom::extension(word) {
+            Some(Variant::MegaChip) if !self.profile.supports(Variant::MegaChip) => Opcode::Sys {
+                address: Address::new(word),
+            },
+            Some(Variant::MegaChip) if opcode == Opcode::MegaOn => {
+                return Err(EmulatorError::UnsupportedVariant {
+                    variant: Variant::MegaChip,
+                    pc,
+                    word,
+                })
+            }
+            Some(variant)
+                if !self.profile.supports(variant) && !matches!(opcode, Opcode::Drw { .. }) =>
+            {
+                Opcode::Invalid(word)
+            }
+            _ => opcode,
+        };
+
+        // Stop on invalid opcodes before moving past them, even at the end of the memory
+        if let (Opcode::Invalid(word), true) = (opcode, self.strict) {
+            return Err(EmulatorError::InvalidOpcode { word, pc });
+        }
+
+        // Move the program counter past the instruction
+        self.pc.add_assign(opcode.size())?;
 
         match opcode {
             Opcode::Cls => self.display.clear(),
-            Opcode::Ret => self.pc = self.stack.pop()?,
+            Opcode::Ret => {
+                self.pc = self
+                    .stack
+                    .pop()
+                    .ok_or(EmulatorError::StackUnderFlow { pc, word })?
+            }
             Opcode::Jp { address } => self.pc = address,
-            Opcode::Sys { address } | Opcode::Call { address } => {
-                self.stack.push(self.pc)?;
+            // Machine code routines can not be executed, modern interpreters ignore them.
+            Opcode::Sys { address } => {
+                debug!("Ignoring SYS #{:X}", address.inner())
+            }
+            Opcode::Call { address } => {
+                if self.stack.push(self.pc).is_err() {
+                    return Err(EmulatorError::StackOverFlow {
+                        depth: self.stack.depth(),
+                        pc,
+                        word,
+                        returns: self.stack.as_slice().to_vec(),
+                    });
+                }
                 self.pc = address;
             }
             Opcode::SeByte { x, byte } => jump_if!(==, V![x], byte),
@@ -204,75 +787,245 @@ impl Emulator {
             Opcode::LdByte { x, byte } => V![x] = byte,
             Opcode::AddByte { x, byte } => V![x] = V![x].wrapping_add(byte),
             Opcode::LdRegister { x, y } => V![x] = V![y],
-            Opcode::Or { x, y } => V![x] |= V![y],
-            Opcode::And { x, y } => V![x] &= V![y],
-            Opcode::Xor { x, y } => V![x] ^= V![y],
+            Opcode::Or { x, y } => {
+                V![x] |= V![y];
+                self.reset_flag();
+            }
+            Opcode::And { x, y } => {
+                V![x] &= V![y];
+                self.reset_flag();
+            }
+            Opcode::Xor { x, y } => {
+                V![x] ^= V![y];
+                self.reset_flag();
+            }
             Opcode::AddRegister { x, y } => {
                 let result = V![x] as u16 + V![y] as u16;
                 V![x] = (result & 0xFF) as u8;
                 V![FLAGS] = if result & 0xFF00 != 0 { 1 } else { 0 }
             }
+            // The flag is 1 without a borrow, equal values included
             Opcode::Sub { x, y } => {
-                V![FLAGS] = if V![x] > V![y] { 1 } else { 0 };
-                V![x] = V![x].wrapping_sub(V![y]);
+                let (result, borrow) = V![x].overflowing_sub(V![y]);
+                V![x] = result;
+                V![FLAGS] = !borrow as u8;
             }
-            Opcode::Shr { x } => {
-                V![FLAGS] = V![x] & 1;
-                V![x] >>= 1;
+            Opcode::Shr { x, y } => {
+                let value = if self.quirks.shift_uses_vy {
+                    V![y]
+                } else {
+                    V![x]
+                };
+                // The flag is written last, it wins when X is F
+                V![x] = value >> 1;
+                V![FLAGS] = value & 1;
             }
             Opcode::Subn { x, y } => {
-                V![FLAGS] = if V![y] > V![x] { 1 } else { 0 };
-                V![x] = V![y].wrapping_sub(V![x]);
+                let (result, borrow) = V![y].overflowing_sub(V![x]);
+                V![x] = result;
+                V![FLAGS] = !borrow as u8;
             }
-            Opcode::Shl { x } => {
-                V![FLAGS] = (V![x] >> 7) & 1;
-                V![x] <<= 1;
+            Opcode::Shl { x, y } => {
+                let value = if self.quirks.shift_uses_vy {
+                    V![y]
+                } else {
+                    V![x]
+                };
+                V![x] = value << 1;
+                V![FLAGS] = value >> 7;
             }
             Opcode::SneRegister { x, y } => {
                 if V![x] != V![y] {
-                    self.pc.add_assign(2)?
+                    self.skip_next()?
+                }
+            }
+            Opcode::LdI { address } | Opcode::LdILong { address } => self.i = address,
+            // BXNN, the highest nibble of the address is also the register
+            Opcode::JpV0 { address } if self.quirks.jump_uses_vx => {
+                let x = RegisterIndex::new((address.inner() >> 8) as u8);
+                self.pc = address;
+                self.pc.add_assign(V![x] as u16)?
+            }
+            Opcode::JpV0 { address } => {
+                self.pc = address;
+                self.pc.add_assign(V![0] as u16)?
+            }
+            Opcode::Rnd { x, byte } => V![x] = self.rand.next_u8() & byte,
+            Opcode::Scd { n } if self.halve_scroll() => self.display.scroll_down(n / 2),
+            Opcode::Scd { n } => self.display.scroll_down(n),
+            Opcode::Scr if self.halve_scroll() => self.display.scroll_right(2),
+            Opcode::Scr => self.display.scroll_right4(),
+            Opcode::Scl if self.halve_scroll() => self.display.scroll_left(2),
+            Opcode::Scl => self.display.scroll_left4(),
+            Opcode::Low => self.display.set_hires(false),
+            Opcode::High => self.display.set_hires(true),
+            Opcode::Plane { n } => self.display.select_planes(n),
+            Opcode::LdAudioI => {
+                let mut pattern = [0; PATTERN_SIZE];
+                self.memory.write_range(self.i, &mut pattern)?;
+                self.audio.load_pattern(pattern);
+            }
+            Opcode::LdPitchVx { x } => self.audio.pitch = V![x],
+            // Each selected plane gets its own sprite, stored one after the other from I
+            Opcode::Drw { x, y, n: 0 } if self.profile.supports(Variant::SuperChip) => {
+                // 16x16 sprite, each row is drawn as two 8 pixel halves
+                let (x, y) = self.sprite_origin(V![x], V![y]);
+                V![FLAGS] = 0;
+                let clip = self.quirks.clip_sprites;
+                let mut address = self.i.inner();
+                for plane in self.display.selected_planes() {
+                    for row in 0..16 {
+                        let left = self.memory.get(address)?;
+                        let right = self.memory.get(address.wrapping_add(1))?;
+                        let y = y + row;
+                        V![FLAGS] |= self.display.set_plane(plane, x, y, left, clip);
+                        V![FLAGS] |= self.display.set_plane(plane, x + 8, y, right, clip);
+                        address = address.wrapping_add(2);
+                    }
                 }
             }
-            Opcode::LdI { address } => self.i = address,
-            Opcode::JpV0 { address } => self.pc.add_assign(address.inner() + V![0] as u16)?,
-            Opcode::Rnd { x, byte } => V![x] = self.rand.next() & byte,
             Opcode::Drw { x, y, n } => {
+                // VF may be a coordinate, it is read before the flag is cleared
+                let (x, y) = self.sprite_origin(V![x], V![y]);
                 V![FLAGS] = 0;
-                let (x, y) = (V![x], V![y]);
-                for row in 0..n {
-                    V![FLAGS] |= self.display.set(
-                        x,
-                        y % crate::constants::HEIGHT as u8 + row,
-                        self.memory[(self.i.inner() + row as u16).try_into()?],
-                    )
+                let clip = self.quirks.clip_sprites;
+                let mut address = self.i.inner();
+                for plane in self.display.selected_planes() {
+                    for row in 0..n {
+                        let byte = self.memory.get(address)?;
+                        V![FLAGS] |= self.display.set_plane(plane, x, y + row, byte, clip);
+                        address = address.wrapping_add(1);
+                    }
                 }
             }
             Opcode::Skp { x } => {
                 if self.keyboard.is_set(V![x] & 0xF) {
-                    self.pc.add_assign(2)?;
+                    self.skip_next()?;
                 }
             }
             Opcode::Sknp { x } => {
                 if !self.keyboard.is_set(V![x] & 0xF) {
-                    self.pc.add_assign(2)?;
+                    self.skip_next()?;
+                }
+            }
+            Opcode::Skp2 { x } => {
+                if self.keyboard2.is_set(V![x] & 0xF) {
+                    self.skip_next()?;
+                }
+            }
+            Opcode::Sknp2 { x } => {
+                if !self.keyboard2.is_set(V![x] & 0xF) {
+                    self.skip_next()?;
                 }
             }
+            Opcode::Bgc => self.display.step_background(),
+            // Never reached, MEGAON stops a MEGA-CHIP profile before its instructions are
+            // decoded, and the other profiles run it as SYS
+            Opcode::MegaOff
+            | Opcode::MegaOn
+            | Opcode::LdHi { .. }
+            | Opcode::LdPal { .. }
+            | Opcode::SprW { .. }
+            | Opcode::SprH { .. }
+            | Opcode::Alpha { .. }
+            | Opcode::DigiSnd { .. }
+            | Opcode::StopSnd
+            | Opcode::BMode { .. }
+            | Opcode::CCol { .. } => {
+                return Err(EmulatorError::UnsupportedVariant {
+                    variant: Variant::MegaChip,
+                    pc,
+                    word,
+                })
+            }
+            // Zone columns and rows as low (first) and high (last) nibbles
+            Opcode::Col { x, y, n: 0 } => {
+                let (columns, rows) = (V![x], V![RegisterIndex::new(x.inner() + 1)]);
+                self.display.set_zone_color(
+                    (columns & 0xF) as usize..=(columns >> 4) as usize,
+                    (rows & 0xF) as usize..=(rows >> 4) as usize,
+                    V![y],
+                );
+            }
+            // A single zone column, N pixels tall
+            Opcode::Col { x, y, n } => {
+                let left = V![x] as usize;
+                let top = V![RegisterIndex::new(x.inner() + 1)] as usize;
+                self.display.set_zone_color(
+                    left / ZONE_WIDTH..=left / ZONE_WIDTH,
+                    top / ZONE_HEIGHT..=(top + n as usize - 1) / ZONE_HEIGHT,
+                    V![y],
+                );
+            }
             Opcode::LdVxDT { x } => V![x] = self.delay_timer.get(),
-            Opcode::LdVxK { x } => self.state = State::WaitingKey { x },
+            Opcode::LdVxK { x } => {
+                let held = if self.quirks.wait_key_held {
+                    0
+                } else {
+                    self.keyboard.as_mask()
+                };
+                self.state = State::WaitingKey { x, held };
+            }
             Opcode::LdDTVx { x } => self.delay_timer.set(V![x]),
             Opcode::LdSTVx { x } => self.sound_timer.set(V![x]),
+            // The 16-bit I of XO-CHIP wraps around
+            Opcode::AddIVx { x } if self.memory.size() > MEMORY_SIZE => {
+                self.i = Address::new_long(self.i.inner().wrapping_add(V![x] as u16))
+            }
+            // The Amiga interpreter wraps I and reports the overflow in VF
+            Opcode::AddIVx { x } if self.quirks.add_i_overflow_vf => {
+                let result = self.i.inner() + V![x] as u16;
+                V![FLAGS] = if result > 0xFFF { 1 } else { 0 };
+                self.i = Address::new(result & 0xFFF);
+            }
             Opcode::AddIVx { x } => self.i.add_assign(V![x] as u16)?,
-            Opcode::LdFVx { x } => self.i = Address::new((V![x] & 0xF) as u16 * 5),
-            Opcode::LdBVx { x } => self.memory.read_range(self.i, &bcd(V![x]))?,
-            Opcode::LdIVx { x } => self.memory.read_range(self.i, &V![0 => x])?,
-            Opcode::LdVxI { x } => self.memory.write_range(self.i, &mut V![0 => x])?,
-            Opcode::Invalid(data) => {
-                error!(
-                    "Unrecognized OpCode: | 0x{PC:X} | {:X?}",
-                    data,
-                    PC = self.pc.inner()
-                )
+            // Only the low nibble selects the character
+            Opcode::LdFVx { x } => {
+                let offset = (V![x] & 0xF) as u16 * self.memory.font().height() as u16;
+                self.i = Address::new(self.memory.font_base().inner() + offset)
+            }
+            Opcode::LdHfVx { x } => {
+                let offset = (V![x] & 0xF) as u16 * 10;
+                self.i = Address::new(self.memory.large_font_base().inner() + offset)
+            }
+            Opcode::LdBVx { x } => self.write_memory(self.i, &bcd(V![x]), pc)?,
+            Opcode::LdIVx { x } => {
+                let mut registers = [0; 16];
+                let count = V![0 => x].len();
+                registers[..count].copy_from_slice(&V![0 => x]);
+                self.write_memory(self.i, &registers[..count], pc)?;
+                self.increment_index(x)?;
+            }
+            Opcode::LdVxI { x } => {
+                self.memory.write_range(self.i, &mut V![0 => x])?;
+                self.increment_index(x)?;
+            }
+            Opcode::LdRVx { x } => {
+                let x = rpl_register(x, self.quirks.reject_rpl_overflow, fault)?;
+                let count = x.inner() as usize + 1;
+                if self.rpl_flags[..count] != V![0 => x] {
+                    self.rpl_flags[..count].copy_from_slice(&V![0 => x]);
+                    if let Some(hook) = self.persistence.as_mut() {
+                        hook.save(&self.rpl_flags);
+                    }
+                }
+            }
+            Opcode::LdVxR { x } => {
+                let x = rpl_register(x, self.quirks.reject_rpl_overflow, fault)?;
+                let count = x.inner() as usize + 1;
+                V![0 => x].copy_from_slice(&self.rpl_flags[..count]);
             }
+            Opcode::Invalid(word) => {
+                let err = EmulatorError::InvalidOpcode { word, pc };
+                if self.strict {
+                    return Err(err);
+                }
+                error!("{err}")
+            }
+        }
+
+        if self.quirks.display_wait && matches!(opcode, Opcode::Drw { .. }) {
+            self.state = State::WaitingVblank;
         }
 
         Ok(())
@@ -287,16 +1040,738 @@ impl Emulator {
         &self.display
     }
 
+    /// Returns the display rows changed since the last call, see `Display::take_dirty_rows`.
+    ///
+    /// # Returns
+    ///
+    /// * `RowMask` - The rows frontends need to redraw.
+    pub fn take_dirty_rows(&mut self) -> RowMask {
+        self.display.take_dirty_rows()
+    }
 
-    /// Change the state of the virtual `key` key to pressed.
-    pub fn press_key(&mut self, key: keyboard::Key) {
-        self.keyboard.set(key as u8);
+    /// Returns the pixels that changed since the last call, see `Display::take_diff`.
+    ///
+    /// # Returns
+    ///
+    /// * `&[(u8, u8, bool)]` - The coordinates and the new value of each changed pixel.
+    pub fn take_diff(&mut self) -> &[(u8, u8, bool)] {
+        self.display.take_diff()
     }
 
-    /// Change the state of the virtual `key` key to released.
-    pub fn release_key(&mut self, key: keyboard::Key) {
+    /// Loads pixels packed by `Display::to_bytes`, see `Display::from_bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The packed pixels, with the dimensions of the current display mode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the data does not fit the display.
+    pub fn load_display(&mut self, data: &[u8]) -> Result<(), EmulatorError> {
+        self.display.from_bytes(data)
+    }
+
+    /// Saves the state of the machine, to restore it with [`Emulator::load_state`].
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The state, little endian: `R8SS`, the format version (7), `pc` and `i`
+    ///   (2 bytes each), the V registers, the delay and sound timers, the state, its register
+    ///   and the keys held when it began (4 bytes), the frame (8 bytes), the CRC-32, length
+    ///   (4 bytes each) and SHA-1 of the ROM, the RPL user flags, then the memory, the display (the resolution, the selected planes and
+    ///   [`Display::to_bytes`]), both keyboards ([`KeyBoard::to_bytes`]), the return
+    ///   addresses on the stack (2 bytes each, from the bottom), the state of the random
+    ///   number generator ([`RngSource::state`]), the XO-CHIP audio (whether a pattern was
+    ///   loaded, the pitch and the pattern) and the profile (its index in [`PRESETS`]) with
+    ///   the quirks ([`Quirks::to_bytes`]), each one after its length (4 bytes).
+    ///
+    /// # Notes
+    ///
+    /// The other settings (double buffering, stack depth, strict mode) are not saved, load
+    /// the state into an emulator with the same ones. A restored state draws the same random
+    /// numbers as the saved session from then on.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut bytes = b"R8SS".to_vec();
+        bytes.push(SAVE_STATE_VERSION);
+        bytes.extend(self.pc.inner().to_le_bytes());
+        bytes.extend(self.i.inner().to_le_bytes());
+        bytes.extend((0..16).map(|x| self.registers[RegisterIndex::new(x)]));
+        bytes.extend([self.delay_timer.get(), self.sound_timer.get()]);
+        bytes.extend(match self.state {
+            State::New => [0, 0, 0, 0],
+            State::Running => [1, 0, 0, 0],
+            State::WaitingKey { x, held } => [2, x.inner(), held as u8, (held >> 8) as u8],
+            State::WaitingVblank => [3, 0, 0, 0],
+        });
+        bytes.extend(self.frame.to_le_bytes());
+        bytes.extend(self.rom_hash.crc32.to_le_bytes());
+        bytes.extend((self.rom_hash.len as u32).to_le_bytes());
+        bytes.extend(self.rom_hash.sha1);
+        bytes.extend(self.rpl_flags);
+
+        let mut memory = vec![0; self.memory.size()];
+        // The buffer is exactly as large as the memory
+        let _ = self.memory.write_range(Address::new(0), &mut memory);
+        let mut display = vec![self.display.is_hires() as u8, self.display.selected()];
+        display.extend(self.display.to_bytes());
+        let mut stack = Vec::new();
+        for address in self.stack.iter() {
+            stack.extend(address.inner().to_le_bytes());
+        }
+        let (keyboard, keyboard2) = (self.keyboard.to_bytes(), self.keyboard2.to_bytes());
+        let rng = self.rand.state();
+        let audio = self.audio.to_bytes();
+        let mut settings = vec![self.profile as u8];
+        settings.extend(self.quirks.to_bytes());
+        let sections = [memory, display, keyboard, keyboard2, stack, rng, audio, settings];
+        for section in sections {
+            bytes.extend((section.len() as u32).to_le_bytes());
+            bytes.extend(section);
+        }
+        bytes
+    }
+
+    /// Restores a state saved with [`Emulator::save_state`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The saved state.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `InvalidSaveState` if the data is not a save state or
+    ///   does not fit this emulator, the emulator is then left untouched.
+    ///
+    /// # Notes
+    ///
+    /// The random number generator must be of the kind that saved the state, see
+    /// [`RngSource::set_state`].
+    ///
+    /// A state saved while `FX0A` waits waits again, the next key pressed completes it as it
+    /// would have in the saved session.
+    ///
+    /// The profile and the quirks of the saved session are restored, the memory is resized
+    /// when the profile needs another size.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmulatorError> {
+        let invalid = |reason: &str| EmulatorError::InvalidSaveState(reason.to_string());
+        let mut rest = data;
+        let mut take = |count: usize| {
+            let (field, tail) = rest
+                .split_at_checked(count)
+                .ok_or_else(|| invalid("The save state is truncated"))?;
+            rest = tail;
+            Ok::<_, EmulatorError>(field)
+        };
+        let u16_at = |bytes: &[u8]| u16::from_le_bytes(bytes.try_into().unwrap());
+
+        if take(4)? != b"R8SS" {
+            return Err(invalid("The data is not a save state"));
+        }
+        let version = take(1)?[0];
+        if version != SAVE_STATE_VERSION {
+            return Err(EmulatorError::InvalidSaveState(format!(
+                "The format version {version} is not supported"
+            )));
+        }
+        let pc = Address::new_long(u16_at(take(2)?));
+        let i = Address::new_long(u16_at(take(2)?));
+        let registers = take(16)?;
+        let [delay, sound] = take(2)?.try_into().unwrap();
+        let state = match *take(4)? {
+            [0, ..] => State::New,
+            [1, ..] => State::Running,
+            [2, x, low, high] if x <= 0xF => State::WaitingKey {
+                x: RegisterIndex::new(x),
+                held: u16::from_le_bytes([low, high]),
+            },
+            [3, ..] => State::WaitingVblank,
+            _ => return Err(invalid("The state is invalid")),
+        };
+        let frame = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let rom_hash = RomHash {
+            crc32: u32::from_le_bytes(take(4)?.try_into().unwrap()),
+            len: u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize,
+            sha1: take(20)?.try_into().unwrap(),
+        };
+        let rpl_flags = take(RPL_FLAG_COUNT)?.try_into().unwrap();
+        let mut sections = Vec::new();
+        for _ in 0..8 {
+            let length = u32::from_le_bytes(take(4)?.try_into().unwrap());
+            sections.push(take(length as usize)?);
+        }
+        if !rest.is_empty() {
+            return Err(invalid("The save state has trailing bytes"));
+        }
+        let [memory, display, keyboard, keyboard2, stack, rng, audio, settings] = sections[..]
+        else {
+            unreachable!()
+        };
+
+        let &[profile, ref quirks @ ..] = settings else {
+            return Err(invalid("The settings are invalid"));
+        };
+        let profile = PRESETS
+            .get(profile as usize)
+            .ok_or_else(|| invalid("The profile is invalid"))?
+            .profile;
+        let quirks = quirks
+            .try_into()
+            .map_err(|_| invalid("The settings are invalid"))
+            .and_then(Quirks::from_bytes)?;
+        if memory.len() != profile.memory_size() {
+            return Err(EmulatorError::InvalidSaveState(format!(
+                "The memory is {} bytes, the {profile:?} profile has {} bytes",
+                memory.len(),
+                profile.memory_size()
+            )));
+        }
+        let &[hires, selected, ref pixels @ ..] = display else {
+            return Err(invalid("The display is truncated"));
+        };
+        // Checked on a blank display first, loading the pixels can not fail after
+        let mut scratch = Display::new();
+        scratch.set_lores_height(profile.lores_height());
+        scratch.set_hires(hires != 0);
+        scratch
+            .from_bytes(pixels)
+            .map_err(|e| EmulatorError::InvalidSaveState(e.to_string()))?;
+        let keyboard = KeyBoard::from_bytes(keyboard)?;
+        let keyboard2 = KeyBoard::from_bytes(keyboard2)?;
+        if !stack.len().is_multiple_of(2) {
+            return Err(invalid("The stack is truncated"));
+        }
+        let returns = stack.chunks(2).map(|word| Address::new_long(u16_at(word)));
+        let mut restored = Stack::with_depth(self.stack.depth());
+        for address in returns {
+            if restored.push(address).is_err() {
+                return Err(EmulatorError::InvalidSaveState(format!(
+                    "The stack is deeper than {} return addresses",
+                    restored.depth()
+                )));
+            }
+        }
+        let audio = Audio::from_bytes(audio)?;
+        // The last check, nothing else changes if it fails
+        self.rand.set_state(rng)?;
+
+        if self.memory.size() != profile.memory_size() {
+            self.memory.resize(profile.memory_size());
+        }
+        self.display.set_lores_height(profile.lores_height());
+        self.profile = profile;
+        self.quirks = quirks;
+        self.pc = pc;
+        self.i = i;
+        for (x, &value) in registers.iter().enumerate() {
+            self.registers[RegisterIndex::new(x as u8)] = value;
+        }
+        self.delay_timer.set(delay);
+        self.sound_timer.set(sound);
+        self.state = state;
+        self.frame = frame;
+        self.rom_hash = rom_hash;
+        self.rpl_flags = rpl_flags;
+        self.memory.read_range(Address::new(0), memory)?;
+        self.display.set_hires(hires != 0);
+        self.display.select_planes(selected);
+        self.display.from_bytes(pixels)?;
+        self.keyboard = keyboard;
+        self.keyboard2 = keyboard2;
+        self.stack = restored;
+        self.audio = audio;
+        Ok(())
+    }
+
+    /// Sets whether the display is double buffered.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - If true, instructions draw to a back buffer that is presented on every
+    ///   vertical blank, so reading the display between instructions never shows a
+    ///   half-drawn frame. Otherwise the display is read as it is drawn (default).
+    pub fn set_double_buffered(&mut self, enabled: bool) {
+        self.display.set_double_buffered(enabled);
+    }
+
+    /// Presents the back buffer of a double buffered display now, see `Display::present`.
+    pub fn present(&mut self) {
+        self.display.present();
+    }
+
+    /// Sets whether invalid opcodes stop the emulator.
+    ///
+    /// # Arguments
+    ///
+    /// * `strict` - If true, `tick` returns `EmulatorError::InvalidOpcode` on invalid opcodes,
+    ///   otherwise they are logged and skipped (default).
+    ///
+    /// # Notes
+    ///
+    /// The write protection follows the strict mode, use `set_write_protection` after this
+    /// to change it.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+        self.write_protection = strict;
+    }
+
+    /// Sets whether the program can write below `0x200`, where the interpreter keeps the
+    /// fonts.
+    ///
+    /// # Arguments
+    ///
+    /// * `protect` - If true, `FX33`, `FX55` and `poke` return `EmulatorError::ProtectedWrite`
+    ///   instead of writing there. Off by default, some programs use that memory.
+    ///
+    /// # Notes
+    ///
+    /// Loading a ROM, a font or data with `load_at` still writes there.
+    pub fn set_write_protection(&mut self, protect: bool) {
+        self.write_protection = protect;
+    }
+
+    /// Sets the number of nested calls the stack holds, 16 by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The maximum number of return addresses, at least 1. The COSMAC VIP held
+    ///   12, `usize::MAX` has no limit.
+    ///
+    /// # Notes
+    ///
+    /// The stack is emptied, set the depth before loading a ROM or create the emulator with
+    /// it, see [`EmulatorConfig::stack_depth`].
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack = Stack::with_depth(depth);
+    }
+
+    /// Sets whether `load_rom` removes the zero padding at the end of ROMs.
+    ///
+    /// # Arguments
+    ///
+    /// * `normalize` - If true, ROMs are loaded as [`rom::normalize`] returns them, so
+    ///   `rom_hash` is the same for dumps that only differ by their padding. Off by default.
+    pub fn set_normalize_roms(&mut self, normalize: bool) {
+        self.normalize_roms = normalize;
+    }
+
+    /// Writes to memory for the program or a debugger, checking the write protection and
+    /// notifying the memory hooks.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The first address written.
+    /// * `data` - The bytes to write.
+    /// * `pc` - The address of the instruction writing.
+    pub(crate) fn write_memory(
+        &mut self,
+        address: Address,
+        data: &[u8],
+        pc: Address,
+    ) -> Result<(), EmulatorError> {
+        if self.write_protection && address < Address::ENTRY_POINT {
+            return Err(EmulatorError::ProtectedWrite {
+                addr: address,
+                pc,
+                word: None,
+            });
+        }
+        if self.memory_hooks.is_empty() {
+            return self.memory.read_range(address, data);
+        }
+        let start = address.inner() as usize;
+        let mut old = vec![0; data.len()];
+        self.memory.write_range(address, &mut old)?;
+        self.memory.read_range(address, data)?;
+        self.memory_hooks.notify(start, &old, data, pc);
+        Ok(())
+    }
+
+    /// Calls a function on every write to a range of memory, by `FX33`, `FX55` or `poke`.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to watch.
+    /// * `callback` - The function, called once per byte written with the address, the old
+    ///   and new bytes and the instruction writing.
+    ///
+    /// # Returns
+    ///
+    /// * `HookId` - The id to remove the hook with `remove_hook`.
+    ///
+    /// # Notes
+    ///
+    /// Several hooks can watch the same addresses, they are called in the order they were
+    /// added. The emulator keeps running, hooks only observe it.
+    pub fn on_memory_write(
+        &mut self,
+        range: Range<u16>,
+        callback: impl FnMut(&MemoryWrite) + Send + Sync + 'static,
+    ) -> HookId {
+        let range = range.start as usize..range.end as usize;
+        self.memory_hooks.add(range, Box::new(callback))
+    }
+
+    /// Removes a hook added with `on_memory_write`.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - False if the hook was already removed.
+    pub fn remove_hook(&mut self, id: HookId) -> bool {
+        self.memory_hooks.remove(id)
+    }
+
+    /// Sets the hook that keeps the RPL user flags between sessions.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The hook, the flags it saved before are loaded immediately.
+    ///   `None` removes the current hook and keeps the flags in memory only.
+    pub fn set_persistence_hook(&mut self, mut hook: Option<Box<dyn PersistenceHook>>) {
+        if let Some(flags) = hook.as_mut().and_then(|hook| hook.load()) {
+            self.rpl_flags = flags;
+        }
+        self.persistence = hook;
+    }
+
+    /// Sets the interpreter the program targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The profile, SUPER-CHIP and XO-CHIP instructions are invalid opcodes
+    ///   unless the profile supports them.
+    ///
+    /// # Notes
+    ///
+    /// * The memory is replaced, and so cleared, when the profile needs a different size
+    ///   (64KB for XO-CHIP). Set the profile before loading the ROM.
+    /// * The display is cleared and takes the low resolution height of the profile (48 rows for ETI-660).
+    /// * The quirks are replaced by the preset of the profile, see [`Profile::preset`].
+    ///   Use `set_quirks` after this to change them.
+    pub fn set_profile(&mut self, profile: Profile) {
+        if self.memory.size() != profile.memory_size() {
+            self.memory.resize(profile.memory_size());
+        }
+        self.display.set_lores_height(profile.lores_height());
+        self.profile = profile;
+        self.quirks = profile.quirks();
+    }
+
+    /// Returns the interpreter the program targets.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Sets the behaviors that differ between interpreters.
+    ///
+    /// # Arguments
+    ///
+    /// * `quirks` - The quirks, they take effect on the next instruction.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Returns the behaviors that differ between interpreters.
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Clears VF after `8XY1`, `8XY2` and `8XY3` when the `vf_reset` quirk is enabled.
+    #[inline]
+    fn reset_flag(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers[RegisterIndex::FLAG] = 0;
+        }
+    }
+
+    /// Returns the position a sprite is drawn at, the coordinates wrap around the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The value of VX.
+    /// * `y` - The value of VY.
+    ///
+    /// # Notes
+    ///
+    /// The origin is always inside the display, so adding up to 16 rows or columns does not
+    /// overflow a `u8`. The pixels past the right and bottom edges wrap around to the left
+    /// and top edges, or are dropped with the `clip_sprites` quirk.
+    #[inline]
+    fn sprite_origin(&self, x: u8, y: u8) -> (u8, u8) {
+        let (width, height) = self.display.dimensions();
+        ((x as usize % width) as u8, (y as usize % height) as u8)
+    }
+
+    /// Moves the program counter past the next instruction, used by the conditional skips.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the program counter leaves the memory.
+    ///
+    /// # Notes
+    ///
+    /// With XO-CHIP, the skipped instruction may be the 4 bytes `F000 NNNN`,
+    /// skipping only its first word would execute the address as an instruction.
+    #[inline]
+    fn skip_next(&mut self) -> Result<(), EmulatorError> {
+        let mut word = 0;
+        if self.profile.supports(Variant::XoChip) {
+            word = self.memory.read_u16(self.pc)?;
+        }
+        match word {
+            0xF000 => self.pc.add_assign(4),
+            _ => self.pc.add_assign(2),
+        }
+    }
+
+    /// Changes I after `FX55` or `FX65`, as set by the `memory_increments_i` quirk.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The last register stored or loaded.
+    fn increment_index(&mut self, x: RegisterIndex) -> Result<(), EmulatorError> {
+        let increment = match self.quirks.memory_increments_i {
+            IndexIncrement::Unchanged => return Ok(()),
+            IndexIncrement::X => x.inner() as u16,
+            IndexIncrement::XPlusOne => x.inner() as u16 + 1,
+        };
+        if self.memory.size() > MEMORY_SIZE {
+            // The 16-bit I of XO-CHIP wraps around
+            self.i = Address::new_long(self.i.inner().wrapping_add(increment));
+            Ok(())
+        } else {
+            self.i.add_assign(increment)
+        }
+    }
+
+    /// Returns true if scroll instructions move the display by half the amount they encode.
+    fn halve_scroll(&self) -> bool {
+        self.quirks.lores_scroll_halved && !self.display.is_hires()
+    }
+
+    /// Sets the source line map used to report source locations.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_map` - The line map of the loaded ROM, or `None` to report addresses only.
+    #[cfg(feature = "std")]
+    pub fn set_source_map(&mut self, source_map: Option<SourceMap>) {
+        self.source_map = source_map;
+    }
+
+    /// Fills an audio buffer with the sound the emulator is playing.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to fill with mono samples in the range `[-1.0, 1.0]`.
+    /// * `sample_rate` - The sample rate of the output in Hz.
+    ///
+    /// # Notes
+    ///
+    /// * The buffer is silent while the sound timer is zero.
+    /// * The XO-CHIP pattern loaded by `F002` is played at the rate set by `FX3A`,
+    ///   programs that never load a pattern play the classic square beep.
+    pub fn fill_audio_buffer(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        let playing = self.sound_timer.get() > 0;
+        self.audio.fill(buffer, sample_rate, playing);
+    }
+
+    /// Seeds the random number generator, the same seed always produces the same numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rand = Box::new(RandGen::with_seed(seed));
+    }
+
+    /// Replaces the random number generator of `CXNN`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The source of the random bytes, e.g. a counter or a recorded byte stream.
+    ///
+    /// # Notes
+    ///
+    /// `seed_rng` and `start_recording` replace the source with a seeded [`RandGen`].
+    pub fn set_rng(&mut self, rng: Box<dyn RngSource>) {
+        self.rand = rng;
+    }
+
+    /// Starts recording the keyboard input, to replay the session with [`Emulator::replay`].
+    ///
+    /// # Notes
+    ///
+    /// * The random number generator is reseeded, the seed is part of the recording.
+    /// * Start recording right after loading the ROM, a replay starts from the same point.
+    /// * A recording in progress is discarded.
+    pub fn start_recording(&mut self) {
+        let seed = u64::from_le_bytes(core::array::from_fn(|_| self.rand.next_u8()));
+        self.seed_rng(seed);
+        self.recording = Some(Recording::new(self.rom_hash.crc32, seed));
+    }
+
+    /// Stops recording the keyboard input.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Recording>` - The recording, or `None` if no recording was in progress.
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+
+    /// Plays a recording back, the keyboard follows the recorded state for as many frames as
+    /// were recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `recording` - The recording, see [`Emulator::start_recording`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `InvalidRecording` if the recording was made with
+    ///   another ROM.
+    ///
+    /// # Notes
+    ///
+    /// Replay right after loading the ROM with the same profile and quirks, the session is
+    /// then reproduced exactly. Input pushed during the replay is overridden.
+    pub fn replay(&mut self, recording: Recording) -> Result<(), EmulatorError> {
+        if recording.rom_crc32() != self.rom_hash.crc32 {
+            return Err(EmulatorError::InvalidRecording(format!(
+                "The recording was made with the ROM {:08X}, the loaded ROM is {:08X}",
+                recording.rom_crc32(),
+                self.rom_hash.crc32
+            )));
+        }
+        self.seed_rng(recording.seed());
+        self.replay = Some(Replay::new(recording));
+        Ok(())
+    }
+
+    /// Returns true while a recording is played back.
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Replaces the keyboard state by the replayed one, then records it, once per frame.
+    #[inline]
+    fn sync_recording(&mut self) {
+        if let Some(replay) = &mut self.replay {
+            match replay.next_mask() {
+                Some(mask) => self.keyboard.set_mask(mask),
+                None => self.replay = None,
+            }
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.record(self.keyboard.as_mask());
+        }
+    }
+
+    /// Queues a key press or release, applied at the start of the next tick.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event, timestamped with the current frame.
+    ///
+    /// # Notes
+    ///
+    /// Unlike `press_key` and `release_key`, a key pressed and released between two ticks
+    /// is still seen pressed by one frame, all the instructions up to the next tick, see
+    /// [`KeyBoard::update`].
+    pub fn push_key_event(&mut self, event: KeyEvent) {
+        self.keyboard.push(self.frame, event);
+    }
+
+    /// Returns a handle to push key events from other threads.
+    ///
+    /// # Returns
+    ///
+    /// * `SharedKeyboard` - The handle, every call returns a clone of the same one. Events
+    ///   pushed to it are queued at the start of the next tick, as with `push_key_event`.
+    #[cfg(feature = "std")]
+    pub fn shared_keyboard(&mut self) -> SharedKeyboard {
+        self.shared_keyboard
+            .get_or_insert_with(SharedKeyboard::new)
+            .clone()
+    }
+
+    /// Queues a key press or release for a later frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame the event happens at, see [`Emulator::frame`]. Past frames
+    ///   apply at the start of the next tick.
+    /// * `event` - The event.
+    pub fn push_key_event_at(&mut self, frame: u64, event: KeyEvent) {
+        self.keyboard.push(frame, event);
+    }
+
+    /// Presses a key for a number of ticks, then releases it.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key, pressed now.
+    /// * `ticks` - The number of ticks that see the key pressed, at least 1. A tick is a
+    ///   vertical blank, with [`Emulator::run_frame`] the key stays pressed for every
+    ///   instruction of as many frames.
+    ///
+    /// # Notes
+    ///
+    /// * Several keys can be held at once, each one is released on its own schedule.
+    /// * A key held while `FX0A` waits completes the wait once, the key is released before
+    ///   the next one can see it if it is held for fewer frames than the program takes to
+    ///   get back to `FX0A`.
+    /// * `press_key` and `release_key` cancel the release.
+    pub fn press_for(&mut self, key: keyboard::Key, ticks: u32) {
+        self.keyboard.hold(key as u8, ticks);
+    }
+
+    /// Presses a key for a number of frames, then releases it.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key, pressed now.
+    /// * `frames` - The number of vertical blanks before the key is released, see
+    ///   [`Emulator::frame`].
+    pub fn press_for_frames(&mut self, key: keyboard::Key, frames: u32) {
+        self.keyboard.set(key as u8);
+        let frame = self.frame + frames.max(1) as u64;
+        self.keyboard.push(frame, KeyEvent::Up(key));
+    }
+
+    /// Returns the keyboard, e.g. to show the pressed keys.
+    pub fn keyboard(&self) -> &KeyBoard {
+        &self.keyboard
+    }
+
+    /// Replaces the state of all the keys at once, see [`KeyBoard::set_mask`].
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - Bit n is set if key n is pressed.
+    pub fn set_key_mask(&mut self, mask: u16) {
+        self.keyboard.set_mask(mask);
+    }
+
+    /// Change the state of the virtual `key` key to pressed.
+    pub fn press_key(&mut self, key: keyboard::Key) {
+        self.keyboard.set(key as u8);
+    }
+
+    /// Change the state of the virtual `key` key to released.
+    pub fn release_key(&mut self, key: keyboard::Key) {
         self.keyboard.unset(key as u8);
     }
+
+    /// Change the state of the virtual `key` key of the CHIP-8X second keypad to pressed.
+    pub fn press_key2(&mut self, key: keyboard::Key) {
+        self.keyboard2.set(key as u8);
+    }
+
+    /// Change the state of the virtual `key` key of the CHIP-8X second keypad to released.
+    pub fn release_key2(&mut self, key: keyboard::Key) {
+        self.keyboard2.unset(key as u8);
+    }
 }
 
 impl Default for Emulator {
@@ -305,6 +1780,37 @@ impl Default for Emulator {
     }
 }
 
+/// Checks the last register stored or loaded by `FX75` and `FX85` against the available flags.
+///
+/// # Arguments
+///
+/// * `x` - The register encoded in the instruction.
+/// * `reject` - The `reject_rpl_overflow` quirk.
+/// * `fault` - The instruction.
+///
+/// # Returns
+///
+/// * `Result<RegisterIndex, EmulatorError>` - `x`, `V7` when `x` is above it, or
+///   `RplOverflow` when `x` is above it and `reject` is set.
+fn rpl_register(
+    x: RegisterIndex,
+    reject: bool,
+    fault: Fault,
+) -> Result<RegisterIndex, EmulatorError> {
+    let last = RPL_FLAG_COUNT as u8 - 1;
+    if x.inner() <= last {
+        Ok(x)
+    } else if reject {
+        Err(EmulatorError::RplOverflow {
+            register: x.inner(),
+            fault,
+        })
+    } else {
+        warn!("Only V0 to V{last} fit in the RPL user flags, V{x:X} is clamped");
+        Ok(RegisterIndex::new(last))
+    }
+}
+
 /// Translate a number to BCD.
 /// 
 /// # Arguments
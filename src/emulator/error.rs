@@ -1,48 +1,526 @@
+use crate::{memory::Address, opcode::Opcode, prelude::*, rom::Variant};
+
+/// The instruction that was running when an error occurred.
+///
+/// # Fields
+///
+/// * `pc` - The address the instruction was fetched from.
+/// * `word` - The raw opcode word of the instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub pc: Address,
+    pub word: u16,
+}
+
+impl core::fmt::Display for Fault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "0x{:04X} ({}) at 0x{:03X}",
+            self.word,
+            disassemble(self.word),
+            self.pc.inner()
+        )
+    }
+}
+
+/// Decodes a word for the error messages, with the disassembler's formatting.
+fn disassemble(word: u16) -> Opcode {
+    Opcode::try_from(word).unwrap_or(Opcode::Invalid(word))
+}
+
+/// The kind of an error, for frontends and bindings to handle errors without reading their
+/// messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// An access outside the memory or to protected memory.
+    Memory,
+    /// A call nested too deep or a return without a call.
+    Stack,
+    /// A word or text that is not a valid instruction.
+    Decode,
+    /// A ROM that can not be loaded.
+    Rom,
+    /// A setting or an argument that the emulator does not accept.
+    Config,
+    /// A file or a stream that can not be read or written.
+    Io,
+    /// Saved data (a save state, a recording, a symbol file...) that can not be parsed.
+    Format,
+}
+
+/// The stable code and the category of each variant, in the order they are declared.
+///
+/// # Notes
+///
+/// The codes never change once released, new variants get new codes. The hundreds are the
+/// category: 1xx memory, 2xx stack, 3xx decode, 4xx ROM, 5xx config, 6xx I/O, 7xx format.
+const CODES: [(&str, u32, ErrorCategory); 28] = [
+    ("LoadError", 601, ErrorCategory::Io),
+    ("StackOverFlow", 201, ErrorCategory::Stack),
+    ("StackUnderFlow", 202, ErrorCategory::Stack),
+    ("InvalidAddress", 101, ErrorCategory::Memory),
+    ("OutOfBounds", 102, ErrorCategory::Memory),
+    ("InvalidRegister", 301, ErrorCategory::Decode),
+    ("RomTooLarge", 401, ErrorCategory::Rom),
+    ("EmptyRom", 402, ErrorCategory::Rom),
+    ("LoadOutOfBounds", 403, ErrorCategory::Rom),
+    ("InvalidOpcode", 302, ErrorCategory::Decode),
+    ("UnsupportedVariant", 303, ErrorCategory::Decode),
+    ("InvalidInstruction", 304, ErrorCategory::Decode),
+    ("InvalidSymbol", 701, ErrorCategory::Format),
+    ("InvalidSourceMap", 702, ErrorCategory::Format),
+    ("InvalidBufferSize", 501, ErrorCategory::Config),
+    ("InvalidDisplayData", 703, ErrorCategory::Format),
+    ("InvalidScale", 502, ErrorCategory::Config),
+    ("ScreenshotError", 602, ErrorCategory::Io),
+    ("RecordingError", 603, ErrorCategory::Io),
+    ("InvalidRecording", 704, ErrorCategory::Format),
+    ("InvalidSaveState", 705, ErrorCategory::Format),
+    ("InvalidKeyMap", 706, ErrorCategory::Format),
+    ("InvalidIntelHex", 707, ErrorCategory::Format),
+    ("InvalidHexText", 708, ErrorCategory::Format),
+    ("FontCollision", 503, ErrorCategory::Config),
+    ("ProtectedWrite", 103, ErrorCategory::Memory),
+    ("AddressOutOfRange", 104, ErrorCategory::Memory),
+    ("RplOverflow", 305, ErrorCategory::Decode),
+];
+
 /// Error types for the emulator.
 ///
-/// This is a list of all the errors that can occur while running the emulator.
+/// This is a list of all the errors that can occur while running the emulator. See
+/// [`EmulatorError::code`] and [`EmulatorError::category`] to handle them without matching
+/// every variant.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum EmulatorError {
     /// An error occurred while loading the ROM.
+    #[cfg(feature = "std")]
     LoadError(std::io::Error),
-    /// The stack is full and cannot push any more items.
-    StackOverFlow,
-    /// The stack is empty and cannot pop any more items.
-    StackUnderFlow,
-    /// The address is not valid.
-    InvalidAddress(u16),
-    // The address is out of bounds.
-    OutOfBounds(u16),
+    /// The stack is full, the `CALL` (`word`) at `pc` can not push its return address.
+    /// `returns` are the `depth` return addresses on the stack, from the first call to the
+    /// last.
+    StackOverFlow {
+        depth: usize,
+        pc: Address,
+        word: u16,
+        returns: Vec<Address>,
+    },
+    /// The stack is empty, the `RET` (`word`) at `pc` has no return address to pop.
+    StackUnderFlow { pc: Address, word: u16 },
+    /// The address is not valid. `fault` is the instruction that used it, if one was running.
+    InvalidAddress { address: u16, fault: Option<Fault> },
+    /// The address is out of bounds. `fault` is the instruction that used it, if one was
+    /// running.
+    OutOfBounds { address: u16, fault: Option<Fault> },
     /// The register is not valid.
     InvalidRegister(u8),
+    /// The ROM, `len` bytes long, is larger than the `max` bytes available to load it.
+    RomTooLarge { len: usize, max: usize },
+    /// The ROM has no data.
+    EmptyRom,
+    /// The data loaded at `address` runs past the end of the memory.
+    LoadOutOfBounds {
+        address: u16,
+        len: usize,
+        size: usize,
+    },
+    /// The word fetched at `pc` is not a valid instruction.
+    InvalidOpcode { word: u16, pc: Address },
+    /// The instruction `word` at `pc` belongs to an extension the emulator does not implement.
+    UnsupportedVariant {
+        variant: Variant,
+        pc: Address,
+        word: u16,
+    },
+    /// The text is not a valid instruction.
+    InvalidInstruction(String),
+    /// A line of a symbol file can not be loaded.
+    InvalidSymbol { line: usize, reason: String },
+    /// A line of a source line map can not be loaded.
+    InvalidSourceMap { line: usize, reason: String },
+    /// The buffer does not have the size the output needs.
+    InvalidBufferSize { expected: usize, actual: usize },
+    /// The packed pixels can not be loaded in the display.
+    InvalidDisplayData(String),
+    /// The display can not be upscaled by this factor.
+    InvalidScale(usize),
+    /// A screenshot can not be written.
+    #[cfg(feature = "std")]
+    ScreenshotError {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+    /// A recording can not be encoded.
+    RecordingError(String),
+    /// An input recording can not be loaded or replayed.
+    InvalidRecording(String),
+    /// A save state can not be loaded.
+    InvalidSaveState(String),
+    /// A line of a saved key map can not be loaded.
+    InvalidKeyMap { line: usize, reason: String },
+    /// A line of an Intel HEX image can not be loaded.
+    InvalidIntelHex { line: usize, reason: String },
+    /// A token of a hex listing can not be loaded.
+    InvalidHexText {
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    /// The fonts at `base..end` would overlap the ROM or not fit in the first 4KB.
+    FontCollision { base: u16, end: usize },
+    /// The instruction `word` at `pc` wrote below `0x200` with the write protection on. `pc`
+    /// is the next instruction and `word` is `None` for writes of a debugger.
+    ProtectedWrite {
+        addr: Address,
+        pc: Address,
+        word: Option<u16>,
+    },
+    /// A debugger accessed an address outside the memory, valid addresses are `0..size`.
+    AddressOutOfRange { address: usize, size: usize },
+    /// The `FX75` or `FX85` of `fault` uses the `register` VX, above the last RPL user flag,
+    /// with the `reject_rpl_overflow` quirk.
+    RplOverflow { register: u8, fault: Fault },
 }
 
-impl std::fmt::Display for EmulatorError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl EmulatorError {
+    /// Returns the stable numeric code of the error, for bindings to pass it across FFI.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The code, its hundreds are the category, see [`EmulatorError::category`].
+    pub fn code(&self) -> u32 {
+        CODES[self.index()].1
+    }
+
+    /// Returns the kind of the error.
+    ///
+    /// # Returns
+    ///
+    /// * `ErrorCategory` - The category, for frontends to branch on.
+    pub fn category(&self) -> ErrorCategory {
+        CODES[self.index()].2
+    }
+
+    /// Returns the position of the variant in `CODES`.
+    fn index(&self) -> usize {
         match self {
-            EmulatorError::StackOverFlow => write!(
-                f,
-                "Stack Overflow: Unable to push item, the stack is already full."
-            ),
-            EmulatorError::StackUnderFlow => write!(
+            #[cfg(feature = "std")]
+            EmulatorError::LoadError(_) => 0,
+            EmulatorError::StackOverFlow { .. } => 1,
+            EmulatorError::StackUnderFlow { .. } => 2,
+            EmulatorError::InvalidAddress { .. } => 3,
+            EmulatorError::OutOfBounds { .. } => 4,
+            EmulatorError::InvalidRegister(_) => 5,
+            EmulatorError::RomTooLarge { .. } => 6,
+            EmulatorError::EmptyRom => 7,
+            EmulatorError::LoadOutOfBounds { .. } => 8,
+            EmulatorError::InvalidOpcode { .. } => 9,
+            EmulatorError::UnsupportedVariant { .. } => 10,
+            EmulatorError::InvalidInstruction(_) => 11,
+            EmulatorError::InvalidSymbol { .. } => 12,
+            EmulatorError::InvalidSourceMap { .. } => 13,
+            EmulatorError::InvalidBufferSize { .. } => 14,
+            EmulatorError::InvalidDisplayData(_) => 15,
+            EmulatorError::InvalidScale(_) => 16,
+            #[cfg(feature = "std")]
+            EmulatorError::ScreenshotError { .. } => 17,
+            EmulatorError::RecordingError(_) => 18,
+            EmulatorError::InvalidRecording(_) => 19,
+            EmulatorError::InvalidSaveState(_) => 20,
+            EmulatorError::InvalidKeyMap { .. } => 21,
+            EmulatorError::InvalidIntelHex { .. } => 22,
+            EmulatorError::InvalidHexText { .. } => 23,
+            EmulatorError::FontCollision { .. } => 24,
+            EmulatorError::ProtectedWrite { .. } => 25,
+            EmulatorError::AddressOutOfRange { .. } => 26,
+            EmulatorError::RplOverflow { .. } => 27,
+        }
+    }
+
+    /// Adds the running instruction to the errors raised without it, e.g. by the memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `fault` - The instruction that was running.
+    ///
+    /// # Returns
+    ///
+    /// * `EmulatorError` - The error, with `fault` as its context if it had none.
+    pub(crate) fn with_fault(mut self, fault: Fault) -> Self {
+        match &mut self {
+            EmulatorError::InvalidAddress { fault: context, .. }
+            | EmulatorError::OutOfBounds { fault: context, .. } => {
+                context.get_or_insert(fault);
+            }
+            EmulatorError::ProtectedWrite { word, .. } => {
+                word.get_or_insert(fault.word);
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+/// Writes where an error occurred, after the message and before its final dot.
+fn write_fault(f: &mut core::fmt::Formatter<'_>, fault: &Option<Fault>) -> core::fmt::Result {
+    match fault {
+        Some(fault) => write!(f, " in {fault}."),
+        None => write!(f, "."),
+    }
+}
+
+impl core::fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EmulatorError::StackOverFlow {
+                depth,
+                pc,
+                word,
+                returns,
+            } => {
+                let call = Fault { pc: *pc, word: *word };
+                write!(
+                    f,
+                    "Stack Overflow: The call {call} is nested more than {depth} deep, returning to"
+                )?;
+                for address in returns {
+                    write!(f, " 0x{:03X}", address.inner())?;
+                }
+                write!(f, ".")
+            }
+            EmulatorError::StackUnderFlow { pc, word } => write!(
                 f,
-                "Stack Underflow: Unable to pop item, the stack is empty."
+                "Stack Underflow: The return {} has no call to return to.",
+                Fault { pc: *pc, word: *word }
             ),
+            #[cfg(feature = "std")]
             EmulatorError::LoadError(e) => write!(f, "Cannot Load the ROM: {e}"),
-            EmulatorError::InvalidAddress(address) => {
-                write!(f, "Invalid Address: The address {address} is not valid.")
+            EmulatorError::InvalidAddress { address, fault } => {
+                write!(f, "Invalid Address: The address {address} is not valid")?;
+                write_fault(f, fault)
             }
-            EmulatorError::OutOfBounds(end_address) => {
+            EmulatorError::OutOfBounds { address, fault } => {
                 write!(
                     f,
-                    "Out of Bounds: The address {end_address} is out of bounds. [0x000, 0xFFF]"
-                )
+                    "Out of Bounds: The address {address} is out of bounds of the memory"
+                )?;
+                write_fault(f, fault)
             }
             EmulatorError::InvalidRegister(x) => write!(
                 f,
-                "Invalid Register: The register {x} is not valid. [0x0, 0xF]")
+                "Invalid Register: The register {x} is not valid. [0x0, 0xF]"),
+            EmulatorError::RomTooLarge { len, max } => write!(
+                f,
+                "ROM Too Large: The ROM is {len} bytes long, the maximum size is {max} bytes."
+            ),
+            EmulatorError::EmptyRom => write!(f, "Empty ROM: The ROM has no data."),
+            EmulatorError::LoadOutOfBounds { address, len, size } => write!(
+                f,
+                "Load Out of Bounds: {len} bytes at 0x{address:03X} do not fit in {size} bytes of memory."
+            ),
+            EmulatorError::InvalidOpcode { word, pc } => write!(
+                f,
+                "Invalid Opcode: The word 0x{word:04X} ({}) at 0x{:03X} is not a valid instruction.",
+                Opcode::Invalid(*word),
+                pc.inner()
+            ),
+            EmulatorError::UnsupportedVariant { variant, pc, word } => write!(
+                f,
+                "Unsupported Variant: The instruction {} is a {variant} instruction, which is not supported.",
+                Fault { pc: *pc, word: *word }
+            ),
+            EmulatorError::InvalidInstruction(text) => write!(
+                f,
+                "Invalid Instruction: `{text}` can not be parsed as an instruction."
+            ),
+            EmulatorError::InvalidSymbol { line, reason } => {
+                write!(f, "Invalid Symbol: {reason} at line {line}.")
+            }
+            EmulatorError::InvalidSourceMap { line, reason } => {
+                write!(f, "Invalid Source Map: {reason} at line {line}.")
+            }
+            EmulatorError::InvalidBufferSize { expected, actual } => write!(
+                f,
+                "Invalid Buffer Size: The buffer is {actual} bytes long, {expected} bytes are needed."
+            ),
+            EmulatorError::InvalidDisplayData(reason) => {
+                write!(f, "Invalid Display Data: {reason}.")
+            }
+            EmulatorError::InvalidScale(scale) => write!(
+                f,
+                "Invalid Scale: The display can not be upscaled by {scale}, the scale must be at least 1."
+            ),
+            #[cfg(feature = "std")]
+            EmulatorError::ScreenshotError { path, reason } => {
+                write!(f, "Screenshot Error: {reason} while writing {}.", path.display())
+            }
+            EmulatorError::RecordingError(reason) => write!(f, "Recording Error: {reason}."),
+            EmulatorError::InvalidRecording(reason) => {
+                write!(f, "Invalid Recording: {reason}.")
+            }
+            EmulatorError::InvalidSaveState(reason) => {
+                write!(f, "Invalid Save State: {reason}.")
+            }
+            EmulatorError::InvalidKeyMap { line, reason } => {
+                write!(f, "Invalid Key Map: {reason} at line {line}.")
+            }
+            EmulatorError::InvalidIntelHex { line, reason } => {
+                write!(f, "Invalid Intel HEX: {reason} at line {line}.")
+            }
+            EmulatorError::InvalidHexText {
+                line,
+                column,
+                reason,
+            } => write!(f, "Invalid Hex Text: {reason} at line {line}, column {column}."),
+            EmulatorError::FontCollision { base, end } => write!(
+                f,
+                "Font Collision: The fonts at 0x{base:03X}..0x{end:03X} overlap the ROM or do not fit in 4KB."
+            ),
+            EmulatorError::ProtectedWrite { addr, pc, word } => match word {
+                Some(word) => write!(
+                    f,
+                    "Protected Write: The instruction {} wrote to 0x{:03X}, below 0x200.",
+                    Fault { pc: *pc, word: *word },
+                    addr.inner()
+                ),
+                None => write!(
+                    f,
+                    "Protected Write: A write at 0x{:03X} went to 0x{:03X}, below 0x200.",
+                    pc.inner(),
+                    addr.inner()
+                ),
+            },
+            EmulatorError::AddressOutOfRange { address, size } => write!(
+                f,
+                "Address Out of Range: 0x{address:03X} is outside the memory, valid addresses are 0x000..0x{size:03X}."
+            ),
+            EmulatorError::RplOverflow { register, fault } => write!(
+                f,
+                "RPL Overflow: The instruction {fault} uses V{register:X}, only V0 to V7 fit in the RPL user flags."
+            ),
         }
     }
 }
 
-impl std::error::Error for EmulatorError {}
+impl core::error::Error for EmulatorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        let pc = Address::ENTRY_POINT;
+        let text = || "reason".to_string();
+        let errors = [
+            #[cfg(feature = "std")]
+            EmulatorError::LoadError(std::io::ErrorKind::NotFound.into()),
+            EmulatorError::StackOverFlow {
+                depth: 16,
+                pc,
+                word: 0x2200,
+                returns: Vec::new(),
+            },
+            EmulatorError::StackUnderFlow { pc, word: 0x00EE },
+            EmulatorError::InvalidAddress {
+                address: 0x1000,
+                fault: None,
+            },
+            EmulatorError::OutOfBounds {
+                address: 0x1001,
+                fault: None,
+            },
+            EmulatorError::InvalidRegister(0x10),
+            EmulatorError::RomTooLarge {
+                len: 4096,
+                max: 3584,
+            },
+            EmulatorError::EmptyRom,
+            EmulatorError::LoadOutOfBounds {
+                address: 0xFFF,
+                len: 2,
+                size: 4096,
+            },
+            EmulatorError::InvalidOpcode { word: 0x5123, pc },
+            EmulatorError::UnsupportedVariant {
+                variant: Variant::MegaChip,
+                pc,
+                word: 0x0011,
+            },
+            EmulatorError::InvalidInstruction(text()),
+            EmulatorError::InvalidSymbol {
+                line: 1,
+                reason: text(),
+            },
+            EmulatorError::InvalidSourceMap {
+                line: 1,
+                reason: text(),
+            },
+            EmulatorError::InvalidBufferSize {
+                expected: 1,
+                actual: 0,
+            },
+            EmulatorError::InvalidDisplayData(text()),
+            EmulatorError::InvalidScale(0),
+            #[cfg(feature = "std")]
+            EmulatorError::ScreenshotError {
+                path: "shot.png".into(),
+                reason: text(),
+            },
+            EmulatorError::RecordingError(text()),
+            EmulatorError::InvalidRecording(text()),
+            EmulatorError::InvalidSaveState(text()),
+            EmulatorError::InvalidKeyMap {
+                line: 1,
+                reason: text(),
+            },
+            EmulatorError::InvalidIntelHex {
+                line: 1,
+                reason: text(),
+            },
+            EmulatorError::InvalidHexText {
+                line: 1,
+                column: 1,
+                reason: text(),
+            },
+            EmulatorError::FontCollision {
+                base: 0x1C0,
+                end: 0x2B0,
+            },
+            EmulatorError::ProtectedWrite {
+                addr: Address::new(0x050),
+                pc,
+                word: None,
+            },
+            EmulatorError::AddressOutOfRange {
+                address: 0x1000,
+                size: 0x1000,
+            },
+            EmulatorError::RplOverflow {
+                register: 0xF,
+                fault: Fault { pc, word: 0xFF75 },
+            },
+        ];
+        // Without `std` the I/O variants do not exist, the others keep their codes
+        #[cfg(feature = "std")]
+        assert_eq!(errors.len(), CODES.len());
+        for (error, next) in errors.iter().zip(errors.iter().skip(1)) {
+            assert!(error.index() < next.index());
+        }
+        for error in &errors {
+            let (name, code, category) = CODES[error.index()];
+            assert!(format!("{error:?}").starts_with(name), "{name}");
+            assert_eq!((error.code(), error.category()), (code, category));
+        }
+        // Each variant has its own code, in the hundreds of its category
+        let mut codes: Vec<_> = CODES.iter().map(|&(_, code, _)| code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), CODES.len());
+        for (_, code, category) in CODES {
+            assert_eq!(code / 100, category as u32 + 1);
+        }
+    }
+}
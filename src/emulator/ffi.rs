@@ -0,0 +1,454 @@
+//! A C ABI to embed the emulator in other languages, built with the `ffi` feature.
+//!
+//! The functions are exported by the shared library of `bindings/` and declared in
+//! `include/r8.h`, regenerate it with `scripts/generate-header.sh` after changing them.
+//! A typical frontend creates an emulator with `r8_new`, loads a ROM with
+//! `r8_load_rom`, then calls `r8_run_frame`, `r8_framebuffer` and `r8_key_event` 60 times
+//! per second, and frees the emulator with `r8_free`.
+//!
+//! # Errors
+//!
+//! The functions that can fail return `R8_OK`, or the code of the error: a positive
+//! `EmulatorError::code`, `R8_ERROR_NULL` or `R8_ERROR_PANIC`. The code and the message of
+//! the last error of the calling thread are kept for `r8_last_error_code` and
+//! `r8_last_error_message`.
+//!
+//! # Thread safety
+//!
+//! An `R8` can be moved to and used from any thread, but not from two threads at once:
+//! calls on the same emulator must be serialized by the caller. Different emulators are
+//! independent. The last error is kept per thread.
+//!
+//! # Panics
+//!
+//! No panic unwinds into the caller, a panic is caught at the boundary and reported as
+//! `R8_ERROR_PANIC`. The emulator it happened in should be freed then.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{
+    constants::{Palette, MONOCHROME_PALETTE},
+    emulator::Emulator,
+    error::EmulatorError,
+    keyboard::Key,
+};
+
+/// The call succeeded.
+pub const R8_OK: i32 = 0;
+/// A pointer argument is null.
+pub const R8_ERROR_NULL: i32 = -1;
+/// The emulator panicked, it should be freed.
+pub const R8_ERROR_PANIC: i32 = -2;
+
+thread_local! {
+    /// The code and the message of the last error of the thread.
+    static LAST_ERROR: RefCell<Option<(i32, CString)>> = const { RefCell::new(None) };
+}
+
+/// An error at the boundary, with the code returned to the caller and its message.
+struct Failure(i32, String);
+
+impl From<EmulatorError> for Failure {
+    fn from(error: EmulatorError) -> Self {
+        Failure(error.code() as i32, error.to_string())
+    }
+}
+
+/// Runs a call at the boundary, catching the panics and recording the errors.
+///
+/// # Arguments
+///
+/// * `call` - The call.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or the code of the error. The error is kept for
+///   `r8_last_error_code` and `r8_last_error_message`.
+fn guard(call: impl FnOnce() -> Result<(), Failure>) -> i32 {
+    let result = panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|payload| {
+        let reason = payload
+            .downcast_ref::<&str>()
+            .map(|reason| reason.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown reason".to_string());
+        Err(Failure(
+            R8_ERROR_PANIC,
+            format!("The emulator panicked: {reason}"),
+        ))
+    });
+    match result {
+        Ok(()) => R8_OK,
+        Err(Failure(code, message)) => {
+            let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some((code, message)));
+            code
+        }
+    }
+}
+
+/// The error of a null pointer argument.
+fn null() -> Failure {
+    Failure(R8_ERROR_NULL, "A pointer argument is null".to_string())
+}
+
+/// Returns the emulator behind a pointer given by the caller.
+///
+/// # Safety
+///
+/// `r8` must be null or point to an emulator created by `r8_new` and not freed, that no
+/// other thread uses.
+unsafe fn emulator<'a>(r8: *mut R8) -> Result<&'a mut R8, Failure> {
+    // SAFETY: the caller guarantees `r8` is null or valid and not shared
+    unsafe { r8.as_mut() }.ok_or_else(null)
+}
+
+/// Returns a slice from a pointer and a length given by the caller.
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes. A null pointer is only accepted
+/// with a length of 0.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], Failure> {
+    match data.is_null() {
+        true if len == 0 => Ok(&[]),
+        true => Err(null()),
+        // SAFETY: the caller guarantees `data` points to `len` bytes
+        false => Ok(unsafe { slice::from_raw_parts(data, len) }),
+    }
+}
+
+/// An emulator behind the C ABI, opaque to the caller.
+///
+/// # Fields
+///
+/// * `emulator` - The emulator.
+/// * `palette` - The colors of the framebuffer.
+/// * `ticks_per_frame` - The instructions run by `r8_run_frame`.
+/// * `framebuffer` - The RGBA pixels returned by `r8_framebuffer`.
+pub struct R8 {
+    emulator: Emulator,
+    palette: Palette,
+    ticks_per_frame: u32,
+    framebuffer: Vec<u8>,
+}
+
+/// Creates an emulator running one instruction per frame.
+///
+/// # Returns
+///
+/// * `*mut R8` - The emulator, free it with `r8_free`. Null if it can not be created.
+#[no_mangle]
+pub extern "C" fn r8_new() -> *mut R8 {
+    let mut r8 = ptr::null_mut();
+    guard(|| {
+        r8 = Box::into_raw(Box::new(R8 {
+            emulator: Emulator::new(),
+            palette: MONOCHROME_PALETTE,
+            ticks_per_frame: 1,
+            framebuffer: Vec::new(),
+        }));
+        Ok(())
+    });
+    r8
+}
+
+/// Frees an emulator created by `r8_new`.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator, nothing is done if it is null.
+///
+/// # Safety
+///
+/// `r8` must be null or point to an emulator created by `r8_new` and not freed, that no
+/// other thread uses. It must not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn r8_free(r8: *mut R8) {
+    if !r8.is_null() {
+        // SAFETY: the caller guarantees `r8` comes from `r8_new` and is not used after
+        guard(|| {
+            drop(unsafe { Box::from_raw(r8) });
+            Ok(())
+        });
+    }
+}
+
+/// Loads a ROM, see `Emulator::load_rom_bytes`.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+/// * `data` - The content of the ROM.
+/// * `len` - The length of the ROM in bytes.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or the code of the error, e.g. 402 for an empty ROM.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`, and `data` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn r8_load_rom(r8: *mut R8, data: *const u8, len: usize) -> i32 {
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid and `data` points to `len` bytes
+        let (r8, rom) = unsafe { (emulator(r8)?, bytes(data, len)?) };
+        Ok(r8.emulator.load_rom_bytes(rom)?)
+    })
+}
+
+/// Sets the instructions run by each `r8_run_frame`.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+/// * `ticks` - The instructions per frame, at least 1.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or `R8_ERROR_NULL`.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`.
+#[no_mangle]
+pub unsafe extern "C" fn r8_set_ticks_per_frame(r8: *mut R8, ticks: u32) -> i32 {
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid
+        let r8 = unsafe { emulator(r8) }?;
+        r8.ticks_per_frame = ticks.max(1);
+        Ok(())
+    })
+}
+
+/// Runs a frame, call it 60 times per second.
+///
+/// The timers are decremented once per frame whatever the instructions per frame, see
+/// `Emulator::run_frame`.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or the code of the error, e.g. 202 when the program returns with an
+///   empty stack.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`.
+#[no_mangle]
+pub unsafe extern "C" fn r8_run_frame(r8: *mut R8) -> i32 {
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid
+        let r8 = unsafe { emulator(r8) }?;
+        r8.emulator.run_frame(r8.ticks_per_frame)?;
+        Ok(())
+    })
+}
+
+/// Presses or releases a key.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+/// * `key` - The key, only its low nibble is used.
+/// * `down` - True when the key is pressed, false when it is released.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or `R8_ERROR_NULL`.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`.
+#[no_mangle]
+pub unsafe extern "C" fn r8_key_event(r8: *mut R8, key: u8, down: bool) -> i32 {
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid
+        let r8 = unsafe { emulator(r8) }?;
+        if let Some(&key) = Key::all().nth((key & 0xF) as usize) {
+            match down {
+                true => r8.emulator.press_key(key),
+                false => r8.emulator.release_key(key),
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Returns true while the sound timer runs.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator, false is returned if it is null.
+///
+/// # Safety
+///
+/// `r8` must be null or a valid emulator, see `r8_free`.
+#[no_mangle]
+pub unsafe extern "C" fn r8_beep_active(r8: *const R8) -> bool {
+    // SAFETY: the caller guarantees `r8` is null or valid
+    unsafe { r8.as_ref() }.is_some_and(|r8| r8.emulator.sound_timer() > 0)
+}
+
+/// Renders the display as RGBA pixels, 4 bytes per pixel.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+/// * `width` - Set to the width of the image in pixels, if not null.
+/// * `height` - Set to the height of the image in pixels, if not null.
+/// * `stride` - Set to the bytes between the start of two rows, if not null.
+///
+/// # Returns
+///
+/// * `*const u8` - The pixels, row by row. They belong to the emulator and are valid until
+///   the next call with it. Null on error.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`, and the other pointers must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn r8_framebuffer(
+    r8: *mut R8,
+    width: *mut usize,
+    height: *mut usize,
+    stride: *mut usize,
+) -> *const u8 {
+    let mut pixels = ptr::null();
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid and the outputs null or writable
+        let r8 = unsafe { emulator(r8) }?;
+        let display = r8.emulator.display();
+        let (w, h) = display.dimensions();
+        r8.framebuffer.resize(w * h * 4, 0);
+        display.write_rgba8(&mut r8.framebuffer, &r8.palette)?;
+        for (output, value) in [(width, w), (height, h), (stride, w * 4)] {
+            if let Some(output) = unsafe { output.as_mut() } {
+                *output = value;
+            }
+        }
+        pixels = r8.framebuffer.as_ptr();
+        Ok(())
+    });
+    pixels
+}
+
+/// Saves the state of the emulator, see `Emulator::save_state`.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+/// * `buffer` - Where the state is written, it may be null to query the size.
+/// * `capacity` - The size of `buffer` in bytes.
+/// * `len` - Set to the size of the state, if not null.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or 501 if `buffer` is too small for the state. `len` is set then,
+///   call again with a buffer of that size.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`, `buffer` must be null or point to
+/// `capacity` writable bytes, and `len` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn r8_save_state(
+    r8: *mut R8,
+    buffer: *mut u8,
+    capacity: usize,
+    len: *mut usize,
+) -> i32 {
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid and the outputs null or writable
+        let r8 = unsafe { emulator(r8) }?;
+        let state = r8.emulator.save_state();
+        if let Some(len) = unsafe { len.as_mut() } {
+            *len = state.len();
+        }
+        if buffer.is_null() || capacity < state.len() {
+            return Err(EmulatorError::InvalidBufferSize {
+                expected: state.len(),
+                actual: if buffer.is_null() { 0 } else { capacity },
+            }
+            .into());
+        }
+        // SAFETY: `buffer` has room for the state
+        unsafe { ptr::copy_nonoverlapping(state.as_ptr(), buffer, state.len()) };
+        Ok(())
+    })
+}
+
+/// Restores a state saved by `r8_save_state`, see `Emulator::load_state`.
+///
+/// # Arguments
+///
+/// * `r8` - The emulator.
+/// * `data` - The state.
+/// * `len` - The size of the state in bytes.
+///
+/// # Returns
+///
+/// * `i32` - `R8_OK`, or the code of the error, e.g. 705 for an invalid state. The
+///   emulator is left untouched then.
+///
+/// # Safety
+///
+/// `r8` must be a valid emulator, see `r8_free`, and `data` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn r8_load_state(r8: *mut R8, data: *const u8, len: usize) -> i32 {
+    guard(|| {
+        // SAFETY: the caller guarantees `r8` is valid and `data` points to `len` bytes
+        let (r8, state) = unsafe { (emulator(r8)?, bytes(data, len)?) };
+        Ok(r8.emulator.load_state(state)?)
+    })
+}
+
+/// Returns the code of the last error of the calling thread, `R8_OK` if there was none.
+#[no_mangle]
+pub extern "C" fn r8_last_error_code() -> i32 {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(R8_OK, |(code, _)| *code))
+}
+
+/// Returns the message of the last error of the calling thread.
+///
+/// # Returns
+///
+/// * `*const c_char` - A NUL terminated UTF-8 string, valid until the next error of the
+///   thread. Null if there was no error.
+#[no_mangle]
+pub extern "C" fn r8_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |(_, message)| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    #[test]
+    fn test_guard() {
+        assert_eq!(guard(|| Ok(())), R8_OK);
+        assert_eq!(guard(|| Err(EmulatorError::EmptyRom.into())), 402);
+        assert_eq!(r8_last_error_code(), 402);
+
+        // Panics are caught, NUL bytes do not cut the message
+        assert_eq!(guard(|| panic!("bad\0opcode")), R8_ERROR_PANIC);
+        assert_eq!(r8_last_error_code(), R8_ERROR_PANIC);
+        let message = unsafe { CStr::from_ptr(r8_last_error_message()) };
+        assert_eq!(message.to_str(), Ok("The emulator panicked: badopcode"));
+    }
+}
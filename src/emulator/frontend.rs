@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+use log::error;
+
+use crate::{display::Display, emulator::Emulator, error::EmulatorError};
+
+/// The input of a frame, as read by the frontend.
+///
+/// # Fields
+///
+/// * `keys` - The keys held, bit n is the key n, see `Emulator::set_key_mask`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputState {
+    pub keys: u16,
+}
+
+/// What [`run`] needs from a host: its input, a screen and a speaker.
+///
+/// # Notes
+///
+/// SDL, terminal or web frontends implement this, [`run`] does the timing.
+pub trait Frontend {
+    /// Reads the input, once per frame before the instructions run.
+    fn poll_input(&mut self) -> InputState;
+
+    /// Draws the display, each time it changes.
+    fn present(&mut self, display: &Display);
+
+    /// Starts or stops the beep, each time the sound timer starts or stops.
+    fn set_beep(&mut self, on: bool);
+
+    /// Returns true to stop [`run`], checked before each frame.
+    fn should_exit(&self) -> bool;
+}
+
+/// The settings of [`run`].
+///
+/// # Fields
+///
+/// * `ticks_per_frame` - The instructions per frame, at least 1. The frame is run by
+///   `Emulator::run_frame`, the timers are decremented once per frame whatever the count.
+/// * `frame_rate` - The frames per second.
+/// * `throttle` - If true, each frame lasts `1 / frame_rate` seconds. Off, frames run as
+///   fast as they can, for tests and benchmarks.
+/// * `max_frames` - The frames to run before returning, `None` runs until the frontend
+///   exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunConfig {
+    pub ticks_per_frame: u32,
+    pub frame_rate: u32,
+    pub throttle: bool,
+    pub max_frames: Option<u64>,
+}
+
+impl Default for RunConfig {
+    /// One instruction per frame at 60 frames per second, as the bundled frontends run.
+    fn default() -> Self {
+        Self {
+            ticks_per_frame: 1,
+            frame_rate: 60,
+            throttle: true,
+            max_frames: None,
+        }
+    }
+}
+
+/// Runs the emulator on a frontend until the frontend exits or `max_frames` have run.
+///
+/// # Arguments
+///
+/// * `emulator` - The emulator, with a ROM loaded.
+/// * `frontend` - The host.
+/// * `config` - The timing.
+///
+/// # Returns
+///
+/// * `Result<(), EmulatorError>` - The first error of the emulator, it is logged and the
+///   loop stops.
+///
+/// # Notes
+///
+/// Each frame polls the input, runs the instructions, then presents the display if it
+/// changed and updates the beep. While `FX0A` waits the instructions do nothing, the next
+/// frames poll the keys that complete the wait.
+pub fn run(
+    emulator: &mut Emulator,
+    frontend: &mut impl Frontend,
+    config: &RunConfig,
+) -> Result<(), EmulatorError> {
+    let frame_duration = Duration::from_secs(1) / config.frame_rate.max(1);
+    // The version of the display last presented, none yet
+    let mut drawn = None;
+    let mut beeping = false;
+    let mut frame = 0;
+
+    while !frontend.should_exit() && config.max_frames.is_none_or(|max| frame < max) {
+        let frame_start = Instant::now();
+
+        emulator.set_key_mask(frontend.poll_input().keys);
+        if let Err(err) = emulator.run_frame(config.ticks_per_frame) {
+            error!("Fatal emulator error: {err}");
+            return Err(err);
+        }
+
+        let version = emulator.display().version();
+        if drawn != Some(version) {
+            drawn = Some(version);
+            frontend.present(emulator.display());
+        }
+        let beep = emulator.sound_timer() > 0;
+        if beep != beeping {
+            beeping = beep;
+            frontend.set_beep(beep);
+        }
+        frame += 1;
+
+        let elapsed = frame_start.elapsed();
+        if config.throttle && elapsed < frame_duration {
+            std::thread::sleep(frame_duration - elapsed);
+        }
+    }
+    if beeping {
+        frontend.set_beep(false);
+    }
+    Ok(())
+}
+
+/// A frontend without a screen or a speaker, for tests.
+///
+/// # Fields
+///
+/// * `input` - The input returned by every poll.
+/// * `polls` - The frames polled so far.
+/// * `presents` - The times the display was presented.
+/// * `beeps` - The beep states set, in order.
+/// * `exit_after` - The frames to run before exiting, `None` never exits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NullFrontend {
+    pub input: InputState,
+    pub polls: u64,
+    pub presents: u64,
+    pub beeps: Vec<bool>,
+    pub exit_after: Option<u64>,
+}
+
+impl Frontend for NullFrontend {
+    fn poll_input(&mut self) -> InputState {
+        self.polls += 1;
+        self.input
+    }
+
+    fn present(&mut self, _display: &Display) {
+        self.presents += 1;
+    }
+
+    fn set_beep(&mut self, on: bool) {
+        self.beeps.push(on);
+    }
+
+    fn should_exit(&self) -> bool {
+        self.exit_after.is_some_and(|frames| self.polls >= frames)
+    }
+}
@@ -0,0 +1,205 @@
+//! Fuzzing the emulator with random programs.
+//!
+//! A [`FuzzInput`] is a ROM with a profile, a seed and a key schedule. [`FuzzInput::run`]
+//! runs it and panics if the emulator breaks one of its invariants, the errors of the
+//! emulator are fine. The inputs come from `cargo fuzz` with the `arbitrary` feature, see
+//! `fuzz/fuzz_targets/run.rs`, or from [`FuzzInput::random`] for the seeded test run by
+//! `cargo test`. The module is only built with the `arbitrary` feature and for the tests.
+
+#[cfg(feature = "arbitrary")]
+use crate::opcode::Opcode;
+use crate::{
+    emulator::Emulator,
+    error::EmulatorError,
+    keyboard::{Key, KeyEvent},
+    prelude::*,
+    quirks::{Profile, PRESETS},
+    rand::RandGen,
+};
+
+/// The frames a run lasts at most, so each input runs quickly.
+pub const MAX_FRAMES: u16 = 120;
+
+/// The instructions per frame at most.
+pub const MAX_INSTRUCTIONS_PER_FRAME: u8 = 32;
+
+/// A random program and how to run it.
+///
+/// # Fields
+///
+/// * `profile` - The interpreter.
+/// * `rom` - The program.
+/// * `seed` - The seed of the random number generator.
+/// * `keys` - The key events, with the frame they happen at.
+/// * `frames` - The frames to run, up to [`MAX_FRAMES`].
+/// * `instructions_per_frame` - The instructions of each frame, from 1 to
+///   [`MAX_INSTRUCTIONS_PER_FRAME`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzInput {
+    pub profile: Profile,
+    pub rom: Vec<u8>,
+    pub seed: u64,
+    pub keys: Vec<(u16, KeyEvent)>,
+    pub frames: u16,
+    pub instructions_per_frame: u8,
+}
+
+impl FuzzInput {
+    /// Generates an input from a seed, with the random number generator of the emulator.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed, the same seed always gives the same input.
+    ///
+    /// # Returns
+    ///
+    /// * `FuzzInput` - A profile of [`PRESETS`], a ROM of 0 to 255 random bytes, a 32-bit
+    ///   seed, up to 7 key events in the first [`MAX_FRAMES`] frames, 1 to [`MAX_FRAMES`]
+    ///   frames and 1 to [`MAX_INSTRUCTIONS_PER_FRAME`] instructions per frame.
+    ///
+    /// # Notes
+    ///
+    /// The bytes are not built from opcodes as with `arbitrary`, so the ROM may be empty,
+    /// have an odd length and hold words that are invalid on its profile.
+    pub fn random(seed: u64) -> Self {
+        let mut rand = RandGen::with_seed(seed);
+        let mut next = || rand.next();
+        let profile = PRESETS[next() as usize % PRESETS.len()].profile;
+        let rom = (0..next()).map(|_| next()).collect();
+        let seed = u64::from_le_bytes([next(), next(), next(), next(), 0, 0, 0, 0]);
+        let keys = (0..next() % 8)
+            .map(|_| (next() as u16 % MAX_FRAMES, key_event(next())))
+            .collect();
+        Self {
+            profile,
+            rom,
+            seed,
+            keys,
+            frames: next() as u16 % MAX_FRAMES + 1,
+            instructions_per_frame: next() % MAX_INSTRUCTIONS_PER_FRAME + 1,
+        }
+    }
+
+    /// Runs the input and checks the invariants of the emulator after each instruction.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - The error that stopped the emulator, if any.
+    ///
+    /// # Panics
+    ///
+    /// If an invariant does not hold: the program counter is in memory and the stack is
+    /// not deeper than its depth.
+    pub fn run(&self) -> Result<(), EmulatorError> {
+        let mut emulator = Emulator::with_profile(self.profile);
+        emulator.seed_rng(self.seed);
+        emulator.load_rom_bytes(&self.rom)?;
+        let instructions = self
+            .instructions_per_frame
+            .clamp(1, MAX_INSTRUCTIONS_PER_FRAME);
+        for frame in 0..self.frames.min(MAX_FRAMES) {
+            for &(_, event) in self.keys.iter().filter(|&&(at, _)| at == frame) {
+                emulator.push_key_event(event);
+            }
+            for instruction in 0..instructions {
+                let result = match instruction {
+                    0 => emulator.tick(),
+                    _ => emulator.step(),
+                };
+                // A failed instruction may leave the program counter anywhere
+                result?;
+                check_invariants(&emulator);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Panics if the emulator breaks an invariant, see [`FuzzInput::run`].
+fn check_invariants(emulator: &Emulator) {
+    let pc = emulator.pc().inner() as usize;
+    let size = emulator.memory.size();
+    assert!(
+        pc < size,
+        "The PC {pc:#X} is beyond the {size:#X} bytes of memory"
+    );
+    let stack = emulator.stack();
+    assert!(
+        stack.len() <= stack.depth(),
+        "The stack holds {} addresses, its depth is {}",
+        stack.len(),
+        stack.depth()
+    );
+}
+
+/// Returns a key press or release from a random byte.
+fn key_event(byte: u8) -> KeyEvent {
+    let key = Key::all()
+        .nth((byte & 0xF) as usize)
+        .copied()
+        .unwrap_or(Key::K0);
+    match byte & 0x10 {
+        0 => KeyEvent::Down(key),
+        _ => KeyEvent::Up(key),
+    }
+}
+
+/// Appends an opcode to a ROM, with the operand word of the long loads.
+#[cfg(feature = "arbitrary")]
+fn push_opcode(rom: &mut Vec<u8>, opcode: Opcode) {
+    rom.extend(opcode.encode().to_be_bytes());
+    match opcode {
+        Opcode::LdILong { address } => rom.extend(address.inner().to_be_bytes()),
+        Opcode::LdHi { address } => rom.extend((address as u16).to_be_bytes()),
+        _ => {}
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for FuzzInput {
+    /// The ROM is a list of opcodes, see `Opcode::arbitrary`, followed by data bytes for
+    /// the sprites and the loads.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let profile = PRESETS[u.choose_index(PRESETS.len())?].profile;
+        let mut rom = Vec::new();
+        for _ in 0..u.int_in_range(0..=256)? {
+            push_opcode(&mut rom, u.arbitrary::<Opcode>()?);
+        }
+        rom.extend(u.arbitrary::<Vec<u8>>()?);
+        let mut keys = Vec::new();
+        for _ in 0..u.int_in_range(0..=16)? {
+            keys.push((u.int_in_range(0..=MAX_FRAMES)?, key_event(u.arbitrary()?)));
+        }
+        Ok(Self {
+            profile,
+            rom,
+            seed: u.arbitrary()?,
+            keys,
+            frames: u.int_in_range(1..=MAX_FRAMES)?,
+            instructions_per_frame: u.int_in_range(1..=MAX_INSTRUCTIONS_PER_FRAME)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random() {
+        assert_eq!(FuzzInput::random(7), FuzzInput::random(7));
+        assert_ne!(FuzzInput::random(7), FuzzInput::random(8));
+        let input = FuzzInput::random(7);
+        assert!((1..=MAX_FRAMES).contains(&input.frames));
+        assert!((1..=MAX_INSTRUCTIONS_PER_FRAME).contains(&input.instructions_per_frame));
+    }
+
+    #[test]
+    /// A few thousand random programs on every profile, none of them panics
+    fn test_mini_fuzz() {
+        for seed in 0..3000 {
+            // The errors of the emulator are expected, only panics fail
+            let _ = FuzzInput::random(seed).run();
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use crate::prelude::*;
+
+/// A button of a gamepad, named by its position on the common layouts.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    /// The bottom face button, A on Xbox controllers.
+    South,
+    /// The right face button, B on Xbox controllers.
+    East,
+    /// The left face button, X on Xbox controllers.
+    West,
+    /// The top face button, Y on Xbox controllers.
+    North,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+}
+
+/// An analog axis of a gamepad, from -1.0 (left or up) to 1.0 (right or down).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+/// The state of a gamepad for one frame, as read by the frontend.
+///
+/// # Fields
+///
+/// * `buttons` - The pressed buttons, bit n is the button `n as Button`.
+/// * `axes` - The position of each axis, indexed by `Axis as usize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GamepadState {
+    pub buttons: u32,
+    pub axes: [f32; 4],
+}
+
+impl GamepadState {
+    /// Returns true if a button is pressed.
+    pub fn is_pressed(&self, button: Button) -> bool {
+        (self.buttons >> button as u8) & 1 == 1
+    }
+
+    /// Presses or releases a button.
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        match pressed {
+            true => self.buttons |= 1 << button as u8,
+            false => self.buttons &= !(1 << button as u8),
+        }
+    }
+
+    /// Returns the position of an axis.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes[axis as usize]
+    }
+}
+
+/// Maps an axis to a pair of keys, one for each direction.
+///
+/// # Fields
+///
+/// * `axis` - The axis.
+/// * `negative` - The key pressed when the axis is pushed left or up, if any.
+/// * `positive` - The key pressed when the axis is pushed right or down, if any.
+/// * `press` - How far the axis must be pushed to press a key, from 0.0 to 1.0.
+/// * `release` - How far back it must return to release it, below `press` so a stick
+///   resting near the threshold does not chatter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisBinding {
+    pub axis: Axis,
+    pub negative: Option<u8>,
+    pub positive: Option<u8>,
+    pub press: f32,
+    pub release: f32,
+}
+
+impl AxisBinding {
+    /// Creates a binding with the default thresholds, 0.5 to press and 0.3 to release.
+    ///
+    /// # Arguments
+    ///
+    /// * `axis` - The axis.
+    /// * `negative` - The key pressed when the axis is pushed left or up.
+    /// * `positive` - The key pressed when the axis is pushed right or down.
+    pub fn new(axis: Axis, negative: u8, positive: u8) -> Self {
+        Self {
+            axis,
+            negative: Some(negative),
+            positive: Some(positive),
+            press: 0.5,
+            release: 0.3,
+        }
+    }
+}
+
+/// Which keys the buttons and axes of a gamepad press.
+///
+/// # Fields
+///
+/// * `buttons` - The key each bound button presses, several buttons can press the same key.
+/// * `axes` - The keys each bound axis presses.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GamepadMapping {
+    pub buttons: Vec<(Button, u8)>,
+    pub axes: Vec<AxisBinding>,
+}
+
+impl GamepadMapping {
+    /// The mapping for games moving with the keys 2, 4, 6 and 8 and acting with 5, the
+    /// most common CHIP-8 layout.
+    ///
+    /// The d-pad and the left stick move, the south button is 5 and the east button 0.
+    pub fn numpad() -> Self {
+        Self::directions([0x2, 0x8, 0x4, 0x6], [0x5, 0x0])
+    }
+
+    /// The mapping for games moving with the keys 5, 7, 8 and 9 (WASD on a QWERTY keyboard)
+    /// and acting with 6 and 4 (E and Q).
+    ///
+    /// The d-pad and the left stick move, the south button is 6 and the east button 4.
+    pub fn wasd() -> Self {
+        Self::directions([0x5, 0x8, 0x7, 0x9], [0x6, 0x4])
+    }
+
+    /// Creates a mapping moving with the d-pad and the left stick.
+    ///
+    /// # Arguments
+    ///
+    /// * `[up, down, left, right]` - The keys of the directions.
+    /// * `[south, east]` - The keys of the south and east buttons.
+    fn directions([up, down, left, right]: [u8; 4], [south, east]: [u8; 2]) -> Self {
+        Self {
+            buttons: vec![
+                (Button::DPadUp, up),
+                (Button::DPadDown, down),
+                (Button::DPadLeft, left),
+                (Button::DPadRight, right),
+                (Button::South, south),
+                (Button::East, east),
+            ],
+            axes: vec![
+                AxisBinding::new(Axis::LeftX, left, right),
+                AxisBinding::new(Axis::LeftY, up, down),
+            ],
+        }
+    }
+}
+
+/// Turns gamepad states into keypad states, one frame at a time.
+///
+/// # Fields
+///
+/// * `mapping` - The mapping.
+/// * `directions` - The direction each axis binding is pushed to: -1, 0 or 1.
+///
+/// # Notes
+///
+/// The mapper only produces a mask, e.g. for `Emulator::set_key_mask`, frontends combine
+/// it with the keyboard if they want both.
+#[derive(Debug, Clone)]
+pub struct GamepadMapper {
+    mapping: GamepadMapping,
+    directions: Vec<i8>,
+}
+
+impl GamepadMapper {
+    /// Creates a mapper with every axis at rest.
+    pub fn new(mapping: GamepadMapping) -> Self {
+        Self {
+            directions: vec![0; mapping.axes.len()],
+            mapping,
+        }
+    }
+
+    /// Returns the mapping.
+    pub fn mapping(&self) -> &GamepadMapping {
+        &self.mapping
+    }
+
+    /// Maps the state of a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The gamepad state.
+    ///
+    /// # Returns
+    ///
+    /// * `u16` - The pressed keys, bit n is key n. Keys above 0xF are ignored.
+    pub fn map(&mut self, state: &GamepadState) -> u16 {
+        let mut mask = 0u32;
+        for &(button, key) in &self.mapping.buttons {
+            if state.is_pressed(button) {
+                mask |= 1 << (key & 0x1F);
+            }
+        }
+        for (binding, direction) in self.mapping.axes.iter().zip(&mut self.directions) {
+            let value = state.axis(binding.axis);
+            // A pushed axis stays pushed until it returns below the release threshold
+            let held = *direction != 0 && value * *direction as f32 >= binding.release;
+            if !held {
+                *direction = match value {
+                    _ if value >= binding.press => 1,
+                    _ if value <= -binding.press => -1,
+                    _ => 0,
+                };
+            }
+            let key = match *direction {
+                -1 => binding.negative,
+                1 => binding.positive,
+                _ => None,
+            };
+            if let Some(key) = key {
+                mask |= 1 << (key & 0x1F);
+            }
+        }
+        mask as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(buttons: &[Button], axes: [f32; 4]) -> GamepadState {
+        let mut state = GamepadState {
+            axes,
+            ..Default::default()
+        };
+        for &button in buttons {
+            state.set_button(button, true);
+        }
+        state
+    }
+
+    #[test]
+    fn test_buttons() {
+        let mut mapper = GamepadMapper::new(GamepadMapping::numpad());
+        let rest = [0.0; 4];
+        assert_eq!(mapper.map(&state(&[], rest)), 0);
+        let pressed = state(&[Button::DPadUp, Button::South, Button::Start], rest);
+        assert_eq!(mapper.map(&pressed), 1 << 0x2 | 1 << 0x5);
+
+        let mut mapper = GamepadMapper::new(GamepadMapping::wasd());
+        let pressed = state(&[Button::DPadLeft, Button::East], rest);
+        assert_eq!(mapper.map(&pressed), 1 << 0x7 | 1 << 0x4);
+    }
+
+    #[test]
+    fn test_axis_hysteresis() {
+        let mut mapper = GamepadMapper::new(GamepadMapping::numpad());
+        let x = |value| state(&[], [value, 0.0, 0.0, 0.0]);
+        let sequence = [
+            (0.4, 0),
+            (0.6, 1 << 0x6),
+            // Chatter around the press threshold keeps the key pressed
+            (0.45, 1 << 0x6),
+            (0.55, 1 << 0x6),
+            (0.35, 1 << 0x6),
+            (0.25, 0),
+            (0.45, 0),
+            (-0.7, 1 << 0x4),
+            // Flicked to the other side in one frame
+            (0.9, 1 << 0x6),
+            (-0.4, 0),
+        ];
+        for (value, expected) in sequence {
+            assert_eq!(mapper.map(&x(value)), expected, "{value}");
+        }
+
+        // Diagonals press both keys
+        let diagonal = state(&[], [-0.8, -0.8, 0.0, 0.0]);
+        assert_eq!(mapper.map(&diagonal), 1 << 0x4 | 1 << 0x2);
+    }
+}
@@ -0,0 +1,89 @@
+use core::ops::Range;
+
+use crate::{memory::Address, prelude::*};
+
+/// A byte written by the program or a debugger, as seen by a memory hook.
+///
+/// # Fields
+///
+/// * `address` - The address written.
+/// * `old` - The byte before the write.
+/// * `new` - The byte written, it may be equal to `old`.
+/// * `pc` - The address of the instruction writing, or the next instruction for writes of a
+///   debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryWrite {
+    pub address: u16,
+    pub old: u8,
+    pub new: u8,
+    pub pc: Address,
+}
+
+/// Identifies a hook, to remove it with `Emulator::remove_hook`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u32);
+
+/// A callback notified of the writes to a range of memory.
+pub type MemoryCallback = Box<dyn FnMut(&MemoryWrite) + Send + Sync>;
+
+/// The memory hooks of an emulator.
+///
+/// # Fields
+///
+/// * `hooks` - The id, range and callback of each hook, in the order they were added.
+/// * `next` - The id of the next hook.
+#[derive(Default)]
+pub(crate) struct MemoryHooks {
+    hooks: Vec<(HookId, Range<usize>, MemoryCallback)>,
+    next: u32,
+}
+
+impl MemoryHooks {
+    /// Adds a hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses watched.
+    /// * `callback` - The callback, called once per byte written in the range.
+    pub(crate) fn add(&mut self, range: Range<usize>, callback: MemoryCallback) -> HookId {
+        let id = HookId(self.next);
+        self.next += 1;
+        self.hooks.push((id, range, callback));
+        id
+    }
+
+    /// Removes a hook, returns false if there is none with this id.
+    pub(crate) fn remove(&mut self, id: HookId) -> bool {
+        let count = self.hooks.len();
+        self.hooks.retain(|(hook, _, _)| *hook != id);
+        self.hooks.len() != count
+    }
+
+    /// Returns true if there are no hooks.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Notifies the hooks watching a write.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first address written.
+    /// * `old` - The bytes before the write.
+    /// * `new` - The bytes written, as many as `old`.
+    /// * `pc` - The address of the instruction writing.
+    pub(crate) fn notify(&mut self, start: usize, old: &[u8], new: &[u8], pc: Address) {
+        for (_, range, callback) in &mut self.hooks {
+            let first = range.start.max(start);
+            let end = range.end.min(start + new.len());
+            for address in first..end {
+                callback(&MemoryWrite {
+                    address: address as u16,
+                    old: old[address - start],
+                    new: new[address - start],
+                    pc,
+                });
+            }
+        }
+    }
+}
@@ -1,11 +1,42 @@
-#[repr(transparent)]
-#[derive(Default)]
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+
+use crate::{error::EmulatorError, prelude::*};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#keyboard
 /// Represents the keyboard of the Chip8 system as a bitmask.
-pub struct KeyBoard(u16);
+///
+/// # Fields
+///
+/// * `keys` - The pressed keys, bit n is key n.
+/// * `releases` - The keys pressed and released by the same batch of events, they are
+///   released by the next update so programs see them pressed once.
+/// * `events` - The queued events, with the frame they happen at.
+/// * `holds` - The number of updates, vertical blanks in the emulator, each key pressed by
+///   [`KeyBoard::hold`] stays pressed.
+/// * `holding` - The keys with a hold, bit n is key n, so most updates skip `holds`.
+pub struct KeyBoard {
+    keys: u16,
+    releases: u16,
+    events: VecDeque<(u64, KeyEvent)>,
+    holds: [Option<u32>; 16],
+    holding: u16,
+}
+
+/// A key press or release, see [`KeyBoard::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyEvent {
+    Down(Key),
+    Up(Key),
+}
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents the keys on the Chip8 keyboard
 pub enum Key {
     K0 = 0x0,
@@ -56,30 +87,460 @@ impl KeyBoard {
     /// 
     /// # Arguments
     /// 
-    /// * `key` - The index of the key to set
+    /// * `key` - The index of the key to set, only the low nibble is used like `EX9E` does
     pub fn set(&mut self, key: u8) {
-        self.0 |= 1 << key
+        let key = key & 0xF;
+        self.keys |= 1 << key;
+        self.releases &= !(1 << key);
+        self.holds[key as usize] = None;
+        self.holding &= !(1 << key);
     }
 
     /// Unset the key at the given index
     /// 
     /// # Arguments
     /// 
-    /// * `key` - The index of the key to unset
+    /// * `key` - The index of the key to unset, only the low nibble is used
     pub fn unset(&mut self, key: u8) {
-        self.0 &= !(1 << key)
+        let key = key & 0xF;
+        self.keys &= !(1 << key);
+        self.releases &= !(1 << key);
+        self.holds[key as usize] = None;
+        self.holding &= !(1 << key);
     }
 
     /// Check if the key at the given index is set
     /// 
     /// # Arguments
     /// 
-    /// * `key` - The index of the key to check
+    /// * `key` - The index of the key to check, only the low nibble is used
     /// 
     /// # Returns
     /// 
     /// * `bool` - Returns true if the key is set, otherwise returns false
+    #[inline]
     pub fn is_set(&self, key: u8) -> bool {
-        (self.0 >> key) & 1 == 1
+        (self.keys >> (key & 0xF)) & 1 == 1
+    }
+
+    /// Returns the pressed keys.
+    ///
+    /// # Returns
+    ///
+    /// * `u16` - Bit n is set if key n is pressed, e.g. `0x0021` for keys 0 and 5.
+    #[inline]
+    pub fn as_mask(&self) -> u16 {
+        self.keys
+    }
+
+    /// Replaces all the pressed keys at once, for frontends polling a whole controller.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - Bit n is set if key n is pressed.
+    ///
+    /// # Notes
+    ///
+    /// Pending releases, of taps and of [`KeyBoard::hold`], are cancelled. Queued events
+    /// are kept.
+    pub fn set_mask(&mut self, mask: u16) {
+        self.keys = mask;
+        self.releases = 0;
+        self.holds = [None; 16];
+        self.holding = 0;
+    }
+
+    /// Returns the pressed keys, lowest first.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = u8>` - The index of each pressed key.
+    pub fn pressed(&self) -> impl Iterator<Item = u8> {
+        let keys = self.keys;
+        (0..16).filter(move |key| (keys >> key) & 1 == 1)
+    }
+
+    /// Returns the lowest pressed key.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u8>` - The index of the key, or `None` if no key is pressed.
+    pub fn lowest_pressed(&self) -> Option<u8> {
+        (self.keys != 0).then(|| self.keys.trailing_zeros() as u8)
+    }
+
+    /// Presses a key now and releases it after a number of updates.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The index of the key to press, only the low nibble is used.
+    /// * `updates` - The number of updates the key stays pressed for, at least 1.
+    ///
+    /// # Notes
+    ///
+    /// Setting or unsetting the key cancels the release.
+    pub fn hold(&mut self, key: u8, updates: u32) {
+        self.set(key);
+        self.holds[(key & 0xF) as usize] = Some(updates.max(1));
+        self.holding |= 1 << (key & 0xF);
+    }
+
+    /// Queues a key event, it changes the keys on the first update at or after its frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame the event happens at.
+    /// * `event` - The event.
+    pub fn push(&mut self, frame: u64, event: KeyEvent) {
+        self.events.push_back((frame, event));
+    }
+
+    /// Returns the queued events, in the order they were pushed.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Iterator<Item = (u64, KeyEvent)>` - The frame and the event of each one.
+    pub fn pending(&self) -> impl Iterator<Item = (u64, KeyEvent)> + '_ {
+        self.events.iter().copied()
+    }
+
+    /// Applies the queued events up to a frame, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The current frame, later events stay queued.
+    ///
+    /// # Notes
+    ///
+    /// A key pressed and released by the same update stays pressed until the next one, so
+    /// a tap shorter than a frame is still seen by the program for a whole frame. Held keys
+    /// are released before the events are applied. The emulator updates on every vertical
+    /// blank, an update with nothing to apply returns early.
+    #[inline]
+    pub fn update(&mut self, frame: u64) {
+        if self.releases == 0 && self.holding == 0 && self.events.is_empty() {
+            return;
+        }
+        self.keys &= !self.releases;
+        self.releases = 0;
+        let mut holding = self.holding;
+        while holding != 0 {
+            let key = holding.trailing_zeros() as usize;
+            holding &= holding - 1;
+            match self.holds[key] {
+                Some(0) => self.unset(key as u8),
+                Some(updates) => self.holds[key] = Some(updates - 1),
+                None => {}
+            }
+        }
+        let mut pressed = 0;
+        while let Some(&(at, event)) = self.events.front() {
+            if at > frame {
+                break;
+            }
+            self.events.pop_front();
+            match event {
+                KeyEvent::Down(key) => {
+                    pressed |= 1 << key as u8;
+                    self.set(key as u8);
+                }
+                KeyEvent::Up(key) if pressed & (1 << key as u8) != 0 => {
+                    self.releases |= 1 << key as u8;
+                }
+                KeyEvent::Up(key) => self.unset(key as u8),
+            }
+        }
+    }
+}
+
+impl KeyBoard {
+    /// Packs the whole keyboard state, for save states.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The pressed keys and the pending releases (2 bytes each), the hold of
+    ///   each key (a flag byte and 4 bytes), then the number of queued events (4 bytes) and
+    ///   each event: its frame (8 bytes) and the key, with bit 4 set for a press. Little
+    ///   endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(self.keys.to_le_bytes());
+        bytes.extend(self.releases.to_le_bytes());
+        for hold in self.holds {
+            bytes.push(hold.is_some() as u8);
+            bytes.extend(hold.unwrap_or(0).to_le_bytes());
+        }
+        bytes.extend((self.events.len() as u32).to_le_bytes());
+        for &(frame, event) in &self.events {
+            bytes.extend(frame.to_le_bytes());
+            bytes.push(match event {
+                KeyEvent::Down(key) => 0x10 | key as u8,
+                KeyEvent::Up(key) => key as u8,
+            });
+        }
+        bytes
+    }
+
+    /// Unpacks a keyboard state packed with [`KeyBoard::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The packed state.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<KeyBoard, EmulatorError>` - The keyboard, or `InvalidSaveState` if the data
+    ///   is not a packed keyboard.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, EmulatorError> {
+        let invalid = |reason: &str| EmulatorError::InvalidSaveState(reason.to_string());
+        let mut rest = data;
+        let mut take = |count: usize| {
+            let (field, tail) = rest
+                .split_at_checked(count)
+                .ok_or_else(|| invalid("The keyboard state is truncated"))?;
+            rest = tail;
+            Ok::<_, EmulatorError>(field)
+        };
+
+        let mut keyboard = Self {
+            keys: u16::from_le_bytes(take(2)?.try_into().unwrap()),
+            releases: u16::from_le_bytes(take(2)?.try_into().unwrap()),
+            ..Self::default()
+        };
+        for (key, hold) in keyboard.holds.iter_mut().enumerate() {
+            let flag = take(1)?[0];
+            let updates = u32::from_le_bytes(take(4)?.try_into().unwrap());
+            *hold = (flag != 0).then_some(updates);
+            keyboard.holding |= ((flag != 0) as u16) << key;
+        }
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        for _ in 0..count {
+            let frame = u64::from_le_bytes(take(8)?.try_into().unwrap());
+            let code = take(1)?[0];
+            let key = *Key::all()
+                .nth(code as usize & 0xF)
+                .filter(|_| code < 0x20)
+                .ok_or_else(|| invalid("A queued key event is invalid"))?;
+            let event = match code & 0x10 {
+                0 => KeyEvent::Up(key),
+                _ => KeyEvent::Down(key),
+            };
+            keyboard.events.push_back((frame, event));
+        }
+        if !rest.is_empty() {
+            return Err(invalid("The keyboard state has trailing bytes"));
+        }
+        Ok(keyboard)
+    }
+}
+
+/// A handle to push key events from any thread, e.g. from the input callbacks of a window.
+///
+/// Clones share the same queue. The emulator drains it at the start of every tick, see
+/// `Emulator::shared_keyboard`, events keep the order they were pushed in.
+///
+/// # Fields
+///
+/// * `events` - The events pushed since the last drain.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct SharedKeyboard {
+    events: Arc<Mutex<Vec<KeyEvent>>>,
+}
+
+#[cfg(feature = "std")]
+impl SharedKeyboard {
+    /// Creates a handle to an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a key event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event.
+    pub fn push(&self, event: KeyEvent) {
+        // A writer panicking mid-push leaves the queue consistent, the lock is still usable
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        events.push(event);
+    }
+
+    /// Queues a key press.
+    pub fn press(&self, key: Key) {
+        self.push(KeyEvent::Down(key));
+    }
+
+    /// Queues a key release.
+    pub fn release(&self, key: Key) {
+        self.push(KeyEvent::Up(key));
+    }
+
+    /// Takes the queued events.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<KeyEvent>` - The events, in the order they were pushed.
+    pub(crate) fn drain(&self) -> Vec<KeyEvent> {
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        core::mem::take(&mut *events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_shared_keyboard() {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<SharedKeyboard>();
+
+        let shared = SharedKeyboard::new();
+        let writers: Vec<_> = [Key::K1, Key::K2, Key::K3, Key::K4]
+            .into_iter()
+            .map(|key| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        shared.press(key);
+                        shared.release(key);
+                    }
+                })
+            })
+            .collect();
+        let mut events = Vec::new();
+        // Drained while the writers push
+        while events.len() < 8000 {
+            events.extend(shared.drain());
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        events.extend(shared.drain());
+        assert_eq!(events.len(), 8000);
+
+        // Each writer's events are in order
+        for key in [Key::K1, Key::K2, Key::K3, Key::K4] {
+            let own = events.iter().filter(|event| match event {
+                KeyEvent::Down(k) | KeyEvent::Up(k) => *k == key,
+            });
+            let alternating = own.enumerate().all(|(index, event)| match index % 2 {
+                0 => *event == KeyEvent::Down(key),
+                _ => *event == KeyEvent::Up(key),
+            });
+            assert!(alternating, "{key:?}");
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let mut keyboard = KeyBoard::default();
+        keyboard.push(0, KeyEvent::Down(Key::K5));
+        keyboard.push(0, KeyEvent::Up(Key::K5));
+        keyboard.push(0, KeyEvent::Down(Key::KA));
+        keyboard.push(2, KeyEvent::Up(Key::KA));
+        assert!(!keyboard.is_set(0x5));
+
+        // The tap is seen by one update
+        keyboard.update(0);
+        assert!(keyboard.is_set(0x5) && keyboard.is_set(0xA));
+        let pending: Vec<_> = keyboard.pending().collect();
+        assert_eq!(pending, [(2, KeyEvent::Up(Key::KA))]);
+        keyboard.update(1);
+        assert!(!keyboard.is_set(0x5) && keyboard.is_set(0xA));
+        keyboard.update(2);
+        assert!(!keyboard.is_set(0xA));
+        assert_eq!(keyboard.pending().count(), 0);
+    }
+
+    #[test]
+    fn test_press_again() {
+        let mut keyboard = KeyBoard::default();
+        // Released and pressed again before the key is seen released
+        keyboard.push(0, KeyEvent::Down(Key::K1));
+        keyboard.push(0, KeyEvent::Up(Key::K1));
+        keyboard.push(0, KeyEvent::Down(Key::K1));
+        keyboard.update(0);
+        keyboard.update(1);
+        assert!(keyboard.is_set(0x1));
+
+        // A held key is released by the same update
+        keyboard.push(1, KeyEvent::Up(Key::K1));
+        keyboard.update(1);
+        assert!(!keyboard.is_set(0x1));
+
+        // Overlapping holds
+        keyboard.hold(0x3, 1);
+        keyboard.hold(0x4, 2);
+        keyboard.update(2);
+        assert!(keyboard.is_set(0x3) && keyboard.is_set(0x4));
+        keyboard.update(3);
+        assert!(!keyboard.is_set(0x3) && keyboard.is_set(0x4));
+        keyboard.update(4);
+        assert!(!keyboard.is_set(0x4));
+
+        // Masks
+        keyboard.set_mask(0x8421);
+        assert_eq!(keyboard.pressed().collect::<Vec<_>>(), [0x0, 0x5, 0xA, 0xF]);
+        assert_eq!(keyboard.lowest_pressed(), Some(0x0));
+        keyboard.unset(0x0);
+        assert_eq!(keyboard.as_mask(), 0x8420);
+        assert_eq!(keyboard.lowest_pressed(), Some(0x5));
+        keyboard.set_mask(0);
+        assert_eq!(keyboard.pressed().count(), 0);
+        assert_eq!(keyboard.lowest_pressed(), None);
+
+        // Packed with its pending releases and events
+        keyboard.hold(0x6, 3);
+        keyboard.push(9, KeyEvent::Down(Key::KB));
+        keyboard.push(9, KeyEvent::Up(Key::K6));
+        let bytes = keyboard.to_bytes();
+        assert_eq!(bytes.len(), 4 + 16 * 5 + 4 + 2 * 9);
+        assert_eq!(KeyBoard::from_bytes(&bytes).unwrap(), keyboard);
+        // The restored hold is released like the original one
+        let mut restored = KeyBoard::from_bytes(&bytes).unwrap();
+        for frame in 0..3 {
+            restored.update(frame);
+        }
+        assert!(restored.is_set(0x6));
+        restored.update(3);
+        assert!(!restored.is_set(0x6));
+        assert!(KeyBoard::from_bytes(&bytes[1..]).is_err());
+        let mut invalid = bytes.clone();
+        *invalid.last_mut().unwrap() = 0x20;
+        assert!(KeyBoard::from_bytes(&invalid).is_err());
+        keyboard.set_mask(0);
+        keyboard.update(9);
+
+        // Polling still works
+        keyboard.set(0x2);
+        assert!(keyboard.is_set(0x2));
+        keyboard.unset(0x2);
+        assert!(!keyboard.is_set(0x2));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut keyboard = KeyBoard::default();
+        keyboard.set(0x1);
+        keyboard.hold(0x6, 3);
+        keyboard.push(0, KeyEvent::Down(Key::K2));
+        keyboard.push(0, KeyEvent::Up(Key::K2));
+        keyboard.push(9, KeyEvent::Down(Key::KB));
+        keyboard.update(0);
+
+        let json = serde_json::to_string(&keyboard).unwrap();
+        let mut restored: KeyBoard = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, keyboard);
+        // The pending tap, hold and event carry on
+        restored.update(1);
+        assert!(!restored.is_set(0x2) && restored.is_set(0x6));
+        assert_eq!(
+            restored.pending().collect::<Vec<_>>(),
+            [(9, KeyEvent::Down(Key::KB))]
+        );
     }
 }
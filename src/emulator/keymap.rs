@@ -0,0 +1,213 @@
+use std::{collections::HashMap, fmt};
+
+use crate::error::EmulatorError;
+
+/// A key of the host keyboard, the character it types without modifiers.
+pub type HostKey = char;
+
+/// The CHIP-8 keypad, row by row, as it is laid out on the COSMAC VIP.
+const KEYPAD: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// Maps host keys to the keys of the CHIP-8 keypad.
+///
+/// # Fields
+///
+/// * `keys` - The keypad key of each host key, host keys are lowercase.
+///
+/// # Notes
+///
+/// A map is saved as text with one host key per line, `host = key` with the keypad key as a
+/// hex digit, for example `q = 4`. Empty lines are ignored. See [`KeyMap::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    keys: HashMap<HostKey, u8>,
+}
+
+impl KeyMap {
+    /// Creates the standard QWERTY map, the left 4x4 block of keys is the keypad.
+    ///
+    /// ```text
+    /// 1 2 3 4     1 2 3 C
+    /// q w e r     4 5 6 D
+    /// a s d f  →  7 8 9 E
+    /// z x c v     A 0 B F
+    /// ```
+    pub fn qwerty() -> Self {
+        Self::from_rows("1234qwerasdfzxcv")
+    }
+
+    /// Creates the AZERTY map, the same physical keys as [`KeyMap::qwerty`].
+    ///
+    /// ```text
+    /// 1 2 3 4     1 2 3 C
+    /// a z e r     4 5 6 D
+    /// q s d f  →  7 8 9 E
+    /// w x c v     A 0 B F
+    /// ```
+    pub fn azerty() -> Self {
+        Self::from_rows("1234azerqsdfwxcv")
+    }
+
+    /// Creates a map from user defined keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The keypad key of each host key. Keys above 0xF are ignored.
+    ///
+    /// # Notes
+    ///
+    /// Letters are case insensitive, several host keys can map to the same keypad key.
+    pub fn custom(keys: HashMap<HostKey, u8>) -> Self {
+        let keys = keys
+            .into_iter()
+            .filter(|&(_, key)| key <= 0xF)
+            .map(|(host, key)| (normalize(host), key));
+        Self {
+            keys: keys.collect(),
+        }
+    }
+
+    /// Creates a map from 16 host keys, in the order of the keypad rows.
+    fn from_rows(hosts: &str) -> Self {
+        Self {
+            keys: hosts.chars().zip(KEYPAD).collect(),
+        }
+    }
+
+    /// Returns the keypad key of a host key.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host key.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u8>` - The keypad key, or `None` if the host key is not mapped.
+    pub fn map(&self, host: HostKey) -> Option<u8> {
+        self.keys.get(&normalize(host)).copied()
+    }
+
+    /// Returns the host key to show as a hint for a keypad key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The keypad key.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<HostKey>` - The host key mapped to it, the lowest one if there are several.
+    ///   `None` if no host key is.
+    pub fn host_key(&self, key: u8) -> Option<HostKey> {
+        let hosts = self.keys.iter().filter(|&(_, &mapped)| mapped == key);
+        hosts.map(|(&host, _)| host).min()
+    }
+
+    /// Parses a map saved with its `Display` implementation.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The saved map.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<KeyMap, EmulatorError>` - The map, or an error with the offending line number.
+    pub fn parse(text: &str) -> Result<Self, EmulatorError> {
+        let mut keys = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = |reason: String| EmulatorError::InvalidKeyMap {
+                line: index + 1,
+                reason,
+            };
+            let Some((host, key)) = line.rsplit_once('=') else {
+                return Err(invalid(format!("Expected `host = key`, found `{line}`")));
+            };
+            let host = host.trim();
+            let mut chars = host.chars();
+            let (Some(host), None) = (chars.next(), chars.next()) else {
+                return Err(invalid(format!("`{host}` is not a single key")));
+            };
+            let key = match u8::from_str_radix(key.trim(), 16) {
+                Ok(key) if key <= 0xF => key,
+                _ => return Err(invalid(format!("`{}` is not a keypad key", key.trim()))),
+            };
+            keys.insert(host, key);
+        }
+        Ok(Self::custom(keys))
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::qwerty()
+    }
+}
+
+impl fmt::Display for KeyMap {
+    /// Writes one `host = key` line per host key, sorted by keypad position.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = |key: u8| KEYPAD.iter().position(|&pad| pad == key);
+        let mut keys: Vec<_> = self.keys.iter().collect();
+        keys.sort_by_key(|&(&host, &key)| (position(key), host));
+        for (host, key) in keys {
+            writeln!(f, "{host} = {key:X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Makes letters case insensitive.
+fn normalize(host: HostKey) -> HostKey {
+    host.to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts() {
+        let qwerty = KeyMap::qwerty();
+        assert_eq!(qwerty.map('1'), Some(0x1));
+        assert_eq!(qwerty.map('4'), Some(0xC));
+        assert_eq!(qwerty.map('x'), Some(0x0));
+        assert_eq!(qwerty.map('V'), Some(0xF));
+        assert_eq!(qwerty.map('p'), None);
+        assert_eq!(qwerty.host_key(0x5), Some('w'));
+
+        let azerty = KeyMap::azerty();
+        assert_eq!(azerty.map('a'), Some(0x4));
+        assert_eq!(azerty.map('q'), Some(0x7));
+        assert_eq!(azerty.map('w'), Some(0xA));
+        assert_eq!(azerty.host_key(0x5), Some('z'));
+    }
+
+    #[test]
+    fn test_custom() {
+        let keymap = KeyMap::custom(HashMap::from([('K', 0x8), ('j', 0x8), ('z', 0x10)]));
+        assert_eq!(keymap.map('k'), Some(0x8));
+        assert_eq!(keymap.map('z'), None);
+        assert_eq!(keymap.host_key(0x8), Some('j'));
+        assert_eq!(keymap.host_key(0x1), None);
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let text = KeyMap::qwerty().to_string();
+        assert!(text.starts_with("1 = 1\n2 = 2\n3 = 3\n4 = C\nq = 4\n"));
+        assert_eq!(KeyMap::parse(&text).unwrap(), KeyMap::qwerty());
+
+        let keymap = KeyMap::parse("\n  = = A\n; = 1\n").unwrap();
+        assert_eq!(keymap.map('='), Some(0xA));
+        assert_eq!(keymap.map(';'), Some(0x1));
+
+        for (text, line) in [("q = 4\nq 4", 2), ("qw = 4", 1), ("q = 10", 1), ("= 1", 1)] {
+            let error = KeyMap::parse(text).unwrap_err();
+            assert!(matches!(error, EmulatorError::InvalidKeyMap { line: l, .. } if l == line));
+        }
+    }
+}
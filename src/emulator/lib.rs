@@ -1,22 +1,81 @@
+//! A CHIP-8, SUPER-CHIP and XO-CHIP emulator.
+//!
+//! The `std` feature (default) adds what needs an operating system: loading from readers,
+//! files, threads and the run loop. Without it the crate is `no_std` and only needs `alloc`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 use self::error::EmulatorError;
 
+/// The `alloc` items the `std` prelude would provide, for the `no_std` build.
+#[allow(unused_imports)]
+mod prelude {
+    pub use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+}
+
 /// Module to centralize all the errors that can occur in the emulator.
 pub mod error;
 
 pub mod constants;
 
-mod display;
+pub mod audio;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod compat;
+pub mod disasm;
+pub mod display;
+#[cfg(feature = "egui")]
+pub mod egui;
 pub mod emulator;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod frontend;
+#[cfg(any(test, feature = "arbitrary"))]
+pub mod fuzz;
+pub mod gamepad;
+pub mod hooks;
 pub mod keyboard;
-mod memory;
-mod opcode;
-mod rand;
-mod register;
+#[cfg(feature = "std")]
+pub mod keymap;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod memory;
+pub mod opcode;
+pub mod persistence;
+#[cfg(feature = "std")]
+pub mod phosphor;
+pub mod quirks;
+pub mod rand;
+#[cfg(feature = "gif")]
+pub mod recorder;
+pub mod register;
+pub mod replay;
+pub mod rom;
+#[cfg(feature = "std")]
+pub mod source_map;
 mod stack;
+pub mod symbols;
+#[macro_use]
+pub mod testing;
+#[cfg(feature = "terminal")]
+pub mod terminal;
 mod timer;
+pub mod timing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub mod debug;
 
+#[cfg(feature = "std")]
 pub mod assembler;
 
 #[cfg(test)]
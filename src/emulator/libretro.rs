@@ -0,0 +1,917 @@
+//! A libretro core, built with the `libretro` feature, to run R8 in RetroArch.
+//!
+//! The shared library of `bindings/` exports the `retro_*` functions of `libretro.h`.
+//! RetroArch looks for cores named after the core, so `libr8_bindings.so` is installed as
+//! `r8_libretro.so` (`r8_libretro.dll` on Windows, `r8_libretro.dylib` on macOS).
+//!
+//! # Frames
+//!
+//! `retro_run` polls the RetroPad of port 1, maps it to the keypad with a
+//! [`GamepadMapper`], runs a frame with `Emulator::run_frame`, uploads the display as
+//! XRGB8888 and sends the audio of the frame as 16 bits stereo.
+//!
+//! # Options
+//!
+//! * `r8_profile` - The interpreter, `auto` picks it with `compat::recommend`. A change
+//!   restarts the game.
+//! * `r8_speed` - The instructions per second.
+//!
+//! # Save states
+//!
+//! The states of `Emulator::save_state` vary in size, RetroArch needs a fixed one. They are
+//! stored after their length (4 bytes) in a buffer of the size reported when the game was
+//! loaded, the rest is zeros.
+
+use std::{
+    ffi::{c_char, c_void, CStr},
+    ptr, slice,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use log::error;
+
+use crate::{
+    compat,
+    constants::{Palette, MONOCHROME_PALETTE},
+    emulator::Emulator,
+    error::EmulatorError,
+    gamepad::{Axis, Button, GamepadMapper, GamepadMapping, GamepadState},
+    quirks::Profile,
+};
+
+/// The version of the API, `RETRO_API_VERSION`.
+const API_VERSION: u32 = 1;
+
+const ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+const ENVIRONMENT_GET_VARIABLE: u32 = 15;
+const ENVIRONMENT_SET_VARIABLES: u32 = 16;
+const ENVIRONMENT_GET_VARIABLE_UPDATE: u32 = 17;
+const PIXEL_FORMAT_XRGB8888: u32 = 1;
+const DEVICE_JOYPAD: u32 = 1;
+const DEVICE_ANALOG: u32 = 5;
+const DEVICE_INDEX_ANALOG_LEFT: u32 = 0;
+const REGION_NTSC: u32 = 0;
+
+/// The RetroPad buttons, by their `RETRO_DEVICE_ID_JOYPAD_*` id, and the buttons of
+/// [`GamepadState`] at their position.
+const RETROPAD: [(u32, Button); 12] = [
+    (0, Button::South),
+    (1, Button::West),
+    (2, Button::Select),
+    (3, Button::Start),
+    (4, Button::DPadUp),
+    (5, Button::DPadDown),
+    (6, Button::DPadLeft),
+    (7, Button::DPadRight),
+    (8, Button::East),
+    (9, Button::North),
+    (10, Button::LeftShoulder),
+    (11, Button::RightShoulder),
+];
+
+/// The sample rate of the audio in Hz.
+const SAMPLE_RATE: u32 = 44100;
+
+/// The audio frames of a video frame at 60Hz.
+const AUDIO_FRAMES: usize = SAMPLE_RATE as usize / 60;
+
+/// The room left in the save states for the display switching to high resolution, the stack
+/// and the keyboard events growing after the size was reported.
+const STATE_SLACK: usize = 4096;
+
+/// The instructions per second without the `r8_speed` option.
+const DEFAULT_IPS: u32 = 700;
+
+/// The options, their description and their values, the first being the default.
+const VARIABLES: [(&CStr, &CStr); 2] = [
+    (
+        c"r8_profile",
+        c"Quirk profile; auto|chip8|chip48|superchip|superchip-modern|chip8x|eti660|xochip",
+    ),
+    (
+        c"r8_speed",
+        c"Instructions per second; 700|1000|1500|2000|3000|5000|10000|60|200|500",
+    ),
+];
+
+/// `struct retro_system_info`
+#[repr(C)]
+pub struct SystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+/// `struct retro_game_geometry`
+#[repr(C)]
+pub struct GameGeometry {
+    pub base_width: u32,
+    pub base_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub aspect_ratio: f32,
+}
+
+/// `struct retro_system_timing`
+#[repr(C)]
+pub struct SystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+/// `struct retro_system_av_info`
+#[repr(C)]
+pub struct SystemAvInfo {
+    pub geometry: GameGeometry,
+    pub timing: SystemTiming,
+}
+
+/// `struct retro_game_info`
+#[repr(C)]
+pub struct GameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+/// `struct retro_variable`
+#[repr(C)]
+pub struct Variable {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+pub type EnvironmentFn = unsafe extern "C" fn(cmd: u32, data: *mut c_void) -> bool;
+pub type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: u32, height: u32, pitch: usize);
+pub type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+pub type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type InputPollFn = unsafe extern "C" fn();
+pub type InputStateFn = unsafe extern "C" fn(port: u32, device: u32, index: u32, id: u32) -> i16;
+
+/// The callbacks given by the frontend, unset ones are skipped.
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+impl Callbacks {
+    /// No callback set, as before the frontend sets them.
+    const NONE: Callbacks = Callbacks {
+        environment: None,
+        video_refresh: None,
+        audio_sample_batch: None,
+        input_poll: None,
+        input_state: None,
+    };
+
+    /// Runs an environment command, false if it is not supported.
+    fn environment(&self, cmd: u32, data: *mut c_void) -> bool {
+        // SAFETY: the frontend gave the callback for this command and data
+        self.environment.is_some_and(|f| unsafe { f(cmd, data) })
+    }
+
+    /// Returns the value of an option, if the frontend has one.
+    fn variable(&self, key: &CStr) -> Option<String> {
+        let mut variable = Variable {
+            key: key.as_ptr(),
+            value: ptr::null(),
+        };
+        let found = self.environment(ENVIRONMENT_GET_VARIABLE, &mut variable as *mut _ as _);
+        // SAFETY: the frontend sets the value to null or to a C string
+        (found && !variable.value.is_null())
+            .then(|| unsafe { CStr::from_ptr(variable.value) })
+            .map(|value| value.to_string_lossy().into_owned())
+    }
+
+    /// Returns true if the options changed since they were last read.
+    fn variables_updated(&self) -> bool {
+        let mut updated = false;
+        self.environment(ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated as *mut _ as _) && updated
+    }
+
+    /// Polls the input and returns the state of the RetroPad of port 1.
+    fn gamepad(&self) -> GamepadState {
+        let mut state = GamepadState::default();
+        if let Some(poll) = self.input_poll {
+            // SAFETY: the frontend gave the callback
+            unsafe { poll() };
+        }
+        let Some(input) = self.input_state else {
+            return state;
+        };
+        for (id, button) in RETROPAD {
+            // SAFETY: the frontend gave the callback
+            state.set_button(button, unsafe { input(0, DEVICE_JOYPAD, 0, id) } != 0);
+        }
+        for (id, axis) in [(0, Axis::LeftX), (1, Axis::LeftY)] {
+            // SAFETY: the frontend gave the callback
+            let value = unsafe { input(0, DEVICE_ANALOG, DEVICE_INDEX_ANALOG_LEFT, id) };
+            state.axes[axis as usize] = value as f32 / 32768.0;
+        }
+        state
+    }
+
+    /// Uploads a frame of XRGB8888 pixels.
+    fn video(&self, pixels: &[u32], width: usize, height: usize) {
+        if let Some(refresh) = self.video_refresh {
+            // SAFETY: the pixels are valid for the call, `width * height` of them
+            unsafe { refresh(pixels.as_ptr() as _, width as u32, height as u32, width * 4) };
+        }
+    }
+
+    /// Sends interleaved stereo samples, as many calls as the frontend needs.
+    fn audio(&self, mut samples: &[i16]) {
+        let Some(batch) = self.audio_sample_batch else {
+            return;
+        };
+        while !samples.is_empty() {
+            // SAFETY: the samples are valid for the call, 2 per frame
+            let written = unsafe { batch(samples.as_ptr(), samples.len() / 2) };
+            if written == 0 {
+                break;
+            }
+            samples = &samples[(written * 2).min(samples.len())..];
+        }
+    }
+}
+
+/// The values of the options.
+///
+/// # Fields
+///
+/// * `profile` - The interpreter, `None` to pick it from the ROM.
+/// * `ips` - The instructions per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Options {
+    profile: Option<Profile>,
+    ips: u32,
+}
+
+impl Options {
+    /// Reads the options from the frontend, the defaults for the unknown ones.
+    fn read(callbacks: &Callbacks) -> Options {
+        let profile = callbacks.variable(VARIABLES[0].0);
+        let ips = callbacks.variable(VARIABLES[1].0);
+        Options {
+            profile: profile.as_deref().and_then(profile_by_name),
+            ips: ips
+                .and_then(|ips| ips.parse().ok())
+                .filter(|&ips| ips > 0)
+                .unwrap_or(DEFAULT_IPS),
+        }
+    }
+}
+
+/// Returns the profile of a value of `r8_profile`, `None` for `auto`.
+fn profile_by_name(name: &str) -> Option<Profile> {
+    match name {
+        "chip8" => Some(Profile::Chip8),
+        "chip48" => Some(Profile::Chip48),
+        "superchip" => Some(Profile::SuperChip),
+        "superchip-modern" => Some(Profile::SuperChipModern),
+        "chip8x" => Some(Profile::Chip8X),
+        "eti660" => Some(Profile::Eti660),
+        "xochip" => Some(Profile::XoChip),
+        _ => None,
+    }
+}
+
+/// A loaded game.
+///
+/// # Fields
+///
+/// * `emulator` - The emulator.
+/// * `rom` - The ROM, loaded again by `retro_reset` and profile changes.
+/// * `options` - The options the game runs with.
+/// * `mapper` - Maps the RetroPad to the keypad.
+/// * `palette` - The colors of the display.
+/// * `framebuffer` - The XRGB8888 pixels of the last frame.
+/// * `audio` - The samples of the last frame, as the emulator fills them.
+/// * `samples` - The samples of the last frame, as the frontend takes them.
+/// * `state_size` - The size of the save states reported to the frontend.
+/// * `halted` - Set by an error of the emulator, the frames do not run anymore.
+struct Core {
+    emulator: Emulator,
+    rom: Vec<u8>,
+    options: Options,
+    mapper: GamepadMapper,
+    palette: Palette,
+    framebuffer: Vec<u32>,
+    audio: Vec<f32>,
+    samples: Vec<i16>,
+    state_size: usize,
+    halted: bool,
+}
+
+impl Core {
+    /// Loads a game.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The ROM.
+    /// * `options` - The options to run it with.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Core, EmulatorError>` - The game, or the error of `Emulator::load_rom_bytes`.
+    fn new(rom: Vec<u8>, options: Options) -> Result<Core, EmulatorError> {
+        let emulator = Self::boot(&rom, options)?;
+        Ok(Core {
+            state_size: 4 + emulator.save_state().len() + STATE_SLACK,
+            emulator,
+            rom,
+            options,
+            mapper: GamepadMapper::new(GamepadMapping::numpad()),
+            palette: MONOCHROME_PALETTE,
+            framebuffer: Vec::new(),
+            audio: vec![0.0; AUDIO_FRAMES],
+            samples: vec![0; AUDIO_FRAMES * 2],
+            halted: false,
+        })
+    }
+
+    /// Creates an emulator running the ROM with the profile of the options.
+    fn boot(rom: &[u8], options: Options) -> Result<Emulator, EmulatorError> {
+        let mut emulator = Emulator::new();
+        match options.profile {
+            Some(profile) => emulator.set_profile(profile),
+            None => {
+                let recommendation = compat::recommend(rom);
+                emulator.set_profile(recommendation.profile);
+                emulator.set_quirks(recommendation.quirks);
+            }
+        }
+        emulator.load_rom_bytes(rom)?;
+        Ok(emulator)
+    }
+
+    /// Restarts the game.
+    fn reset(&mut self) {
+        match Self::boot(&self.rom, self.options) {
+            Ok(emulator) => {
+                self.emulator = emulator;
+                self.halted = false;
+            }
+            Err(err) => error!("Failed to reset the game: {err}"),
+        }
+    }
+
+    /// Applies new options, a different profile restarts the game.
+    fn configure(&mut self, options: Options) {
+        let restart = options.profile != self.options.profile;
+        self.options = options;
+        if restart {
+            self.reset();
+        }
+    }
+
+    /// Runs a frame and hands its video and audio to the frontend.
+    fn run(&mut self, callbacks: &Callbacks) {
+        if callbacks.variables_updated() {
+            self.configure(Options::read(callbacks));
+        }
+        let keys = self.mapper.map(&callbacks.gamepad());
+        self.emulator.set_key_mask(keys);
+        if !self.halted {
+            if let Err(err) = self.emulator.run_frame(self.options.ips.div_ceil(60)) {
+                error!("Fatal emulator error: {err}");
+                self.halted = true;
+            }
+        }
+
+        let display = self.emulator.display();
+        let (width, height) = display.dimensions();
+        self.framebuffer.clear();
+        self.framebuffer.extend(
+            display
+                .to_rgba8(&self.palette)
+                .chunks_exact(4)
+                .map(|rgba| u32::from_be_bytes([0, rgba[0], rgba[1], rgba[2]])),
+        );
+        callbacks.video(&self.framebuffer, width, height);
+
+        self.emulator
+            .fill_audio_buffer(&mut self.audio, SAMPLE_RATE);
+        for (frame, &sample) in self.samples.chunks_exact_mut(2).zip(&self.audio) {
+            frame.fill((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+        }
+        callbacks.audio(&self.samples);
+    }
+
+    /// Writes the state after its length, see the module documentation.
+    fn serialize(&self, buffer: &mut [u8]) -> bool {
+        let state = self.emulator.save_state();
+        if buffer.len() < 4 + state.len() {
+            error!(
+                "The save state needs {} bytes, the frontend gave {}",
+                4 + state.len(),
+                buffer.len()
+            );
+            return false;
+        }
+        let (len, rest) = buffer.split_at_mut(4);
+        len.copy_from_slice(&(state.len() as u32).to_le_bytes());
+        rest[..state.len()].copy_from_slice(&state);
+        rest[state.len()..].fill(0);
+        true
+    }
+
+    /// Restores a state written by `serialize`.
+    fn unserialize(&mut self, data: &[u8]) -> bool {
+        let state = data
+            .split_first_chunk::<4>()
+            .and_then(|(len, rest)| rest.get(..u32::from_le_bytes(*len) as usize));
+        let result = match state {
+            Some(state) => self.emulator.load_state(state),
+            None => Err(EmulatorError::InvalidSaveState(
+                "The length of the state is truncated".to_string(),
+            )),
+        };
+        match result {
+            Ok(()) => {
+                self.halted = false;
+                true
+            }
+            Err(err) => {
+                error!("Failed to load the save state: {err}");
+                false
+            }
+        }
+    }
+}
+
+/// The callbacks, set before `retro_init`.
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks::NONE);
+
+/// The game, between `retro_load_game` and `retro_unload_game`.
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+/// Returns a copy of the callbacks, so none is called with the lock held.
+fn callbacks() -> Callbacks {
+    *CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the game, after a panic in a previous call too.
+fn core() -> MutexGuard<'static, Option<Core>> {
+    CORE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Sets a callback.
+fn set_callback(set: impl FnOnce(&mut Callbacks)) {
+    set(&mut CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> u32 {
+    API_VERSION
+}
+
+/// # Safety
+///
+/// `info` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    // SAFETY: the caller guarantees `info` is null or writable
+    if let Some(info) = unsafe { info.as_mut() } {
+        *info = SystemInfo {
+            library_name: c"R8".as_ptr(),
+            library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as _,
+            valid_extensions: c"ch8|c8|sc8|xo8|c8x|bin".as_ptr(),
+            need_fullpath: false,
+            block_extract: false,
+        };
+    }
+}
+
+/// # Safety
+///
+/// `info` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    // SAFETY: the caller guarantees `info` is null or writable
+    if let Some(info) = unsafe { info.as_mut() } {
+        *info = SystemAvInfo {
+            geometry: GameGeometry {
+                base_width: 64,
+                base_height: 32,
+                max_width: 128,
+                max_height: 64,
+                // The width over the height of each frame
+                aspect_ratio: 0.0,
+            },
+            timing: SystemTiming {
+                fps: 60.0,
+                sample_rate: SAMPLE_RATE as f64,
+            },
+        };
+    }
+}
+
+/// Keeps the environment callback and declares the options.
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: Option<EnvironmentFn>) {
+    set_callback(|callbacks| callbacks.environment = environment);
+    let mut variables: Vec<Variable> = VARIABLES
+        .iter()
+        .map(|(key, value)| Variable {
+            key: key.as_ptr(),
+            value: value.as_ptr(),
+        })
+        .collect();
+    // The list ends with an empty variable
+    variables.push(Variable {
+        key: ptr::null(),
+        value: ptr::null(),
+    });
+    callbacks().environment(ENVIRONMENT_SET_VARIABLES, variables.as_mut_ptr() as _);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: Option<VideoRefreshFn>) {
+    set_callback(|callbacks| callbacks.video_refresh = video_refresh);
+}
+
+/// Unused, the audio is sent a frame at a time with the batch callback.
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: Option<AudioSampleFn>) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: Option<AudioSampleBatchFn>) {
+    set_callback(|callbacks| callbacks.audio_sample_batch = audio_sample_batch);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: Option<InputPollFn>) {
+    set_callback(|callbacks| callbacks.input_poll = input_poll);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: Option<InputStateFn>) {
+    set_callback(|callbacks| callbacks.input_state = input_state);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *core() = None;
+}
+
+/// Only the RetroPad is supported.
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = core().as_mut() {
+        core.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    if let Some(core) = core().as_mut() {
+        core.run(&callbacks);
+    }
+}
+
+/// Returns the size of the save states, fixed while the game is loaded.
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    core().as_ref().map_or(0, |core| core.state_size)
+}
+
+/// # Safety
+///
+/// `data` must be null or point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    // SAFETY: the caller guarantees `data` points to `size` writable bytes
+    let buffer = unsafe { slice::from_raw_parts_mut(data as *mut u8, size) };
+    core().as_ref().is_some_and(|core| core.serialize(buffer))
+}
+
+/// # Safety
+///
+/// `data` must be null or point to `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    // SAFETY: the caller guarantees `data` points to `size` bytes
+    let data = unsafe { slice::from_raw_parts(data as *const u8, size) };
+    core().as_mut().is_some_and(|core| core.unserialize(data))
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+/// Unsupported.
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: u32, _enabled: bool, _code: *const c_char) {}
+
+/// Loads the game given in memory, with the options of the frontend.
+///
+/// # Safety
+///
+/// `game` must be null or valid, with `data` pointing to `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    // SAFETY: the caller guarantees `game` is null or valid
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+    // SAFETY: the caller guarantees `data` points to `size` bytes
+    let rom = unsafe { slice::from_raw_parts(game.data as *const u8, game.size) }.to_vec();
+
+    let callbacks = callbacks();
+    let mut format = PIXEL_FORMAT_XRGB8888;
+    if !callbacks.environment(ENVIRONMENT_SET_PIXEL_FORMAT, &mut format as *mut _ as _) {
+        error!("The frontend does not support XRGB8888");
+        return false;
+    }
+    match Core::new(rom, Options::read(&callbacks)) {
+        Ok(core) => {
+            *self::core() = Some(core);
+            true
+        }
+        Err(err) => {
+            error!("Failed to load the game: {err}");
+            false
+        }
+    }
+}
+
+/// Unsupported, there are no special games.
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: u32,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *core() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> u32 {
+    REGION_NTSC
+}
+
+/// The memory is not exposed.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: u32) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: u32) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::register::RegisterIndex;
+
+    /// What the mock frontend received and answers, per thread as the tests run in parallel.
+    #[derive(Default)]
+    struct Frontend {
+        commands: Vec<u32>,
+        variables: Vec<String>,
+        speed: Option<&'static CStr>,
+        speed_updated: bool,
+        frames: Vec<(u32, u32, usize, usize)>,
+        audio_frames: usize,
+        polls: usize,
+        pressed: Option<u32>,
+    }
+
+    thread_local! {
+        static FRONTEND: RefCell<Frontend> = RefCell::new(Frontend::default());
+    }
+
+    fn frontend<T>(f: impl FnOnce(&mut Frontend) -> T) -> T {
+        FRONTEND.with(|frontend| f(&mut frontend.borrow_mut()))
+    }
+
+    unsafe extern "C" fn environment(cmd: u32, data: *mut c_void) -> bool {
+        frontend(|frontend| {
+            frontend.commands.push(cmd);
+            match cmd {
+                ENVIRONMENT_SET_VARIABLES => {
+                    let mut variable = data as *const Variable;
+                    while !unsafe { (*variable).key }.is_null() {
+                        let key = unsafe { CStr::from_ptr((*variable).key) };
+                        frontend.variables.push(key.to_string_lossy().into_owned());
+                        variable = unsafe { variable.add(1) };
+                    }
+                    true
+                }
+                ENVIRONMENT_GET_VARIABLE => {
+                    let variable = unsafe { &mut *(data as *mut Variable) };
+                    let key = unsafe { CStr::from_ptr(variable.key) };
+                    match (key.to_bytes(), frontend.speed) {
+                        (b"r8_speed", Some(speed)) => {
+                            variable.value = speed.as_ptr();
+                            true
+                        }
+                        (b"r8_profile", _) => {
+                            variable.value = c"chip8".as_ptr();
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                ENVIRONMENT_GET_VARIABLE_UPDATE => {
+                    unsafe { *(data as *mut bool) = std::mem::take(&mut frontend.speed_updated) };
+                    true
+                }
+                ENVIRONMENT_SET_PIXEL_FORMAT => (unsafe { *(data as *const u32) }) == 1,
+                _ => false,
+            }
+        })
+    }
+
+    unsafe extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize) {
+        let pixels =
+            unsafe { slice::from_raw_parts(data as *const u32, (width * height) as usize) };
+        let lit = pixels.iter().filter(|&&pixel| pixel != 0).count();
+        frontend(|frontend| frontend.frames.push((width, height, pitch, lit)));
+    }
+
+    unsafe extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+        // Takes at most 100 frames per call
+        let taken = frames.min(100);
+        frontend(|frontend| frontend.audio_frames += taken);
+        taken
+    }
+
+    unsafe extern "C" fn input_poll() {
+        frontend(|frontend| frontend.polls += 1);
+    }
+
+    unsafe extern "C" fn input_state(_port: u32, device: u32, _index: u32, id: u32) -> i16 {
+        frontend(|frontend| (device == DEVICE_JOYPAD && frontend.pressed == Some(id)) as i16)
+    }
+
+    fn mock() -> Callbacks {
+        Callbacks {
+            environment: Some(environment),
+            video_refresh: Some(video_refresh),
+            audio_sample_batch: Some(audio_sample_batch),
+            input_poll: Some(input_poll),
+            input_state: Some(input_state),
+        }
+    }
+
+    /// Draws 0, starts the beep and waits for a key.
+    const ROM: [u8; 10] = [
+        0x60, 0x05, // LD V0, 5
+        0xF0, 0x18, // LD ST, V0
+        0xD1, 0x15, // DRW V1, V1, 5
+        0xF2, 0x0A, // LD V2, K
+        0x12, 0x08, // JP 0x208
+    ];
+
+    #[test]
+    fn test_options() {
+        let callbacks = mock();
+        assert_eq!(
+            Options::read(&callbacks),
+            Options {
+                profile: Some(Profile::Chip8),
+                ips: DEFAULT_IPS
+            }
+        );
+        frontend(|frontend| frontend.speed = Some(c"1500"));
+        assert_eq!(Options::read(&callbacks).ips, 1500);
+        frontend(|frontend| frontend.speed = Some(c"fast"));
+        assert_eq!(Options::read(&callbacks).ips, DEFAULT_IPS);
+        assert_eq!(profile_by_name("auto"), None);
+        assert_eq!(
+            profile_by_name("superchip-modern"),
+            Some(Profile::SuperChipModern)
+        );
+        // Without a frontend every option has its default
+        assert_eq!(Options::read(&Callbacks::NONE).ips, DEFAULT_IPS);
+    }
+
+    #[test]
+    fn test_run() {
+        let callbacks = mock();
+        frontend(|frontend| frontend.speed = Some(c"240"));
+        let mut core = Core::new(ROM.to_vec(), Options::read(&callbacks)).unwrap();
+        core.run(&callbacks);
+
+        frontend(|frontend| {
+            assert_eq!(frontend.polls, 1);
+            // The 0 is drawn, 14 of its pixels are lit
+            assert_eq!(frontend.frames, vec![(64, 32, 64 * 4, 14)]);
+            assert_eq!(frontend.audio_frames, AUDIO_FRAMES);
+        });
+        assert_ne!(core.samples, vec![0; AUDIO_FRAMES * 2]);
+
+        // The display wait quirk delays the key wait to the next frame
+        core.run(&callbacks);
+        // B is the south button, 5 on the keypad
+        frontend(|frontend| frontend.pressed = Some(0));
+        core.run(&callbacks);
+        assert_eq!(core.emulator.registers[RegisterIndex::new(2)], 5);
+
+        // A new speed is read, the game goes on
+        frontend(|frontend| {
+            frontend.speed = Some(c"60");
+            frontend.speed_updated = true;
+        });
+        core.run(&callbacks);
+        assert_eq!(core.options.ips, 60);
+        assert_eq!(core.emulator.registers[RegisterIndex::new(2)], 5);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut core = Core::new(ROM.to_vec(), Options::read(&mock())).unwrap();
+        let state = core.emulator.save_state();
+        let mut buffer = vec![0xFF; core.state_size];
+        assert!(core.serialize(&mut buffer));
+        assert!(buffer[4 + state.len()..].iter().all(|&byte| byte == 0));
+
+        core.run(&mock());
+        assert_ne!(core.emulator.save_state(), state);
+        assert!(core.unserialize(&buffer));
+        assert_eq!(core.emulator.save_state(), state);
+
+        assert!(!core.serialize(&mut buffer[..state.len()]));
+        assert!(!core.unserialize(&buffer[..10]));
+        assert!(!core.unserialize(&[]));
+    }
+
+    #[test]
+    fn test_api() {
+        retro_set_environment(Some(environment));
+        retro_set_video_refresh(Some(video_refresh));
+        retro_set_audio_sample_batch(Some(audio_sample_batch));
+        retro_set_input_poll(Some(input_poll));
+        retro_set_input_state(Some(input_state));
+        retro_init();
+        assert_eq!(retro_api_version(), 1);
+        frontend(|frontend| {
+            assert_eq!(frontend.commands, vec![ENVIRONMENT_SET_VARIABLES]);
+            assert_eq!(frontend.variables, vec!["r8_profile", "r8_speed"]);
+        });
+        assert_eq!(retro_serialize_size(), 0);
+
+        let game = GameInfo {
+            path: ptr::null(),
+            data: ROM.as_ptr() as _,
+            size: ROM.len(),
+            meta: ptr::null(),
+        };
+        unsafe {
+            assert!(retro_load_game(&game));
+            retro_run();
+            frontend(|frontend| assert_eq!(frontend.frames.len(), 1));
+
+            let mut state = vec![0; retro_serialize_size()];
+            assert!(retro_serialize(state.as_mut_ptr() as _, state.len()));
+            retro_reset();
+            assert!(retro_unserialize(state.as_ptr() as _, state.len()));
+            assert!(!retro_serialize(ptr::null_mut(), 0));
+
+            let mut info = std::mem::zeroed::<SystemAvInfo>();
+            retro_get_system_av_info(&mut info);
+            assert_eq!(info.geometry.max_width, 128);
+            assert_eq!(info.timing.sample_rate, 44100.0);
+            let mut info = std::mem::zeroed::<SystemInfo>();
+            retro_get_system_info(&mut info);
+            assert_eq!(CStr::from_ptr(info.library_name), c"R8");
+        }
+        retro_unload_game();
+        retro_deinit();
+        assert_eq!(retro_serialize_size(), 0);
+    }
+}
@@ -1,9 +1,8 @@
-use std::{
-    io::Read,
-    ops::{Index, IndexMut},
-};
+use core::ops::{Index, IndexMut, Range};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
 
-use super::error::EmulatorError;
+use super::{error::EmulatorError, prelude::*, rom::ihex_record};
 
 /// Represents an address in memory.
 ///
@@ -14,14 +13,18 @@ use super::error::EmulatorError;
 /// # Note
 ///
 /// This is a newtype around `u16` to make it more clear that it represents an address.
-/// Chip-8 Only have 12 bits of address space, so the upper 4 bits are always 0.
+/// Chip-8 Only have 12 bits of address space, so the upper 4 bits are always 0,
+/// except for the 16-bit addresses loaded by the XO-CHIP `F000 NNNN` instruction.
 #[repr(transparent)]
-#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Address(u16);
 
 impl Address {
     /// The address of the fonts in memory.
     pub const FONTS_INDEX: Self = Self(0);
+    /// The address of the SUPER-CHIP large fonts in memory, right after the small fonts.
+    pub const LARGE_FONTS_INDEX: Self = Self(0x50);
     /// The address of the entry point in memory.
     /// http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#memmap
     pub const ENTRY_POINT: Self = Self(0x200);
@@ -35,9 +38,13 @@ impl Address {
     /// # Returns
     ///
     /// * `Result<Address, super::RuntimeError>` - Returns Ok if the address is valid, otherwise returns an error.
+    #[inline]
     pub fn try_new(address: u16) -> Result<Self, super::EmulatorError> {
         if address > 0xFFF {
-            Err(super::EmulatorError::InvalidAddress(address))
+            Err(super::EmulatorError::InvalidAddress {
+                address,
+                fault: None,
+            })
         } else {
             Ok(Self(address))
         }
@@ -54,12 +61,24 @@ impl Address {
     /// # Returns
     ///
     /// * `Address` - The address created.
+    #[inline]
     pub fn new(address: u16) -> Self {
         // The address must be always valid.
         // if invalid address is passed will be truncated to 12 bits.
         Self(address & 0xFFF)
     }
 
+    /// Creates a new 16-bit address, as loaded by the XO-CHIP `F000 NNNN` instruction.
+    ///
+    /// Addresses above `0xFFF` are only valid in a 64KB memory, see [`Memory::address`].
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to create.
+    pub const fn new_long(address: u16) -> Self {
+        Self(address)
+    }
+
     /// Adds a `u16` to the address in place.
     ///
     /// # Arguments
@@ -69,8 +88,9 @@ impl Address {
     /// # Returns
     ///
     /// * `Result<(), RuntimeError>` - Returns Ok if the address is valid, otherwise returns an error.
+    #[inline]
     pub fn add_assign(&mut self, other: u16) -> Result<(), EmulatorError> {
-        *self = Self::try_new(self.0 + other)?;
+        *self = Self::try_new(self.0.saturating_add(other))?;
         Ok(())
     }
 
@@ -79,7 +99,7 @@ impl Address {
     /// # Returns
     ///
     /// * `u16` - The inner address.
-    pub fn inner(&self) -> u16 {
+    pub const fn inner(&self) -> u16 {
         self.0
     }
 }
@@ -94,7 +114,10 @@ impl TryFrom<u16> for Address {
 
 /// http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#memmap
 /// Size of the memory for the Chip8 system.
-const MEMORY_SIZE: usize = 0x1000;
+pub(crate) const MEMORY_SIZE: usize = 0x1000;
+
+/// Size of the memory for the XO-CHIP system, the whole 16-bit address space.
+pub(crate) const XO_MEMORY_SIZE: usize = 0x10000;
 
 /// https://github.com/mattmikolay/chip-8/wiki/Mastering-CHIP%E2%80%908
 /// HIP-8 contains built-in font utilities to allow for simple output of characters using the DXYN instruction.
@@ -107,25 +130,167 @@ const FONT_SET: [u8; 80] = [
     0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
 ];
 
+/// A rounder variant of [`FONT_SET`], with the corners of the glyphs cut.
+const ROUNDED_FONT_SET: [u8; 80] = [
+    0x60, 0x90, 0x90, 0x90, 0x60, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xE0, 0x10, 0x60, 0x80, 0xF0, // 2
+    0xE0, 0x10, 0x60, 0x10, 0xE0, // 3
+    0x20, 0x60, 0xA0, 0xF0, 0x20, // 4
+    0xF0, 0x80, 0xE0, 0x10, 0xE0, // 5
+    0x60, 0x80, 0xE0, 0x90, 0x60, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0x60, 0x90, 0x60, 0x90, 0x60, // 8
+    0x60, 0x90, 0x70, 0x10, 0x60, // 9
+    0x60, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0x70, 0x80, 0x80, 0x80, 0x70, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0x70, 0x80, 0xE0, 0x80, 0x70, // E
+    0x70, 0x80, 0xE0, 0x80, 0x80, // F
+];
+
+/// SUPER-CHIP adds 8x10 sprites for the digits, used through the FX30 instruction.
+/// The set also includes the hexadecimal letters (A - F) as drawn by Octo, 10 bytes per character.
+const LARGE_FONT_SET: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// The sprites of the 16 hexadecimal digits, as pointed to by `FX29`.
+///
+/// # Fields
+///
+/// * `glyphs` - The sprites of the digits 0 to F, one after the other.
+/// * `height` - The number of rows, bytes, of each sprite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontSet {
+    glyphs: &'static [u8],
+    height: u8,
+}
+
+impl FontSet {
+    /// The font of the COSMAC VIP interpreter, used by most emulators.
+    pub const fn classic() -> Self {
+        Self {
+            glyphs: &FONT_SET,
+            height: 5,
+        }
+    }
+
+    /// The classic font with rounded corners.
+    pub const fn rounded() -> Self {
+        Self {
+            glyphs: &ROUNDED_FONT_SET,
+            height: 5,
+        }
+    }
+
+    /// Returns the sprites of the digits 0 to F, one after the other.
+    pub fn glyphs(&self) -> &[u8] {
+        self.glyphs
+    }
+
+    /// Returns the number of rows of each sprite.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+}
+
+impl Default for FontSet {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
 /// Represents the memory of the Chip8 system.
 ///
 /// # Fields
 ///
-/// * `ram` - The memory of the Chip8 system.
-#[repr(transparent)]
+/// * `ram` - The memory of the Chip8 system, 4KB or 64KB for XO-CHIP.
+/// * `loaded` - The ranges written by the ROM and the loads since, sorted and merged.
+/// * `font` - The font restored with each ROM.
+/// * `font_base` - The address of the font, the SUPER-CHIP large font follows it.
 pub struct Memory {
-    ram: [u8; MEMORY_SIZE],
+    ram: Box<[u8]>,
+    loaded: Vec<Range<usize>>,
+    font: FontSet,
+    font_base: Address,
 }
 
 impl Memory {
-    /// Creates a new memory for the Chip8 system.
+    /// Creates a new 4KB memory for the Chip8 system.
     ///
     /// # Returns
     ///
     /// * `Memory` - The memory created.
     pub fn new() -> Self {
+        Self::with_size(MEMORY_SIZE)
+    }
+
+    /// Creates a new memory with a given size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size in bytes, clamped between 4KB and 64KB.
+    pub fn with_size(size: usize) -> Self {
         Self {
-            ram: [0; MEMORY_SIZE],
+            ram: vec![0; size.clamp(MEMORY_SIZE, XO_MEMORY_SIZE)].into_boxed_slice(),
+            loaded: Vec::new(),
+            font: FontSet::classic(),
+            font_base: Address::FONTS_INDEX,
+        }
+    }
+
+    /// Replaces the memory with a cleared one of another size, keeping the font.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size in bytes, clamped between 4KB and 64KB.
+    pub fn resize(&mut self, size: usize) {
+        *self = Self {
+            font: self.font,
+            font_base: self.font_base,
+            ..Self::with_size(size)
+        };
+    }
+
+    /// Returns the size of the memory in bytes.
+    pub fn size(&self) -> usize {
+        self.ram.len()
+    }
+
+    /// Checks that an address is inside the memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to check, up to `0xFFFF` in a 64KB memory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Address, EmulatorError>` - The address, or `InvalidAddress` if it is beyond the memory.
+    pub fn address(&self, address: u16) -> Result<Address, EmulatorError> {
+        if (address as usize) < self.ram.len() {
+            Ok(Address(address))
+        } else {
+            Err(EmulatorError::InvalidAddress {
+                address,
+                fault: None,
+            })
         }
     }
 
@@ -137,38 +302,407 @@ impl Memory {
     ///
     /// # Returns
     ///
-    /// * `Result<(), RuntimeError>` - Returns Ok if successful, otherwise returns an error.
+    /// * `Result<(), RuntimeError>` - Returns Ok if successful, `RomTooLarge` if the ROM does not
+    ///   fit between the entry point and the end of the memory, otherwise returns an error.
     ///
     /// # Note
     ///
     /// This function will clear the memory before loading the ROM.
-    pub fn load_rom<R: Read>(&mut self, mut reader: R) -> Result<(), EmulatorError> {
-        // Load the fonts at the start of the memory.
-        self.read_range(Address::FONTS_INDEX, &FONT_SET)?;
+    #[cfg(feature = "std")]
+    pub fn load_rom<R: Read>(&mut self, reader: R) -> Result<(), EmulatorError> {
+        self.load_rom_at(reader, Address::ENTRY_POINT)
+    }
 
-        // Clear the memory between the fonts and the entry point.
-        self.ram[Address::FONTS_INDEX.0 as usize + FONT_SET.len()..Address::ENTRY_POINT.0 as usize]
-            .fill(0);
+    /// Loads a new ROM into memory at a given entry point, restores the fonts, and clears the rest of the memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the ROM from.
+    /// * `entry_point` - The address to load the ROM at, e.g. `0x600` for ETI-660 programs.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RuntimeError>` - Returns Ok if successful, `RomTooLarge` if the ROM does not
+    ///   fit between the entry point and the end of the memory, otherwise returns an error.
+    #[cfg(feature = "std")]
+    pub fn load_rom_at<R: Read>(
+        &mut self,
+        mut reader: R,
+        entry_point: Address,
+    ) -> Result<(), EmulatorError> {
+        self.clear_below(entry_point)?;
 
         // Load the ROM.
-        let mut buf = &mut self.ram[Address::ENTRY_POINT.0 as usize..];
+        let size = self.ram.len();
+        let mut buf = &mut self.ram[entry_point.0 as usize..];
         while !buf.is_empty() {
             match reader.read(buf) {
                 Ok(0) => break,
                 Ok(n) => {
                     buf = &mut buf[n..];
                 }
-                Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
                 Err(e) => return Err(EmulatorError::LoadError(e)),
             }
         }
         // Clear the rest of the memory.
+        let end = size - buf.len();
         if !buf.is_empty() {
             buf.fill(0)
+        } else {
+            let max = self.ram.len() - entry_point.0 as usize;
+            let extra = remaining(&mut reader)?;
+            if extra > 0 {
+                return Err(EmulatorError::RomTooLarge {
+                    len: max + extra,
+                    max,
+                });
+            }
+        }
+        self.loaded.clear();
+        self.mark_loaded(entry_point.0 as usize..end);
+        Ok(())
+    }
+
+    /// Loads a new ROM from a slice at a given entry point, restores the fonts, and clears the rest of the memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the ROM.
+    /// * `entry_point` - The address to load the ROM at, e.g. `0x600` for ETI-660 programs.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RuntimeError>` - Returns Ok if successful, `RomTooLarge` if the ROM does not
+    ///   fit between the entry point and the end of the memory, in which case the memory is
+    ///   left untouched.
+    pub fn load_rom_bytes_at(
+        &mut self,
+        bytes: &[u8],
+        entry_point: Address,
+    ) -> Result<(), EmulatorError> {
+        let start = entry_point.0 as usize;
+        let max = self.ram.len() - start;
+        if bytes.len() > max {
+            let len = bytes.len();
+            return Err(EmulatorError::RomTooLarge { len, max });
+        }
+        self.clear_below(entry_point)?;
+        let end = start + bytes.len();
+        self.ram[start..end].copy_from_slice(bytes);
+        self.ram[end..].fill(0);
+        self.loaded.clear();
+        self.mark_loaded(start..end);
+        Ok(())
+    }
+
+    /// Clears the memory below the entry point and restores the fonts.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_point` - The address the ROM is loaded at.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `FontCollision` if the fonts do not fit below the
+    ///   entry point, nothing is cleared then.
+    fn clear_below(&mut self, entry_point: Address) -> Result<(), EmulatorError> {
+        let fonts = self.fonts_range(self.font_base);
+        if fonts.end > entry_point.0 as usize {
+            return Err(EmulatorError::FontCollision {
+                base: self.font_base.0,
+                end: fonts.end,
+            });
         }
+        self.ram[..entry_point.0 as usize].fill(0);
+        self.write_fonts();
         Ok(())
     }
 
+    /// Loads a font, replacing the previous one for the current and the next ROMs.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - The font.
+    /// * `base` - The address of the font, e.g. `0x050` as on many interpreters. The
+    ///   SUPER-CHIP large font is moved right after it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `FontCollision` if the fonts would overlap the ROM or
+    ///   data loaded since, or would not fit in the first 4KB where `I` can point to them.
+    ///
+    /// # Notes
+    ///
+    /// The bytes of the previous font are left as they are.
+    pub fn load_font(&mut self, font: &FontSet, base: Address) -> Result<(), EmulatorError> {
+        let previous = core::mem::replace(&mut self.font, *font);
+        let fonts = self.fonts_range(base);
+        let overlaps = |loaded: &Range<usize>| loaded.start < fonts.end && fonts.start < loaded.end;
+        if fonts.end > MEMORY_SIZE || self.loaded.iter().any(overlaps) {
+            self.font = previous;
+            return Err(EmulatorError::FontCollision {
+                base: base.0,
+                end: fonts.end,
+            });
+        }
+        self.font_base = base;
+        self.write_fonts();
+        Ok(())
+    }
+
+    /// Returns the font restored with each ROM.
+    pub fn font(&self) -> &FontSet {
+        &self.font
+    }
+
+    /// Returns the address of the font.
+    pub fn font_base(&self) -> Address {
+        self.font_base
+    }
+
+    /// Returns the address of the SUPER-CHIP large font, right after the font.
+    pub fn large_font_base(&self) -> Address {
+        Address(self.font_base.0 + self.font.glyphs.len() as u16)
+    }
+
+    /// Returns the addresses both fonts take when the font is at `base`.
+    fn fonts_range(&self, base: Address) -> Range<usize> {
+        let start = base.0 as usize;
+        start..start + self.font.glyphs.len() + LARGE_FONT_SET.len()
+    }
+
+    /// Writes both fonts at their addresses, which must be checked to fit.
+    fn write_fonts(&mut self) {
+        let fonts = self.fonts_range(self.font_base);
+        let (font, large) = self.ram[fonts].split_at_mut(self.font.glyphs.len());
+        font.copy_from_slice(self.font.glyphs);
+        large.copy_from_slice(&LARGE_FONT_SET);
+    }
+
+    /// Loads data at a given address, on top of the ROM and the previous loads.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the first byte, e.g. `0x000` for font experiments.
+    /// * `data` - The data to load.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - Returns Ok if successful, `LoadOutOfBounds` if the data
+    ///   would run past the end of the memory.
+    ///
+    /// # Notes
+    ///
+    /// * Nothing is written if the data does not fit.
+    /// * A load overlapping the ROM or a previous load overwrites the overlapped bytes, e.g. to
+    ///   patch a routine, and the loaded ranges are merged.
+    /// * Nothing else is cleared, unlike [`Memory::load_rom_at`].
+    pub fn load_at(&mut self, address: Address, data: &[u8]) -> Result<(), EmulatorError> {
+        let start = address.0 as usize;
+        if start + data.len() > self.ram.len() {
+            return Err(EmulatorError::LoadOutOfBounds {
+                address: address.0,
+                len: data.len(),
+                size: self.ram.len(),
+            });
+        }
+        self.ram[start..start + data.len()].copy_from_slice(data);
+        self.mark_loaded(start..start + data.len());
+        Ok(())
+    }
+
+    /// Returns the ranges written by the last ROM and the loads since, sorted by address.
+    ///
+    /// # Notes
+    ///
+    /// Adjacent and overlapping ranges are merged, empty loads are not recorded.
+    pub fn loaded_ranges(&self) -> &[Range<usize>] {
+        &self.loaded
+    }
+
+    /// Records a loaded range, merging it with the ranges it touches.
+    fn mark_loaded(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let (mut start, mut end) = (range.start, range.end);
+        self.loaded.retain(|loaded| {
+            let touches = loaded.start <= end && start <= loaded.end;
+            if touches {
+                start = start.min(loaded.start);
+                end = end.max(loaded.end);
+            }
+            !touches
+        });
+        let index = self.loaded.partition_point(|loaded| loaded.start < start);
+        self.loaded.insert(index, start..end);
+    }
+
+    /// Returns a range of the memory without copying it, e.g. to render a hex dump.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to return.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<&[u8], EmulatorError>` - The bytes, or `AddressOutOfRange` with the first
+    ///   address beyond the memory. Ranges ending before they start report their start.
+    pub fn view(&self, range: Range<usize>) -> Result<&[u8], EmulatorError> {
+        let address = self.out_of_range(&range);
+        let size = self.ram.len();
+        self.ram
+            .get(range)
+            .ok_or(EmulatorError::AddressOutOfRange { address, size })
+    }
+
+    /// Exports a range of the memory as Intel HEX, see [`crate::rom::from_ihex`].
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to export, e.g. `Emulator::loaded_range`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String, EmulatorError>` - The data records, 16 bytes each, and the end of
+    ///   file record. `AddressOutOfRange` if the range is not inside the memory.
+    pub fn to_ihex(&self, range: Range<usize>) -> Result<String, EmulatorError> {
+        let mut text = String::new();
+        let mut address = range.start;
+        for chunk in self.view(range)?.chunks(16) {
+            text.push_str(&ihex_record(0x00, address as u16, chunk));
+            address += chunk.len();
+        }
+        text.push_str(&ihex_record(0x01, 0, &[]));
+        Ok(text)
+    }
+
+    /// Returns a range of the memory to change it in place, see [`Memory::view`].
+    pub fn view_mut(&mut self, range: Range<usize>) -> Result<&mut [u8], EmulatorError> {
+        let address = self.out_of_range(&range);
+        let size = self.ram.len();
+        self.ram
+            .get_mut(range)
+            .ok_or(EmulatorError::AddressOutOfRange { address, size })
+    }
+
+    /// Returns a reader over a range of the memory, e.g. to hash it or save it to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The addresses to read.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<MemReader, EmulatorError>` - The reader, it ends with the range.
+    ///   `AddressOutOfRange` if the range is not inside the memory.
+    #[cfg(feature = "std")]
+    pub fn reader(&self, range: Range<usize>) -> Result<MemReader<'_>, EmulatorError> {
+        let start = range.start;
+        let bytes = self.view(range)?;
+        Ok(MemReader { bytes, start })
+    }
+
+    /// Returns a writer filling the memory from an address, e.g. from a decoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The first address written.
+    ///
+    /// # Returns
+    ///
+    /// * `MemWriter` - The writer, it stops at the end of the memory: writes return the bytes
+    ///   that fit and then 0, so `write_all` fails with `ErrorKind::WriteZero`.
+    ///
+    /// # Notes
+    ///
+    /// The writer does not mark the bytes as loaded, see [`Memory::load_at`] for that.
+    #[cfg(feature = "std")]
+    pub fn writer_at(&mut self, address: Address) -> MemWriter<'_> {
+        MemWriter {
+            ram: &mut self.ram,
+            address: address.0 as usize,
+        }
+    }
+
+    /// Returns the address reported when a range is not inside the memory.
+    fn out_of_range(&self, range: &Range<usize>) -> usize {
+        match range.start > range.end {
+            true => range.start,
+            false => range.start.max(self.ram.len()),
+        }
+    }
+
+    /// Reads a byte, e.g. a row of a sprite.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address, up to `0xFFFF` in a 64KB memory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u8, EmulatorError>` - The byte, or `InvalidAddress` if the address is beyond
+    ///   the memory.
+    #[inline]
+    pub fn get(&self, address: u16) -> Result<u8, EmulatorError> {
+        match self.ram.get(address as usize) {
+            Some(&byte) => Ok(byte),
+            None => Err(EmulatorError::InvalidAddress {
+                address,
+                fault: None,
+            }),
+        }
+    }
+
+    /// Reads a big-endian word, e.g. an instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the high byte.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u16, EmulatorError>` - The word, or `OutOfBounds` if `address` is the last
+    ///   byte of the memory.
+    #[inline]
+    pub fn read_u16(&self, address: Address) -> Result<u16, EmulatorError> {
+        let start = address.0 as usize;
+        match self.ram.get(start..start + 2) {
+            Some(&[high, low]) => Ok(u16::from_be_bytes([high, low])),
+            _ => Err(EmulatorError::OutOfBounds {
+                address: address.0.saturating_add(2),
+                fault: None,
+            }),
+        }
+    }
+
+    /// Writes a big-endian word.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the high byte.
+    /// * `value` - The word.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `OutOfBounds` if `address` is the last byte of the
+    ///   memory, nothing is written then.
+    #[inline]
+    pub fn write_u16(&mut self, address: Address, value: u16) -> Result<(), EmulatorError> {
+        let start = address.0 as usize;
+        match self.ram.get_mut(start..start + 2) {
+            Some(word) => {
+                word.copy_from_slice(&value.to_be_bytes());
+                Ok(())
+            }
+            None => Err(EmulatorError::OutOfBounds {
+                address: address.0.saturating_add(2),
+                fault: None,
+            }),
+        }
+    }
+
     /// Reads a range of data from memory into a given slice.
     ///
     /// # Arguments
@@ -181,10 +715,11 @@ impl Memory {
     /// * `Result<(), RuntimeError>` - Returns Ok if successful, otherwise returns an error.
     pub fn read_range(&mut self, start_address: Address, data: &[u8]) -> Result<(), EmulatorError> {
         // Check if the address is valid.
-        if data.len() + start_address.0 as usize > 0xFFF {
-            return Err(EmulatorError::OutOfBounds(
-                data.len() as u16 + start_address.0,
-            ));
+        if data.len() + start_address.0 as usize > self.ram.len() {
+            return Err(EmulatorError::OutOfBounds {
+                address: (data.len() as u16).saturating_add(start_address.0),
+                fault: None,
+            });
         }
         /*
         I do this in this way to avoid the code to panic if the address is invalid.
@@ -197,7 +732,7 @@ impl Memory {
         SAFETY: Since &mut self.ram is unique, the data slice is unique (no overlapping)
         */
         unsafe {
-            std::ptr::copy_nonoverlapping(
+            core::ptr::copy_nonoverlapping(
                 data.as_ptr(),
                 self.ram.as_mut_ptr().add(start_address.0 as _),
                 data.len(),
@@ -217,10 +752,11 @@ impl Memory {
         start_address: Address,
         data: &mut [u8],
     ) -> Result<(), EmulatorError> {
-        if start_address.0 as usize + data.len() > 0xFFF {
-            return Err(EmulatorError::OutOfBounds(
-                data.len() as u16 + start_address.0,
-            ));
+        if start_address.0 as usize + data.len() > self.ram.len() {
+            return Err(EmulatorError::OutOfBounds {
+                address: (data.len() as u16).saturating_add(start_address.0),
+                fault: None,
+            });
         }
         /*
         I do this in this way to avoid the code to panic if the address is invalid.
@@ -233,7 +769,7 @@ impl Memory {
         SAFETY: Since data: &mut [u8] is unique, the data slice is unique (no overlapping)
          */
         unsafe {
-            std::ptr::copy_nonoverlapping(
+            core::ptr::copy_nonoverlapping(
                 self.ram.as_ptr().add(start_address.0 as _),
                 data.as_mut_ptr(),
                 data.len(),
@@ -243,6 +779,90 @@ impl Memory {
     }
 }
 
+/// An `io::Read` over a range of the memory, see [`Memory::reader`].
+///
+/// # Fields
+///
+/// * `bytes` - The bytes not read yet.
+/// * `start` - The address of the first of them.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MemReader<'a> {
+    bytes: &'a [u8],
+    start: usize,
+}
+
+#[cfg(feature = "std")]
+impl MemReader<'_> {
+    /// Returns the address of the next byte read.
+    pub fn address(&self) -> usize {
+        self.start
+    }
+}
+
+#[cfg(feature = "std")]
+impl Read for MemReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.bytes.read(buf)?;
+        self.start += count;
+        Ok(count)
+    }
+}
+
+/// An `io::Write` filling the memory from an address, see [`Memory::writer_at`].
+///
+/// # Fields
+///
+/// * `ram` - The whole memory.
+/// * `address` - The address of the next byte written.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MemWriter<'a> {
+    ram: &'a mut [u8],
+    address: usize,
+}
+
+#[cfg(feature = "std")]
+impl MemWriter<'_> {
+    /// Returns the address of the next byte written.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for MemWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let free = self.ram.get_mut(self.address..).unwrap_or_default();
+        let count = buf.len().min(free.len());
+        free[..count].copy_from_slice(&buf[..count]);
+        self.address += count;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the number of bytes left in a reader.
+///
+/// # Arguments
+///
+/// * `reader` - The reader, its data is consumed.
+#[cfg(feature = "std")]
+pub(crate) fn remaining<R: Read>(reader: &mut R) -> Result<usize, EmulatorError> {
+    io::copy(reader, &mut io::sink())
+        .map(|count| count as usize)
+        .map_err(EmulatorError::LoadError)
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Index<Address> for Memory {
     type Output = u8;
 
@@ -256,9 +876,12 @@ impl Index<Address> for Memory {
     /// # Returns
     ///
     /// * `&u8` - A reference to the byte at the given address.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a 16-bit address is beyond a 4KB memory.
     fn index(&self, index: Address) -> &Self::Output {
-        // SAFETY: The address by dessign is always valid.
-        unsafe { &*self.ram.as_ptr().add(index.0 as usize) }
+        &self.ram[index.0 as usize]
     }
 }
 
@@ -273,8 +896,11 @@ impl IndexMut<Address> for Memory {
     /// # Returns
     ///
     /// * `&mut u8` - A mutable reference to the byte at the given address.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a 16-bit address is beyond a 4KB memory.
     fn index_mut(&mut self, index: Address) -> &mut Self::Output {
-        // SAFETY: The address is always valid.
-        unsafe { &mut *self.ram.as_mut_ptr().add(index.0 as usize) }
+        &mut self.ram[index.0 as usize]
     }
 }
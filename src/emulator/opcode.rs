@@ -1,10 +1,11 @@
-use std::fmt::Display;
+use core::{fmt::Display, str::FromStr};
 
-use crate::{register::RegisterIndex, error::EmulatorError};
+use crate::{register::RegisterIndex, error::EmulatorError, prelude::*};
 
 use super::memory::Address;
 
 /// Represents a Chip-8 opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     /// Ox00E0 - CLS
     ///
@@ -14,6 +15,26 @@ pub enum Opcode {
     ///
     /// Return from a subroutine.
     Ret,
+    /// 0x00CN - SCD N
+    ///
+    /// Scroll the display down N pixels (SUPER-CHIP).
+    Scd { n: u8 },
+    /// 0x00FB - SCR
+    ///
+    /// Scroll the display right 4 pixels (SUPER-CHIP).
+    Scr,
+    /// 0x00FC - SCL
+    ///
+    /// Scroll the display left 4 pixels (SUPER-CHIP).
+    Scl,
+    /// 0x00FE - LOW
+    ///
+    /// Switch to the 64x32 low resolution mode (SUPER-CHIP).
+    Low,
+    /// 0x00FF - HIGH
+    ///
+    /// Switch to the 128x64 high resolution mode (SUPER-CHIP).
+    High,
     /// 0x0NNN - SYS NNN
     ///
     /// Jump to a machine code routine at NNN.
@@ -73,7 +94,8 @@ pub enum Opcode {
     /// 0x8XY6 - SHR VX {, VY}
     ///
     /// Set VX = VX SHR 1.
-    Shr { x: RegisterIndex },
+    /// `SHR VX` is parsed as `SHR VX, VX`.
+    Shr { x: RegisterIndex, y: RegisterIndex },
     /// 0x8XY7 - SUBN VX, VY
     ///
     /// Set VX = VY - VX, set VF = NOT borrow.
@@ -81,7 +103,8 @@ pub enum Opcode {
     /// 0x8XYE - SHL VX {, VY}
     ///
     /// Set VX = VX SHL 1.
-    Shl { x: RegisterIndex },
+    /// `SHL VX` is parsed as `SHL VX, VX`.
+    Shl { x: RegisterIndex, y: RegisterIndex },
     /// 0x9XY0 - SNE VX, VY
     ///
     /// Skip next instruction if VX != VY.
@@ -101,6 +124,7 @@ pub enum Opcode {
     /// 0xDXYN - DRW VX, VY, N
     ///
     /// Display N-byte sprite starting at memory location I at (VX, VY), set VF = collision.
+    /// With N = 0 (SUPER-CHIP), display a 16x16 sprite made of 32 bytes, two per row.
     Drw { x: RegisterIndex, y: RegisterIndex, n: u8 },
     /// 0xEX9E - SKP VX
     ///
@@ -110,6 +134,76 @@ pub enum Opcode {
     ///
     /// Skip next instruction if key with the value of VX is not pressed.
     Sknp { x: RegisterIndex },
+    /// 0xEXF2 - SKP2 VX
+    ///
+    /// Skip next instruction if key with the value of VX is pressed on the second keypad (CHIP-8X).
+    Skp2 { x: RegisterIndex },
+    /// 0xEXF5 - SKNP2 VX
+    ///
+    /// Skip next instruction if key with the value of VX is not pressed on the second keypad (CHIP-8X).
+    Sknp2 { x: RegisterIndex },
+    /// 0x02A0 - BGC
+    ///
+    /// Step the background color to the next one of the palette (CHIP-8X).
+    /// Decoded as `SYS #2A0` unless reinterpreted with [`Opcode::chip8x`].
+    Bgc,
+    /// 0xBXYN - COL VX, VY, #N
+    ///
+    /// Set the foreground color of display zones = VY (CHIP-8X).
+    /// With N = 0, VX and VX+1 hold the first (low nibble) and last (high nibble) zone
+    /// column and row. Otherwise the zones are 1 column wide at pixel VX, and N pixels
+    /// tall from pixel VX+1.
+    /// Decoded as `JP V0, #XYN` unless reinterpreted with [`Opcode::chip8x`].
+    Col {
+        x: RegisterIndex,
+        y: RegisterIndex,
+        n: u8,
+    },
+    /// 0x0010 - MEGAOFF
+    ///
+    /// Leave MEGA-CHIP mode.
+    MegaOff,
+    /// 0x0011 - MEGAON
+    ///
+    /// Enter MEGA-CHIP mode, the following instructions use the MEGA-CHIP set.
+    MegaOn,
+    /// 0x01NN 0xNNNN - LDHI I, NNNNNN
+    ///
+    /// Set I = NNNNNN, a 24-bit address (MEGA-CHIP).
+    /// Like `LD I, LONG`, the low 16 bits are stored in the word after the instruction.
+    LdHi { address: u32 },
+    /// 0x02NN - LDPAL NN
+    ///
+    /// Load NN colors of the palette from memory starting at location I (MEGA-CHIP).
+    LdPal { byte: u8 },
+    /// 0x03NN - SPRW NN
+    ///
+    /// Set the width of the sprites = NN (MEGA-CHIP).
+    SprW { byte: u8 },
+    /// 0x04NN - SPRH NN
+    ///
+    /// Set the height of the sprites = NN (MEGA-CHIP).
+    SprH { byte: u8 },
+    /// 0x05NN - ALPHA NN
+    ///
+    /// Set the screen alpha = NN (MEGA-CHIP).
+    Alpha { byte: u8 },
+    /// 0x060N - DIGISND N
+    ///
+    /// Play the digitized sound at location I, looping if N is 0 (MEGA-CHIP).
+    DigiSnd { n: u8 },
+    /// 0x0700 - STOPSND
+    ///
+    /// Stop the digitized sound (MEGA-CHIP).
+    StopSnd,
+    /// 0x080N - BMODE N
+    ///
+    /// Set the sprite blend mode = N (MEGA-CHIP).
+    BMode { n: u8 },
+    /// 0x09NN - CCOL NN
+    ///
+    /// Set the collision color index = NN (MEGA-CHIP).
+    CCol { byte: u8 },
     /// 0xFX07 - LD VX, DT
     ///
     /// Set VX = delay timer value.
@@ -134,6 +228,10 @@ pub enum Opcode {
     ///
     /// Set I = location of sprite for digit VX.
     LdFVx { x: RegisterIndex },
+    /// 0xFX30 - LD HF, VX
+    ///
+    /// Set I = location of the 8x10 sprite for digit VX (SUPER-CHIP).
+    LdHfVx { x: RegisterIndex },
     /// 0xFX33 - LD B, VX
     ///
     /// Store BCD representation of VX in memory locations I, I+1, and I+2.
@@ -146,10 +244,58 @@ pub enum Opcode {
     ///
     /// Read registers V0 through VX from memory starting at location I.
     LdVxI { x: RegisterIndex },
+    /// 0xFX75 - LD R, VX
+    ///
+    /// Store registers V0 through VX in the RPL user flags (SUPER-CHIP).
+    LdRVx { x: RegisterIndex },
+    /// 0xFX85 - LD VX, R
+    ///
+    /// Read registers V0 through VX from the RPL user flags (SUPER-CHIP).
+    LdVxR { x: RegisterIndex },
+    /// 0xF002 - LD AUDIO, [I]
+    ///
+    /// Load the 16 bytes audio pattern buffer from memory starting at location I (XO-CHIP).
+    LdAudioI,
+    /// 0xFX3A - LD PITCH, VX
+    ///
+    /// Set the audio pattern playback pitch = VX (XO-CHIP).
+    LdPitchVx { x: RegisterIndex },
+    /// 0xFN01 - PLANE N
+    ///
+    /// Select the bit planes drawn, cleared and scrolled, N is a mask of planes 1 and 2 (XO-CHIP).
+    Plane { n: u8 },
+    /// 0xF000 0xNNNN - LD I, LONG NNNN
+    ///
+    /// Set I = NNNN, a 16-bit address stored in the word after the instruction (XO-CHIP).
+    /// This is the only 4 bytes long instruction, see [`Opcode::size`].
+    LdILong { address: Address },
     /// Invalid opcode.
     Invalid(u16),
 }
 
+/// The operands of an opcode, split by kind.
+///
+/// Each field is `Some` only if the opcode encodes that operand.
+///
+/// # Fields
+///
+/// * `x` - The first register operand (`_X__`).
+/// * `y` - The second register operand (`__Y_`).
+/// * `nnn` - The 12-bit address operand (`_NNN`).
+/// * `kk` - The 8-bit immediate operand (`__KK`).
+/// * `n` - The 4-bit immediate operand (`___N`, or `_N__` for `PLANE N`).
+/// * `address` - The 16-bit address of `LD I, LONG` or the 24-bit address of `LDHI I`,
+///   completed by the word after the instruction (`F000 NNNN`, `01NN NNNN`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Operands {
+    pub x: Option<RegisterIndex>,
+    pub y: Option<RegisterIndex>,
+    pub nnn: Option<Address>,
+    pub kk: Option<u8>,
+    pub n: Option<u8>,
+    pub address: Option<u32>,
+}
+
 impl TryFrom<[u8; 2]> for Opcode {
 
     type Error = EmulatorError;
@@ -169,10 +315,17 @@ impl TryFrom<u16> for Opcode {
     type Error = EmulatorError;
 
     /// Map a u16 value to the corresponding opcode.
+    ///
+    /// Words that do not match any instruction exactly (e.g. `0x5XY3`, `0x8XYA` or `0xEX00`)
+    /// are decoded as `Opcode::Invalid` carrying the raw word.
+    ///
+    /// `0xF000` is decoded as `Opcode::LdILong` with address 0, its operand is the next word.
+    /// [`decode_iter`] and `Emulator::fetch_opcode` read it.
     /// 
     /// # Arguments
     /// 
     /// * `value` - The u16 value to convert.
+    #[inline(always)]
     fn try_from(value: u16) -> Result<Self, Self::Error> {
         // Macros to help with parsing the opcode
         macro_rules! nibble {
@@ -181,9 +334,10 @@ impl TryFrom<u16> for Opcode {
             };
         }
 
+        // A nibble is always a valid register
         macro_rules! register {
             ($n:expr) => {
-                RegisterIndex::try_new(nibble!($n))?
+                RegisterIndex::new(nibble!($n))
             };
         }
 
@@ -199,43 +353,53 @@ impl TryFrom<u16> for Opcode {
             };
         }
 
-        // Map the opcode to the corresponding enum variant
-        let opcode = match value {
-            0x00E0 => Self::Cls,
-            0x00EE => Self::Ret,
-            0x0000..=0x0FFF => Self::Sys {
-                address: address!(),
+        // Map the opcode to the corresponding enum variant, dispatching on the highest
+        // nibble first so the match is a jump table
+        let opcode = match nibble!(0) {
+            0x0 => match value {
+                0x00E0 => Self::Cls,
+                0x00EE => Self::Ret,
+                0x00C0..=0x00CF => Self::Scd { n: nibble!(3) },
+                0x00FB => Self::Scr,
+                0x00FC => Self::Scl,
+                0x00FE => Self::Low,
+                0x00FF => Self::High,
+                0x0010 => Self::MegaOff,
+                0x0011 => Self::MegaOn,
+                _ => Self::Sys {
+                    address: address!(),
+                },
             },
-            0x1000..=0x1FFF => Self::Jp {
+            0x1 => Self::Jp {
                 address: address!(),
             },
-            0x2000..=0x2FFF => Self::Call {
+            0x2 => Self::Call {
                 address: address!(),
             },
-            0x3000..=0x3FFF => Self::SeByte {
+            0x3 => Self::SeByte {
                 x: register!(1),
                 byte: byte!(),
             },
-            0x4000..=0x4FFF => Self::SneByte {
+            0x4 => Self::SneByte {
                 x: register!(1),
                 byte: byte!(),
             },
-            0x5000..=0x5FFF => match nibble!(3) {
+            0x5 => match nibble!(3) {
                 0x0 => Self::SeRegister {
                     x: register!(1),
                     y: register!(2),
                 },
                 _ => Self::Invalid(value),
             },
-            0x6000..=0x6FFF => Self::LdByte {
+            0x6 => Self::LdByte {
                 x: register!(1),
                 byte: byte!(),
             },
-            0x7000..=0x7FFF => Self::AddByte {
+            0x7 => Self::AddByte {
                 x: register!(1),
                 byte: byte!(),
             },
-            0x8000..=0x8FFF => match nibble!(3) {
+            0x8 => match nibble!(3) {
                 0x0 => Self::LdRegister {
                     x: register!(1),
                     y: register!(2),
@@ -260,51 +424,69 @@ impl TryFrom<u16> for Opcode {
                     x: register!(1),
                     y: register!(2),
                 },
-                0x6 => Self::Shr { x: register!(1) },
+                0x6 => Self::Shr {
+                    x: register!(1),
+                    y: register!(2),
+                },
                 0x7 => Self::Subn {
                     x: register!(1),
                     y: register!(2),
                 },
-                0xE => Self::Shl { x: register!(1) },
+                0xE => Self::Shl {
+                    x: register!(1),
+                    y: register!(2),
+                },
                 _ => Self::Invalid(value),
             },
-            0x9000..=0x9FFF => match nibble!(3) {
+            0x9 => match nibble!(3) {
                 0x0 => Self::SneRegister {
                     x: register!(1),
                     y: register!(2),
                 },
                 _ => Self::Invalid(value),
             },
-            0xA000..=0xAFFF => Self::LdI {
+            0xA => Self::LdI {
                 address: address!(),
             },
-            0xB000..=0xBFFF => Self::JpV0 {
+            0xB => Self::JpV0 {
                 address: address!(),
             },
-            0xC000..=0xCFFF => Self::Rnd {
+            0xC => Self::Rnd {
                 x: register!(1),
                 byte: byte!(),
             },
-            0xD000..=0xDFFF => Self::Drw {
+            0xD => Self::Drw {
                 x: register!(1),
                 y: register!(2),
                 n: nibble!(3),
             },
-            0xE000..=0xEFFF => match (nibble!(2), nibble!(3)) {
+            0xE => match (nibble!(2), nibble!(3)) {
                 (0x9, 0xE) => Self::Skp { x: register!(1) },
                 (0xA, 0x1) => Self::Sknp { x: register!(1) },
+                (0xF, 0x2) => Self::Skp2 { x: register!(1) },
+                (0xF, 0x5) => Self::Sknp2 { x: register!(1) },
                 _ => Self::Invalid(value),
             },
-            0xF000..=0xFFFF => match (nibble!(2), nibble!(3)) {
+            // 0xF, the last value of a nibble
+            _ => match (nibble!(2), nibble!(3)) {
+                (0x0, 0x0) if value == 0xF000 => Self::LdILong {
+                    address: Address::new_long(0),
+                },
+                (0x0, 0x1) => Self::Plane { n: nibble!(1) },
+                (0x0, 0x2) if value == 0xF002 => Self::LdAudioI,
                 (0x0, 0x7) => Self::LdVxDT { x: register!(1) },
                 (0x0, 0xA) => Self::LdVxK { x: register!(1) },
                 (0x1, 0x5) => Self::LdDTVx { x: register!(1) },
                 (0x1, 0x8) => Self::LdSTVx { x: register!(1) },
                 (0x1, 0xE) => Self::AddIVx { x: register!(1) },
                 (0x2, 0x9) => Self::LdFVx { x: register!(1) },
+                (0x3, 0x0) => Self::LdHfVx { x: register!(1) },
                 (0x3, 0x3) => Self::LdBVx { x: register!(1) },
+                (0x3, 0xA) => Self::LdPitchVx { x: register!(1) },
                 (0x5, 0x5) => Self::LdIVx { x: register!(1) },
                 (0x6, 0x5) => Self::LdVxI { x: register!(1) },
+                (0x7, 0x5) => Self::LdRVx { x: register!(1) },
+                (0x8, 0x5) => Self::LdVxR { x: register!(1) },
                 _ => Self::Invalid(value),
             },
         };
@@ -312,12 +494,358 @@ impl TryFrom<u16> for Opcode {
     }
 }
 
+impl Opcode {
+    /// Encodes the opcode back into its raw 2-byte representation.
+    ///
+    /// # Returns
+    ///
+    /// * `u16` - The raw opcode word, as it would be stored in memory (big endian).
+    pub fn encode(&self) -> u16 {
+        // Helpers to assemble the opcode from its parts
+        fn xy(prefix: u16, x: RegisterIndex, y: RegisterIndex, n: u16) -> u16 {
+            prefix << 12 | (x.inner() as u16) << 8 | (y.inner() as u16) << 4 | n
+        }
+        fn xkk(prefix: u16, x: RegisterIndex, kk: u16) -> u16 {
+            prefix << 12 | (x.inner() as u16) << 8 | kk
+        }
+        fn nnn(prefix: u16, address: Address) -> u16 {
+            prefix << 12 | address.inner()
+        }
+
+        match *self {
+            Self::Cls => 0x00E0,
+            Self::Ret => 0x00EE,
+            Self::Scd { n } => 0x00C0 | n as u16 & 0xF,
+            Self::Scr => 0x00FB,
+            Self::Scl => 0x00FC,
+            Self::Low => 0x00FE,
+            Self::High => 0x00FF,
+            Self::Sys { address } => nnn(0x0, address),
+            Self::Jp { address } => nnn(0x1, address),
+            Self::Call { address } => nnn(0x2, address),
+            Self::SeByte { x, byte } => xkk(0x3, x, byte as u16),
+            Self::SneByte { x, byte } => xkk(0x4, x, byte as u16),
+            Self::SeRegister { x, y } => xy(0x5, x, y, 0x0),
+            Self::LdByte { x, byte } => xkk(0x6, x, byte as u16),
+            Self::AddByte { x, byte } => xkk(0x7, x, byte as u16),
+            Self::LdRegister { x, y } => xy(0x8, x, y, 0x0),
+            Self::Or { x, y } => xy(0x8, x, y, 0x1),
+            Self::And { x, y } => xy(0x8, x, y, 0x2),
+            Self::Xor { x, y } => xy(0x8, x, y, 0x3),
+            Self::AddRegister { x, y } => xy(0x8, x, y, 0x4),
+            Self::Sub { x, y } => xy(0x8, x, y, 0x5),
+            Self::Shr { x, y } => xy(0x8, x, y, 0x6),
+            Self::Subn { x, y } => xy(0x8, x, y, 0x7),
+            Self::Shl { x, y } => xy(0x8, x, y, 0xE),
+            Self::SneRegister { x, y } => xy(0x9, x, y, 0x0),
+            Self::LdI { address } => nnn(0xA, address),
+            Self::JpV0 { address } => nnn(0xB, address),
+            Self::Rnd { x, byte } => xkk(0xC, x, byte as u16),
+            Self::Drw { x, y, n } => xy(0xD, x, y, n as u16 & 0xF),
+            Self::Skp { x } => xkk(0xE, x, 0x9E),
+            Self::Sknp { x } => xkk(0xE, x, 0xA1),
+            Self::Skp2 { x } => xkk(0xE, x, 0xF2),
+            Self::Sknp2 { x } => xkk(0xE, x, 0xF5),
+            Self::Bgc => 0x02A0,
+            Self::MegaOff => 0x0010,
+            Self::MegaOn => 0x0011,
+            Self::LdHi { address } => 0x0100 | (address >> 16) as u16 & 0xFF,
+            Self::LdPal { byte } => 0x0200 | byte as u16,
+            Self::SprW { byte } => 0x0300 | byte as u16,
+            Self::SprH { byte } => 0x0400 | byte as u16,
+            Self::Alpha { byte } => 0x0500 | byte as u16,
+            Self::DigiSnd { n } => 0x0600 | n as u16 & 0xF,
+            Self::StopSnd => 0x0700,
+            Self::BMode { n } => 0x0800 | n as u16 & 0xF,
+            Self::CCol { byte } => 0x0900 | byte as u16,
+            Self::Col { x, y, n } => xy(0xB, x, y, n as u16 & 0xF),
+            Self::LdVxDT { x } => xkk(0xF, x, 0x07),
+            Self::LdVxK { x } => xkk(0xF, x, 0x0A),
+            Self::LdDTVx { x } => xkk(0xF, x, 0x15),
+            Self::LdSTVx { x } => xkk(0xF, x, 0x18),
+            Self::AddIVx { x } => xkk(0xF, x, 0x1E),
+            Self::LdFVx { x } => xkk(0xF, x, 0x29),
+            Self::LdHfVx { x } => xkk(0xF, x, 0x30),
+            Self::LdBVx { x } => xkk(0xF, x, 0x33),
+            Self::LdIVx { x } => xkk(0xF, x, 0x55),
+            Self::LdVxI { x } => xkk(0xF, x, 0x65),
+            Self::LdRVx { x } => xkk(0xF, x, 0x75),
+            Self::LdVxR { x } => xkk(0xF, x, 0x85),
+            Self::LdAudioI => 0xF002,
+            Self::LdPitchVx { x } => xkk(0xF, x, 0x3A),
+            Self::Plane { n } => 0xF001 | (n as u16 & 0xF) << 8,
+            Self::LdILong { .. } => 0xF000,
+            Self::Invalid(value) => value,
+        }
+    }
+
+    /// Returns the operands encoded in the opcode.
+    ///
+    /// # Returns
+    ///
+    /// * `Operands` - The operands, `Invalid` opcodes have no operands.
+    pub fn operands(&self) -> Operands {
+        let none = Operands::default();
+        match *self {
+            Self::Cls
+            | Self::Ret
+            | Self::Scr
+            | Self::Scl
+            | Self::Low
+            | Self::High
+            | Self::LdAudioI
+            | Self::Bgc
+            | Self::MegaOff
+            | Self::MegaOn
+            | Self::StopSnd
+            | Self::Invalid(_) => none,
+            Self::Plane { n } | Self::DigiSnd { n } | Self::BMode { n } => {
+                Operands { n: Some(n), ..none }
+            }
+            Self::LdILong { address } => Operands {
+                address: Some(address.inner() as u32),
+                ..none
+            },
+            Self::LdHi { address } => Operands {
+                address: Some(address),
+                ..none
+            },
+            Self::LdPal { byte }
+            | Self::SprW { byte }
+            | Self::SprH { byte }
+            | Self::Alpha { byte }
+            | Self::CCol { byte } => Operands {
+                kk: Some(byte),
+                ..none
+            },
+            Self::Scd { n } => Operands { n: Some(n), ..none },
+            Self::Sys { address }
+            | Self::Jp { address }
+            | Self::Call { address }
+            | Self::LdI { address }
+            | Self::JpV0 { address } => Operands {
+                nnn: Some(address),
+                ..none
+            },
+            Self::SeByte { x, byte }
+            | Self::SneByte { x, byte }
+            | Self::LdByte { x, byte }
+            | Self::AddByte { x, byte }
+            | Self::Rnd { x, byte } => Operands {
+                x: Some(x),
+                kk: Some(byte),
+                ..none
+            },
+            Self::SeRegister { x, y }
+            | Self::LdRegister { x, y }
+            | Self::Or { x, y }
+            | Self::And { x, y }
+            | Self::Xor { x, y }
+            | Self::AddRegister { x, y }
+            | Self::Sub { x, y }
+            | Self::Shr { x, y }
+            | Self::Subn { x, y }
+            | Self::Shl { x, y }
+            | Self::SneRegister { x, y } => Operands {
+                x: Some(x),
+                y: Some(y),
+                ..none
+            },
+            Self::Drw { x, y, n } | Self::Col { x, y, n } => Operands {
+                x: Some(x),
+                y: Some(y),
+                n: Some(n),
+                ..none
+            },
+            Self::Skp { x }
+            | Self::Sknp { x }
+            | Self::Skp2 { x }
+            | Self::Sknp2 { x }
+            | Self::LdVxDT { x }
+            | Self::LdVxK { x }
+            | Self::LdDTVx { x }
+            | Self::LdSTVx { x }
+            | Self::AddIVx { x }
+            | Self::LdFVx { x }
+            | Self::LdHfVx { x }
+            | Self::LdPitchVx { x }
+            | Self::LdBVx { x }
+            | Self::LdIVx { x }
+            | Self::LdVxI { x }
+            | Self::LdRVx { x }
+            | Self::LdVxR { x } => Operands { x: Some(x), ..none },
+        }
+    }
+
+    /// Returns true if the opcode is a conditional skip.
+    ///
+    /// Skips are `SE`, `SNE`, `SKP` and `SKNP` (`3XKK`, `4XKK`, `5XY0`, `9XY0`, `EX9E`, `EXA1`),
+    /// and the CHIP-8X `SKP2` and `SKNP2` (`EXF2`, `EXF5`).
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::SeByte { .. }
+                | Self::SneByte { .. }
+                | Self::SeRegister { .. }
+                | Self::SneRegister { .. }
+                | Self::Skp { .. }
+                | Self::Sknp { .. }
+                | Self::Skp2 { .. }
+                | Self::Sknp2 { .. }
+        )
+    }
+
+    /// Returns true if the opcode is a call (`2NNN`).
+    ///
+    /// `SYS` (`0NNN`) is not a call: it targets machine code that the interpreter can not run.
+    pub fn is_call(&self) -> bool {
+        matches!(self, Self::Call { .. })
+    }
+
+    /// Returns true if the opcode may transfer control somewhere other than the next instruction.
+    ///
+    /// Branches are the jumps (`1NNN`, `BNNN`), calls (`2NNN`), returns (`00EE`) and every
+    /// conditional skip (see [`Opcode::is_skip`]). `SYS` (`0NNN`) is not a branch.
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Self::Jp { .. } | Self::JpV0 { .. } | Self::Ret)
+            || self.is_call()
+            || self.is_skip()
+    }
+
+    /// Returns true if the opcode reads from memory at `I`.
+    ///
+    /// Those are `DRW` (`DXYN`) and `LD VX, [I]` (`FX65`).
+    pub fn reads_memory(&self) -> bool {
+        matches!(self, Self::Drw { .. } | Self::LdVxI { .. })
+    }
+
+    /// Returns true if the opcode writes to memory at `I`.
+    ///
+    /// Those are `LD B, VX` (`FX33`) and `LD [I], VX` (`FX55`).
+    pub fn writes_memory(&self) -> bool {
+        matches!(self, Self::LdBVx { .. } | Self::LdIVx { .. })
+    }
+
+    /// Returns true if the opcode depends on the keyboard state.
+    ///
+    /// Those are `SKP` (`EX9E`), `SKNP` (`EXA1`), `LD VX, K` (`FX0A`),
+    /// `SKP2` (`EXF2`) and `SKNP2` (`EXF5`).
+    pub fn reads_keyboard(&self) -> bool {
+        matches!(
+            self,
+            Self::Skp { .. }
+                | Self::Sknp { .. }
+                | Self::Skp2 { .. }
+                | Self::Sknp2 { .. }
+                | Self::LdVxK { .. }
+        )
+    }
+
+    /// Reinterprets an opcode with the MEGA-CHIP instruction set, used after `MEGAON`.
+    ///
+    /// MEGA-CHIP reuses the `01NN` - `09NN` words of `SYS`. `LDHI` only carries the highest
+    /// byte of its address, the low 16 bits are the next word (filled by [`decode_iter`]).
+    /// Every other opcode is returned unchanged.
+    pub fn megachip(self) -> Self {
+        let Self::Sys { address } = self else {
+            return self;
+        };
+        let byte = address.inner() as u8;
+        match (address.inner() >> 8, byte) {
+            (0x1, _) => Self::LdHi {
+                address: (byte as u32) << 16,
+            },
+            (0x2, _) => Self::LdPal { byte },
+            (0x3, _) => Self::SprW { byte },
+            (0x4, _) => Self::SprH { byte },
+            (0x5, _) => Self::Alpha { byte },
+            (0x6, 0x00..=0x0F) => Self::DigiSnd { n: byte },
+            (0x7, 0x00) => Self::StopSnd,
+            (0x8, 0x00..=0x0F) => Self::BMode { n: byte },
+            (0x9, _) => Self::CCol { byte },
+            _ => self,
+        }
+    }
+
+    /// Reinterprets an opcode with the CHIP-8X instruction set.
+    ///
+    /// CHIP-8X reuses words of the original set, `02A0` is `BGC` instead of `SYS #2A0`
+    /// and `BXYN` is `COL VX, VY, #N` instead of `JP V0, #XYN`.
+    /// Every other opcode is returned unchanged.
+    pub fn chip8x(self) -> Self {
+        let word = self.encode();
+        match self {
+            Self::Sys { address } if address.inner() == 0x2A0 => Self::Bgc,
+            Self::JpV0 { .. } => Self::Col {
+                x: RegisterIndex::new((word >> 8) as u8),
+                y: RegisterIndex::new((word >> 4) as u8),
+                n: (word & 0xF) as u8,
+            },
+            opcode => opcode,
+        }
+    }
+
+    /// Returns the size of the instruction in bytes.
+    ///
+    /// Every instruction is 2 bytes long, except for `LD I, LONG NNNN` (`F000 NNNN`) which is 4.
+    #[inline]
+    pub fn size(&self) -> u16 {
+        match self {
+            Self::LdILong { .. } | Self::LdHi { .. } => 4,
+            _ => 2,
+        }
+    }
+
+    /// Formats the opcode as a listing line located at `addr`.
+    ///
+    /// The line has the form `0x0212  A2 1E        LD I, #21E`, the address and raw
+    /// bytes columns have a fixed width so consecutive lines line up. The bytes column
+    /// is as wide as the 4 bytes of `LD I, LONG` and `LDHI I`, see [`Opcode::size`].
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address where the opcode is located.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The formatted listing line.
+    pub fn format_at(&self, addr: Address) -> String {
+        format!("0x{:04X}  {:#}", addr.inner(), self)
+    }
+}
+
 impl Display for Opcode {
     /// Formats the opcode for display.
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    ///
+    /// The alternate flag (`{:#}`) prefixes the mnemonic with the raw opcode
+    /// bytes in hex, padded to the width of 4 bytes, e.g. `A2 1E        LD I, #21E` or
+    /// `F0 00 12 34  LD I, LONG #1234`.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if f.alternate() {
+            let [high, low] = self.encode().to_be_bytes();
+            // The operand word of the long loads follows the opcode
+            let operand = match *self {
+                Self::LdILong { address } => Some(address.inner()),
+                Self::LdHi { address } => Some(address as u16),
+                _ => None,
+            };
+            let bytes = match operand.map(u16::to_be_bytes) {
+                Some([next_high, next_low]) => format!(
+                    "{:02X} {:02X} {:02X} {:02X}",
+                    high, low, next_high, next_low
+                ),
+                None => format!("{:02X} {:02X}", high, low),
+            };
+            write!(f, "{:<11}  ", bytes)?;
+        }
         match self {
             Self::Cls => write!(f, "CLS"),
             Self::Ret => write!(f, "RET"),
+            Self::Scd { n } => write!(f, "SCD #{:X}", n),
+            Self::Scr => write!(f, "SCR"),
+            Self::Scl => write!(f, "SCL"),
+            Self::Low => write!(f, "LOW"),
+            Self::High => write!(f, "HIGH"),
             Self::Sys { address } => write!(f, "SYS #{:X}", address.inner()),
             Self::Jp { address } => write!(f, "JP #{:X}", address.inner()),
             Self::Call { address } => write!(f, "CALL #{:X}", address.inner()),
@@ -332,9 +860,9 @@ impl Display for Opcode {
             Self::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
             Self::AddRegister { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
             Self::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
-            Self::Shr { x } => write!(f, "SHR V{:X}", x),
+            Self::Shr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
             Self::Subn { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
-            Self::Shl { x } => write!(f, "SHL V{:X}", x),
+            Self::Shl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
             Self::SneRegister { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
             Self::LdI { address } => write!(f, "LD I, #{:X}", address.inner()),
             Self::JpV0 { address } => write!(f, "JP V0, #{:X}", address.inner()),
@@ -342,16 +870,735 @@ impl Display for Opcode {
             Self::Drw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, #{:X}", x, y, n),
             Self::Skp { x } => write!(f, "SKP V{:X}", x),
             Self::Sknp { x } => write!(f, "SKNP V{:X}", x),
+            Self::Skp2 { x } => write!(f, "SKP2 V{:X}", x),
+            Self::Sknp2 { x } => write!(f, "SKNP2 V{:X}", x),
+            Self::Bgc => write!(f, "BGC"),
+            Self::MegaOff => write!(f, "MEGAOFF"),
+            Self::MegaOn => write!(f, "MEGAON"),
+            Self::LdHi { address } => write!(f, "LDHI I, #{:X}", address),
+            Self::LdPal { byte } => write!(f, "LDPAL #{:X}", byte),
+            Self::SprW { byte } => write!(f, "SPRW #{:X}", byte),
+            Self::SprH { byte } => write!(f, "SPRH #{:X}", byte),
+            Self::Alpha { byte } => write!(f, "ALPHA #{:X}", byte),
+            Self::DigiSnd { n } => write!(f, "DIGISND #{:X}", n),
+            Self::StopSnd => write!(f, "STOPSND"),
+            Self::BMode { n } => write!(f, "BMODE #{:X}", n),
+            Self::CCol { byte } => write!(f, "CCOL #{:X}", byte),
+            Self::Col { x, y, n } => write!(f, "COL V{:X}, V{:X}, #{:X}", x, y, n),
             Self::LdVxDT { x } => write!(f, "LD V{:X}, DT", x),
             Self::LdVxK { x } => write!(f, "LD V{:X}, K", x),
             Self::LdDTVx { x } => write!(f, "LD DT, V{:X}", x),
             Self::LdSTVx { x } => write!(f, "LD ST, V{:X}", x),
             Self::AddIVx { x } => write!(f, "ADD I, V{:X}", x),
             Self::LdFVx { x } => write!(f, "LD F, V{:X}", x),
+            Self::LdHfVx { x } => write!(f, "LD HF, V{:X}", x),
             Self::LdBVx { x } => write!(f, "LD B, V{:X}", x),
             Self::LdIVx { x } => write!(f, "LD [I], V{:X}", x),
             Self::LdVxI { x } => write!(f, "LD V{:X}, [I]", x),
+            Self::LdRVx { x } => write!(f, "LD R, V{:X}", x),
+            Self::LdVxR { x } => write!(f, "LD V{:X}, R", x),
+            Self::LdAudioI => write!(f, "LD AUDIO, [I]"),
+            Self::LdPitchVx { x } => write!(f, "LD PITCH, V{:X}", x),
+            Self::Plane { n } => write!(f, "PLANE #{:X}", n),
+            Self::LdILong { address } => write!(f, "LD I, LONG #{:X}", address.inner()),
             Self::Invalid(value) => write!(f, "#{:X}", value),
         }
     }
 }
+
+impl FromStr for Opcode {
+    type Err = EmulatorError;
+
+    /// Parses an opcode written in the same syntax used by `Display`.
+    ///
+    /// Mnemonics and operands are case insensitive, numbers can be written as
+    /// `#1F`, `0x1F` or `31`, and a bare `#WORD` is decoded as a raw opcode word.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The text to parse, e.g. `LD I, #21E`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_ascii_uppercase();
+        let invalid = || EmulatorError::InvalidInstruction(s.to_string());
+        let (mnemonic, rest) = text.split_once(' ').unwrap_or((&text, ""));
+        let operands: Vec<&str> = if rest.trim().is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+
+        // Operand parsers
+        let is_register = |token: &str| token.len() == 2 && token.starts_with('V');
+        let register = |token: &str| {
+            let index = token.strip_prefix('V').filter(|_| is_register(token));
+            let index = index.and_then(|index| u8::from_str_radix(index, 16).ok());
+            index.map(RegisterIndex::new).ok_or_else(invalid)
+        };
+        let number = |token: &str, max: u16| {
+            let value = match token.strip_prefix('#').or(token.strip_prefix("0X")) {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => token.parse(),
+            };
+            value.ok().filter(|&value| value <= max).ok_or_else(invalid)
+        };
+        let address = |token: &str| number(token, 0xFFF).map(Address::new);
+        let byte = |token: &str| number(token, 0xFF).map(|value| value as u8);
+
+        let opcode = match (mnemonic, operands.as_slice()) {
+            (word, []) if word.starts_with('#') => Self::try_from(number(word, u16::MAX)?)?,
+            ("CLS", []) => Self::Cls,
+            ("RET", []) => Self::Ret,
+            ("SCD", [n]) => Self::Scd {
+                n: number(n, 0xF)? as u8,
+            },
+            ("PLANE", [n]) => Self::Plane {
+                n: number(n, 0xF)? as u8,
+            },
+            ("SCR", []) => Self::Scr,
+            ("SCL", []) => Self::Scl,
+            ("LOW", []) => Self::Low,
+            ("HIGH", []) => Self::High,
+            ("SYS", [nnn]) => Self::Sys {
+                address: address(nnn)?,
+            },
+            ("JP", ["V0", nnn]) => Self::JpV0 {
+                address: address(nnn)?,
+            },
+            ("JP", [nnn]) => Self::Jp {
+                address: address(nnn)?,
+            },
+            ("CALL", [nnn]) => Self::Call {
+                address: address(nnn)?,
+            },
+            ("SE", [x, y]) if is_register(y) => Self::SeRegister {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("SE", [x, kk]) => Self::SeByte {
+                x: register(x)?,
+                byte: byte(kk)?,
+            },
+            ("SNE", [x, y]) if is_register(y) => Self::SneRegister {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("SNE", [x, kk]) => Self::SneByte {
+                x: register(x)?,
+                byte: byte(kk)?,
+            },
+            ("LD", ["I", nnnn]) if nnnn.starts_with("LONG ") => Self::LdILong {
+                address: Address::new_long(number(nnnn["LONG ".len()..].trim(), 0xFFFF)?),
+            },
+            ("LD", ["I", nnn]) => Self::LdI {
+                address: address(nnn)?,
+            },
+            ("LD", ["DT", x]) => Self::LdDTVx { x: register(x)? },
+            ("LD", ["ST", x]) => Self::LdSTVx { x: register(x)? },
+            ("LD", ["F", x]) => Self::LdFVx { x: register(x)? },
+            ("LD", ["HF", x]) => Self::LdHfVx { x: register(x)? },
+            ("LD", ["AUDIO", "[I]"]) => Self::LdAudioI,
+            ("LD", ["PITCH", x]) => Self::LdPitchVx { x: register(x)? },
+            ("LD", ["R", x]) => Self::LdRVx { x: register(x)? },
+            ("LD", ["B", x]) => Self::LdBVx { x: register(x)? },
+            ("LD", ["[I]", x]) => Self::LdIVx { x: register(x)? },
+            ("LD", [x, "DT"]) => Self::LdVxDT { x: register(x)? },
+            ("LD", [x, "K"]) => Self::LdVxK { x: register(x)? },
+            ("LD", [x, "[I]"]) => Self::LdVxI { x: register(x)? },
+            ("LD", [x, "R"]) => Self::LdVxR { x: register(x)? },
+            ("LD", [x, y]) if is_register(y) => Self::LdRegister {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("LD", [x, kk]) => Self::LdByte {
+                x: register(x)?,
+                byte: byte(kk)?,
+            },
+            ("ADD", ["I", x]) => Self::AddIVx { x: register(x)? },
+            ("ADD", [x, y]) if is_register(y) => Self::AddRegister {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("ADD", [x, kk]) => Self::AddByte {
+                x: register(x)?,
+                byte: byte(kk)?,
+            },
+            ("OR", [x, y]) => Self::Or {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("AND", [x, y]) => Self::And {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("XOR", [x, y]) => Self::Xor {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("SUB", [x, y]) => Self::Sub {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("SUBN", [x, y]) => Self::Subn {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("SHR", [x]) => Self::Shr {
+                x: register(x)?,
+                y: register(x)?,
+            },
+            ("SHR", [x, y]) => Self::Shr {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("SHL", [x]) => Self::Shl {
+                x: register(x)?,
+                y: register(x)?,
+            },
+            ("SHL", [x, y]) => Self::Shl {
+                x: register(x)?,
+                y: register(y)?,
+            },
+            ("RND", [x, kk]) => Self::Rnd {
+                x: register(x)?,
+                byte: byte(kk)?,
+            },
+            ("DRW", [x, y, n]) => Self::Drw {
+                x: register(x)?,
+                y: register(y)?,
+                n: number(n, 0xF)? as u8,
+            },
+            ("SKP", [x]) => Self::Skp { x: register(x)? },
+            ("SKNP", [x]) => Self::Sknp { x: register(x)? },
+            ("SKP2", [x]) => Self::Skp2 { x: register(x)? },
+            ("SKNP2", [x]) => Self::Sknp2 { x: register(x)? },
+            ("BGC", []) => Self::Bgc,
+            ("MEGAOFF", []) => Self::MegaOff,
+            ("MEGAON", []) => Self::MegaOn,
+            ("LDHI", ["I", nnnnnn]) => Self::LdHi {
+                address: nnnnnn
+                    .strip_prefix('#')
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .filter(|&address| address <= 0xFF_FFFF)
+                    .ok_or_else(invalid)?,
+            },
+            ("LDPAL", [nn]) => Self::LdPal { byte: byte(nn)? },
+            ("SPRW", [nn]) => Self::SprW { byte: byte(nn)? },
+            ("SPRH", [nn]) => Self::SprH { byte: byte(nn)? },
+            ("ALPHA", [nn]) => Self::Alpha { byte: byte(nn)? },
+            ("DIGISND", [n]) => Self::DigiSnd {
+                n: number(n, 0xF)? as u8,
+            },
+            ("STOPSND", []) => Self::StopSnd,
+            ("BMODE", [n]) => Self::BMode {
+                n: number(n, 0xF)? as u8,
+            },
+            ("CCOL", [nn]) => Self::CCol { byte: byte(nn)? },
+            ("COL", [x, y, n]) => Self::Col {
+                x: register(x)?,
+                y: register(y)?,
+                n: number(n, 0xF)? as u8,
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(opcode)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Opcode {
+    /// Decodes a random word, as CHIP-8, MegaChip or CHIP-8X, so every opcode the emulator
+    /// can run is generated, `Invalid` included. The long loads take a second word.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let word = u.arbitrary::<u16>()?;
+        let opcode = Self::try_from(word).unwrap_or(Self::Invalid(word));
+        let opcode = match u.int_in_range(0..=2)? {
+            0 => opcode,
+            1 => opcode.megachip(),
+            _ => opcode.chip8x(),
+        };
+        Ok(match opcode {
+            Self::LdILong { .. } => Self::LdILong {
+                address: Address::new_long(u.arbitrary::<u16>()?),
+            },
+            Self::LdHi { address } => Self::LdHi {
+                address: address | u.arbitrary::<u16>()? as u32,
+            },
+            opcode => opcode,
+        })
+    }
+}
+
+/// Decodes the opcodes stored in a byte slice, two bytes at a time.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to decode, e.g. the content of a ROM.
+/// * `base` - The address where `bytes` is loaded, usually `Address::ENTRY_POINT`.
+///
+/// # Returns
+///
+/// * `impl Iterator<Item = (Address, Opcode)>` - Each opcode with the address it is loaded at.
+///
+/// # Notes
+///
+/// * `F000 NNNN` is decoded as a single 4 bytes `LD I, LONG NNNN`.
+/// * The words after `MEGAON` (until `MEGAOFF`) are decoded with [`Opcode::megachip`],
+///   `01NN NNNN` as a single 4 bytes `LDHI I, NNNNNN`.
+/// * A trailing odd byte (or an `F000` without its operand) is not a complete opcode, so the iterator stops before it.
+/// * The iterator also stops at the first opcode that would be loaded beyond `0xFFFF`, the
+///   end of the 64KB XO-CHIP memory, so ROMs bigger than 4KB are decoded whole.
+pub fn decode_iter(bytes: &[u8], base: Address) -> impl Iterator<Item = (Address, Opcode)> + '_ {
+    let word = |offset: usize| {
+        Some(u16::from_be_bytes([
+            *bytes.get(offset)?,
+            *bytes.get(offset + 1)?,
+        ]))
+    };
+    let mut offset = 0;
+    let mut megamode = false;
+    core::iter::from_fn(move || {
+        let address = Address::new_long(base.inner().checked_add(u16::try_from(offset).ok()?)?);
+        let raw = word(offset)?;
+        let mut opcode = Opcode::try_from(raw).unwrap_or(Opcode::Invalid(raw));
+        if megamode {
+            opcode = opcode.megachip();
+        }
+        let opcode = match opcode {
+            Opcode::LdILong { .. } => Opcode::LdILong {
+                address: Address::new_long(word(offset + 2)?),
+            },
+            Opcode::LdHi { address } => Opcode::LdHi {
+                address: address | word(offset + 2)? as u32,
+            },
+            Opcode::MegaOn | Opcode::MegaOff => {
+                megamode = opcode == Opcode::MegaOn;
+                opcode
+            }
+            opcode => opcode,
+        };
+        offset += opcode.size() as usize;
+        Some((address, opcode))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `raw` and formats it with the alternate flag.
+    fn alt(raw: u16) -> String {
+        format!("{:#}", Opcode::try_from(raw).unwrap())
+    }
+
+    #[test]
+    fn test_alternate_format_snapshot() {
+        let expected = [
+            (0x00E0, "00 E0        CLS"),
+            (0x00EE, "00 EE        RET"),
+            (0x00C4, "00 C4        SCD #4"),
+            (0x00FB, "00 FB        SCR"),
+            (0x00FC, "00 FC        SCL"),
+            (0x00FE, "00 FE        LOW"),
+            (0x00FF, "00 FF        HIGH"),
+            (0x0123, "01 23        SYS #123"),
+            (0x1234, "12 34        JP #234"),
+            (0x2ABC, "2A BC        CALL #ABC"),
+            (0x3A0F, "3A 0F        SE VA, #F"),
+            (0x4B12, "4B 12        SNE VB, #12"),
+            (0x5120, "51 20        SE V1, V2"),
+            (0x6C34, "6C 34        LD VC, #34"),
+            (0x7D01, "7D 01        ADD VD, #1"),
+            (0x8120, "81 20        LD V1, V2"),
+            (0x8121, "81 21        OR V1, V2"),
+            (0x8122, "81 22        AND V1, V2"),
+            (0x8123, "81 23        XOR V1, V2"),
+            (0x8124, "81 24        ADD V1, V2"),
+            (0x8125, "81 25        SUB V1, V2"),
+            (0x8126, "81 26        SHR V1, V2"),
+            (0x8127, "81 27        SUBN V1, V2"),
+            (0x812E, "81 2E        SHL V1, V2"),
+            (0x9340, "93 40        SNE V3, V4"),
+            (0xA21E, "A2 1E        LD I, #21E"),
+            (0xB300, "B3 00        JP V0, #300"),
+            (0xC5FF, "C5 FF        RND V5, #FF"),
+            (0xD125, "D1 25        DRW V1, V2, #5"),
+            (0xE69E, "E6 9E        SKP V6"),
+            (0xE7A1, "E7 A1        SKNP V7"),
+            (0xE3F2, "E3 F2        SKP2 V3"),
+            (0xE4F5, "E4 F5        SKNP2 V4"),
+            (0xF807, "F8 07        LD V8, DT"),
+            (0xF90A, "F9 0A        LD V9, K"),
+            (0xFA15, "FA 15        LD DT, VA"),
+            (0xFB18, "FB 18        LD ST, VB"),
+            (0xFC1E, "FC 1E        ADD I, VC"),
+            (0xFD29, "FD 29        LD F, VD"),
+            (0xF130, "F1 30        LD HF, V1"),
+            (0xFE33, "FE 33        LD B, VE"),
+            (0xFF55, "FF 55        LD [I], VF"),
+            (0xF065, "F0 65        LD V0, [I]"),
+            (0xF575, "F5 75        LD R, V5"),
+            (0xF585, "F5 85        LD V5, R"),
+            (0xF301, "F3 01        PLANE #3"),
+            (0xF002, "F0 02        LD AUDIO, [I]"),
+            (0xF43A, "F4 3A        LD PITCH, V4"),
+            (0xF000, "F0 00 00 00  LD I, LONG #0"),
+            (0xFFFF, "FF FF        #FFFF"),
+        ];
+        for (raw, text) in expected {
+            assert_eq!(alt(raw), text, "opcode {:04X}", raw);
+        }
+    }
+
+    #[test]
+    fn test_default_format_has_no_raw_bytes() {
+        let opcode = Opcode::try_from(0xA21E).unwrap();
+        assert_eq!(opcode.to_string(), "LD I, #21E");
+    }
+
+    #[test]
+    fn test_format_at() {
+        let cls = Opcode::try_from(0x00E0).unwrap();
+        let ld = Opcode::try_from(0xA21E).unwrap();
+        assert_eq!(
+            cls.format_at(Address::new(0x200)),
+            "0x0200  00 E0        CLS"
+        );
+        assert_eq!(
+            ld.format_at(Address::new(0x212)),
+            "0x0212  A2 1E        LD I, #21E"
+        );
+        // The mnemonic column always starts at the same offset.
+        let first = cls.format_at(Address::new(0x0));
+        let second = ld.format_at(Address::new(0xFFE));
+        assert_eq!(first.find("CLS"), second.find("LD"));
+    }
+
+    #[test]
+    fn test_format_at_long_loads() {
+        let ld = Opcode::try_from(0xA21E).unwrap();
+        let long = Opcode::LdILong {
+            address: Address::new_long(0x1234),
+        };
+        let high = Opcode::LdHi { address: 0x123456 };
+        assert_eq!(
+            long.format_at(Address::new(0x200)),
+            "0x0200  F0 00 12 34  LD I, LONG #1234"
+        );
+        assert_eq!(
+            high.format_at(Address::new(0x204)),
+            "0x0204  01 12 34 56  LDHI I, #123456"
+        );
+        // The 2 and 4 bytes instructions put their mnemonic in the same column
+        let column = ld.format_at(Address::new(0x208)).find("LD");
+        assert_eq!(column, long.format_at(Address::new(0x200)).find("LD"));
+        assert_eq!(column, high.format_at(Address::new(0x204)).find("LD"));
+    }
+
+    #[test]
+    fn test_operands() {
+        let drw = Opcode::try_from(0xD125).unwrap().operands();
+        assert_eq!(drw.x, Some(RegisterIndex::new(1)));
+        assert_eq!(drw.y, Some(RegisterIndex::new(2)));
+        assert_eq!(drw.n, Some(5));
+        assert_eq!((drw.nnn, drw.kk), (None, None));
+
+        let ld = Opcode::try_from(0x6C34).unwrap().operands();
+        assert_eq!(
+            ld,
+            Operands {
+                x: Some(RegisterIndex::new(0xC)),
+                kk: Some(0x34),
+                ..Default::default()
+            }
+        );
+
+        let jp = Opcode::try_from(0x1234).unwrap().operands();
+        assert_eq!(jp.nnn, Some(Address::new(0x234)));
+        assert_eq!(
+            Opcode::try_from(0x00E0).unwrap().operands(),
+            Operands::default()
+        );
+
+        let plane = Opcode::try_from(0xF301).unwrap().operands();
+        assert_eq!(
+            plane,
+            Operands {
+                n: Some(3),
+                ..Default::default()
+            }
+        );
+
+        // The addresses completed by the next word
+        let rom = [0xF0, 0x00, 0xAB, 0xCD, 0x00, 0x11, 0x01, 0x12, 0x34, 0x56];
+        let addresses: Vec<_> = decode_iter(&rom, Address::ENTRY_POINT)
+            .map(|(_, opcode)| opcode.operands().address)
+            .collect();
+        assert_eq!(addresses, [Some(0xABCD), None, Some(0x123456)]);
+        let ld = decode_iter(&rom, Address::ENTRY_POINT).next().unwrap().1;
+        assert_eq!((ld.operands().nnn, ld.operands().n), (None, None));
+    }
+
+    #[test]
+    fn test_operands_match_encoding() {
+        // Every operand present must be found at its position in the raw word.
+        for raw in 0..=u16::MAX {
+            let operands = Opcode::try_from(raw).unwrap().operands();
+            if let Some(x) = operands.x {
+                assert_eq!(x.inner() as u16, raw >> 8 & 0xF);
+            }
+            if let Some(y) = operands.y {
+                assert_eq!(y.inner() as u16, raw >> 4 & 0xF);
+            }
+            if let Some(nnn) = operands.nnn {
+                assert_eq!(nnn.inner(), raw & 0xFFF);
+            }
+            if let Some(kk) = operands.kk {
+                assert_eq!(kk as u16, raw & 0xFF);
+            }
+            if let Some(n) = operands.n {
+                let shift = if raw & 0xF0FF == 0xF001 { 8 } else { 0 };
+                assert_eq!(n as u16, raw >> shift & 0xF);
+            }
+            // The address is completed by the next word, there is none here
+            if let Some(address) = operands.address {
+                assert_eq!((raw, address), (0xF000, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_predicates() {
+        let op = |raw: u16| Opcode::try_from(raw).unwrap();
+        // Skips are branches
+        for raw in [0x3000, 0x4000, 0x5000, 0x9000, 0xE09E, 0xE0A1] {
+            assert!(op(raw).is_skip() && op(raw).is_branch(), "{:04X}", raw);
+        }
+        for raw in [0x00EE, 0x1200, 0x2200, 0xB200] {
+            assert!(op(raw).is_branch() && !op(raw).is_skip(), "{:04X}", raw);
+        }
+        assert!(op(0x2200).is_call());
+        assert!(!op(0x0200).is_call() && !op(0x0200).is_branch());
+        assert!(!op(0x6000).is_branch());
+
+        assert!(op(0xF033).writes_memory() && op(0xF055).writes_memory());
+        assert!(!op(0xF065).writes_memory());
+        assert!(op(0xF065).reads_memory() && op(0xD001).reads_memory());
+
+        assert!(op(0xE09E).reads_keyboard());
+        assert!(op(0xE0A1).reads_keyboard());
+        assert!(op(0xF00A).reads_keyboard());
+        assert!(!op(0xF007).reads_keyboard());
+    }
+
+    #[test]
+    fn test_decode_iter() {
+        let rom = [0x00, 0xE0, 0xA2, 0x1E, 0x12, 0x00];
+        let decoded: Vec<_> = decode_iter(&rom, Address::ENTRY_POINT).collect();
+        assert_eq!(
+            decoded,
+            [
+                (Address::new(0x200), Opcode::Cls),
+                (
+                    Address::new(0x202),
+                    Opcode::LdI {
+                        address: Address::new(0x21E)
+                    }
+                ),
+                (
+                    Address::new(0x204),
+                    Opcode::Jp {
+                        address: Address::new(0x200)
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_iter_long_load() {
+        let rom = [0xF0, 0x00, 0x12, 0x34, 0x00, 0xE0, 0xF0, 0x00, 0x12];
+        let decoded: Vec<_> = decode_iter(&rom, Address::ENTRY_POINT).collect();
+        assert_eq!(
+            decoded,
+            [
+                (
+                    Address::new(0x200),
+                    Opcode::LdILong {
+                        address: Address::new_long(0x1234)
+                    }
+                ),
+                (Address::new(0x204), Opcode::Cls),
+            ]
+        );
+        assert_eq!(decoded[0].1.size(), 4);
+        assert_eq!(
+            decoded[0].1.format_at(decoded[0].0),
+            "0x0200  F0 00 12 34  LD I, LONG #1234"
+        );
+        assert_eq!("ld i, long 0x1234".parse::<Opcode>().unwrap(), decoded[0].1);
+    }
+
+    #[test]
+    fn test_decode_iter_empty() {
+        assert_eq!(decode_iter(&[], Address::ENTRY_POINT).count(), 0);
+    }
+
+    #[test]
+    fn test_decode_iter_odd_length() {
+        let decoded: Vec<_> = decode_iter(&[0x00, 0xE0, 0x12], Address::ENTRY_POINT).collect();
+        assert_eq!(decoded, [(Address::ENTRY_POINT, Opcode::Cls)]);
+    }
+
+    #[test]
+    fn test_decode_iter_address_overflow() {
+        // Only the words loaded at 0xFFFC and 0xFFFE fit in the address space.
+        let rom = [0x00, 0xE0].repeat(4);
+        let addresses: Vec<_> = decode_iter(&rom, Address::new_long(0xFFFC))
+            .map(|(address, _)| address.inner())
+            .collect();
+        assert_eq!(addresses, [0xFFFC, 0xFFFE]);
+    }
+
+    #[test]
+    fn test_decode_iter_large_rom() {
+        // An XO-CHIP ROM bigger than the 4KB memory, decoded past 0xFFF
+        let mut rom = [0x00, 0xE0].repeat(0x1000);
+        rom.extend([0xF0, 0x00, 0x12, 0x34]);
+        let decoded: Vec<_> = decode_iter(&rom, Address::ENTRY_POINT).collect();
+        assert_eq!(decoded.len(), 0x1001);
+        assert_eq!(decoded[0x6FF], (Address::new(0xFFE), Opcode::Cls));
+        assert_eq!(decoded[0x700], (Address::new_long(0x1000), Opcode::Cls));
+        let long = Opcode::LdILong {
+            address: Address::new_long(0x1234),
+        };
+        assert_eq!(decoded[0x1000], (Address::new_long(0x2200), long));
+    }
+
+    #[test]
+    fn test_strict_decoding() {
+        let invalid = [
+            0x5123, 0x512F, 0x8128, 0x812A, 0x812F, 0x9125, 0xE100, 0xE19F, 0xF100, 0xF1FF,
+        ];
+        for raw in invalid {
+            assert_eq!(Opcode::try_from(raw).unwrap(), Opcode::Invalid(raw));
+        }
+    }
+
+    #[test]
+    fn test_decode_display_consistency() {
+        let mut valid = 0;
+        for raw in 0..=u16::MAX {
+            let opcode = Opcode::try_from(raw).unwrap();
+            let text = opcode.to_string();
+            // Only invalid opcodes fall back to displaying the raw word.
+            match opcode {
+                Opcode::Invalid(word) => {
+                    assert_eq!(word, raw);
+                    assert_eq!(text, format!("#{:X}", raw));
+                }
+                _ => {
+                    assert!(!text.starts_with('#'), "{:04X} displayed as {}", raw, text);
+                    valid += 1;
+                }
+            }
+        }
+        // 0NNN-4XNN, 6XNN, 7XNN and ANNN-DXYN take every word of their group,
+        // 5XY0 and 9XY0 a single final nibble, 8XYN nine of them,
+        // the E and F groups 4 and 14 low bytes respectively, plus F000 and F002.
+        assert_eq!(
+            valid,
+            11 * 0x1000 + 0x100 + 9 * 0x100 + 0x100 + 4 * 0x10 + 14 * 0x10 + 2
+        );
+    }
+
+    #[test]
+    fn test_megachip() {
+        let rom = [
+            0x02, 0x10, // SYS #210
+            0x00, 0x11, // MEGAON
+            0x01, 0x12, 0x34, 0x56, // LDHI I, #123456
+            0x02, 0x10, // LDPAL #10
+            0x06, 0x00, // DIGISND #0
+            0x00, 0x10, // MEGAOFF
+            0x02, 0x10, // SYS #210
+        ];
+        let text: Vec<_> = decode_iter(&rom, Address::ENTRY_POINT)
+            .map(|(address, opcode)| format!("{:03X} {}", address.inner(), opcode))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "200 SYS #210",
+                "202 MEGAON",
+                "204 LDHI I, #123456",
+                "208 LDPAL #10",
+                "20A DIGISND #0",
+                "20C MEGAOFF",
+                "20E SYS #210",
+            ]
+        );
+        for text in [
+            "LDHI I, #123456",
+            "LDPAL #10",
+            "SPRW #8",
+            "BMODE #1",
+            "STOPSND",
+        ] {
+            assert_eq!(text.parse::<Opcode>().unwrap().to_string(), text);
+        }
+        // Words outside of the MEGA-CHIP ranges stay SYS
+        assert_eq!(
+            Opcode::try_from(0x0610).unwrap().megachip().to_string(),
+            "SYS #610"
+        );
+    }
+
+    #[test]
+    fn test_chip8x() {
+        let chip8x = |word: u16| Opcode::try_from(word).unwrap().chip8x();
+        assert_eq!(chip8x(0x02A0), Opcode::Bgc);
+        assert_eq!(chip8x(0x02A0).encode(), 0x02A0);
+        assert_eq!(chip8x(0x02A1).to_string(), "SYS #2A1");
+        let col = chip8x(0xB124);
+        assert_eq!(col.to_string(), "COL V1, V2, #4");
+        assert_eq!(col.encode(), 0xB124);
+        assert_eq!("COL V1, V2, #4".parse::<Opcode>().unwrap(), col);
+        assert_eq!(chip8x(0x1234), Opcode::try_from(0x1234).unwrap());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let parse = |text: &str| text.parse::<Opcode>().unwrap().encode();
+        assert_eq!(parse("CLS"), 0x00E0);
+        assert_eq!(parse("ld i, 0x21e"), 0xA21E);
+        assert_eq!(parse("LD V1, 255"), 0x61FF);
+        assert_eq!(parse("JP V0, #300"), 0xB300);
+        assert_eq!(parse("SHR V3"), 0x8336);
+        assert_eq!(parse("#5124"), 0x5124);
+        for text in [
+            "",
+            "NOP",
+            "LD V1, #100",
+            "JP #1000",
+            "LD VG, V1",
+            "DRW V1, V2, #10",
+        ] {
+            assert!(text.parse::<Opcode>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_display_from_str_round_trip() {
+        for raw in 0..=u16::MAX {
+            let opcode = Opcode::try_from(raw).unwrap();
+            assert_eq!(opcode.to_string().parse::<Opcode>().unwrap(), opcode);
+        }
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        for raw in 0..=u16::MAX {
+            assert_eq!(Opcode::try_from(raw).unwrap().encode(), raw);
+        }
+    }
+}
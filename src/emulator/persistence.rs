@@ -0,0 +1,83 @@
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+#[cfg(feature = "std")]
+use log::error;
+
+use crate::constants::RPL_FLAG_COUNT;
+
+/// Keeps the SUPER-CHIP RPL user flags between sessions.
+///
+/// The HP-48 kept the flags in non-volatile memory, games use `FX75` and `FX85` to save
+/// high scores there. A frontend installs a hook with `Emulator::set_persistence_hook`
+/// to store them on disk, in the browser local storage, etc.
+pub trait PersistenceHook: Send + Sync {
+    /// Returns the flags saved by a previous session, if any.
+    fn load(&mut self) -> Option<[u8; RPL_FLAG_COUNT]>;
+
+    /// Saves the flags, called every time `FX75` changes them.
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - The current value of every flag.
+    fn save(&mut self, flags: &[u8; RPL_FLAG_COUNT]);
+}
+
+/// Keeps the RPL user flags in a file, one byte per flag.
+///
+/// # Fields
+///
+/// * `path` - The path of the file, it is created on the first save.
+#[cfg(feature = "std")]
+pub struct FileHook {
+    path: PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl FileHook {
+    /// Creates a hook that keeps the flags in a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file, e.g. the ROM path with a `.flags` extension.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl PersistenceHook for FileHook {
+    fn load(&mut self) -> Option<[u8; RPL_FLAG_COUNT]> {
+        let bytes = std::fs::read(&self.path).ok()?;
+        bytes.try_into().ok()
+    }
+
+    fn save(&mut self, flags: &[u8; RPL_FLAG_COUNT]) {
+        if let Err(err) = std::fs::write(&self.path, flags) {
+            error!(
+                "Failed to save RPL flags to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_hook() {
+        let path = std::env::temp_dir().join(format!("r8-flags-{}", std::process::id()));
+        let mut hook = FileHook::new(&path);
+        assert_eq!(hook.load(), None);
+        hook.save(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(FileHook::new(&path).load(), Some([1, 2, 3, 4, 5, 6, 7, 8]));
+
+        // Files of a different size are ignored
+        std::fs::write(&path, [1, 2]).unwrap();
+        assert_eq!(hook.load(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{constants::Palette, display::Display};
+
+/// Fades pixels out over a few frames, like the slow phosphor of a CRT.
+///
+/// Programs erase and redraw moving sprites on alternate frames, so they flicker on a
+/// modern screen. A frontend feeds every presented frame to the buffer and draws its
+/// output instead of the display, emulation is not changed.
+///
+/// # Fields
+///
+/// * `width` - The width of the last frame fed.
+/// * `height` - The height of the last frame fed.
+/// * `intensities` - The intensity of each pixel in row-major order, from 0.0 to 1.0.
+/// * `colors` - The color index each pixel was last lit with, in row-major order.
+/// * `decay` - The factor the intensity of an unlit pixel is multiplied by every frame.
+///
+/// # Notes
+///
+/// A lit pixel jumps to full intensity, an unlit one decays exponentially. A resolution
+/// change starts again from a blank screen.
+#[derive(Debug, Clone)]
+pub struct PhosphorBuffer {
+    width: usize,
+    height: usize,
+    intensities: Vec<f32>,
+    colors: Vec<u8>,
+    decay: f32,
+}
+
+impl PhosphorBuffer {
+    /// Creates a blank phosphor buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `half_life` - The number of frames an unlit pixel takes to lose half of its
+    ///   intensity. 0 or less turns the pixels off immediately, like the display.
+    ///
+    /// # Returns
+    ///
+    /// * `PhosphorBuffer` - The buffer, its size is set by the first frame fed.
+    pub fn new(half_life: f32) -> Self {
+        let mut buffer = Self {
+            width: 0,
+            height: 0,
+            intensities: Vec::new(),
+            colors: Vec::new(),
+            decay: 0.0,
+        };
+        buffer.set_half_life(half_life);
+        buffer
+    }
+
+    /// Sets how slowly unlit pixels fade out.
+    ///
+    /// # Arguments
+    ///
+    /// * `half_life` - The number of frames an unlit pixel takes to lose half of its
+    ///   intensity. 0 or less turns the pixels off immediately.
+    pub fn set_half_life(&mut self, half_life: f32) {
+        self.decay = if half_life > 0.0 {
+            0.5f32.powf(half_life.recip())
+        } else {
+            0.0
+        };
+    }
+
+    /// Blends a presented frame into the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The display, read once per frame.
+    pub fn feed(&mut self, display: &Display) {
+        let (width, height) = display.dimensions();
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.intensities = vec![0.0; width * height];
+            self.colors = vec![0; width * height];
+        }
+        for (index, (x, y, _)) in display.pixels().enumerate() {
+            match display.color(x, y) {
+                0 => self.intensities[index] *= self.decay,
+                color => {
+                    self.intensities[index] = 1.0;
+                    self.colors[index] = color;
+                }
+            }
+        }
+    }
+
+    /// Returns the dimensions of the last frame fed.
+    ///
+    /// # Returns
+    ///
+    /// * `(usize, usize)` - The width and height, `(0, 0)` before the first frame.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Returns the intensity of a pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x-coordinate of the pixel.
+    /// * `y` - The y-coordinate of the pixel.
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - 1.0 if the pixel is lit, fading towards 0.0 once it is not. 0.0 outside
+    ///   the buffer.
+    pub fn intensity(&self, x: usize, y: usize) -> f32 {
+        if x >= self.width || y >= self.height {
+            return 0.0;
+        }
+        self.intensities[y * self.width + x]
+    }
+
+    /// Converts the buffer to an RGBA image.
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The color of each color index, see `Display::color`.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - 4 bytes per pixel in row-major order, `width * height * 4` bytes long.
+    ///   Each pixel blends the color it was last lit with into the unlit color by its
+    ///   intensity.
+    pub fn to_rgba8(&self, palette: &Palette) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.intensities.len() * 4);
+        for (&intensity, &color) in self.intensities.iter().zip(&self.colors) {
+            let (off, on) = (palette[0], palette[color as usize & 0x3]);
+            buffer.extend(off.iter().zip(on).map(|(&off, on)| {
+                let channel = off as f32 + (on as f32 - off as f32) * intensity;
+                channel.round() as u8
+            }));
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MONOCHROME_PALETTE;
+
+    #[test]
+    fn test_decay() {
+        let mut display = Display::new();
+        display.set(0, 0, 0xC0);
+        let mut phosphor = PhosphorBuffer::new(2.0);
+        phosphor.feed(&display);
+        assert_eq!(phosphor.dimensions(), (64, 32));
+        assert_eq!(phosphor.intensity(0, 0), 1.0);
+        assert_eq!(phosphor.intensity(2, 0), 0.0);
+
+        // Pixel 0 turns off, pixel 1 stays lit
+        display.set(0, 0, 0x80);
+        for frame in 1..=6 {
+            phosphor.feed(&display);
+            let expected = 0.5f32.powf(frame as f32 / 2.0);
+            assert!((phosphor.intensity(0, 0) - expected).abs() < 1e-5);
+            assert_eq!(phosphor.intensity(1, 0), 1.0);
+        }
+
+        // Half of the way to black after one half-life
+        let mut phosphor = PhosphorBuffer::new(1.0);
+        display.set(0, 0, 0x80);
+        phosphor.feed(&display);
+        display.set(0, 0, 0x80);
+        phosphor.feed(&display);
+        let image = phosphor.to_rgba8(&MONOCHROME_PALETTE);
+        assert_eq!(image.len(), 64 * 32 * 4);
+        assert_eq!(image[..4], [0x80, 0x80, 0x80, 0xFF]);
+        assert_eq!(image[4..8], [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(image[8..12], [0x00, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn test_no_persistence() {
+        let mut display = Display::new();
+        display.set(0, 0, 0x80);
+        let mut phosphor = PhosphorBuffer::new(0.0);
+        phosphor.feed(&display);
+        display.set(0, 0, 0x80);
+        phosphor.feed(&display);
+        assert_eq!(phosphor.intensity(0, 0), 0.0);
+
+        // A resolution change starts from a blank screen
+        display.set_hires(true);
+        phosphor.feed(&display);
+        assert_eq!(phosphor.dimensions(), (128, 64));
+        assert_eq!(phosphor.intensity(127, 63), 0.0);
+        assert_eq!(phosphor.intensity(128, 0), 0.0);
+    }
+}
@@ -0,0 +1,450 @@
+use crate::{
+    constants::{ETI_660_HEIGHT, HEIGHT},
+    error::EmulatorError,
+    memory::{Address, MEMORY_SIZE, XO_MEMORY_SIZE},
+    prelude::*,
+    rom::Variant,
+};
+
+/// The interpreter an emulated program targets.
+///
+/// The profile decides which instruction set extensions are decoded, SUPER-CHIP and
+/// XO-CHIP instructions are treated as invalid opcodes by profiles that do not support them.
+/// Each profile is a [`Preset`] of [`PRESETS`], setting the quirks, the memory size and the
+/// display, so `Emulator::with_profile` is all a frontend needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    /// The original CHIP-8 interpreter of the COSMAC VIP (default).
+    #[default]
+    Chip8,
+    /// The CHIP-48 interpreter for the HP-48 calculators, the CHIP-8 instruction set with
+    /// the quirks many SUPER-CHIP games were written for.
+    Chip48,
+    /// The SUPER-CHIP 1.1 interpreter for the HP-48 calculators, CHIP-8 with the SUPER-CHIP
+    /// extensions and the quirks of the original implementation.
+    SuperChip,
+    /// SUPER-CHIP as modern interpreters such as Octo implement it, without the scroll quirk
+    /// of SUPER-CHIP 1.1.
+    SuperChipModern,
+    /// CHIP-8X, CHIP-8 with the color instructions of the VP-590 board and a second keypad.
+    /// `02A0` and `BXYN` are reinterpreted as `BGC` and `COL`, so `JP V0` is not available.
+    Chip8X,
+    /// The ETI-660 interpreter, CHIP-8 with a 64x48 display and programs loaded at `0x600`.
+    Eti660,
+    /// CHIP-8 with the SUPER-CHIP and XO-CHIP extensions, as Octo implements it.
+    XoChip,
+}
+
+/// The configuration an interpreter runs programs with.
+///
+/// # Fields
+///
+/// * `profile` - The interpreter.
+/// * `extensions` - The instruction set extensions decoded, the others are invalid opcodes.
+/// * `memory_size` - The size of the memory in bytes.
+/// * `entry_point` - The address programs are loaded at and start running from.
+/// * `lores_height` - The height of the display in low resolution mode.
+/// * `quirks` - The behaviors that differ from other interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub profile: Profile,
+    pub extensions: &'static [Variant],
+    pub memory_size: usize,
+    pub entry_point: Address,
+    pub lores_height: usize,
+    pub quirks: Quirks,
+}
+
+/// The presets of every [`Profile`], in declaration order.
+///
+/// | Profile         | `shift_uses_vy` | `memory_increments_i` | `jump_uses_vx` | `lores_scroll_halved` | `vf_reset` | `clip_sprites` | `display_wait` | `add_i_overflow_vf` | `reject_rpl_overflow` |
+/// |-----------------|-----------------|-----------------------|----------------|-----------------------|------------|----------------|----------------|---------------------|-----------------------|
+/// | Chip8           | true            | `XPlusOne`            | false          | false                 | true       | true           | true           | false               | false                 |
+/// | Chip48          | false           | `X`                   | true           | false                 | false      | true           | false          | false               | false                 |
+/// | SuperChip       | false           | `Unchanged`           | true           | true                  | false      | true           | false          | false               | false                 |
+/// | SuperChipModern | false           | `Unchanged`           | true           | false                 | false      | true           | false          | false               | false                 |
+/// | Chip8X          | true            | `XPlusOne`            | false          | false                 | true       | true           | true           | false               | false                 |
+/// | Eti660          | true            | `XPlusOne`            | false          | false                 | true       | true           | true           | false               | false                 |
+/// | XoChip          | true            | `XPlusOne`            | false          | false                 | false      | false          | false          | false               | false                 |
+///
+/// # Notes
+///
+/// * CHIP-48 differs from CHIP-8 in three ways: shifts ignore VY, `FX55` and `FX65`
+///   leave I incremented by X (one less than the original interpreter) and `BNNN` jumps
+///   to `XNN + VX`. SUPER-CHIP 1.1 fixed the load and store increment, leaving I unchanged.
+/// * CHIP-8X and ETI-660 are derived from the VIP interpreter and share its quirks.
+///   XO-CHIP went back to the VIP shifts, loads and stores, but keeps VF and wraps sprites.
+/// * Every profile loads programs at `0x200` into 4KB of memory with a 64x32 display,
+///   except XO-CHIP (64KB of memory) and ETI-660 (`0x600` and a 64x48 display).
+pub const PRESETS: [Preset; 7] = [
+    Preset {
+        profile: Profile::Chip8,
+        extensions: &[],
+        memory_size: MEMORY_SIZE,
+        entry_point: Address::ENTRY_POINT,
+        lores_height: HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: true,
+            memory_increments_i: IndexIncrement::XPlusOne,
+            jump_uses_vx: false,
+            lores_scroll_halved: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+    Preset {
+        profile: Profile::Chip48,
+        extensions: &[],
+        memory_size: MEMORY_SIZE,
+        entry_point: Address::ENTRY_POINT,
+        lores_height: HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: false,
+            memory_increments_i: IndexIncrement::X,
+            jump_uses_vx: true,
+            lores_scroll_halved: false,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+    Preset {
+        profile: Profile::SuperChip,
+        extensions: &[Variant::SuperChip],
+        memory_size: MEMORY_SIZE,
+        entry_point: Address::ENTRY_POINT,
+        lores_height: HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: false,
+            memory_increments_i: IndexIncrement::Unchanged,
+            jump_uses_vx: true,
+            lores_scroll_halved: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+    Preset {
+        profile: Profile::SuperChipModern,
+        extensions: &[Variant::SuperChip],
+        memory_size: MEMORY_SIZE,
+        entry_point: Address::ENTRY_POINT,
+        lores_height: HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: false,
+            memory_increments_i: IndexIncrement::Unchanged,
+            jump_uses_vx: true,
+            lores_scroll_halved: false,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+    Preset {
+        profile: Profile::Chip8X,
+        extensions: &[Variant::Chip8X],
+        memory_size: MEMORY_SIZE,
+        entry_point: Address::ENTRY_POINT,
+        lores_height: HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: true,
+            memory_increments_i: IndexIncrement::XPlusOne,
+            jump_uses_vx: false,
+            lores_scroll_halved: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+    Preset {
+        profile: Profile::Eti660,
+        extensions: &[],
+        memory_size: MEMORY_SIZE,
+        entry_point: Address::new_long(0x600),
+        lores_height: ETI_660_HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: true,
+            memory_increments_i: IndexIncrement::XPlusOne,
+            jump_uses_vx: false,
+            lores_scroll_halved: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+    Preset {
+        profile: Profile::XoChip,
+        extensions: &[Variant::SuperChip, Variant::XoChip],
+        memory_size: XO_MEMORY_SIZE,
+        entry_point: Address::ENTRY_POINT,
+        lores_height: HEIGHT,
+        quirks: Quirks {
+            shift_uses_vy: true,
+            memory_increments_i: IndexIncrement::XPlusOne,
+            jump_uses_vx: false,
+            lores_scroll_halved: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+            add_i_overflow_vf: false,
+            wait_key_held: false,
+            reject_rpl_overflow: false,
+        },
+    },
+];
+
+impl Profile {
+    /// Returns the preset of the profile.
+    #[inline]
+    pub fn preset(&self) -> &'static Preset {
+        &PRESETS[*self as usize]
+    }
+
+    /// Returns true if the profile decodes the instructions of an extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `variant` - The extension the instruction belongs to.
+    #[inline]
+    pub fn supports(&self, variant: Variant) -> bool {
+        self.preset().extensions.contains(&variant)
+    }
+
+    /// Returns the size of the memory in bytes, 4KB or 64KB for XO-CHIP.
+    pub fn memory_size(&self) -> usize {
+        self.preset().memory_size
+    }
+
+    /// Returns the address programs are loaded at and start running from.
+    pub fn entry_point(&self) -> Address {
+        self.preset().entry_point
+    }
+
+    /// Returns the height of the display in low resolution mode, 32 or 48 for ETI-660.
+    pub fn lores_height(&self) -> usize {
+        self.preset().lores_height
+    }
+
+    /// Returns the quirks of the interpreter, see [`PRESETS`].
+    pub fn quirks(&self) -> Quirks {
+        self.preset().quirks
+    }
+}
+
+/// How `FX55` and `FX65` change I after storing or loading the registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexIncrement {
+    /// I is left unchanged (default).
+    #[default]
+    Unchanged,
+    /// I is incremented by X, as CHIP-48 does.
+    X,
+    /// I is incremented by X + 1, pointing past the last register, as the original interpreter does.
+    XPlusOne,
+}
+
+/// Behaviors that differ between interpreters.
+///
+/// # Fields
+///
+/// * `shift_uses_vy` - `8XY6` and `8XYE` shift VY into VX, instead of shifting VX in place.
+/// * `memory_increments_i` - How `FX55` and `FX65` change I.
+/// * `jump_uses_vx` - `BNNN` jumps to `XNN + VX`, X being the highest nibble of the address,
+///   instead of `NNN + V0`.
+/// * `lores_scroll_halved` - In low resolution mode, `00CN`, `00FB` and `00FC` scroll by half
+///   the amount, as the SUPER-CHIP 1.1 interpreter scrolls by high resolution pixels.
+/// * `vf_reset` - `8XY1`, `8XY2` and `8XY3` clear VF, as a side effect of the original interpreter.
+/// * `clip_sprites` - `DXYN` drops the pixels past the right and bottom edges instead of
+///   wrapping them around, only the position of the sprite wraps.
+/// * `display_wait` - `DXYN` waits for the next vertical blank, as the original interpreter
+///   does, so at most one sprite is drawn per frame.
+/// * `add_i_overflow_vf` - `FX1E` wraps I within 12 bits and sets VF to 1 if it overflows,
+///   as the Amiga interpreter does, instead of stopping with an address error.
+/// * `wait_key_held` - `FX0A` is completed by a key already pressed when it runs, instead
+///   of waiting for a key to be pressed. Holding a key then skips "press any key" screens.
+/// * `reject_rpl_overflow` - `FX75` and `FX85` with X above 7 stop with `RplOverflow`,
+///   instead of storing or loading V0 to V7 only, as the HP-48 has 8 user flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    pub shift_uses_vy: bool,
+    pub memory_increments_i: IndexIncrement,
+    pub jump_uses_vx: bool,
+    pub lores_scroll_halved: bool,
+    pub vf_reset: bool,
+    pub clip_sprites: bool,
+    pub display_wait: bool,
+    pub add_i_overflow_vf: bool,
+    pub wait_key_held: bool,
+    pub reject_rpl_overflow: bool,
+}
+
+impl Quirks {
+    /// Packs the quirks, for save states.
+    ///
+    /// # Returns
+    ///
+    /// * `[u8; 3]` - The flags, a bit each from `shift_uses_vy` (bit 0) to `wait_key_held`
+    ///   (bit 7) in the order of the fields, then `memory_increments_i`: 0 for `Unchanged`,
+    ///   1 for `X` and 2 for `XPlusOne`, then `reject_rpl_overflow` (bit 0).
+    pub fn to_bytes(&self) -> [u8; 3] {
+        let flags = [
+            self.shift_uses_vy,
+            self.jump_uses_vx,
+            self.lores_scroll_halved,
+            self.vf_reset,
+            self.clip_sprites,
+            self.display_wait,
+            self.add_i_overflow_vf,
+            self.wait_key_held,
+        ];
+        let flags = flags
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (bit, &flag)| bits | (flag as u8) << bit);
+        [
+            flags,
+            self.memory_increments_i as u8,
+            self.reject_rpl_overflow as u8,
+        ]
+    }
+
+    /// Unpacks quirks packed with [`Quirks::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The packed quirks.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Quirks, EmulatorError>` - The quirks, or `InvalidSaveState` if the
+    ///   increment of I is unknown.
+    pub fn from_bytes([flags, increment, more]: [u8; 3]) -> Result<Self, EmulatorError> {
+        let flag = |bit: u8| flags & (1 << bit) != 0;
+        let memory_increments_i = match increment {
+            0 => IndexIncrement::Unchanged,
+            1 => IndexIncrement::X,
+            2 => IndexIncrement::XPlusOne,
+            _ => {
+                return Err(EmulatorError::InvalidSaveState(format!(
+                    "The increment of I {increment} is invalid"
+                )))
+            }
+        };
+        Ok(Self {
+            shift_uses_vy: flag(0),
+            memory_increments_i,
+            jump_uses_vx: flag(1),
+            lores_scroll_halved: flag(2),
+            vf_reset: flag(3),
+            clip_sprites: flag(4),
+            display_wait: flag(5),
+            add_i_overflow_vf: flag(6),
+            wait_key_held: flag(7),
+            reject_rpl_overflow: more & 1 != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        use IndexIncrement::{Unchanged, XPlusOne, X};
+        // (profile, shift_uses_vy, memory_increments_i, jump_uses_vx, lores_scroll_halved,
+        //  vf_reset, clip_sprites, display_wait)
+        let (t, f) = (true, false);
+        let presets = [
+            (Profile::Chip8, t, XPlusOne, f, f, t, t, t),
+            (Profile::Chip48, f, X, t, f, f, t, f),
+            (Profile::SuperChip, f, Unchanged, t, t, f, t, f),
+            (Profile::SuperChipModern, f, Unchanged, t, f, f, t, f),
+            (Profile::Chip8X, t, XPlusOne, f, f, t, t, t),
+            (Profile::Eti660, t, XPlusOne, f, f, t, t, t),
+            (Profile::XoChip, t, XPlusOne, f, f, f, f, f),
+        ];
+        assert_eq!(presets.len(), PRESETS.len());
+        for preset in presets {
+            let (profile, shift_uses_vy, memory_increments_i, jump_uses_vx) =
+                (preset.0, preset.1, preset.2, preset.3);
+            let (lores_scroll_halved, vf_reset) = (preset.4, preset.5);
+            let (clip_sprites, display_wait) = (preset.6, preset.7);
+            // The table is indexed by the profile
+            assert_eq!(profile.preset().profile, profile);
+            let expected = Quirks {
+                shift_uses_vy,
+                memory_increments_i,
+                jump_uses_vx,
+                lores_scroll_halved,
+                vf_reset,
+                clip_sprites,
+                display_wait,
+                // No profile emulates the Amiga interpreter
+                add_i_overflow_vf: false,
+                wait_key_held: false,
+                reject_rpl_overflow: false,
+            };
+            assert_eq!(profile.quirks(), expected, "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        for preset in PRESETS.iter() {
+            let quirks = preset.quirks;
+            assert_eq!(Quirks::from_bytes(quirks.to_bytes()).unwrap(), quirks);
+        }
+        let quirks = Quirks {
+            add_i_overflow_vf: true,
+            wait_key_held: true,
+            memory_increments_i: IndexIncrement::X,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.to_bytes(), [0xC0, 1, 0]);
+        assert_eq!(Quirks::from_bytes([0xC0, 1, 0]).unwrap(), quirks);
+        let quirks = Quirks {
+            reject_rpl_overflow: true,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.to_bytes(), [0, 0, 1]);
+        assert_eq!(Quirks::from_bytes([0, 0, 1]).unwrap(), quirks);
+        assert!(matches!(
+            Quirks::from_bytes([0, 3, 0]),
+            Err(EmulatorError::InvalidSaveState(_))
+        ));
+    }
+
+    #[test]
+    fn test_supports() {
+        assert!(!Profile::Chip8.supports(Variant::SuperChip));
+        assert!(Profile::SuperChip.supports(Variant::SuperChip));
+        assert!(Profile::SuperChipModern.supports(Variant::SuperChip));
+        assert!(!Profile::SuperChipModern.supports(Variant::XoChip));
+        assert!(Profile::XoChip.supports(Variant::SuperChip));
+        assert!(Profile::XoChip.supports(Variant::XoChip));
+        assert!(Profile::Chip8X.supports(Variant::Chip8X));
+        assert!(PRESETS
+            .iter()
+            .all(|preset| !preset.profile.supports(Variant::MegaChip)));
+    }
+}
@@ -1,27 +1,97 @@
-use std::num::Wrapping;
+use core::num::Wrapping;
+
+use crate::{prelude::*, EmulatorError};
 
 /// Function to get the current time in microseconds since UNIX_EPOCH
 /// 
 /// # Returns
 /// 
 /// * `u128` - The current time in microseconds since UNIX_EPOCH
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 fn get_epoch_micros() -> u128 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map_or(5555u128, |d| d.as_micros())
 }
 
+/// `SystemTime` panics in the browser, the time comes from JavaScript's `Date.now()` with
+/// the `wasm` feature and is fixed without it.
+#[cfg(all(feature = "std", target_arch = "wasm32", target_os = "unknown"))]
+fn get_epoch_micros() -> u128 {
+    #[cfg(feature = "wasm")]
+    return (js_sys::Date::now() * 1000.0) as u128;
+    #[cfg(not(feature = "wasm"))]
+    5555
+}
+
+/// There is no clock without `std`, the seed is fixed. Seed the generator with
+/// `RandGen::with_seed`, e.g. from a hardware timer, to vary it.
+#[cfg(not(feature = "std"))]
+fn get_epoch_micros() -> u128 {
+    5555
+}
+
+/// A source of random bytes for the `CXNN` instruction, see `Emulator::set_rng`.
+///
+/// # Notes
+///
+/// Implement it to run programs with a counter, a recorded byte stream to reproduce a bug
+/// report exactly, or a cryptographic generator. [`RandGen`] is the default source.
+pub trait RngSource: Send + Sync {
+    /// Returns the next random byte.
+    fn next_u8(&mut self) -> u8;
+
+    /// Returns the state of the source, save states keep it to replay the same bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - Opaque bytes for `set_state`, empty for sources without state.
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores a state returned by `state`, the source then produces the same bytes again.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The bytes returned by `state`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - `InvalidSaveState` if the state is not one of this
+    ///   source, the source is then left untouched.
+    fn set_state(&mut self, state: &[u8]) -> Result<(), EmulatorError> {
+        match state {
+            [] => Ok(()),
+            _ => Err(EmulatorError::InvalidSaveState(
+                "The random number generator has no state".to_string(),
+            )),
+        }
+    }
+}
+
+/// The multiplier of the 128-bit LCG of PCG64.
+const MULTIPLIER: u128 = 0x2360_ED05_1FC6_5DA4_4385_DF64_9FCC_F645;
+
+/// The increment of the 128-bit LCG of PCG64, any odd number gives the full period.
+const INCREMENT: u128 = 0x5851_F42D_4C95_7F2D_1405_7B7E_F767_814F;
+
 /// Struct to represent a pseudo-random number generator
-/// 
+///
 /// # Fields
-/// 
-/// * `multiplier` - The multiplier for the linear congruential generator
-/// * `increment` - The increment for the linear congruential generator
-/// * `modulus` - The modulus for the linear congruential generator
+///
+/// * `state` - The state of the linear congruential generator, modulo 2^128
+///
+/// # Notes
+///
+/// This is PCG64 (XSL RR 128/64): the low bits of an LCG repeat with short periods, so
+/// the output folds the high half of the state into the low half and rotates it by its
+/// top bits. Each byte is the top byte of the 64-bit output.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandGen {
-    multiplier: Wrapping<u128>,
-    increment: Wrapping<u128>,
-    modulus: Wrapping<u128>,
     state: Wrapping<u128>,
 }
 
@@ -32,13 +102,34 @@ impl RandGen {
     /// 
     /// * `RandGen` - The new instance of RandGen
     pub fn new() -> Self {
-        let seed = get_epoch_micros(); // Using the current time as seed
-        Self {
-            multiplier: Wrapping(6364136223846793005),
-            increment: Wrapping(1442695040888963407),
-            modulus: Wrapping(u128::MAX),
-            state: Wrapping(seed), // Initial state X_0 is set to the seed
-        }
+        Self::seeded(get_epoch_micros()) // Using the current time as seed
+    }
+
+    /// Function to initialize a new instance of RandGen with a fixed seed
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed, the same seed always produces the same numbers
+    ///
+    /// # Returns
+    ///
+    /// * `RandGen` - The new instance of RandGen
+    pub fn with_seed(seed: u64) -> Self {
+        Self::seeded(seed as u128)
+    }
+
+    /// Initializes the state as PCG does, so close seeds do not start with close numbers.
+    fn seeded(seed: u128) -> Self {
+        let mut rand = Self { state: Wrapping(0) };
+        rand.step();
+        rand.state += seed;
+        rand.step();
+        rand
+    }
+
+    /// Advances the linear congruential generator.
+    fn step(&mut self) {
+        self.state = self.state * Wrapping(MULTIPLIER) + Wrapping(INCREMENT);
     }
 
     /// Function to get the next random number
@@ -46,8 +137,195 @@ impl RandGen {
     /// # Returns
     /// 
     /// * `u8` - The next random number
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> u8 {
-        self.state = (self.multiplier * self.state + self.increment) % self.modulus;
-        (self.state.0 >> 56) as u8
+        (self.next_output() >> 56) as u8
+    }
+
+    /// Advances the generator and returns its 64-bit output.
+    fn next_output(&mut self) -> u64 {
+        self.step();
+        let state = self.state.0;
+        let folded = (state >> 64) as u64 ^ state as u64;
+        folded.rotate_right((state >> 122) as u32)
+    }
+}
+
+impl Default for RandGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RngSource for RandGen {
+    fn next_u8(&mut self) -> u8 {
+        self.next()
+    }
+
+    /// The 128-bit state, little endian.
+    fn state(&self) -> Vec<u8> {
+        self.state.0.to_le_bytes().to_vec()
+    }
+
+    fn set_state(&mut self, state: &[u8]) -> Result<(), EmulatorError> {
+        let state = state.try_into().map_err(|_| {
+            EmulatorError::InvalidSaveState(format!(
+                "The random number generator state is {} bytes, expected 16",
+                state.len()
+            ))
+        })?;
+        self.state = Wrapping(u128::from_le_bytes(state));
+        Ok(())
+    }
+}
+
+/// `rand` integration: the generator is an `RngCore`, the top bytes of its outputs are the
+/// bytes `next` returns.
+#[cfg(feature = "rand")]
+impl rand_core::RngCore for RandGen {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_output() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.next_output()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+}
+
+/// `rand` integration: a 128-bit seed, `seed_from_u64` is [`RandGen::with_seed`].
+#[cfg(feature = "rand")]
+impl rand_core::SeedableRng for RandGen {
+    type Seed = [u8; 16];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::seeded(u128::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(state: u64) -> Self {
+        Self::with_seed(state)
+    }
+}
+
+/// Installs any `rand` generator as the source of `CXNN`, see `Emulator::set_rng`.
+///
+/// # Fields
+///
+/// * `0` - The generator, each byte is the top byte of a `next_u32`.
+#[cfg(feature = "rand")]
+#[derive(Debug, Clone)]
+pub struct RngCoreSource<R>(pub R);
+
+#[cfg(feature = "rand")]
+impl<R: rand_core::RngCore + Send + Sync> RngSource for RngCoreSource<R> {
+    fn next_u8(&mut self) -> u8 {
+        (self.0.next_u32() >> 24) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        let sequence = |seed| {
+            let mut rand = RandGen::with_seed(seed);
+            (0..64).map(|_| rand.next()).collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(42), sequence(42));
+        assert_ne!(sequence(42), sequence(43));
+        let mut source: Box<dyn RngSource> = Box::new(RandGen::with_seed(42));
+        let bytes: Vec<_> = (0..64).map(|_| source.next_u8()).collect();
+        assert_eq!(bytes, sequence(42));
+    }
+
+    #[test]
+    fn test_uniform() {
+        let mut rand = RandGen::with_seed(0);
+        let bytes: Vec<_> = (0..1 << 20).map(|_| rand.next()).collect();
+        let mut histogram = [0u32; 256];
+        for &byte in &bytes {
+            histogram[byte as usize] += 1;
+        }
+        // Chi-squared with 255 degrees of freedom, 330 is exceeded 0.1% of the time
+        let expected = bytes.len() as f64 / 256.0;
+        let chi_squared: f64 = histogram
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        assert!(chi_squared < 330.0, "{chi_squared}");
+        // The low bit of consecutive bytes does not alternate
+        let alternations = bytes.windows(2).filter(|pair| (pair[0] ^ pair[1]) & 1 == 1);
+        let ratio = alternations.count() as f64 / bytes.len() as f64;
+        assert!((0.49..0.51).contains(&ratio), "{ratio}");
+    }
+
+    #[test]
+    fn test_no_short_period() {
+        let mut rand = RandGen::with_seed(1);
+        let bytes: Vec<_> = (0..1 << 20).map(|_| rand.next()).collect();
+        // The first 16 bytes never come back in 1M outputs
+        let start = &bytes[..16];
+        assert!(bytes.windows(16).skip(1).all(|window| window != start));
+    }
+
+    #[test]
+    fn test_state() {
+        let mut rand = RandGen::with_seed(3);
+        rand.next();
+        let state = rand.state();
+        let expected: Vec<_> = (0..100).map(|_| rand.next()).collect();
+        let mut restored = RandGen::with_seed(4);
+        assert!(restored.set_state(&state).is_ok());
+        let bytes: Vec<_> = (0..100).map(|_| restored.next()).collect();
+        assert_eq!(bytes, expected);
+        // A state of another length is rejected
+        assert!(restored.set_state(&state[1..]).is_err());
+        assert!(restored.set_state(&[]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut rand = RandGen::with_seed(5);
+        for _ in 0..10 {
+            rand.next();
+        }
+        let json = serde_json::to_string(&rand).unwrap();
+        let mut restored: RandGen = serde_json::from_str(&json).unwrap();
+        for _ in 0..64 {
+            assert_eq!(restored.next(), rand.next());
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_rand_core() {
+        use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+        // The same bytes through both interfaces
+        let mut rand = RandGen::seed_from_u64(9);
+        let mut source = RngCoreSource(RandGen::with_seed(9));
+        for _ in 0..64 {
+            assert_eq!(source.next_u8(), rand.next());
+        }
+        let mut bytes = [0; 13];
+        RandGen::from_seed([7; 16]).fill_bytes(&mut bytes);
+        let mut again = [0; 13];
+        RandGen::from_seed([7; 16]).fill_bytes(&mut again);
+        assert_eq!(bytes, again);
+
+        let sequence = |seed| {
+            let mut source = RngCoreSource(StdRng::seed_from_u64(seed));
+            (0..64).map(|_| source.next_u8()).collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(1), sequence(1));
+        let mut rng = StdRng::seed_from_u64(1);
+        let expected: Vec<_> = (0..64).map(|_| (rng.next_u32() >> 24) as u8).collect();
+        assert_eq!(sequence(1), expected);
     }
 }
@@ -0,0 +1,211 @@
+use std::io::Write;
+
+use crate::{constants::Palette, display::Display, error::EmulatorError};
+
+/// The frame rate of the display, every presented frame lasts 1/60 of a second.
+const DISPLAY_FPS: u32 = 60;
+
+/// A recorded frame.
+///
+/// # Fields
+///
+/// * `width` - The width of the display when the frame was recorded.
+/// * `height` - The height of the display when the frame was recorded.
+/// * `colors` - The color index of each pixel, in row-major order.
+/// * `ticks` - How many display frames it lasts.
+struct Frame {
+    width: usize,
+    height: usize,
+    colors: Vec<u8>,
+    ticks: u32,
+}
+
+/// Records the presented frames of a display to an animated GIF.
+///
+/// # Fields
+///
+/// * `palette` - The color of each color index.
+/// * `scale` - The width and height in the GIF of each pixel of a high resolution frame.
+/// * `skip` - The number of display frames each recorded frame covers, 2 to record at 30fps.
+/// * `fed` - The number of display frames fed so far.
+/// * `frames` - The recorded frames, identical consecutive frames are merged.
+///
+/// # Notes
+///
+/// * The GIF is as large as the largest frame, smaller frames (low resolution ones) are
+///   upscaled to fill it.
+/// * GIF delays are in hundredths of a second, they are rounded so the total length of
+///   the GIF stays right.
+pub struct GifRecorder {
+    palette: Palette,
+    scale: usize,
+    skip: u32,
+    fed: u32,
+    frames: Vec<Frame>,
+}
+
+impl GifRecorder {
+    /// Creates a recorder.
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - The color of each color index, see `Display::color`. The alpha channel is ignored.
+    /// * `scale` - The width and height in the GIF of each pixel, at least 1.
+    /// * `fps` - The frame rate of the GIF, 60 records every frame and 30 every other frame.
+    ///   It is clamped between 1 and 60.
+    pub fn new(palette: Palette, scale: usize, fps: u32) -> Self {
+        Self {
+            palette,
+            scale: scale.max(1),
+            skip: DISPLAY_FPS / fps.clamp(1, DISPLAY_FPS),
+            fed: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Records a presented frame, call it once per frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - The display.
+    ///
+    /// # Notes
+    ///
+    /// Frames skipped for the frame rate, and frames identical to the previous one, make
+    /// the previous frame last longer.
+    pub fn add_frame(&mut self, display: &Display) {
+        let skipped = !self.fed.is_multiple_of(self.skip);
+        self.fed += 1;
+        if let (true, Some(last)) = (skipped, self.frames.last_mut()) {
+            last.ticks += 1;
+            return;
+        }
+        let (width, height) = display.dimensions();
+        let pixels = display.pixels().map(|(x, y, _)| display.color(x, y));
+        let colors: Vec<u8> = pixels.collect();
+        match self.frames.last_mut() {
+            Some(last) if (last.width, last.height, &last.colors) == (width, height, &colors) => {
+                last.ticks += 1;
+            }
+            _ => self.frames.push(Frame {
+                width,
+                height,
+                colors,
+                ticks: 1,
+            }),
+        }
+    }
+
+    /// Returns the number of frames the GIF will have, after merging identical frames.
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Encodes the recorded frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the GIF is written.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if no frame was recorded or the GIF can not
+    ///   be written.
+    pub fn finish<W: Write>(self, writer: W) -> Result<(), EmulatorError> {
+        let error = |e: gif::EncodingError| EmulatorError::RecordingError(e.to_string());
+        let width = self.frames.iter().map(|frame| frame.width).max();
+        let height = self.frames.iter().map(|frame| frame.height).max();
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(EmulatorError::RecordingError(
+                "No frame was recorded".to_string(),
+            ));
+        };
+        let (width, height) = (width * self.scale, height * self.scale);
+        let rgb = self.palette.iter().flat_map(|rgba| &rgba[..3]);
+        let palette: Vec<u8> = rgb.copied().collect();
+        let mut encoder =
+            gif::Encoder::new(writer, width as u16, height as u16, &palette).map_err(error)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(error)?;
+
+        let mut elapsed = 0;
+        for frame in &self.frames {
+            // Nearest neighbor, from the GIF to the frame
+            let buffer: Vec<u8> = (0..width * height)
+                .map(|index| {
+                    let x = index % width * frame.width / width;
+                    let y = index / width * frame.height / height;
+                    frame.colors[y * frame.width + x]
+                })
+                .collect();
+            let start = elapsed * 100 / DISPLAY_FPS;
+            elapsed += frame.ticks;
+            let end = elapsed * 100 / DISPLAY_FPS;
+            let frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: (end - start) as u16,
+                buffer: buffer.into(),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame).map_err(error)?;
+        }
+        // Writes the trailer
+        encoder
+            .into_inner()
+            .map(|_| ())
+            .map_err(|e| EmulatorError::RecordingError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MONOCHROME_PALETTE;
+
+    #[test]
+    fn test_record() {
+        let mut display = Display::new();
+        let mut recorder = GifRecorder::new(MONOCHROME_PALETTE, 2, 60);
+        recorder.add_frame(&display);
+        display.set(0, 0, 0xFF);
+        // Three identical frames are merged
+        for _ in 0..3 {
+            recorder.add_frame(&display);
+        }
+        display.set_hires(true);
+        recorder.add_frame(&display);
+        assert_eq!(recorder.frame_count(), 3);
+
+        let mut gif = Vec::new();
+        assert!(recorder.finish(&mut gif).is_ok());
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(gif.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (256, 128));
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((frame.width, frame.height), (256, 128));
+            delays.push(frame.delay);
+        }
+        // 1/60, 3/60 and 1/60 of a second, in hundredths
+        assert_eq!(delays, [1, 5, 2]);
+    }
+
+    #[test]
+    fn test_frame_skip() {
+        let mut display = Display::new();
+        let mut recorder = GifRecorder::new(MONOCHROME_PALETTE, 1, 30);
+        for frame in 0..4 {
+            display.set(frame, 0, 0x80);
+            recorder.add_frame(&display);
+        }
+        // Frames 1 and 3 are skipped
+        assert_eq!(recorder.frame_count(), 2);
+
+        let recorder = GifRecorder::new(MONOCHROME_PALETTE, 1, 60);
+        assert!(matches!(
+            recorder.finish(Vec::new()),
+            Err(EmulatorError::RecordingError(_))
+        ));
+    }
+}
@@ -3,7 +3,7 @@ use crate::error::EmulatorError;
 
 /// Represents a CHIP-8 Register Index. 
 #[repr(transparent)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RegisterIndex(u8);
 
 impl RegisterIndex {
@@ -41,6 +41,7 @@ impl RegisterIndex {
      * 
      * * `Result<RegisterIndex, EmulatorError>` - The newly created RegisterIndex, or an error if the value is invalid.
      */
+    #[inline]
     pub fn try_new(value: u8) -> Result<Self, EmulatorError> {
         if value > 0x0F {
             Err(EmulatorError::InvalidRegister(value))
@@ -48,9 +49,20 @@ impl RegisterIndex {
             Ok(Self::new(value))
         }
     }
+
+    /**
+     * Returns the RegisterIndex as a `u8`.
+     *
+     * # Returns
+     *
+     * * `u8` - The inner index, always in the range `[0x0, 0xF]`.
+     */
+    pub const fn inner(&self) -> u8 {
+        self.0
+    }
 }
 
-impl std::convert::TryFrom<u8> for RegisterIndex {
+impl core::convert::TryFrom<u8> for RegisterIndex {
     type Error = EmulatorError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
@@ -58,7 +70,7 @@ impl std::convert::TryFrom<u8> for RegisterIndex {
     }
 }
 
-impl std::fmt::UpperHex for RegisterIndex {
+impl core::fmt::UpperHex for RegisterIndex {
     /**
      * Formats the RegisterIndex as uppercase hexadecimal.
      * 
@@ -68,9 +80,9 @@ impl std::fmt::UpperHex for RegisterIndex {
      * 
      * # Returns
      * 
-     * * `std::fmt::Result` - The result of the formatting.
+     * * `core::fmt::Result` - The result of the formatting.
      */
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:X}", self.0)
     }
 }
@@ -109,7 +121,7 @@ impl VRegisters {
     }
 }
 
-impl std::ops::Index<RegisterIndex> for VRegisters {
+impl core::ops::Index<RegisterIndex> for VRegisters {
     type Output = u8;
 
     /**
@@ -123,13 +135,14 @@ impl std::ops::Index<RegisterIndex> for VRegisters {
      * 
      * * `u8` - The value at the index.
      */
+    #[inline]
     fn index(&self, index: RegisterIndex) -> &Self::Output {
         // Safety: We know that the index is valid because we checked it in the constructor
         unsafe { &*self.registers.as_ptr().add(index.0 as usize) }
     }
 }
 
-impl std::ops::IndexMut<RegisterIndex> for VRegisters {
+impl core::ops::IndexMut<RegisterIndex> for VRegisters {
     /**
      * Indexes the VRegisters.
      * 
@@ -141,6 +154,7 @@ impl std::ops::IndexMut<RegisterIndex> for VRegisters {
      * 
      * * `u8` - The value at the index.
      */
+    #[inline]
     fn index_mut(&mut self, index: RegisterIndex) -> &mut Self::Output {
         // Safety: We know that the index is valid because we checked it in the constructor
         unsafe { &mut *self.registers.as_mut_ptr().add(index.0 as usize) }
@@ -148,7 +162,7 @@ impl std::ops::IndexMut<RegisterIndex> for VRegisters {
 }
 
 // Impl index range for VRegisters
-impl std::ops::Index<std::ops::RangeInclusive<RegisterIndex>> for VRegisters {
+impl core::ops::Index<core::ops::RangeInclusive<RegisterIndex>> for VRegisters {
     type Output = [u8];
 
     /**
@@ -162,10 +176,10 @@ impl std::ops::Index<std::ops::RangeInclusive<RegisterIndex>> for VRegisters {
      * 
      * * `u8` - The value at the index.
      */
-    fn index(&self, index: std::ops::RangeInclusive<RegisterIndex>) -> &Self::Output {
+    fn index(&self, index: core::ops::RangeInclusive<RegisterIndex>) -> &Self::Output {
         // Safety: We know that the index is valid because we checked it in the constructor
         unsafe {
-            std::slice::from_raw_parts(
+            core::slice::from_raw_parts(
                 self.registers.as_ptr().add(index.start().0 as usize),
                 index.end().0 as usize - index.start().0 as usize + 1,
             )
@@ -173,7 +187,7 @@ impl std::ops::Index<std::ops::RangeInclusive<RegisterIndex>> for VRegisters {
     }
 }
 
-impl std::ops::IndexMut<std::ops::RangeInclusive<RegisterIndex>> for VRegisters {
+impl core::ops::IndexMut<core::ops::RangeInclusive<RegisterIndex>> for VRegisters {
     /**
      * Indexes the VRegisters.
      * 
@@ -185,10 +199,10 @@ impl std::ops::IndexMut<std::ops::RangeInclusive<RegisterIndex>> for VRegisters
      * 
      * * `u8` - The value at the index.
      */
-    fn index_mut(&mut self, index: std::ops::RangeInclusive<RegisterIndex>) -> &mut Self::Output {
+    fn index_mut(&mut self, index: core::ops::RangeInclusive<RegisterIndex>) -> &mut Self::Output {
         // Safety: We know that the index is valid because we checked it in the constructor
         unsafe {
-            std::slice::from_raw_parts_mut(
+            core::slice::from_raw_parts_mut(
                 self.registers.as_mut_ptr().add(index.start().0 as usize),
                 index.end().0 as usize - index.start().0 as usize + 1,
             )
@@ -0,0 +1,286 @@
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+use crate::{error::EmulatorError, prelude::*};
+
+/// The first bytes of a saved recording.
+const MAGIC: &[u8; 4] = b"R8IN";
+
+/// The version of the saved format, bumped on incompatible changes.
+const VERSION: u8 = 2;
+
+/// The keyboard input of a session, to replay it deterministically.
+///
+/// # Fields
+///
+/// * `rom_crc32` - The CRC-32 of the ROM the session ran, see `compat::crc32`.
+/// * `seed` - The seed of the random number generator.
+/// * `changes` - The frame each keyboard state starts at and the state, bit n is key n.
+/// * `frames` - The number of frames recorded.
+///
+/// # Notes
+///
+/// Frames are counted from the start of the recording, the keyboard state is recorded on
+/// each vertical blank (each `tick`, once per `Emulator::run_frame`) after the queued key
+/// events are applied, so every input path is captured. Keys changed between two steps of
+/// a frame are not recorded, change them between frames.
+///
+/// A recording is saved as little endian binary:
+///
+/// | Bytes     | Content                                       |
+/// |-----------|-----------------------------------------------|
+/// | 4         | `R8IN`                                        |
+/// | 1         | The format version, 2                         |
+/// | 4         | The CRC-32 of the ROM                         |
+/// | 8         | The seed                                      |
+/// | 8         | The number of frames recorded                 |
+/// | 4         | The number of changes                         |
+/// | 10 × each | The frame (8 bytes) and keyboard state (2 bytes) of a change |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    rom_crc32: u32,
+    seed: u64,
+    changes: Vec<(u64, u16)>,
+    frames: u64,
+}
+
+impl Recording {
+    /// Creates an empty recording, see `Emulator::start_recording`.
+    pub(crate) fn new(rom_crc32: u32, seed: u64) -> Self {
+        Self {
+            rom_crc32,
+            seed,
+            changes: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    /// Records the keyboard state of a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - The pressed keys, bit n is key n.
+    pub(crate) fn record(&mut self, mask: u16) {
+        let last = self.changes.last().map_or(0, |&(_, mask)| mask);
+        if mask != last {
+            self.changes.push((self.frames, mask));
+        }
+        self.frames += 1;
+    }
+
+    /// Returns the CRC-32 of the ROM the session ran.
+    pub fn rom_crc32(&self) -> u32 {
+        self.rom_crc32
+    }
+
+    /// Returns the seed of the random number generator.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of frames recorded.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Saves the recording, see [`Recording`] for the format.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the recording is written.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), EmulatorError>` - An error if the recording can not be written.
+    #[cfg(feature = "std")]
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), EmulatorError> {
+        writer
+            .write_all(&self.to_bytes())
+            .map_err(|e| EmulatorError::RecordingError(e.to_string()))
+    }
+
+    /// Encodes the recording in the saved format, see [`Recording::save`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(self.rom_crc32.to_le_bytes());
+        bytes.extend(self.seed.to_le_bytes());
+        bytes.extend(self.frames.to_le_bytes());
+        bytes.extend((self.changes.len() as u32).to_le_bytes());
+        for &(frame, mask) in &self.changes {
+            bytes.extend(frame.to_le_bytes());
+            bytes.extend(mask.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Loads a recording saved with [`Recording::save`].
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the recording from.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Recording, EmulatorError>` - The recording, or an error if it can not be read
+    ///   or is not a valid recording.
+    #[cfg(feature = "std")]
+    pub fn load<R: Read>(mut reader: R) -> Result<Self, EmulatorError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(EmulatorError::LoadError)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Decodes a recording encoded with [`Recording::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The encoded recording.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Recording, EmulatorError>` - The recording, or an error if it is not a valid
+    ///   recording.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EmulatorError> {
+        let invalid = |reason: &str| EmulatorError::InvalidRecording(reason.to_string());
+        let mut rest = bytes;
+        let mut take = |count: usize| {
+            let (field, tail) = rest
+                .split_at_checked(count)
+                .ok_or_else(|| invalid("The recording is truncated"))?;
+            rest = tail;
+            Ok::<_, EmulatorError>(field)
+        };
+
+        if take(4)? != MAGIC {
+            return Err(invalid("The data is not an input recording"));
+        }
+        let version = take(1)?[0];
+        if version != VERSION {
+            return Err(EmulatorError::InvalidRecording(format!(
+                "The format version {version} is not supported"
+            )));
+        }
+        let rom_crc32 = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let seed = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let frames = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let mut changes = Vec::new();
+        for _ in 0..count {
+            let frame = u64::from_le_bytes(take(8)?.try_into().unwrap());
+            let mask = u16::from_le_bytes(take(2)?.try_into().unwrap());
+            changes.push((frame, mask));
+        }
+        if !rest.is_empty() {
+            return Err(invalid("The recording has trailing bytes"));
+        }
+        let ordered = changes.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if !ordered || changes.last().is_some_and(|&(frame, _)| frame >= frames) {
+            return Err(invalid("The changes are not in order"));
+        }
+        Ok(Self {
+            rom_crc32,
+            seed,
+            changes,
+            frames,
+        })
+    }
+}
+
+/// Plays a recording back, one frame at a time.
+///
+/// # Fields
+///
+/// * `recording` - The recording.
+/// * `frame` - The next frame to play.
+/// * `next` - The index of the next change.
+/// * `mask` - The keyboard state of the last frame played.
+pub(crate) struct Replay {
+    recording: Recording,
+    frame: u64,
+    next: usize,
+    mask: u16,
+}
+
+impl Replay {
+    /// Starts playing a recording from its first frame.
+    pub(crate) fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            frame: 0,
+            next: 0,
+            mask: 0,
+        }
+    }
+
+    /// Plays the next frame.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u16>` - The keyboard state of the frame, or `None` once every frame was
+    ///   played.
+    pub(crate) fn next_mask(&mut self) -> Option<u16> {
+        if self.frame >= self.recording.frames {
+            return None;
+        }
+        if let Some(&(frame, mask)) = self.recording.changes.get(self.next) {
+            if frame == self.frame {
+                self.mask = mask;
+                self.next += 1;
+            }
+        }
+        self.frame += 1;
+        Some(self.mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_load() {
+        let mut recording = Recording::new(0xDEADBEEF, 42);
+        for mask in [0, 0, 0x20, 0x20, 0x22, 0, 0] {
+            recording.record(mask);
+        }
+        assert_eq!(recording.changes, [(2, 0x20), (4, 0x22), (5, 0)]);
+        let mut bytes = Vec::new();
+        assert!(recording.save(&mut bytes).is_ok());
+        assert_eq!(bytes.len(), 29 + 3 * 10);
+        let loaded = Recording::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded, recording);
+
+        let mut replay = Replay::new(loaded);
+        let masks: Vec<_> = core::iter::from_fn(|| replay.next_mask()).collect();
+        assert_eq!(masks, [0, 0, 0x20, 0x20, 0x22, 0, 0]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut recording = Recording::new(0, 0);
+        recording.record(1);
+        let bytes = recording.to_bytes();
+
+        let truncated = bytes[..bytes.len() - 1].to_vec();
+        let mut cases = vec![truncated, [bytes.clone(), vec![0]].concat()];
+        // A recording of the first format, indexed by instruction
+        let mut other = bytes.clone();
+        other[4] = 1;
+        cases.push(other);
+        let mut other = bytes.clone();
+        other[0] = b'X';
+        cases.push(other);
+        // The change is after the last frame
+        let mut other = bytes.clone();
+        other[17] = 0;
+        cases.push(other);
+        for case in cases {
+            let result = Recording::from_bytes(&case);
+            assert!(matches!(result, Err(EmulatorError::InvalidRecording(_))));
+        }
+    }
+}
@@ -0,0 +1,1229 @@
+use alloc::collections::BTreeSet;
+
+use crate::{
+    compat::RomHash,
+    constants::STACK_SIZE,
+    error::EmulatorError,
+    memory::{Address, MEMORY_SIZE, XO_MEMORY_SIZE},
+    opcode::{decode_iter, Opcode},
+    prelude::*,
+    quirks::Profile,
+};
+
+/// Biggest ROM that fits in memory after the entry point.
+pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - Address::ENTRY_POINT.inner() as usize;
+
+/// Biggest ROM that fits in the 64KB memory of XO-CHIP after the entry point.
+pub const MAX_XO_ROM_SIZE: usize = XO_MEMORY_SIZE - Address::ENTRY_POINT.inner() as usize;
+
+/// Biggest ROM that does not overlap the area the COSMAC VIP reserved for its stack,
+/// variables and display buffer (`0xEA0` - `0xFFF`).
+pub const MAX_VIP_ROM_SIZE: usize = 0xEA0 - Address::ENTRY_POINT.inner() as usize;
+
+/// Severity of a lint finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Something worth knowing, but not a problem.
+    Info,
+    /// Something that may not work on every interpreter or may be a bug.
+    Warning,
+    /// Something that will not work.
+    Error,
+}
+
+/// A problem found by the linter.
+///
+/// # Fields
+///
+/// * `severity` - How serious the problem is.
+/// * `address` - The address of the offending instruction, `None` for problems with the whole ROM.
+/// * `message` - A human readable description of the problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub address: Option<Address>,
+    pub message: String,
+}
+
+/// The result of linting a ROM.
+///
+/// # Fields
+///
+/// * `findings` - The problems found, sorted by address.
+#[derive(Debug, Default)]
+pub struct LintReport {
+    pub findings: Vec<Finding>,
+}
+
+impl LintReport {
+    /// Returns true if any finding is an error.
+    pub fn has_errors(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+    }
+
+    /// Returns an iterator over the findings with the given severity.
+    ///
+    /// # Arguments
+    ///
+    /// * `severity` - The severity to filter by.
+    pub fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.severity == severity)
+    }
+
+    /// Adds a finding to the report.
+    fn push(&mut self, severity: Severity, address: Option<Address>, message: String) {
+        self.findings.push(Finding {
+            severity,
+            address,
+            message,
+        });
+    }
+}
+
+/// A CHIP-8 extension that adds instructions to the original set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    /// SUPER-CHIP 1.0 / 1.1.
+    SuperChip,
+    /// XO-CHIP.
+    XoChip,
+    /// CHIP-8X, the COSMAC VIP interpreter for the VP-590 color board and the VP-580 second keypad.
+    Chip8X,
+    /// MEGA-CHIP, only detected: the other profiles run `MEGAON` as `SYS`.
+    MegaChip,
+}
+
+impl core::fmt::Display for Variant {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Variant::SuperChip => write!(f, "SUPER-CHIP"),
+            Variant::XoChip => write!(f, "XO-CHIP"),
+            Variant::Chip8X => write!(f, "CHIP-8X"),
+            Variant::MegaChip => write!(f, "MEGA-CHIP"),
+        }
+    }
+}
+
+/// An instruction that requires an extension.
+///
+/// # Fields
+///
+/// * `variant` - The extension that defines the instruction.
+/// * `address` - The address of the instruction.
+/// * `word` - The raw instruction word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evidence {
+    pub variant: Variant,
+    pub address: Address,
+    pub word: u16,
+}
+
+/// The extensions used by a ROM.
+///
+/// # Fields
+///
+/// * `evidence` - Every reachable instruction that requires an extension, sorted by address.
+#[derive(Debug, Default)]
+pub struct VariantReport {
+    pub evidence: Vec<Evidence>,
+}
+
+impl VariantReport {
+    /// Returns the extensions referenced by the ROM.
+    pub fn variants(&self) -> BTreeSet<Variant> {
+        self.evidence
+            .iter()
+            .map(|evidence| evidence.variant)
+            .collect()
+    }
+
+    /// Returns true if the ROM references instructions of the given extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `variant` - The extension to check.
+    pub fn uses(&self, variant: Variant) -> bool {
+        self.evidence
+            .iter()
+            .any(|evidence| evidence.variant == variant)
+    }
+
+    /// Returns true if the ROM only uses the original CHIP-8 instructions.
+    pub fn is_chip8(&self) -> bool {
+        self.evidence.is_empty()
+    }
+}
+
+/// Returns the extension that defines an instruction word, if any.
+///
+/// # Arguments
+///
+/// * `word` - The raw instruction word.
+///
+/// # Returns
+///
+/// * `Option<Variant>` - The extension, or `None` for CHIP-8 instructions and invalid words.
+///
+/// # Notes
+///
+/// * SUPER-CHIP: `00CN`, `00FB`, `00FC`, `00FD`, `00FE`, `00FF`, `DXY0`, `FX30`, `FX75` and `FX85`.
+/// * XO-CHIP: `00DN`, `5XY2`, `5XY3`, `F000`, `FN01`, `F002` and `FX3A`.
+/// * CHIP-8X: `EXF2` and `EXF5`. Its `02A0` and `BXYN` are valid CHIP-8 words too,
+///   so they are not evidence of the extension.
+/// * MEGA-CHIP: `0010` and `0011`. The rest of its instructions are only valid after `0011`.
+#[inline(always)]
+pub fn extension(word: u16) -> Option<Variant> {
+    let variant = match (word >> 12, word & 0xF, word & 0xFF) {
+        (0x0, _, 0xC0..=0xCF | 0xFB..=0xFF) if word & 0xF00 == 0 => Variant::SuperChip,
+        (0x0, _, 0xD0..=0xDF) if word & 0xF00 == 0 => Variant::XoChip,
+        (0x5, 0x2 | 0x3, _) => Variant::XoChip,
+        (0xD, 0x0, _) => Variant::SuperChip,
+        (0xE, _, 0xF2 | 0xF5) => Variant::Chip8X,
+        (0x0, _, 0x10 | 0x11) if word & 0xF00 == 0 => Variant::MegaChip,
+        (0xF, _, 0x30 | 0x75 | 0x85) => Variant::SuperChip,
+        (0xF, _, 0x01 | 0x3A) => Variant::XoChip,
+        (0xF, _, 0x00 | 0x02) if word & 0xF00 == 0 => Variant::XoChip,
+        _ => return None,
+    };
+    Some(variant)
+}
+
+/// Where the analysis assumes a ROM runs, see [`lint_with`].
+///
+/// # Fields
+///
+/// * `entry_point` - The address the ROM is loaded at and starts running from.
+/// * `stack_depth` - The number of nested calls the stack holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LintOptions {
+    pub entry_point: Address,
+    pub stack_depth: usize,
+}
+
+impl Default for LintOptions {
+    /// Loaded at `0x200` with a stack of 16 calls, as most interpreters run ROMs.
+    fn default() -> Self {
+        Self {
+            entry_point: Address::ENTRY_POINT,
+            stack_depth: STACK_SIZE,
+        }
+    }
+}
+
+impl LintOptions {
+    /// Returns the options of the interpreter a ROM targets, e.g. loaded at `0x600` for
+    /// ETI-660.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The interpreter.
+    /// * `stack_depth` - The number of nested calls the stack holds, see
+    ///   `EmulatorConfig::stack_depth`.
+    pub fn for_profile(profile: Profile, stack_depth: usize) -> Self {
+        Self {
+            entry_point: profile.entry_point(),
+            stack_depth,
+        }
+    }
+}
+
+/// The state of a path while walking the ROM.
+///
+/// # Fields
+///
+/// * `address` - The address of the next instruction.
+/// * `i_set` - Whether `I` has been initialized on the path (or may have been).
+/// * `depth` - The number of return addresses on the stack.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct PathState {
+    address: u16,
+    i_set: bool,
+    depth: usize,
+}
+
+/// Result of walking every path of a ROM from the entry point.
+///
+/// # Fields
+///
+/// * `reachable` - The addresses of the instructions that may be executed.
+/// * `findings` - The problems found while walking.
+struct Walk {
+    reachable: BTreeSet<Address>,
+    findings: LintReport,
+}
+
+/// Decodes the opcode located at `address` in a ROM loaded at `entry_point`.
+///
+/// # Returns
+///
+/// * `Option<Opcode>` - The opcode, or `None` if the address is outside the ROM.
+pub(crate) fn fetch(bytes: &[u8], entry_point: Address, address: u16) -> Option<Opcode> {
+    let offset = address.checked_sub(entry_point.inner())? as usize;
+    decode_iter(bytes.get(offset..)?, Address::new_long(address))
+        .next()
+        .map(|(_, opcode)| opcode)
+}
+
+/// Follows every path of the ROM from the entry point of `options`.
+///
+/// Calls are assumed to return, the code after a call is walked as if `I` was
+/// initialized by the subroutine. `JP V0, NNN` is followed as if `V0` was 0.
+/// Paths stop at `RET` and at invalid opcodes.
+fn walk(bytes: &[u8], options: &LintOptions) -> Walk {
+    let entry = options.entry_point.inner();
+    let end = entry as usize + bytes.len();
+    let mut report = LintReport::default();
+    let mut reachable = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    // Only report each problem once per address
+    let mut reported = BTreeSet::new();
+    let mut pending = vec![PathState {
+        address: entry,
+        i_set: false,
+        depth: 0,
+    }];
+
+    macro_rules! report {
+        ($severity:expr, $address:expr, $($arg:tt)*) => {
+            if reported.insert(($address, $severity)) {
+                report.push($severity, Some(Address::new_long($address)), format!($($arg)*));
+            }
+        };
+    }
+
+    while let Some(state) = pending.pop() {
+        if !visited.insert(state) {
+            continue;
+        }
+        let address = state.address;
+        let Some(opcode) = fetch(bytes, options.entry_point, address) else {
+            report!(
+                Severity::Error,
+                address,
+                "Execution runs past the end of the ROM at 0x{:03X}",
+                address
+            );
+            continue;
+        };
+        reachable.insert(Address::new_long(address));
+        if let Some(variant) = extension(opcode.encode()) {
+            report!(
+                Severity::Warning,
+                address,
+                "0x{:04X} is a {} instruction",
+                opcode.encode(),
+                variant
+            );
+        }
+
+        let next = PathState {
+            address: address.saturating_add(2),
+            ..state
+        };
+        // Target of a jump or call and the state of the path there
+        let mut branch = None;
+
+        match opcode {
+            Opcode::Invalid(word) => match extension(word) {
+                // Exits the interpreter
+                Some(_) if word == 0x00FD => {}
+                Some(_) => pending.push(next),
+                None => report!(
+                    Severity::Error,
+                    address,
+                    "Reachable word 0x{:04X} is not a valid instruction",
+                    word
+                ),
+            },
+            Opcode::Ret => {}
+            // The rest of the path uses the MEGA-CHIP instruction set
+            Opcode::MegaOn => report!(
+                Severity::Error,
+                address,
+                "{} enters MEGA-CHIP mode, which is not supported",
+                opcode
+            ),
+            Opcode::Jp { address } | Opcode::JpV0 { address } => branch = Some((address, state)),
+            Opcode::Call { address: target } => {
+                if state.depth >= options.stack_depth {
+                    report!(
+                        Severity::Error,
+                        address,
+                        "{} may overflow the stack, more than {} nested calls",
+                        opcode,
+                        options.stack_depth
+                    );
+                } else {
+                    let depth = state.depth + 1;
+                    branch = Some((target, PathState { depth, ..state }));
+                }
+                pending.push(PathState {
+                    i_set: true,
+                    ..next
+                });
+            }
+            Opcode::LdI { .. } => pending.push(PathState {
+                i_set: true,
+                ..next
+            }),
+            // Followed by a 16-bit address
+            Opcode::LdILong { .. } => pending.push(PathState {
+                address: address.saturating_add(4),
+                i_set: true,
+                ..state
+            }),
+            Opcode::LdIVx { .. } | Opcode::LdVxI { .. } if !state.i_set => {
+                report!(
+                    Severity::Warning,
+                    address,
+                    "{} uses I before it is initialized",
+                    opcode
+                );
+                pending.push(next);
+            }
+            _ if opcode.is_skip() => {
+                pending.push(next);
+                pending.push(PathState {
+                    address: address.saturating_add(4),
+                    ..state
+                });
+            }
+            _ => pending.push(next),
+        }
+
+        if let Some((target, state)) = branch {
+            let target = target.inner();
+            if target < entry || target as usize >= end {
+                report!(
+                    Severity::Error,
+                    address,
+                    "{} targets 0x{:03X}, outside of the ROM [0x{:03X}, 0x{:03X})",
+                    opcode,
+                    target,
+                    entry,
+                    end
+                );
+            } else {
+                pending.push(PathState {
+                    address: target,
+                    ..state
+                });
+            }
+        }
+    }
+
+    Walk {
+        reachable,
+        findings: report,
+    }
+}
+
+/// Returns the addresses of the instructions that may be executed when running the ROM.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM, loaded at the entry point.
+///
+/// # Returns
+///
+/// * `BTreeSet<Address>` - The reachable addresses, in order.
+pub fn reachable(bytes: &[u8]) -> BTreeSet<Address> {
+    walk(bytes, &LintOptions::default()).reachable
+}
+
+/// Scans the reachable code of a ROM for instructions that require an extension.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM, loaded at the entry point.
+///
+/// # Returns
+///
+/// * `VariantReport` - The extension instructions found.
+///
+/// # Notes
+///
+/// Only reachable instructions are considered, so data that happens to look like
+/// an extension instruction is not reported.
+pub fn detect_variant(bytes: &[u8]) -> VariantReport {
+    let evidence = reachable(bytes)
+        .into_iter()
+        .filter_map(|address| {
+            let word = fetch(bytes, Address::ENTRY_POINT, address.inner())?.encode();
+            Some(Evidence {
+                variant: extension(word)?,
+                address,
+                word,
+            })
+        })
+        .collect();
+    VariantReport { evidence }
+}
+
+/// Checks a ROM for common problems before running it.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM, loaded at the entry point.
+///
+/// # Returns
+///
+/// * `LintReport` - The problems found, sorted by address, see [`lint_with`].
+pub fn lint(bytes: &[u8]) -> LintReport {
+    lint_with(bytes, &LintOptions::default())
+}
+
+/// Checks a ROM for common problems before running it on a given interpreter.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM.
+/// * `options` - The entry point the ROM is loaded at and the depth of the stack.
+///
+/// # Returns
+///
+/// * `LintReport` - The problems found, sorted by address.
+///
+/// # Notes
+///
+/// The checks are best-effort, code reached through computed jumps or
+/// returning to a different address than the caller is not followed.
+pub fn lint_with(bytes: &[u8], options: &LintOptions) -> LintReport {
+    let mut report = LintReport::default();
+    // The sizes of MAX_XO_ROM_SIZE, MAX_ROM_SIZE and MAX_VIP_ROM_SIZE from the entry point
+    let entry = options.entry_point.inner() as usize;
+    let max_xo_size = XO_MEMORY_SIZE.saturating_sub(entry);
+    let max_size = MEMORY_SIZE.saturating_sub(entry);
+    let max_vip_size = 0xEA0usize.saturating_sub(entry);
+
+    if bytes.is_empty() {
+        report.push(Severity::Error, None, "The ROM is empty".to_string());
+        return report;
+    }
+    if !bytes.len().is_multiple_of(2) {
+        report.push(
+            Severity::Warning,
+            None,
+            format!("The ROM has an odd length of {} bytes", bytes.len()),
+        );
+    }
+    if bytes.len() > max_xo_size {
+        report.push(
+            Severity::Error,
+            None,
+            format!(
+                "The ROM is {} bytes long, only {} bytes fit in memory",
+                bytes.len(),
+                max_xo_size
+            ),
+        );
+    } else if bytes.len() > max_size {
+        report.push(
+            Severity::Warning,
+            None,
+            format!(
+                "The ROM is {} bytes long, it only fits in the 64KB memory of XO-CHIP after {} bytes",
+                bytes.len(),
+                max_size
+            ),
+        );
+    } else if bytes.len() > max_vip_size {
+        report.push(
+            Severity::Warning,
+            None,
+            format!(
+                "The ROM is {} bytes long, it overlaps the memory reserved by the original interpreter after {} bytes",
+                bytes.len(),
+                max_vip_size
+            ),
+        );
+    }
+
+    let invalid = decode_iter(bytes, options.entry_point)
+        .filter(|(_, opcode)| matches!(opcode, Opcode::Invalid(_)))
+        .count();
+    if invalid > 0 {
+        report.push(
+            Severity::Info,
+            None,
+            format!(
+                "{} words are not valid instructions (they may be data)",
+                invalid
+            ),
+        );
+    }
+
+    report
+        .findings
+        .extend(walk(bytes, options).findings.findings);
+    report
+        .findings
+        .sort_by_key(|finding| finding.address.map(|address| address.inner()));
+    report
+}
+
+/// How much of the trailing zeros of a ROM [`normalize_with`] removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Trim {
+    /// Keeps the reachable code and the data `LD I` points to in the zeros (default).
+    #[default]
+    KeepUsed,
+    /// Removes every trailing zero, the reachability analysis is not run.
+    Zeros,
+}
+
+/// A ROM without its trailing zero padding, see [`normalize`].
+///
+/// # Fields
+///
+/// * `bytes` - The canonical content of the ROM.
+/// * `removed` - The number of zeros removed from the end.
+/// * `hash` - The hashes of `bytes`, equal for dumps that only differ by their padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedRom {
+    pub bytes: Vec<u8>,
+    pub removed: usize,
+    pub hash: RomHash,
+}
+
+/// Removes the zero padding at the end of a ROM, keeping the code and data it uses.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM, loaded at the entry point.
+///
+/// # Returns
+///
+/// * `NormalizedRom` - The ROM without its padding, see [`normalize_with`].
+pub fn normalize(bytes: &[u8]) -> NormalizedRom {
+    normalize_with(bytes, Trim::default())
+}
+
+/// Removes the zero padding at the end of a ROM.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM, loaded at the entry point.
+/// * `trim` - How much of the zeros to remove.
+///
+/// # Returns
+///
+/// * `NormalizedRom` - The ROM without its padding.
+///
+/// # Notes
+///
+/// An even length is kept, a zero is left after an odd number of bytes. The memory after
+/// the ROM is cleared when it is loaded, so the zeros removed do not change how it runs.
+///
+/// With [`Trim::KeepUsed`], the reachable instructions are kept even if they are zeros, and
+/// so is the rest of the ROM when a reachable `LD I` points into the zeros, the length of
+/// its data is unknown.
+pub fn normalize_with(bytes: &[u8], trim: Trim) -> NormalizedRom {
+    let data_end = bytes
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |last| last + 1);
+    let mut len = match trim {
+        Trim::KeepUsed => data_end.max(used_len(bytes, data_end)).min(bytes.len()),
+        Trim::Zeros => data_end,
+    };
+    if len % 2 == 1 && len < bytes.len() {
+        len += 1;
+    }
+    NormalizedRom {
+        bytes: bytes[..len].to_vec(),
+        removed: bytes.len() - len,
+        hash: RomHash::new(&bytes[..len]),
+    }
+}
+
+/// Returns the length of a ROM its reachable code uses.
+///
+/// # Arguments
+///
+/// * `bytes` - The content of the ROM.
+/// * `data_end` - The length of the ROM without its trailing zeros.
+fn used_len(bytes: &[u8], data_end: usize) -> usize {
+    let entry = Address::ENTRY_POINT.inner() as usize;
+    let mut len = 0;
+    for address in reachable(bytes) {
+        let Some(opcode) = fetch(bytes, Address::ENTRY_POINT, address.inner()) else {
+            continue;
+        };
+        let offset = address.inner() as usize - entry;
+        len = len.max(offset + opcode.size() as usize);
+        if let Opcode::LdI { address } | Opcode::LdILong { address } = opcode {
+            let target = (address.inner() as usize).wrapping_sub(entry);
+            if (data_end..bytes.len()).contains(&target) {
+                len = bytes.len();
+            }
+        }
+    }
+    len
+}
+
+/// Parses an Intel HEX image, as used to flash microcontrollers.
+///
+/// # Arguments
+///
+/// * `text` - The records, one per line. Empty lines are ignored.
+///
+/// # Returns
+///
+/// * `Result<(Vec<u8>, u16), EmulatorError>` - The bytes from the lowest to the highest address
+///   written, gaps filled with zeros, and the lowest address (the origin). An image without
+///   data is empty with the entry point as origin. `InvalidIntelHex` with the line number if
+///   a record is malformed, has a wrong checksum or the end of file record is missing.
+///
+/// # Notes
+///
+/// Extended segment (`02`) and linear (`04`) address records are supported as long as the
+/// addresses stay within 64KB. Start address records (`03` and `05`) are ignored, the
+/// program starts at the entry point of the profile.
+pub fn from_ihex(text: &str) -> Result<(Vec<u8>, u16), EmulatorError> {
+    let mut chunks = Vec::new();
+    let mut base = 0usize;
+    let mut end_of_file = None;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = |reason: &str| EmulatorError::InvalidIntelHex {
+            line: index + 1,
+            reason: reason.to_string(),
+        };
+        if end_of_file.is_some() {
+            return Err(invalid("Record after the end of file record"));
+        }
+        let record = line
+            .strip_prefix(':')
+            .ok_or_else(|| invalid("The record does not start with `:`"))?;
+        let byte = |i: usize| u8::from_str_radix(record.get(i..i + 2)?, 16).ok();
+        let bytes = (0..record.len())
+            .step_by(2)
+            .map(byte)
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| invalid("The record is not made of hex bytes"))?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(invalid("The byte count does not match the record"));
+        }
+        if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+            return Err(invalid("Wrong checksum"));
+        }
+        let address = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+        let data = &bytes[4..bytes.len() - 1];
+        match (bytes[3], data) {
+            (0x00, _) => {
+                let start = base + address;
+                if start + data.len() > 0x10000 {
+                    return Err(invalid("The data is beyond 64KB"));
+                }
+                chunks.push((start, data.to_vec()));
+            }
+            (0x01, []) => end_of_file = Some(index),
+            (0x02, &[high, low]) => base = (u16::from_be_bytes([high, low]) as usize) << 4,
+            (0x04, &[high, low]) => base = (u16::from_be_bytes([high, low]) as usize) << 16,
+            (0x03, [_, _, _, _]) | (0x05, [_, _, _, _]) => {}
+            (0x00..=0x05, _) => return Err(invalid("Wrong data length for the record type")),
+            _ => return Err(invalid("Unknown record type")),
+        }
+    }
+    if end_of_file.is_none() {
+        return Err(EmulatorError::InvalidIntelHex {
+            line: text.lines().count() + 1,
+            reason: "Missing end of file record".to_string(),
+        });
+    }
+
+    let Some(origin) = chunks.iter().map(|&(start, _)| start).min() else {
+        return Ok((Vec::new(), Address::ENTRY_POINT.inner()));
+    };
+    let end = chunks.iter().map(|(start, data)| start + data.len());
+    let mut image = vec![0; end.max().unwrap_or(origin) - origin];
+    for (start, data) in chunks {
+        image[start - origin..start - origin + data.len()].copy_from_slice(&data);
+    }
+    Ok((image, origin as u16))
+}
+
+/// Parses a program listed as hex bytes, as printed in tutorials, e.g. `A2 1E 60 00`.
+///
+/// # Arguments
+///
+/// * `text` - The listing. Tokens are separated by whitespace, may have a `0x` prefix and
+///   hold several bytes (`A21E`). Comments start with `#` or `;` and end with the line.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, EmulatorError>` - The bytes, empty if the text has no tokens.
+///   `InvalidHexText` with the line and column of the first token that is not hex or has
+///   an odd number of digits.
+pub fn from_hex_text(text: &str) -> Result<Vec<u8>, EmulatorError> {
+    let mut bytes = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let code = line.split(['#', ';']).next().unwrap_or_default();
+        let mut rest = code;
+        while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+            let token = rest[start..].split_whitespace().next().unwrap_or_default();
+            let invalid = |reason: String| EmulatorError::InvalidHexText {
+                line: index + 1,
+                column: code[..code.len() - rest.len() + start].chars().count() + 1,
+                reason,
+            };
+            let digits = token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid(format!("`{token}` is not a hex number")));
+            }
+            if !digits.len().is_multiple_of(2) {
+                return Err(invalid(format!("`{token}` has an odd number of digits")));
+            }
+            let pairs = (0..digits.len()).step_by(2);
+            bytes.extend(pairs.map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap()));
+            rest = &rest[start + token.len()..];
+        }
+    }
+    Ok(bytes)
+}
+
+/// Formats an Intel HEX record, see [`from_ihex`].
+///
+/// # Arguments
+///
+/// * `kind` - The record type, `00` for data and `01` for the end of file.
+/// * `address` - The address of the data.
+/// * `data` - The data, up to 255 bytes.
+pub(crate) fn ihex_record(kind: u8, address: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(address.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend(data);
+    let sum = bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    bytes.push(sum.wrapping_neg());
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+    format!(":{hex}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(report: &LintReport, severity: Severity) -> Vec<(Option<u16>, &str)> {
+        report
+            .with_severity(severity)
+            .map(|finding| {
+                (
+                    finding.address.map(|address| address.inner()),
+                    finding.message.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_rom() {
+        let rom = [
+            0xA2, 0x06, // LD I, 0x206
+            0xF0, 0x65, // LD V0, [I]
+            0x12, 0x04, // JP 0x204
+        ];
+        let report = lint(&rom);
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+        assert_eq!(reachable(&rom).len(), 3);
+    }
+
+    #[test]
+    fn test_empty_and_odd_rom() {
+        assert!(lint(&[]).has_errors());
+        let report = lint(&[0x12, 0x00, 0x00]);
+        assert_eq!(
+            messages(&report, Severity::Warning),
+            [(None, "The ROM has an odd length of 3 bytes")]
+        );
+    }
+
+    #[test]
+    fn test_rom_size() {
+        let mut rom = [0x12, 0x00].repeat(MAX_VIP_ROM_SIZE / 2 + 1);
+        assert!(!lint(&rom).has_errors());
+        assert_eq!(lint(&rom).with_severity(Severity::Warning).count(), 1);
+        rom.extend([0x12, 0x00].repeat(MAX_ROM_SIZE / 2));
+        assert!(!lint(&rom).has_errors());
+        assert_eq!(lint(&rom).with_severity(Severity::Warning).count(), 1);
+        rom.resize(MAX_XO_ROM_SIZE + 2, 0);
+        assert!(lint(&rom).has_errors());
+    }
+
+    #[test]
+    fn test_large_rom() {
+        // XO-CHIP code running past 0xFFF: CLS up to 0xFFE, then JP 0x200 at 0x1000
+        let mut rom = [0x00, 0xE0].repeat(MAX_ROM_SIZE / 2);
+        rom.extend([0x12, 0x00]);
+        let report = lint(&rom);
+        assert!(!report.has_errors(), "{:?}", report.findings);
+        let reachable = reachable(&rom);
+        assert_eq!(reachable.len(), MAX_ROM_SIZE / 2 + 1);
+        assert_eq!(reachable.last(), Some(&Address::new_long(0x1000)));
+    }
+
+    #[test]
+    fn test_branch_targets() {
+        let rom = [
+            0x30, 0x00, // SE V0, 0
+            0x11, 0x00, // JP 0x100
+            0x22, 0x08, // CALL 0x208
+            0x12, 0x04, // JP 0x204
+        ];
+        let report = lint(&rom);
+        assert_eq!(
+            messages(&report, Severity::Error),
+            [
+                (
+                    Some(0x202),
+                    "JP #100 targets 0x100, outside of the ROM [0x200, 0x208)"
+                ),
+                (
+                    Some(0x204),
+                    "CALL #208 targets 0x208, outside of the ROM [0x200, 0x208)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_words() {
+        let rom = [
+            0x12, 0x04, // JP 0x204
+            0x51, 0x24, // Data
+            0x51, 0x24, // Invalid
+        ];
+        let report = lint(&rom);
+        assert_eq!(
+            messages(&report, Severity::Info),
+            [(
+                None,
+                "2 words are not valid instructions (they may be data)"
+            )]
+        );
+        assert_eq!(
+            messages(&report, Severity::Error),
+            [(
+                Some(0x204),
+                "Reachable word 0x5124 is not a valid instruction"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_uninitialized_i() {
+        let rom = [
+            0x30, 0x00, // SE V0, 0
+            0xA2, 0x00, // LD I, 0x200
+            0xF0, 0x55, // LD [I], V0
+            0x12, 0x06, // JP 0x206
+        ];
+        let report = lint(&rom);
+        assert_eq!(
+            messages(&report, Severity::Warning),
+            [(Some(0x204), "LD [I], V0 uses I before it is initialized")]
+        );
+    }
+
+    #[test]
+    fn test_extension() {
+        for word in [0x00C4, 0x00FB, 0x00FF, 0xD120, 0xF130, 0xF575, 0xF585] {
+            assert_eq!(extension(word), Some(Variant::SuperChip), "{:04X}", word);
+        }
+        for word in [0x00D4, 0x5122, 0x5123, 0xF000, 0xF201, 0xF002, 0xF13A] {
+            assert_eq!(extension(word), Some(Variant::XoChip), "{:04X}", word);
+        }
+        for word in [0xE1F2, 0xE1F5] {
+            assert_eq!(extension(word), Some(Variant::Chip8X), "{:04X}", word);
+        }
+        for word in [0x0010, 0x0011] {
+            assert_eq!(extension(word), Some(Variant::MegaChip), "{:04X}", word);
+        }
+        for word in [
+            0x00E0, 0x01FF, 0xD121, 0xF100, 0xF065, 0x5120, 0x02A0, 0xB124,
+        ] {
+            assert_eq!(extension(word), None, "{:04X}", word);
+        }
+    }
+
+    #[test]
+    fn test_detect_variant() {
+        let chip8 = [0x00, 0xE0, 0x12, 0x00];
+        assert!(detect_variant(&chip8).is_chip8());
+
+        let rom = [
+            0x00, 0xFF, // HIGH
+            0xF0, 0x00, // LD I, LONG 0x0000
+            0x00, 0x00, //
+            0xF1, 0x30, // LD HF, V1
+            0x12, 0x00, // JP 0x200
+            0xF1, 0x75, // Data
+        ];
+        let report = detect_variant(&rom);
+        assert_eq!(
+            report
+                .evidence
+                .iter()
+                .map(|evidence| (evidence.variant, evidence.address.inner(), evidence.word))
+                .collect::<Vec<_>>(),
+            [
+                (Variant::SuperChip, 0x200, 0x00FF),
+                (Variant::XoChip, 0x202, 0xF000),
+                (Variant::SuperChip, 0x206, 0xF130),
+            ]
+        );
+        assert!(report.uses(Variant::SuperChip) && report.uses(Variant::XoChip));
+        assert_eq!(report.variants().len(), 2);
+        // The linter warns about them instead of rejecting them
+        assert!(!lint(&rom).has_errors());
+    }
+
+    #[test]
+    fn test_detect_megachip() {
+        let rom = [
+            0x00, 0x11, // MEGAON
+            0x01, 0x00, 0x00, 0x00, // LDHI I, #0
+        ];
+        let report = detect_variant(&rom);
+        assert!(report.uses(Variant::MegaChip));
+        assert_eq!(
+            messages(&lint(&rom), Severity::Error),
+            [(
+                Some(0x200),
+                "MEGAON enters MEGA-CHIP mode, which is not supported"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_stack_depth() {
+        let rom = [
+            0x22, 0x00, // CALL 0x200
+        ];
+        let report = lint(&rom);
+        assert_eq!(
+            messages(&report, Severity::Error),
+            [
+                (
+                    Some(0x200),
+                    "CALL #200 may overflow the stack, more than 16 nested calls"
+                ),
+                (
+                    Some(0x202),
+                    "Execution runs past the end of the ROM at 0x202"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_options() {
+        let rom = [
+            0x26, 0x04, // CALL 0x604
+            0x12, 0x00, // JP 0x200
+            0x26, 0x04, // CALL 0x604
+        ];
+        let options = LintOptions::for_profile(Profile::Eti660, 12);
+        assert_eq!(options.entry_point, Address::new(0x600));
+        let report = lint_with(&rom, &options);
+        assert_eq!(
+            messages(&report, Severity::Error),
+            [
+                (
+                    Some(0x602),
+                    "JP #200 targets 0x200, outside of the ROM [0x600, 0x606)"
+                ),
+                (
+                    Some(0x604),
+                    "CALL #604 may overflow the stack, more than 12 nested calls"
+                ),
+                (
+                    Some(0x606),
+                    "Execution runs past the end of the ROM at 0x606"
+                ),
+            ]
+        );
+        // Loaded at 0x200, the calls leave the ROM
+        assert_eq!(
+            lint(&rom).findings,
+            lint_with(&rom, &LintOptions::default()).findings
+        );
+        assert!(messages(&lint(&rom), Severity::Error)
+            .iter()
+            .any(|(_, message)| message.contains("outside of the ROM [0x200, 0x206)")));
+    }
+
+    #[test]
+    fn test_ihex_round_trip() {
+        use crate::memory::Memory;
+
+        let data: Vec<u8> = (0..40).collect();
+        let mut memory = Memory::new();
+        assert!(memory.load_at(Address::new(0x300), &data).is_ok());
+        let text = memory.to_ihex(0x300..0x328).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], ":10030000000102030405060708090A0B0C0D0E0F75");
+        assert_eq!(lines[3], ":00000001FF");
+        assert_eq!(from_ihex(&text).unwrap(), (data, 0x300));
+
+        let text = memory.to_ihex(0x200..0x200).unwrap();
+        assert_eq!(from_ihex(&text).unwrap(), (Vec::new(), 0x200));
+        assert!(memory.to_ihex(0xFF0..0x1010).is_err());
+    }
+
+    #[test]
+    fn test_ihex_records() {
+        // Extended addresses, a gap, a start address and records out of order
+        let text = "
+            :020000020F00ED
+            :020FFE00CCDD48
+            :020000040000FA
+            :0400000500000200F5
+            :020200001234B6
+            :00000001FF
+        ";
+        let (bytes, origin) = from_ihex(text).unwrap();
+        assert_eq!(origin, 0x200);
+        assert_eq!(bytes.len(), 0xFE00);
+        assert_eq!(bytes[..2], [0x12, 0x34]);
+        assert_eq!(bytes[0xFDFE..], [0xCC, 0xDD]);
+    }
+
+    #[test]
+    fn test_ihex_errors() {
+        let cases = [
+            (":0100000012EE\n:00000001FF", 1, "Wrong checksum"),
+            (
+                "\n0100000012ED\n:00000001FF",
+                2,
+                "The record does not start with `:`",
+            ),
+            (
+                ":0200000012ED\n:00000001FF",
+                1,
+                "The byte count does not match the record",
+            ),
+            (
+                ":01000000XYED\n:00000001FF",
+                1,
+                "The record is not made of hex bytes",
+            ),
+            (
+                ":00000001FF\n:00000001FF",
+                2,
+                "Record after the end of file record",
+            ),
+            (":0100000012ED", 2, "Missing end of file record"),
+            (
+                ":0200000400FFFB\n:0100000012ED\n:00000001FF",
+                2,
+                "The data is beyond 64KB",
+            ),
+            (":00000006FA\n:00000001FF", 1, "Unknown record type"),
+            (
+                ":0100000212EB\n:00000001FF",
+                1,
+                "Wrong data length for the record type",
+            ),
+        ];
+        for (text, expected_line, expected_reason) in cases {
+            let error = from_ihex(text).unwrap_err();
+            let EmulatorError::InvalidIntelHex { line, reason } = error else {
+                panic!("{error}");
+            };
+            assert_eq!(
+                (line, reason.as_str()),
+                (expected_line, expected_reason),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hex_text() {
+        let text = "
+            # Draws a digit
+            A2 1E 60 00 ; LD I, 0x21E and LD V0, 0
+            0xD0 0x15\t6001
+        ";
+        let expected = [0xA2, 0x1E, 0x60, 0x00, 0xD0, 0x15, 0x60, 0x01];
+        assert_eq!(from_hex_text(text).unwrap(), expected);
+        assert!(from_hex_text("").unwrap().is_empty());
+        assert!(from_hex_text("  ; Nothing yet\n\n").unwrap().is_empty());
+
+        let cases = [
+            ("A2 1E 6", 1, 7, "`6` has an odd number of digits"),
+            ("A2\n  60 0x1", 2, 6, "`0x1` has an odd number of digits"),
+            ("A2 1E\nLD V0, 0", 2, 1, "`LD` is not a hex number"),
+            ("# é\n é 0x", 2, 2, "`é` is not a hex number"),
+            ("0x", 1, 1, "`0x` is not a hex number"),
+        ];
+        for (text, expected_line, expected_column, expected_reason) in cases {
+            let error = from_hex_text(text).unwrap_err();
+            let EmulatorError::InvalidHexText {
+                line,
+                column,
+                reason,
+            } = error
+            else {
+                panic!("{error}");
+            };
+            let expected = (expected_line, expected_column, expected_reason);
+            assert_eq!((line, column, reason.as_str()), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        // LD V0, 1 and JP 0x202, then padding
+        let padded = [0x60, 0x01, 0x12, 0x02, 0xAB, 0x00, 0x00, 0x00];
+        let normalized = normalize(&padded);
+        assert_eq!(normalized.bytes, [0x60, 0x01, 0x12, 0x02, 0xAB, 0x00]);
+        assert_eq!(normalized.removed, 2);
+        assert_eq!(normalized.hash, RomHash::new(&padded[..6]));
+        // Dumps with more padding normalize to the same ROM
+        let mut longer = padded.to_vec();
+        longer.resize(0x100, 0);
+        let normalized_longer = normalize(&longer);
+        assert_eq!(normalized_longer.bytes, normalized.bytes);
+        assert_eq!(normalized_longer.hash, normalized.hash);
+        // Nothing to remove
+        let unpadded = normalize(&normalized.bytes);
+        assert_eq!((unpadded.bytes.len(), unpadded.removed), (6, 0));
+        assert_eq!(normalize(&padded[..5]).bytes, &padded[..5]);
+        assert!(normalize(&[]).bytes.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_keeps_used_zeros() {
+        // LD I, 0x206, DRW V0, V0, 4 and JP 0x204, then a blank sprite
+        let sprite = [0xA2, 0x06, 0xD0, 0x04, 0x12, 0x04, 0x00, 0x00, 0x00, 0x00];
+        let normalized = normalize(&sprite);
+        assert_eq!((normalized.bytes.len(), normalized.removed), (10, 0));
+        let trimmed = normalize_with(&sprite, Trim::Zeros);
+        assert_eq!((trimmed.bytes.len(), trimmed.removed), (6, 4));
+
+        // LD I before the zeros only keeps what the code uses
+        let data = [0xA2, 0x04, 0x12, 0x02, 0xFF, 0x00, 0x00, 0x00];
+        assert_eq!(normalize(&data).bytes, [0xA2, 0x04, 0x12, 0x02, 0xFF, 0x00]);
+
+        // Reachable zero words are kept, the program runs into them
+        let code = [0x60, 0x01, 0x00, 0x00, 0x12, 0x02, 0x00, 0x00];
+        assert_eq!(normalize(&code).bytes.len(), 6);
+        let code = [0x60, 0x01, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(normalize(&code).bytes.len(), 6);
+        assert_eq!(normalize_with(&code, Trim::Zeros).bytes.len(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, fmt::Display, io::Read, sync::Arc};
+
+use crate::{error::EmulatorError, memory::Address};
+
+/// A position in a source file.
+///
+/// # Fields
+///
+/// * `file` - The path of the source file, as written in the line map.
+/// * `line` - The line number, starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: Arc<str>,
+    pub line: usize,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Maps the address of each instruction to the source line that produced it.
+///
+/// # Notes
+///
+/// The line map format has one entry per line, `0xNNN file:line`, e.g. `0x202 pong.8o:42`.
+/// Empty lines and lines starting with `#` are ignored. File names may contain spaces,
+/// the line number is everything after the last `:`.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    locations: BTreeMap<Address, SourceLocation>,
+}
+
+impl SourceMap {
+    /// Creates an empty line map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a line map.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The content of the line map file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SourceMap, EmulatorError>` - The line map, or an error with the offending line number.
+    pub fn parse(text: &str) -> Result<Self, EmulatorError> {
+        let mut map = Self::new();
+        // Share the file names between entries
+        let mut files: Vec<Arc<str>> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || EmulatorError::InvalidSourceMap {
+                line: index + 1,
+                reason: format!("`{line}` is not a `0xNNN file:line` entry"),
+            };
+
+            let (address, location) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let (file, number) = location.trim().rsplit_once(':').ok_or_else(invalid)?;
+            let address = address
+                .strip_prefix("0x")
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                .and_then(|address| Address::try_new(address).ok())
+                .ok_or_else(invalid)?;
+            let number = number.parse().map_err(|_| invalid())?;
+            if file.is_empty() {
+                return Err(invalid());
+            }
+
+            let file = match files.iter().find(|known| known.as_ref() == file) {
+                Some(known) => known.clone(),
+                None => {
+                    files.push(Arc::from(file));
+                    files[files.len() - 1].clone()
+                }
+            };
+            map.locations
+                .insert(address, SourceLocation { file, line: number });
+        }
+        Ok(map)
+    }
+
+    /// Loads a line map from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the line map from.
+    pub fn load<R: Read>(mut reader: R) -> Result<Self, EmulatorError> {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .map_err(EmulatorError::LoadError)?;
+        Self::parse(&text)
+    }
+
+    /// Returns the source location of the instruction at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the instruction.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&SourceLocation>` - The location, or `None` if the address has no mapping.
+    pub fn location(&self, address: Address) -> Option<&SourceLocation> {
+        self.locations.get(&address)
+    }
+
+    /// Formats an address with its source location when it has one.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to format.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - `0x0202 (pong.8o:42)`, or `0x0202` when the address has no mapping.
+    pub fn format(&self, address: Address) -> String {
+        match self.location(address) {
+            Some(location) => format!("0x{:04X} ({})", address.inner(), location),
+            None => format!("0x{:04X}", address.inner()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let map = SourceMap::parse(
+            "# pong\n\
+             0x200 pong.8o:3\n\
+             0x202 pong.8o:4\n\
+             0x300 my lib.8o:10\n",
+        )
+        .unwrap();
+        let location = map.location(Address::new(0x202)).unwrap();
+        assert_eq!((location.file.as_ref(), location.line), ("pong.8o", 4));
+        assert_eq!(location.to_string(), "pong.8o:4");
+        assert_eq!(
+            map.location(Address::new(0x300)).unwrap().to_string(),
+            "my lib.8o:10"
+        );
+        assert_eq!(map.location(Address::new(0x204)), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let map = SourceMap::parse("0x200 pong.8o:3").unwrap();
+        assert_eq!(map.format(Address::new(0x200)), "0x0200 (pong.8o:3)");
+        assert_eq!(map.format(Address::new(0x202)), "0x0202");
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| match SourceMap::parse(text) {
+            Err(EmulatorError::InvalidSourceMap { line, .. }) => line,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(error("0x200 pong.8o"), 1);
+        assert_eq!(error("0x200 pong.8o:3\n0x1000 pong.8o:4"), 2);
+        assert_eq!(error("\n0x200 pong.8o:x"), 2);
+        assert_eq!(error("0x200 :3"), 1);
+    }
+}
@@ -1,9 +1,11 @@
+use crate::prelude::*;
+
 /// The `Stack` struct represents a stack data structure for storing `Address` values that are the return point on call instructions.
 ///
 /// # Fields
 ///
-/// * `array` - The array that stores the `Address` values.
-/// * `top` - The top of the stack.
+/// * `items` - The items on the stack, the top is the last one.
+/// * `depth` - The maximum number of items.
 ///
 /// # Type Parameters
 ///
@@ -12,24 +14,39 @@
 /// # Notes
 ///
 /// It is generic to facilite testing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stack<T: Copy + Default> {
-    array: [T; crate::constants::STACK_SIZE],
-    top: usize,
+    items: Vec<T>,
+    depth: usize,
 }
 
 impl<T> Stack<T>
 where
     T: Copy + Default,
 {
-    /// Creates a new `Stack` with all elements initialized to 0 and the top of the stack pointing to the first position.
+    /// Creates a new empty `Stack` of the default depth, `STACK_SIZE`.
     ///
     /// # Returns
     ///
     /// * `Stack<T>` - The new stack.
     pub fn new() -> Self {
+        Self::with_depth(crate::constants::STACK_SIZE)
+    }
+
+    /// Creates a new empty `Stack` of a given depth.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The maximum number of items, at least 1. `usize::MAX` is unlimited.
+    ///
+    /// # Returns
+    ///
+    /// * `Stack<T>` - The new stack.
+    pub fn with_depth(depth: usize) -> Self {
         Self {
-            array: [T::default(); crate::constants::STACK_SIZE],
-            top: 0,
+            items: Vec::new(),
+            depth: depth.max(1),
         }
     }
 
@@ -41,13 +58,14 @@ where
     ///
     /// # Returns
     ///
-    /// * `Result<(), RuntimeError>` - Returns Ok if the item was pushed onto the stack, otherwise returns an error.
-    pub fn push(&mut self, item: T) -> Result<(), super::error::EmulatorError> {
-        if self.top >= crate::constants::STACK_SIZE {
-            Err(super::error::EmulatorError::StackOverFlow)
+    /// * `Result<(), T>` - Returns Ok if the item was pushed onto the stack, otherwise returns
+    ///   the item back if the stack is full.
+    #[inline]
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        if self.items.len() >= self.depth {
+            Err(item)
         } else {
-            self.array[self.top] = item;
-            self.top += 1;
+            self.items.push(item);
             Ok(())
         }
     }
@@ -56,14 +74,10 @@ where
     ///
     /// # Returns
     ///
-    /// * `Result<T, RuntimeError>` - Returns Ok if the item was popped from the stack, otherwise returns an error.
-    pub fn pop(&mut self) -> Result<T, super::error::EmulatorError> {
-        if self.top == 0 {
-            Err(super::error::EmulatorError::StackUnderFlow)
-        } else {
-            self.top -= 1;
-            Ok(self.array[self.top])
-        }
+    /// * `Option<T>` - The item on the top of the stack, or `None` if it is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        self.items.pop()
     }
 
     /// Returns the number of items on the stack.
@@ -72,12 +86,33 @@ where
     ///
     /// * `usize` - The number of items on the stack.
     pub fn len(&self) -> usize {
-        self.top
+        self.items.len()
+    }
+
+    /// Returns true if the stack is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the maximum number of items on the stack.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the items on the stack, from the bottom to the top.
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
     }
 
-    /// Clears the stack by setting the top of the stack to 0.
+    /// Returns an iterator over the items on the stack, from the bottom to the top, e.g. the
+    /// return addresses from the first call to the last for a backtrace.
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    /// Clears the stack.
     pub fn clear(&mut self) {
-        self.top = 0;
+        self.items.clear();
     }
 }
 
@@ -97,16 +132,16 @@ mod tests {
     #[test]
     fn test_new() {
         let stack: Stack<u8> = Stack::new();
-        assert!(stack.top == 0);
-        assert!(stack.array.iter().all(|&x| x == 0));
+        assert!(stack.is_empty());
+        assert_eq!(stack.depth(), crate::constants::STACK_SIZE);
     }
 
     #[test]
     fn test_push() {
         let mut stack = Stack::new();
         assert!(matches!(stack.push(1), Ok(())));
-        assert!(stack.top == 1);
-        assert!(stack.array[0] == 1);
+        assert!(stack.len() == 1);
+        assert!(stack.as_slice() == [1]);
     }
 
     #[test]
@@ -115,27 +150,61 @@ mod tests {
         for i in 0..crate::constants::STACK_SIZE {
             assert!(matches!(stack.push(i), Ok(())));
         }
-        assert!(matches!(
-            stack.push(100),
-            Err(super::super::error::EmulatorError::StackOverFlow)
-        ));
+        assert!(matches!(stack.push(100), Err(100)));
+        assert_eq!(stack.len(), crate::constants::STACK_SIZE);
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut stack = Stack::with_depth(12);
+        for i in 0..12 {
+            assert!(matches!(stack.push(i), Ok(())));
+        }
+        assert!(matches!(stack.push(12), Err(12)));
+        assert_eq!(Stack::<u8>::with_depth(0).depth(), 1);
     }
 
     #[test]
     fn test_pop() {
         let mut stack = Stack::new();
         stack.push(1).unwrap();
-        assert!(matches!(stack.pop(), Ok(1)));
-        assert!(stack.top == 0);
+        assert!(matches!(stack.pop(), Some(1)));
+        assert!(stack.is_empty());
     }
 
     #[test]
     fn test_pop_underflow() {
         let mut stack: Stack<()> = Stack::new();
-        assert!(matches!(
-            stack.pop(),
-            Err(super::super::error::EmulatorError::StackUnderFlow)
-        ));
+        assert!(stack.pop().is_none());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut stack = Stack::new();
+        for i in 1..=3 {
+            stack.push(i).unwrap();
+        }
+        assert!(stack.iter().copied().eq([1, 2, 3]));
+        assert_eq!(stack.as_slice(), [1, 2, 3]);
+        let copy = stack.clone();
+        assert_eq!(stack.pop(), Some(3));
+        assert_ne!(stack, copy);
+        assert!(stack.iter().copied().eq([1, 2]));
+        assert_eq!(format!("{copy:?}"), "Stack { items: [1, 2, 3], depth: 16 }");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use crate::memory::Address;
+
+        let mut stack = Stack::with_depth(12);
+        stack.push(Address::new(0x202)).unwrap();
+        stack.push(Address::new(0x31A)).unwrap();
+        let json = serde_json::to_string(&stack).unwrap();
+        assert_eq!(json, r#"{"items":[514,794],"depth":12}"#);
+        let restored: Stack<Address> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, stack);
     }
 
     #[test]
@@ -145,6 +214,6 @@ mod tests {
             stack.push(i).unwrap();
         }
         stack.clear();
-        assert!(stack.top == 0);
+        assert!(stack.is_empty());
     }
 }
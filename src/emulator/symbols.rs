@@ -0,0 +1,223 @@
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use crate::{error::EmulatorError, memory::Address, prelude::*};
+
+/// Named addresses loaded from a symbol file.
+///
+/// # Fields
+///
+/// * `by_address` - The symbol names, sorted by address.
+/// * `by_name` - The symbol addresses, indexed by name.
+///
+/// # Notes
+///
+/// Two formats are supported, one symbol per line:
+///
+/// * `name = 0xNNN`
+/// * `0xNNN name`, the address-first flavor used by Octo style `.sym` files.
+///
+/// Empty lines and lines starting with `#` or `;` are ignored.
+#[derive(Debug, Default, Clone)]
+pub struct Symbols {
+    by_address: BTreeMap<Address, String>,
+    by_name: BTreeMap<String, Address>,
+}
+
+impl Symbols {
+    /// Creates an empty symbol table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a symbol file.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The content of the symbol file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Symbols, EmulatorError>` - The symbols, or an error with the offending line number.
+    pub fn parse(text: &str) -> Result<Self, EmulatorError> {
+        let mut symbols = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let invalid = |reason: String| EmulatorError::InvalidSymbol {
+                line: line_number,
+                reason,
+            };
+
+            let (name, address) = match line.split_once('=') {
+                Some((name, address)) => (name.trim(), address.trim()),
+                None => match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    &[address, name] => (name, address),
+                    _ => return Err(invalid(format!("`{line}` is not a symbol definition"))),
+                },
+            };
+            let address = parse_address(address)
+                .ok_or_else(|| invalid(format!("`{address}` is not a valid address")))?;
+            symbols.insert(name, address).map_err(invalid)?;
+        }
+        Ok(symbols)
+    }
+
+    /// Loads a symbol file from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the symbol file from.
+    #[cfg(feature = "std")]
+    pub fn load<R: Read>(mut reader: R) -> Result<Self, EmulatorError> {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .map_err(EmulatorError::LoadError)?;
+        Self::parse(&text)
+    }
+
+    /// Adds a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the symbol.
+    /// * `address` - The address the symbol names.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - An error if the name is already defined or another symbol starts at the same address.
+    fn insert(&mut self, name: &str, address: Address) -> Result<(), String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("`{name}` is not a valid symbol name"));
+        }
+        if self.by_name.contains_key(name) {
+            return Err(format!("`{name}` is already defined"));
+        }
+        if let Some(other) = self.by_address.get(&address) {
+            return Err(format!(
+                "`{name}` overlaps `{other}` at 0x{:03X}",
+                address.inner()
+            ));
+        }
+        self.by_address.insert(address, name.to_string());
+        self.by_name.insert(name.to_string(), address);
+        Ok(())
+    }
+
+    /// Returns the address of a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the symbol.
+    pub fn address_of(&self, name: &str) -> Option<Address> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Returns the symbol defined exactly at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to look up.
+    pub fn name_at(&self, address: Address) -> Option<&str> {
+        self.by_address.get(&address).map(String::as_str)
+    }
+
+    /// Returns the nearest symbol at or before an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to look up.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(&str, u16)>` - The symbol name and the offset of `address` from it.
+    pub fn lookup(&self, address: Address) -> Option<(&str, u16)> {
+        self.by_address
+            .range(..=address)
+            .next_back()
+            .map(|(start, name)| (name.as_str(), address.inner() - start.inner()))
+    }
+
+    /// Formats an address relative to the nearest preceding symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to format.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - `name`, `name+0x4`, or `0x0204` when no symbol covers the address.
+    pub fn format(&self, address: Address) -> String {
+        match self.lookup(address) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{name}+0x{offset:X}"),
+            None => format!("0x{:04X}", address.inner()),
+        }
+    }
+
+    /// Returns the number of symbols.
+    pub fn len(&self) -> usize {
+        self.by_address.len()
+    }
+
+    /// Returns true if there are no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.by_address.is_empty()
+    }
+}
+
+/// Parses an address written as `0xNNN`, `#NNN` or in decimal.
+fn parse_address(text: &str) -> Option<Address> {
+    let value = match text.strip_prefix("0x").or(text.strip_prefix('#')) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+        None => text.parse().ok()?,
+    };
+    Address::try_new(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_both_flavors() {
+        let symbols = Symbols::parse(
+            "# game symbols\n\
+             main = 0x200\n\
+             \n\
+             0x21A game_loop\n\
+             sprite = #300\n",
+        )
+        .unwrap();
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.address_of("game_loop"), Some(Address::new(0x21A)));
+        assert_eq!(symbols.name_at(Address::new(0x300)), Some("sprite"));
+        assert_eq!(symbols.address_of("missing"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let symbols = Symbols::parse("main = 0x200\ngame_loop = 0x210").unwrap();
+        assert_eq!(symbols.format(Address::new(0x200)), "main");
+        assert_eq!(symbols.format(Address::new(0x20E)), "main+0xE");
+        assert_eq!(symbols.format(Address::new(0x214)), "game_loop+0x4");
+        assert_eq!(symbols.format(Address::new(0x1FE)), "0x01FE");
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| match Symbols::parse(text) {
+            Err(EmulatorError::InvalidSymbol { line, .. }) => line,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(error("main = 0x200\nmain = 0x202"), 2);
+        assert_eq!(error("main = 0x200\n\nstart = 0x200"), 3);
+        assert_eq!(error("main = 0x1000"), 1);
+        assert_eq!(error("main 0x200 extra"), 1);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    io::{self, Stdout, Write},
+    panic,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    queue,
+    style::Print,
+    terminal,
+};
+use log::error;
+
+use crate::{
+    display::{Display, TextStyle},
+    frontend::{Frontend, InputState},
+    keymap::KeyMap,
+};
+
+/// How long a key is held after the terminal reports it.
+///
+/// Terminals only report presses, a held key is repeated after a delay. Keys are released
+/// when this time passes without a repeat, or at once if the terminal reports releases.
+pub const KEY_HOLD: Duration = Duration::from_millis(150);
+
+/// A [`Frontend`] drawing in the terminal with half blocks, built with the `terminal` feature.
+///
+/// # Fields
+///
+/// * `stdout` - The terminal.
+/// * `keymap` - The keypad key of each host key.
+/// * `held` - When each keypad key is released, `None` if it is not held.
+/// * `drawn` - The cells on the screen, one line per row of `TextStyle::HalfBlock`.
+/// * `size` - The columns and rows of the terminal.
+/// * `exit` - Set by Esc, Ctrl+C or a write error.
+///
+/// # Notes
+///
+/// The terminal is in raw mode on the alternate screen while the frontend lives, it is
+/// restored on drop and on panic. Only the cells that changed since the last frame are
+/// written, so it stays usable over slow links such as SSH.
+pub struct TerminalFrontend {
+    stdout: Stdout,
+    keymap: KeyMap,
+    held: [Option<Instant>; 16],
+    drawn: Vec<Vec<char>>,
+    size: (u16, u16),
+    exit: bool,
+}
+
+impl TerminalFrontend {
+    /// Enters raw mode and the alternate screen, with the QWERTY map.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Self>` - The frontend, or the error of the terminal.
+    pub fn new() -> io::Result<Self> {
+        Self::with_keymap(KeyMap::qwerty())
+    }
+
+    /// Enters raw mode and the alternate screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - The keypad key of each host key.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Self>` - The frontend, or the error of the terminal.
+    pub fn with_keymap(keymap: KeyMap) -> io::Result<Self> {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous(info);
+        }));
+
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        queue!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        stdout.flush()?;
+        Ok(Self {
+            stdout,
+            keymap,
+            held: [None; 16],
+            drawn: Vec::new(),
+            size: terminal::size()?,
+            exit: false,
+        })
+    }
+
+    /// Writes the cells in `cells` that differ from `drawn`, or all of them if `full`.
+    fn draw(&mut self, cells: Vec<Vec<char>>, full: bool) -> io::Result<()> {
+        let previous = if full {
+            Vec::new()
+        } else {
+            std::mem::take(&mut self.drawn)
+        };
+        let (left, top) = origin(self.size, &cells);
+        for (x, y, run) in changes(&previous, &cells) {
+            let (column, row) = (left + x, top + y);
+            if row >= self.size.1 as usize || column >= self.size.0 as usize {
+                continue;
+            }
+            let visible: String = run.chars().take(self.size.0 as usize - column).collect();
+            queue!(
+                self.stdout,
+                cursor::MoveTo(column as u16, row as u16),
+                Print(visible)
+            )?;
+        }
+        self.stdout.flush()?;
+        self.drawn = cells;
+        Ok(())
+    }
+
+    /// Clears the terminal after a resize and draws the last frame again, centered.
+    fn resize(&mut self, size: (u16, u16)) -> io::Result<()> {
+        self.size = size;
+        queue!(self.stdout, terminal::Clear(terminal::ClearType::All))?;
+        let cells = std::mem::take(&mut self.drawn);
+        self.draw(cells, true)
+    }
+
+    /// Handles an event of the terminal.
+    fn handle(&mut self, event: Event) -> io::Result<()> {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Esc => self.exit = true,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.exit = true
+                }
+                KeyCode::Char(host) => {
+                    if let Some(pad) = self.keymap.map(host.to_ascii_lowercase()) {
+                        self.held[pad as usize] = match key.kind {
+                            KeyEventKind::Release => None,
+                            _ => Some(Instant::now() + KEY_HOLD),
+                        };
+                    }
+                }
+                _ => {}
+            },
+            Event::Resize(columns, rows) => self.resize((columns, rows))?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    /// Reads the pending events without blocking, keys stay held for [`KEY_HOLD`].
+    fn poll_input(&mut self) -> InputState {
+        loop {
+            let event = match event::poll(Duration::ZERO) {
+                Ok(true) => event::read(),
+                Ok(false) => break,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = event.and_then(|event| self.handle(event)) {
+                error!("Failed to read the terminal: {err}");
+                self.exit = true;
+                break;
+            }
+        }
+
+        let now = Instant::now();
+        let mut keys = 0;
+        for (key, held) in self.held.iter_mut().enumerate() {
+            match held {
+                Some(until) if *until > now => keys |= 1 << key,
+                _ => *held = None,
+            }
+        }
+        InputState { keys }
+    }
+
+    fn present(&mut self, display: &Display) {
+        let cells = display
+            .render_to_string(TextStyle::HalfBlock)
+            .lines()
+            .map(|line| line.chars().collect())
+            .collect();
+        // A new resolution moves the origin, every cell is drawn again
+        let full = self.drawn.len() != display.dimensions().1.div_ceil(2);
+        if full {
+            let _ = queue!(self.stdout, terminal::Clear(terminal::ClearType::All));
+        }
+        if let Err(err) = self.draw(cells, full) {
+            error!("Failed to draw to the terminal: {err}");
+            self.exit = true;
+        }
+    }
+
+    /// Rings the terminal bell when the beep starts.
+    fn set_beep(&mut self, on: bool) {
+        if on {
+            let _ = queue!(self.stdout, Print('\x07'));
+            let _ = self.stdout.flush();
+        }
+    }
+
+    fn should_exit(&self) -> bool {
+        self.exit
+    }
+}
+
+impl Drop for TerminalFrontend {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leaves the alternate screen and raw mode, errors are ignored as there is nothing left to do.
+fn restore() {
+    let mut stdout = io::stdout();
+    let _ = queue!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = stdout.flush();
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Returns the top-left cell of the frame, centered in the terminal.
+fn origin((columns, rows): (u16, u16), cells: &[Vec<char>]) -> (usize, usize) {
+    let width = cells.first().map_or(0, Vec::len);
+    (
+        (columns as usize).saturating_sub(width) / 2,
+        (rows as usize).saturating_sub(cells.len()) / 2,
+    )
+}
+
+/// Returns the runs of cells of `next` that differ from `previous`.
+///
+/// # Arguments
+///
+/// * `previous` - The cells on the screen, empty to draw every cell.
+/// * `next` - The cells to draw.
+///
+/// # Returns
+///
+/// * `Vec<(usize, usize, String)>` - The column, the row and the characters of each run of
+///   changed cells, left to right.
+fn changes(previous: &[Vec<char>], next: &[Vec<char>]) -> Vec<(usize, usize, String)> {
+    let mut runs = Vec::new();
+    for (y, row) in next.iter().enumerate() {
+        let before = previous.get(y);
+        let mut run: Option<(usize, String)> = None;
+        for (x, &cell) in row.iter().enumerate() {
+            if before.and_then(|before| before.get(x)) == Some(&cell) {
+                if let Some((start, text)) = run.take() {
+                    runs.push((start, y, text));
+                }
+            } else {
+                run.get_or_insert_with(|| (x, String::new())).1.push(cell);
+            }
+        }
+        if let Some((start, text)) = run {
+            runs.push((start, y, text));
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(text: &str) -> Vec<Vec<char>> {
+        text.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = cells("█▀  \n  ▄ \n");
+        assert_eq!(changes(&before, &before), vec![]);
+        assert_eq!(
+            changes(&before, &cells("█▀ █\n▄▄▄ \n")),
+            vec![(3, 0, "█".to_string()), (0, 1, "▄▄".to_string())]
+        );
+        // Nothing drawn yet, every cell is a change
+        assert_eq!(
+            changes(&[], &before),
+            vec![(0, 0, "█▀  ".to_string()), (0, 1, "  ▄ ".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_changes_display() {
+        let mut display = Display::new();
+        let render = |display: &Display| cells(&display.render_to_string(TextStyle::HalfBlock));
+        let before = render(&display);
+        display.set(2, 1, 0x80);
+        assert_eq!(
+            changes(&before, &render(&display)),
+            vec![(2, 0, "▄".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_origin() {
+        let frame = cells(&Display::new().render_to_string(TextStyle::HalfBlock));
+        assert_eq!(origin((80, 24), &frame), (8, 4));
+        // A terminal smaller than the frame draws from its top-left corner
+        assert_eq!(origin((40, 10), &frame), (0, 0));
+    }
+}
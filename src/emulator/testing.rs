@@ -0,0 +1,595 @@
+use crate::{
+    compat::{self, Confidence},
+    display::{Display, TextStyle},
+    emulator::{Emulator, State},
+    error::EmulatorError,
+    keyboard::{Key, KeyEvent},
+    memory::Address,
+    opcode::Opcode,
+    prelude::*,
+    quirks::Profile,
+};
+
+/// Asserts that a display shows an ASCII art frame, with a side-by-side diff on failure.
+///
+/// The frame has one line per row, `#` for lit pixels and `.` for unlit ones. Blank lines
+/// and the whitespace around rows are ignored, so frames can be indented raw strings.
+///
+/// * `assert_display_eq!(display, frame)` compares the whole display, the frame must have
+///   its dimensions.
+/// * `assert_display_eq!(display, (x, y), frame)` only compares the region the frame
+///   covers, with its top-left pixel at (x, y).
+///
+/// See [`compare_display`] and [`compare_display_at`].
+#[macro_export]
+macro_rules! assert_display_eq {
+    ($display:expr, $origin:expr, $expected:expr $(,)?) => {
+        if let Err(message) = $crate::testing::compare_display_at(&$display, $origin, $expected) {
+            panic!("{}", message);
+        }
+    };
+    ($display:expr, $expected:expr $(,)?) => {
+        if let Err(message) = $crate::testing::compare_display(&$display, $expected) {
+            panic!("{}", message);
+        }
+    };
+}
+
+/// Compares the whole display with an ASCII art frame.
+///
+/// # Arguments
+///
+/// * `display` - The display.
+/// * `expected` - The frame, see [`assert_display_eq`].
+///
+/// # Returns
+///
+/// * `Result<(), String>` - The differing rows side by side if the display does not show
+///   the frame, or why the frame can not be compared.
+pub fn compare_display(display: &Display, expected: &str) -> Result<(), String> {
+    let rows = parse_ascii_art(expected)?;
+    let (width, height) = display.dimensions();
+    let size = (rows.first().map_or(0, Vec::len), rows.len());
+    if size != (width, height) {
+        return Err(format!(
+            "The expected frame is {}x{}, the display is {width}x{height}",
+            size.0, size.1
+        ));
+    }
+    compare(display, (0, 0), &rows)
+}
+
+/// Compares a region of the display with an ASCII art frame.
+///
+/// # Arguments
+///
+/// * `display` - The display.
+/// * `(x, y)` - The position of the top-left pixel of the frame on the display.
+/// * `expected` - The frame, see [`assert_display_eq`].
+///
+/// # Returns
+///
+/// * `Result<(), String>` - The differing rows side by side if the region does not show
+///   the frame, or why the frame can not be compared.
+pub fn compare_display_at(
+    display: &Display,
+    (x, y): (usize, usize),
+    expected: &str,
+) -> Result<(), String> {
+    let rows = parse_ascii_art(expected)?;
+    let (width, height) = display.dimensions();
+    let size = (rows.first().map_or(0, Vec::len), rows.len());
+    if x + size.0 > width || y + size.1 > height {
+        return Err(format!(
+            "The expected frame is {}x{} at ({x}, {y}), it does not fit in the {width}x{height} display",
+            size.0, size.1
+        ));
+    }
+    compare(display, (x, y), &rows)
+}
+
+/// Compares a region of the display with parsed rows, and formats the differences.
+fn compare(display: &Display, origin: (usize, usize), rows: &[Vec<bool>]) -> Result<(), String> {
+    let (left, top) = origin;
+    let text = display.render_to_string(TextStyle::Plain);
+    let actual: Vec<&str> = text.lines().skip(top).take(rows.len()).collect();
+    let glyph = |lit: bool| if lit { '#' } else { '.' };
+    let mut differences = 0;
+    let mut report = String::new();
+    for (y, (expected, actual)) in rows.iter().zip(actual).enumerate() {
+        let actual = &actual[left..left + expected.len()];
+        let expected: String = expected.iter().map(|&lit| glyph(lit)).collect();
+        let marks: String = expected
+            .chars()
+            .zip(actual.chars())
+            .map(|(expected, actual)| if expected == actual { '.' } else { 'X' })
+            .collect();
+        differences += marks.matches('X').count();
+        report += &format!("{:>3} {expected}  {actual}  {marks}\n", top + y);
+    }
+    if differences == 0 {
+        return Ok(());
+    }
+    let width = rows[0].len();
+    Err(format!(
+        "The display does not match the expected frame at ({left}, {top}), {differences} pixels differ\n    {:width$}  {:width$}  {:width$}\n{report}",
+        "expected", "actual", "diff"
+    ))
+}
+
+/// Runs a ROM headless and asserts the display it shows at the end, see [`run_headless`].
+///
+/// * `assert_rom_frame!(rom, frames, frame)` runs the ROM with the default
+///   [`HarnessOptions`].
+/// * `assert_rom_frame!(rom, frames, options, frame)` runs it with other options.
+///
+/// The frame is an ASCII art frame as in [`assert_display_eq`], of the whole display. The
+/// assertion also fails if the emulator stopped on an error, see
+/// [`HarnessResult::compare_frame`].
+#[macro_export]
+macro_rules! assert_rom_frame {
+    ($rom:expr, $frames:expr, $options:expr, $expected:expr $(,)?) => {
+        let result = $crate::testing::run_headless($rom, $frames, $options);
+        if let Err(message) = result.compare_frame($expected) {
+            panic!("{}", message);
+        }
+    };
+    ($rom:expr, $frames:expr, $expected:expr $(,)?) => {
+        $crate::assert_rom_frame!($rom, $frames, Default::default(), $expected)
+    };
+}
+
+/// Runs a ROM headless and asserts the hash of the display it shows at the end, see
+/// [`run_headless`].
+///
+/// * `assert_rom_hash!(rom, frames, hash)` runs the ROM with the default [`HarnessOptions`].
+/// * `assert_rom_hash!(rom, frames, options, hash)` runs it with other options.
+///
+/// On failure the message shows the display, to check it before updating the hash. See
+/// [`HarnessResult::compare_hash`].
+#[macro_export]
+macro_rules! assert_rom_hash {
+    ($rom:expr, $frames:expr, $options:expr, $hash:expr $(,)?) => {
+        let result = $crate::testing::run_headless($rom, $frames, $options);
+        if let Err(message) = result.compare_hash($hash) {
+            panic!("{}", message);
+        }
+    };
+    ($rom:expr, $frames:expr, $hash:expr $(,)?) => {
+        $crate::assert_rom_hash!($rom, $frames, Default::default(), $hash)
+    };
+}
+
+/// How [`run_headless`] runs a ROM.
+///
+/// # Fields
+///
+/// * `profile` - The interpreter the ROM targets, with its quirks.
+/// * `seed` - The seed of the random number generator, so `RND` gives the same numbers on
+///   every run.
+/// * `instructions_per_frame` - The instructions run by each frame, see
+///   `Emulator::run_frame`.
+/// * `keys` - The key events, with the frame they happen at, the first frame is 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HarnessOptions {
+    pub profile: Profile,
+    pub seed: u64,
+    pub instructions_per_frame: u32,
+    pub keys: Vec<(u64, KeyEvent)>,
+}
+
+impl Default for HarnessOptions {
+    /// CHIP-8, the seed 0 and 12 instructions per frame, about 700 per second.
+    fn default() -> Self {
+        Self {
+            profile: Profile::default(),
+            seed: 0,
+            instructions_per_frame: 12,
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl HarnessOptions {
+    /// Presses a key for a number of frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame the key is pressed at, the first frame is 0.
+    /// * `key` - The key.
+    /// * `frames` - The number of frames it stays pressed, at least 1.
+    pub fn press(mut self, frame: u64, key: Key, frames: u64) -> Self {
+        self.keys.push((frame, KeyEvent::Down(key)));
+        self.keys.push((frame + frames.max(1), KeyEvent::Up(key)));
+        self
+    }
+}
+
+/// Something [`run_headless`] noticed while running a ROM.
+#[derive(Debug)]
+pub enum Diagnostic {
+    /// The emulator stopped on an error during a frame, the first frame is 0. The rest of
+    /// the frames did not run. A ROM that fails to load stops at the frame 0.
+    Halted { frame: u64, error: EmulatorError },
+    /// An invalid opcode was skipped, reported once per address.
+    InvalidOpcode { address: Address, word: u16 },
+    /// The ROM is known to target another interpreter, see `compat::recommend`.
+    ProfileMismatch { recommended: Profile },
+}
+
+impl core::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Halted { frame, error } => write!(f, "Halted at the frame {frame}: {error}"),
+            Self::InvalidOpcode { address, word } => {
+                write!(f, "Invalid opcode {word:04X} at 0x{:04X}", address.inner())
+            }
+            Self::ProfileMismatch { recommended } => {
+                write!(f, "The ROM targets the {recommended:?} profile")
+            }
+        }
+    }
+}
+
+/// The end of a run of [`run_headless`].
+///
+/// # Fields
+///
+/// * `display` - The display after the last frame.
+/// * `text` - The display rendered with `TextStyle::Plain`, `#` for lit pixels.
+/// * `hash` - The CRC-32 of the display in the PBM format, see `Display::to_pbm`.
+/// * `frames` - The frames run, fewer than asked if the emulator stopped on an error.
+/// * `instructions` - The instructions run.
+/// * `diagnostics` - What went wrong, in the order it happened.
+pub struct HarnessResult {
+    pub display: Display,
+    pub text: String,
+    pub hash: u32,
+    pub frames: u64,
+    pub instructions: u64,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl HarnessResult {
+    /// Returns the error the emulator stopped on, if any.
+    pub fn error(&self) -> Option<&EmulatorError> {
+        self.diagnostics
+            .iter()
+            .find_map(|diagnostic| match diagnostic {
+                Diagnostic::Halted { error, .. } => Some(error),
+                _ => None,
+            })
+    }
+
+    /// Compares the display with an ASCII art frame, see [`assert_rom_frame`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - The error the emulator stopped on, or the differing rows
+    ///   as in [`compare_display`].
+    pub fn compare_frame(&self, expected: &str) -> Result<(), String> {
+        self.check_halted()?;
+        compare_display(&self.display, expected)
+    }
+
+    /// Compares the hash of the display, see [`assert_rom_hash`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - The error the emulator stopped on, or both hashes and the
+    ///   display if they differ.
+    pub fn compare_hash(&self, expected: u32) -> Result<(), String> {
+        self.check_halted()?;
+        if self.hash == expected {
+            return Ok(());
+        }
+        Err(format!(
+            "The display hash is 0x{:08X}, expected 0x{expected:08X}\n{}",
+            self.hash, self.text
+        ))
+    }
+
+    /// Returns an error if the emulator stopped on an error.
+    fn check_halted(&self) -> Result<(), String> {
+        match self
+            .diagnostics
+            .iter()
+            .find(|diagnostic| matches!(diagnostic, Diagnostic::Halted { .. }))
+        {
+            Some(halted) => Err(format!("The ROM did not run to the end: {halted}")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Runs a ROM without a frontend, e.g. to test it in CI.
+///
+/// # Arguments
+///
+/// * `rom` - The content of the ROM.
+/// * `frames` - The number of 60Hz frames to run.
+/// * `options` - The profile, the seed and the key events.
+///
+/// # Returns
+///
+/// * `HarnessResult` - The display after the last frame, with the count of instructions
+///   and the diagnostics. The run stops at the first error, see [`Diagnostic::Halted`].
+///
+/// # Notes
+///
+/// The run only depends on its arguments, the same ROM and options always give the same
+/// display. Each frame is an `Emulator::run_frame`, key events apply at the start of
+/// their frame. The frames are counted by the harness, not by `Emulator::frame`, which
+/// does not count the frames spent waiting for a key.
+pub fn run_headless(rom: &[u8], frames: u32, options: HarnessOptions) -> HarnessResult {
+    let mut emulator = Emulator::with_profile(options.profile);
+    emulator.seed_rng(options.seed);
+    let mut diagnostics = Vec::new();
+    let recommendation = compat::recommend(rom);
+    if recommendation.confidence == Confidence::High && recommendation.profile != options.profile {
+        diagnostics.push(Diagnostic::ProfileMismatch {
+            recommended: recommendation.profile,
+        });
+    }
+
+    let run = match emulator.load_rom_bytes(rom) {
+        Ok(()) => run_frames(&mut emulator, frames, &options, &mut diagnostics),
+        Err(error) => {
+            diagnostics.push(Diagnostic::Halted { frame: 0, error });
+            0
+        }
+    };
+
+    let display = emulator.display().clone();
+    HarnessResult {
+        text: display.render_to_string(TextStyle::Plain),
+        hash: compat::crc32(display.to_pbm().as_bytes()),
+        display,
+        frames: run,
+        instructions: emulator.instructions(),
+        diagnostics,
+    }
+}
+
+/// Runs the frames of [`run_headless`], noting the invalid opcodes before they run.
+///
+/// # Returns
+///
+/// * `u64` - The frames run, the frame of an error included.
+fn run_frames(
+    emulator: &mut Emulator,
+    frames: u32,
+    options: &HarnessOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> u64 {
+    for frame in 0..frames as u64 {
+        for &(_, event) in options.keys.iter().filter(|&&(at, _)| at == frame) {
+            emulator.push_key_event(event);
+        }
+        for instruction in 0..options.instructions_per_frame.max(1) {
+            if let (State::Running, Ok(Opcode::Invalid(word))) =
+                (emulator.state(), emulator.fetch_opcode())
+            {
+                let address = emulator.pc();
+                let known = diagnostics.iter().any(|diagnostic| match diagnostic {
+                    Diagnostic::InvalidOpcode { address: other, .. } => *other == address,
+                    _ => false,
+                });
+                if !known {
+                    diagnostics.push(Diagnostic::InvalidOpcode { address, word });
+                }
+            }
+            let result = match instruction {
+                0 => emulator.tick(),
+                _ => emulator.step(),
+            };
+            if let Err(error) = result {
+                diagnostics.push(Diagnostic::Halted { frame, error });
+                return frame + 1;
+            }
+        }
+    }
+    frames as u64
+}
+
+/// Parses an ASCII art frame, see [`assert_display_eq`].
+///
+/// # Arguments
+///
+/// * `art` - The frame.
+///
+/// # Returns
+///
+/// * `Result<Vec<Vec<bool>>, String>` - The rows, or why the frame is invalid: a character
+///   other than `#` and `.`, rows of different lengths or no row at all.
+pub(crate) fn parse_ascii_art(art: &str) -> Result<Vec<Vec<bool>>, String> {
+    let lines = art.lines().map(str::trim).filter(|line| !line.is_empty());
+    let mut rows: Vec<Vec<bool>> = Vec::new();
+    for (y, line) in lines.enumerate() {
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, glyph)| match glyph {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                _ => Err(format!(
+                    "The expected frame has a `{glyph}` at ({x}, {y}), only `#` and `.` are pixels"
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(first) = rows.first().filter(|first| first.len() != row.len()) {
+            return Err(format!(
+                "The row {y} of the expected frame is {} pixels wide, the first one is {}",
+                row.len(),
+                first.len()
+            ));
+        }
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return Err("The expected frame has no row".to_string());
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a low resolution display with pixels at (1, 0) and (2, 1).
+    fn display() -> Display {
+        let mut display = Display::new();
+        display.set(0, 0, 0b0100_0000);
+        display.set(0, 1, 0b0010_0000);
+        display
+    }
+
+    #[test]
+    fn test_compare_display_at() {
+        let display = display();
+        assert_display_eq!(
+            display,
+            (0, 0),
+            "
+            .#..
+            ..#.
+            ....
+            "
+        );
+        assert_display_eq!(display, (2, 1), "#.");
+        assert_display_eq!(display, (60, 29), "....\n....\n....");
+
+        let error = compare_display_at(&display, (0, 0), ".#\n.#").unwrap_err();
+        assert!(error.contains("1 pixels differ"), "{error}");
+        assert!(error.contains("  1 .#  ..  .X"), "{error}");
+    }
+
+    #[test]
+    fn test_compare_display() {
+        let display = display();
+        let mut frame = vec![".".repeat(64); 32];
+        frame[0].replace_range(..2, ".#");
+        frame[1].replace_range(..3, "..#");
+        assert_display_eq!(display, frame.join("\n").as_str());
+        let expected = Display::from_ascii_art(&frame.join("\n")).unwrap();
+        assert_eq!(expected.to_pbm(), display.to_pbm());
+
+        frame[31].replace_range(63.., "#");
+        let error = compare_display(&display, &frame.join("\n")).unwrap_err();
+        assert!(error.contains(" 31 ") && error.ends_with("X\n"), "{error}");
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let display = display();
+        let at = |origin, frame| compare_display_at(&display, origin, frame);
+        let errors = [
+            (compare_display(&display, "#."), "is 2x1, the display"),
+            (at((63, 0), "#."), "does not fit"),
+            (at((0, 31), "#\n#"), "does not fit"),
+            (at((0, 0), "#x"), "has a `x` at (1, 0)"),
+            (at((0, 0), "#.\n#"), "row 1"),
+            (at((0, 0), "\n  \n"), "no row"),
+        ];
+        for (result, message) in errors {
+            let error = result.unwrap_err();
+            assert!(error.contains(message), "{error}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The expected frame is 1x1, the display is 64x32")]
+    fn test_assert_display_eq_dimensions() {
+        assert_display_eq!(display(), "#");
+    }
+
+    /// Draws 0 in the top-left corner and waits for a key, then draws it below the 0.
+    const KEY_ROM: [u8; 12] = [
+        0xD1, 0x15, // DRW V1, V1, 5
+        0xF2, 0x0A, // LD V2, K
+        0xF2, 0x29, // LD F, V2
+        0x60, 0x08, // LD V0, 8
+        0xD1, 0x05, // DRW V1, V0, 5
+        0x12, 0x0A, // JP 0x20A
+    ];
+
+    #[test]
+    fn test_run_headless_keys() {
+        let result = run_headless(&KEY_ROM, 10, HarnessOptions::default());
+        assert_eq!(result.frames, 10);
+        assert!(result.diagnostics.is_empty());
+        assert_display_eq!(result.display, (0, 8), "....\n....\n....\n....\n....");
+
+        let options = HarnessOptions::default().press(5, Key::K5, 2);
+        assert_eq!(options.keys.len(), 2);
+        let result = run_headless(&KEY_ROM, 10, options);
+        assert_display_eq!(result.display, (0, 8), "####\n#...\n####\n...#\n####");
+        assert_eq!(
+            result.text.lines().nth(8),
+            Some(&*format!("####{}", ".".repeat(60)))
+        );
+        assert!(result.compare_hash(result.hash).is_ok());
+        let error = result.compare_hash(!result.hash).unwrap_err();
+        assert!(
+            error.contains("expected") && error.contains("####"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_run_headless_seed() {
+        // Draws the 0 at a random position every frame
+        let rom = [
+            0xC0, 0x3F, // RND V0, #3F
+            0xC1, 0x1F, // RND V1, #1F
+            0xD0, 0x15, // DRW V0, V1, 5
+            0x12, 0x00, // JP 0x200
+        ];
+        let options = HarnessOptions {
+            seed: 8,
+            ..HarnessOptions::default()
+        };
+        let first = run_headless(&rom, 30, options.clone());
+        let second = run_headless(&rom, 30, options);
+        assert_eq!(first.hash, second.hash);
+        assert_eq!(first.text, second.text);
+        assert_eq!(first.instructions, second.instructions);
+    }
+
+    #[test]
+    fn test_run_headless_diagnostics() {
+        let rom = [
+            0x50, 0x01, // Invalid
+            0x12, 0x00, // JP 0x200
+        ];
+        let result = run_headless(&rom, 5, HarnessOptions::default());
+        assert_eq!(result.frames, 5);
+        assert_eq!(result.instructions, 60);
+        assert!(matches!(
+            result.diagnostics[..],
+            [Diagnostic::InvalidOpcode { word: 0x5001, .. }]
+        ));
+        assert!(result.error().is_none());
+
+        // RET with an empty stack
+        let result = run_headless(&[0x00, 0xEE], 5, HarnessOptions::default());
+        assert_eq!(result.frames, 1);
+        assert!(matches!(
+            result.diagnostics[..],
+            [Diagnostic::Halted { frame: 0, .. }]
+        ));
+        assert!(result.error().is_some());
+        let error = result.compare_frame("#").unwrap_err();
+        assert!(error.contains("did not run to the end"), "{error}");
+
+        let result = run_headless(&vec![0; 0x1000], 5, HarnessOptions::default());
+        assert_eq!((result.frames, result.instructions), (0, 0));
+        assert!(result.error().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "The display hash is")]
+    fn test_assert_rom_hash() {
+        assert_rom_hash!(&KEY_ROM, 1, 0);
+    }
+}
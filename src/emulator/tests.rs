@@ -1,10 +1,21 @@
-use crate::register::RegisterIndex;
+use crate::{error::EmulatorError, register::RegisterIndex};
 
-use super::emulator::Emulator;
+use super::emulator::{Emulator, EmulatorConfig};
 
 fn initialize_empty_emulator() -> Emulator {
     let mut emulator = Emulator::new();
-    assert!(emulator.load_rom(&[0u8] as &[u8]).is_ok());
+    assert!(emulator.load_rom_bytes(&[0]).is_ok());
+    emulator
+}
+
+/// Runs the first instructions of a program with a set of quirks.
+fn run_with_quirks(quirks: super::quirks::Quirks, program: &[u8], ticks: usize) -> Emulator {
+    let mut emulator = Emulator::new();
+    emulator.set_quirks(quirks);
+    assert!(emulator.load_rom_bytes(program).is_ok());
+    for _ in 0..ticks {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
     emulator
 }
 
@@ -34,6 +45,307 @@ fn memory_load_and_access() {
     assert_eq!(buffer, data)
 }
 
+#[test]
+/// Test loads at arbitrary addresses and the range they report
+fn test_load_at() {
+    use super::memory::Address;
+
+    let mut emulator = Emulator::new();
+    assert_eq!(emulator.loaded_range(), None);
+    assert!(emulator.load_rom_bytes(&[0x12u8, 0x00, 0x00, 0xE0]).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x204));
+
+    // A second part after a gap
+    assert!(emulator.load_at(Address::new(0x300), &[1, 2]).is_ok());
+    let ranges = emulator.memory.loaded_ranges();
+    assert_eq!(ranges, [0x200..0x204, 0x300..0x302]);
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x302));
+
+    // A patch over the end of the ROM overwrites it and merges the ranges
+    let patch = [0xAA, 0xBB, 0xCC];
+    assert!(emulator.load_at(Address::new(0x202), &patch).is_ok());
+    let mut buffer = [0; 6];
+    let memory = &emulator.memory;
+    memory
+        .write_range(Address::ENTRY_POINT, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, [0x12, 0x00, 0xAA, 0xBB, 0xCC, 0x00]);
+    assert_eq!(memory.loaded_ranges(), [0x200..0x205, 0x300..0x302]);
+    // Filling the gap joins both parts
+    assert!(emulator.load_at(Address::new(0x205), &[0; 0xFB]).is_ok());
+    assert_eq!(emulator.memory.loaded_ranges().len(), 1);
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x302));
+
+    // Loads past the end write nothing
+    let result = emulator.load_at(Address::new(0xFFE), &[7, 7, 7]);
+    assert!(matches!(
+        result,
+        Err(EmulatorError::LoadOutOfBounds {
+            address: 0xFFE,
+            len: 3,
+            size: 0x1000
+        })
+    ));
+    assert_eq!(emulator.memory[Address::new(0xFFE)], 0);
+    assert!(emulator.load_at(Address::new(0xFFD), &[7, 7, 7]).is_ok());
+    assert!(emulator.load_at(Address::new(0x000), &[]).is_ok());
+    let ranges = emulator.memory.loaded_ranges();
+    assert_eq!(ranges, [0x200..0x302, 0xFFD..0x1000]);
+
+    // Loading a ROM forgets the previous loads
+    assert!(emulator.load_rom_bytes(&[0x00u8, 0xE0]).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x202));
+}
+
+#[test]
+/// Test word accesses and fetching an instruction from the last byte of the memory
+fn test_memory_words() {
+    use super::memory::Address;
+
+    let mut emulator = initialize_empty_emulator();
+    let memory = &mut emulator.memory;
+    assert!(memory.write_u16(Address::new(0x300), 0xABCD).is_ok());
+    assert_eq!(memory[Address::new(0x300)], 0xAB);
+    assert_eq!(memory[Address::new(0x301)], 0xCD);
+    assert_eq!(memory.read_u16(Address::new(0x300)).unwrap(), 0xABCD);
+    assert_eq!(memory.read_u16(Address::new(0xFFE)).unwrap(), 0);
+
+    let result = memory.write_u16(Address::new(0xFFF), 0x1234);
+    assert!(matches!(
+        result,
+        Err(EmulatorError::OutOfBounds {
+            address: 0x1001,
+            ..
+        })
+    ));
+    assert_eq!(memory[Address::new(0xFFF)], 0);
+    let result = memory.read_u16(Address::new(0xFFF));
+    assert!(matches!(
+        result,
+        Err(EmulatorError::OutOfBounds {
+            address: 0x1001,
+            ..
+        })
+    ));
+
+    emulator.pc = Address::new(0xFFF);
+    let result = emulator.fetch_opcode();
+    assert!(matches!(
+        result,
+        Err(EmulatorError::OutOfBounds {
+            address: 0x1001,
+            ..
+        })
+    ));
+}
+
+#[test]
+/// Test the debugger accesses to the memory
+fn test_peek_poke() {
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&[0x12u8, 0x34]).is_ok());
+    assert_eq!(emulator.peek(0x201).unwrap(), 0x34);
+    // The fonts start at 0
+    assert_eq!(emulator.peek(0x000).unwrap(), 0xF0);
+    assert!(emulator.poke(0xFFF, 0xAB).is_ok());
+    assert_eq!(emulator.peek(0xFFF).unwrap(), 0xAB);
+
+    assert_eq!(emulator.memory_view(0x200..0x202).unwrap(), [0x12, 0x34]);
+    assert_eq!(emulator.memory_view(0xFFF..0x1000).unwrap(), [0xAB]);
+    assert!(emulator.memory_view(0x300..0x300).unwrap().is_empty());
+
+    let out_of_range = |result: Result<_, EmulatorError>, expected: usize| {
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error,
+            EmulatorError::AddressOutOfRange { address, size: 0x1000 } if address == expected
+        ));
+    };
+    out_of_range(emulator.peek(0x1000).map(|_| ()), 0x1000);
+    out_of_range(emulator.poke(0x1234, 1), 0x1234);
+    out_of_range(emulator.memory_view(0xFF0..0x1010).map(|_| ()), 0x1000);
+    let (start, end) = (0x300, 0x200);
+    out_of_range(emulator.memory_view(start..end).map(|_| ()), 0x300);
+
+    // Every address of a 64KB memory is valid
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    assert!(emulator.poke(0xFFFF, 1).is_ok());
+    assert_eq!(emulator.peek(0xFFFF).unwrap(), 1);
+}
+
+#[test]
+/// Test ROMs in Intel HEX are loaded at their origin
+fn test_load_rom_ihex() {
+    let text = ":020200001300E9\n:02030000602A71\n:00000001FF\n";
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_ihex(text).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x302));
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.registers[RegisterIndex::ZERO], 0x2A);
+    let exported = emulator.memory.to_ihex(0x200..0x302).unwrap();
+    let round_trip = crate::rom::from_ihex(&exported).unwrap();
+    assert_eq!(round_trip.1, 0x200);
+    assert_eq!(round_trip.0, emulator.memory_view(0x200..0x302).unwrap());
+
+    // Only the data is loaded, the program starts at the entry point
+    let text = ":02030000602A71\n:00000001FF\n";
+    assert!(emulator.load_rom_ihex(text).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x300..0x302));
+    assert_eq!(emulator.pc.inner(), 0x200);
+    let result = emulator.load_rom_ihex(":02030000602A71\n");
+    assert!(matches!(
+        result,
+        Err(EmulatorError::InvalidIntelHex { line: 2, .. })
+    ));
+}
+
+#[test]
+/// Test ROMs pasted as hex listings
+fn test_load_rom_hex_text() {
+    let mut emulator = Emulator::new();
+    let text = "60 2A ; LD V0, 0x2A\n0x1202";
+    assert!(emulator.load_rom_hex_text(text).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x204));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::ZERO], 0x2A);
+    let result = emulator.load_rom_hex_text("60 2A\n12 2");
+    assert!(matches!(
+        result,
+        Err(EmulatorError::InvalidHexText {
+            line: 2,
+            column: 4,
+            ..
+        })
+    ));
+}
+
+#[test]
+/// Test the exact format of hex dumps
+fn test_hexdump() {
+    let mut emulator = Emulator::new();
+    let rom = b"`\0\xA2\x1E\xD0\x15\x12\x06ABCD";
+    assert!(emulator.load_rom_bytes(rom).is_ok());
+
+    let dump = emulator.hexdump(0x1FA..0x20C).unwrap();
+    let expected = "\
+01F0                           0000 0000 0000  |          ......|
+0200  6000 A21E D015 1206 4142 4344            |`.......ABCD    |
+";
+    assert_eq!(dump, expected);
+    let dump = emulator.hexdump(0x203..0x205).unwrap();
+    let expected = "\
+0200         1E D0                             |   ..           |
+";
+    assert_eq!(dump, expected);
+    let dump = emulator.hexdump(0x000..0x010).unwrap();
+    let expected = "\
+0000  F090 9090 F020 6020 2070 F010 F080 F0F0  |..... `  p......|
+";
+    assert_eq!(dump, expected);
+
+    assert_eq!(emulator.hexdump(0x300..0x300).unwrap(), "");
+    let result = emulator.hexdump(0xFF8..0x1008);
+    assert!(matches!(
+        result,
+        Err(EmulatorError::AddressOutOfRange {
+            address: 0x1000,
+            size: 0x1000
+        })
+    ));
+}
+
+#[test]
+/// Test memory hooks see the writes to their range
+fn test_memory_hooks() {
+    use super::hooks::MemoryWrite;
+    use super::memory::Address;
+    use std::sync::{Arc, Mutex};
+
+    let program = [
+        0xA3, 0x00, // LD I, 0x300
+        0x60, 0x07, // LD V0, 7
+        0x61, 0x08, // LD V1, 8
+        0xF1, 0x55, // LD [I], V1
+        0xF0, 0x33, // LD B, V0
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let log = writes.clone();
+    let first = emulator.on_memory_write(0x301..0x400, move |write| {
+        log.lock().unwrap().push(*write);
+    });
+    let count = Arc::new(Mutex::new(0));
+    let counter = count.clone();
+    let second = emulator.on_memory_write(0x300..0x301, move |_| {
+        *counter.lock().unwrap() += 1;
+    });
+    for _ in 0..5 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    let write = |address, old, new, pc| MemoryWrite {
+        address,
+        old,
+        new,
+        pc: Address::new(pc),
+    };
+    let expected = [
+        write(0x301, 0, 8, 0x206),
+        write(0x301, 8, 0, 0x208),
+        write(0x302, 0, 7, 0x208),
+    ];
+    assert_eq!(*writes.lock().unwrap(), expected);
+    assert_eq!(*count.lock().unwrap(), 2);
+
+    assert!(emulator.remove_hook(first));
+    assert!(!emulator.remove_hook(first));
+    assert!(emulator.poke(0x300, 0xAA).is_ok());
+    assert_eq!(writes.lock().unwrap().len(), 3);
+    assert_eq!(*count.lock().unwrap(), 3);
+
+    // Blocked writes are not seen
+    emulator.set_write_protection(true);
+    let _third = emulator.on_memory_write(0x000..0x200, |_| panic!("Blocked write seen"));
+    assert!(emulator.poke(0x100, 1).is_err());
+    assert!(emulator.remove_hook(second));
+}
+
+#[test]
+/// Test the ROM hash covers the bytes read, not the memory
+fn test_rom_hash() {
+    use super::compat::{crc32, RomHash};
+    use super::memory::Address;
+
+    let program = [
+        0xA2, 0x00, // LD I, 0x200
+        0x60, 0xFF, // LD V0, 0xFF
+        0xF0, 0x55, // LD [I], V0
+    ];
+    let mut emulator = Emulator::new();
+    assert_eq!(emulator.rom_hash(), RomHash::new(&[]));
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let hash = emulator.rom_hash();
+    assert_eq!((hash.crc32, hash.len), (crc32(&program), 6));
+    assert_eq!(hash, RomHash::new(&program));
+
+    // The program overwrites itself
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.peek(0x200).unwrap(), 0xFF);
+    assert!(emulator.load_at(Address::new(0x300), &[1]).is_ok());
+    assert_eq!(emulator.rom_hash(), hash);
+
+    // Save states keep the identity of the ROM
+    let state = emulator.save_state();
+    assert!(emulator.load_rom_bytes(&[0x12u8, 0x00]).is_ok());
+    assert_ne!(emulator.rom_hash(), hash);
+    assert!(emulator.load_state(&state).is_ok());
+    assert_eq!(emulator.rom_hash(), hash);
+}
+
 #[test]
 /// Test 1NNN, 2NNN and 00EE chip-8 instructions
 fn test_jump_instructions() {
@@ -141,3 +453,2404 @@ fn test_skip() {
         super::memory::Address::ENTRY_POINT.inner() + 18
     );
 }
+
+#[test]
+/// Test 0NNN is ignored instead of being executed as a call
+fn test_sys_is_ignored() {
+    let mut emulator = initialize_empty_emulator();
+
+    let program = [
+        0x03, 0x00, // SYS 0x300
+        0x60, 0x2A, // LD V0, 0x2A
+    ];
+    emulator
+        .memory
+        .read_range(super::memory::Address::ENTRY_POINT, &program)
+        .unwrap();
+
+    assert!(matches!(emulator.tick(), Ok(())));
+    // The stack stays empty and execution continues with the next instruction
+    assert_eq!(emulator.stack.len(), 0);
+    assert_eq!(
+        emulator.pc.inner(),
+        super::memory::Address::ENTRY_POINT.inner() + 2
+    );
+
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::new(0)], 0x2A);
+    assert_eq!(emulator.stack.len(), 0);
+}
+
+#[test]
+/// Test invalid opcodes report the address they were fetched from
+fn test_invalid_opcode() {
+    let mut emulator = initialize_empty_emulator();
+
+    let program = [
+        0x51, 0x23, // Invalid
+        0x51, 0x23, // Invalid
+    ];
+    emulator
+        .memory
+        .read_range(super::memory::Address::ENTRY_POINT, &program)
+        .unwrap();
+
+    // Skipped by default
+    assert!(matches!(emulator.tick(), Ok(())));
+
+    emulator.set_strict(true);
+    let err = emulator.tick().unwrap_err();
+    assert!(matches!(
+        err,
+        EmulatorError::InvalidOpcode { word: 0x5123, pc } if pc.inner() == 0x202
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Invalid Opcode: The word 0x5123 (#5123) at 0x202 is not a valid instruction."
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test the source location follows the program counter
+fn test_current_source_line() {
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.current_source_line().is_none());
+
+    let map = super::source_map::SourceMap::parse("0x200 pong.8o:3\n0x202 pong.8o:4").unwrap();
+    emulator.set_source_map(Some(map));
+    let program = [
+        0x60, 0x01, // LD V0, 1
+        0x12, 0x04, // JP 0x204
+        0x12, 0x04, // JP 0x204
+    ];
+    emulator
+        .memory
+        .read_range(super::memory::Address::ENTRY_POINT, &program)
+        .unwrap();
+
+    assert_eq!(
+        emulator.current_source_line().unwrap().to_string(),
+        "pong.8o:3"
+    );
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.current_source_line().unwrap().line, 4);
+    assert!(matches!(emulator.tick(), Ok(())));
+    // Addresses without a mapping have no location
+    assert!(emulator.current_source_line().is_none());
+}
+
+#[test]
+/// Test 00FF, 00FE and DXY0 SUPER-CHIP instructions
+fn test_hires_mode() {
+    let mut emulator = initialize_empty_emulator();
+    emulator.set_profile(super::quirks::Profile::SuperChip);
+    let program = [
+        0x00, 0xFF, // HIGH
+        0xA3, 0x00, // LD I, 0x300
+        0x60, 0x70, // LD V0, 0x70
+        0xD0, 0x00, // DRW V0, V0, 0
+        0x00, 0xFE, // LOW
+    ];
+    emulator
+        .memory
+        .read_range(super::memory::Address::ENTRY_POINT, &program)
+        .unwrap();
+    // 16x16 sprite with only the right column lit
+    emulator
+        .memory
+        .read_range(super::memory::Address::new(0x300), &[0x00, 0x01].repeat(16))
+        .unwrap();
+
+    assert_eq!(emulator.display().dimensions(), (64, 32));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(emulator.display().is_hires());
+    assert_eq!(emulator.display().dimensions(), (128, 64));
+
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    for y in 0x70..0x80 {
+        assert!(emulator.display().get(0x7F, y % 64));
+        assert!(!emulator.display().get(0x7E, y % 64));
+    }
+
+    // Switching modes clears the screen
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.display().dimensions(), (64, 32));
+    assert!(emulator
+        .display()
+        .get_vram()
+        .iter()
+        .flatten()
+        .all(|pixel| !pixel));
+}
+
+#[test]
+/// Test 00CN, 00FB and 00FC SUPER-CHIP instructions and the low resolution scroll quirk
+fn test_scroll() {
+    let program = [
+        0xA3, 0x00, // LD I, 0x300
+        0xD0, 0x01, // DRW V0, V0, 1
+        0x00, 0xC2, // SCD 2
+        0x00, 0xFB, // SCR
+    ];
+    let run = |profile, lores_scroll_halved| {
+        let mut emulator = initialize_empty_emulator();
+        emulator.set_profile(profile);
+        emulator.set_quirks(super::quirks::Quirks {
+            lores_scroll_halved,
+            ..Default::default()
+        });
+        emulator
+            .memory
+            .read_range(super::memory::Address::ENTRY_POINT, &program)
+            .unwrap();
+        emulator
+            .memory
+            .read_range(super::memory::Address::new(0x300), &[0x80])
+            .unwrap();
+        for _ in 0..4 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+        emulator
+    };
+
+    use super::quirks::Profile;
+    assert!(run(Profile::SuperChip, false).display().get(4, 2));
+    assert!(run(Profile::SuperChip, true).display().get(2, 1));
+    // Plain CHIP-8 does not scroll
+    assert!(run(Profile::Chip8, false).display().get(0, 0));
+}
+
+#[test]
+/// Test FX29 and FX30 follow the font wherever it is loaded
+fn test_font_sets() {
+    use super::memory::{Address, FontSet};
+
+    let program = [
+        0x6A, 0x1A, // LD VA, 0x1A
+        0xFA, 0x29, // LD F, VA
+        0xFA, 0x30, // LD HF, VA
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::SuperChip);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    // Only the low nibble selects the digit
+    assert_eq!(emulator.i.inner(), 0xA * 5);
+    assert_eq!(emulator.font_base(), Address::FONTS_INDEX);
+
+    let rounded = FontSet::rounded();
+    assert!(emulator.set_font(rounded, Address::new(0x050)).is_ok());
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.i.inner(), 0x050 + 0xA * 5);
+    let glyph = emulator.memory_view(0x082..0x087).unwrap();
+    assert_eq!(glyph, &rounded.glyphs()[0x32..0x37]);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.i.inner(), 0x0A0 + 0xA * 10);
+    // The memory below the font is cleared
+    assert_eq!(emulator.peek(0x000).unwrap(), 0);
+
+    // The font can not overlap the ROM or leave the first 4KB
+    for base in [0x1F0, 0xF80] {
+        let result = emulator.set_font(FontSet::classic(), Address::new(base));
+        assert!(matches!(
+            result,
+            Err(EmulatorError::FontCollision { base: b, .. }) if b == base
+        ));
+    }
+    assert_eq!(emulator.font_base(), Address::new(0x050));
+    assert_eq!(emulator.memory.font(), &rounded);
+
+    // The font survives profile changes, and ROMs loaded over it are rejected
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    assert_eq!(emulator.font_base(), Address::new(0x050));
+    assert!(emulator.set_font(rounded, Address::new(0x1C0)).is_ok());
+    let result = emulator.load_rom_bytes(&program);
+    assert!(matches!(
+        result,
+        Err(EmulatorError::FontCollision {
+            base: 0x1C0,
+            end: 0x2B0
+        })
+    ));
+}
+
+#[test]
+/// Test the write protection of the memory below 0x200
+fn test_write_protection() {
+    use super::memory::Address;
+
+    let program = [
+        0xA0, 0x50, // LD I, 0x050
+        0x60, 0xFF, // LD V0, 0xFF
+        0xF0, 0x55, // LD [I], V0
+        0xF0, 0x33, // LD B, V0
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_write_protection(true);
+    // Loading the ROM still places the fonts
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert_eq!(emulator.peek(0x000).unwrap(), 0xF0);
+    let font = emulator.memory_view(0x050..0x060).unwrap().to_vec();
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    let result = emulator.tick();
+    assert!(matches!(
+        result,
+        Err(EmulatorError::ProtectedWrite { addr, pc, word: Some(0xF055) })
+            if addr == Address::new(0x050) && pc == Address::new(0x204)
+    ));
+    // FX33 is blocked as well
+    emulator.pc = Address::new(0x206);
+    let result = emulator.tick();
+    let pc = Address::new(0x206);
+    assert!(matches!(result, Err(EmulatorError::ProtectedWrite { pc: p, .. }) if p == pc));
+    assert_eq!(emulator.memory_view(0x050..0x060).unwrap(), font);
+    assert!(matches!(
+        emulator.poke(0x1FF, 0),
+        Err(EmulatorError::ProtectedWrite { .. })
+    ));
+    assert!(emulator.poke(0x200, 0xA0).is_ok());
+
+    // Off, the program can overwrite the fonts
+    emulator.set_write_protection(false);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..4 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.peek(0x050).unwrap(), 2);
+    assert!(emulator.poke(0x000, 0).is_ok());
+
+    // The strict mode turns it on
+    emulator.set_strict(true);
+    assert!(emulator.poke(0x000, 0).is_err());
+}
+
+#[test]
+/// Test FX30 points I at the 8x10 SUPER-CHIP digit sprites
+fn test_large_font() {
+    let mut emulator = initialize_empty_emulator();
+    emulator.set_profile(super::quirks::Profile::SuperChip);
+    let program = [
+        0x60, 0x07, // LD V0, 7
+        0xF0, 0x30, // LD HF, V0
+        0x61, 0x00, // LD V1, 0
+        0xD1, 0x1A, // DRW V1, V1, 10
+    ];
+    emulator
+        .memory
+        .read_range(super::memory::Address::ENTRY_POINT, &program)
+        .unwrap();
+    for _ in 0..4 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.i.inner(), 0x50 + 7 * 10);
+
+    crate::assert_display_eq!(
+        emulator.display(),
+        (0, 0),
+        "
+        ########.
+        ########.
+        ......##.
+        ......##.
+        .....##..
+        ....##...
+        ...##....
+        ...##....
+        ...##....
+        ...##....
+        .........
+        "
+    );
+}
+
+#[test]
+/// Test FX75 and FX85 SUPER-CHIP instructions and the persistence hook
+fn test_rpl_flags() {
+    use std::sync::{Arc, Mutex};
+
+    /// Records every save, starts from a previous session
+    struct Recorder(Arc<Mutex<Vec<[u8; 8]>>>);
+    impl super::persistence::PersistenceHook for Recorder {
+        fn load(&mut self) -> Option<[u8; 8]> {
+            Some([9; 8])
+        }
+        fn save(&mut self, flags: &[u8; 8]) {
+            self.0.lock().unwrap().push(*flags);
+        }
+    }
+
+    let mut emulator = initialize_empty_emulator();
+    emulator.set_profile(super::quirks::Profile::SuperChip);
+    let saves = Arc::new(Mutex::new(Vec::new()));
+    emulator.set_persistence_hook(Some(Box::new(Recorder(saves.clone()))));
+    assert_eq!(emulator.rpl_flags(), &[9; 8]);
+
+    let program = [
+        0xF2, 0x85, // LD V2, R
+        0x60, 0x01, // LD V0, 1
+        0xF1, 0x75, // LD R, V1
+        0xF1, 0x75, // LD R, V1
+        0xFF, 0x75, // LD R, VF
+    ];
+    emulator
+        .memory
+        .read_range(super::memory::Address::ENTRY_POINT, &program)
+        .unwrap();
+    for _ in 0..5 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 9);
+    // Saving the same values again does not call the hook
+    assert_eq!(
+        *saves.lock().unwrap(),
+        [[1, 9, 9, 9, 9, 9, 9, 9], [1, 9, 9, 0, 0, 0, 0, 0]]
+    );
+}
+
+#[test]
+/// Test FX75 and FX85 above V7 clamp to V7, or stop with the reject_rpl_overflow quirk
+fn test_rpl_overflow() {
+    use super::error::Fault;
+
+    let program = [
+        0x6F, 0x05, // LD VF, 5
+        0x67, 0x07, // LD V7, 7
+        0xFF, 0x75, // LD R, VF
+        0xF9, 0x85, // LD V9, R
+    ];
+    let run = |reject_rpl_overflow| {
+        let mut emulator = initialize_empty_emulator();
+        emulator.set_profile(super::quirks::Profile::SuperChip);
+        emulator.set_quirks(super::quirks::Quirks {
+            reject_rpl_overflow,
+            ..emulator.quirks()
+        });
+        assert!(emulator.load_rom_bytes(&program).is_ok());
+        for _ in 0..2 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+        emulator
+    };
+
+    // V0 to V7 are stored and loaded, VF is left out
+    let mut emulator = run(false);
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.rpl_flags(), &[0, 0, 0, 0, 0, 0, 0, 7]);
+    assert_eq!(emulator.registers[RegisterIndex::new(7)], 7);
+    assert_eq!(emulator.registers[RegisterIndex::new(9)], 0);
+
+    let mut emulator = run(true);
+    let expected = Fault {
+        pc: super::memory::Address::new(0x204),
+        word: 0xFF75,
+    };
+    let error = emulator.tick().unwrap_err();
+    assert!(matches!(
+        error,
+        EmulatorError::RplOverflow { register: 0xF, fault } if fault == expected
+    ));
+    assert_eq!(error.code(), 305);
+    assert_eq!(emulator.rpl_flags(), &[0; 8]);
+}
+
+#[test]
+/// Test F000 NNNN XO-CHIP instruction and the 64KB memory
+fn test_long_index() {
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    let program = [
+        0xF0, 0x00, 0x80, 0x00, // LD I, LONG 0x8000
+        0x60, 0x01, // LD V0, 1
+        0xF0, 0x1E, // ADD I, V0
+        0xD0, 0x01, // DRW V0, V0, 1
+    ];
+    let mut rom = program.to_vec();
+    rom.resize(0x8001 - 0x200, 0);
+    rom.push(0x80);
+    assert!(emulator.load_rom_bytes(&rom).is_ok());
+
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!((emulator.pc.inner(), emulator.i.inner()), (0x204, 0x8000));
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.i.inner(), 0x8001);
+    assert!(emulator.display().get(1, 1));
+}
+
+#[test]
+/// Test FN01 XO-CHIP plane selection and drawing to both planes
+fn test_planes() {
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    let program = [
+        0xA2, 0x0C, // LD I, 0x20C
+        0xF2, 0x01, // PLANE 2
+        0xD0, 0x01, // DRW V0, V0, 1
+        0xF3, 0x01, // PLANE 3
+        0xD0, 0x01, // DRW V0, V0, 1
+        0x00, 0xE0, // CLS
+        0xC0, 0xA0, // Sprites of the first and second planes
+    ];
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.display().color(0, 0), 2);
+    assert_eq!(emulator.display().color(1, 0), 2);
+    assert_eq!(emulator.registers[RegisterIndex::FLAG], 0);
+
+    // The first plane gets the first sprite, the second plane the next one
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(matches!(emulator.tick(), Ok(())));
+    // The second sprite turns off (0, 0) on the second plane
+    assert_eq!(emulator.registers[RegisterIndex::FLAG], 1);
+    assert_eq!(
+        [0, 1, 2, 3].map(|x| emulator.display().color(x, 0)),
+        [1, 3, 2, 0]
+    );
+
+    // CLS clears the selected planes only
+    emulator.display.select_planes(1);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(
+        [0, 1, 2, 3].map(|x| emulator.display().color(x, 0)),
+        [0, 2, 2, 0]
+    );
+
+    // Loading a ROM selects the first plane again
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert_eq!(emulator.display().selected(), 1);
+}
+
+#[test]
+/// Test F002 and FX3A XO-CHIP audio instructions
+fn test_audio_pattern() {
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    let mut program = vec![
+        0xA2, 0x0A, // LD I, 0x20A
+        0xF0, 0x02, // LD AUDIO, [I]
+        0x60, 0x70, // LD V0, 112
+        0xF0, 0x3A, // LD PITCH, V0
+        0xF0, 0x18, // LD ST, V0
+    ];
+    program.extend([0xF0, 0x0F].repeat(8));
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+
+    let mut buffer = [1.0; 16];
+    emulator.fill_audio_buffer(&mut buffer, 8000);
+    assert!(buffer.iter().all(|&sample| sample == 0.0));
+
+    for _ in 0..5 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.pattern_buffer()[..2], [0xF0, 0x0F]);
+    assert_eq!(emulator.pitch(), 112);
+    // Pitch 112 plays 8000 pattern samples per second, one per output sample
+    emulator.fill_audio_buffer(&mut buffer, 8000);
+    let wave: String = buffer
+        .iter()
+        .map(|&sample| if sample > 0.0 { '#' } else { '.' })
+        .collect();
+    assert_eq!(wave, "####........####");
+
+    // Save states keep the pattern and the pitch
+    let state = emulator.save_state();
+    let mut restored = Emulator::with_profile(super::quirks::Profile::XoChip);
+    assert!(restored.load_rom_bytes(&program).is_ok());
+    assert_eq!(restored.pitch(), 64);
+    assert!(restored.load_state(&state).is_ok());
+    assert_eq!(restored.pattern_buffer()[..2], [0xF0, 0x0F]);
+    assert_eq!(restored.pitch(), 112);
+    assert_eq!(restored.save_state(), state);
+    // The waveform starts over, the pattern repeats every 16 samples
+    let mut restored_buffer = [0.0; 16];
+    restored.fill_audio_buffer(&mut restored_buffer, 8000);
+    emulator.fill_audio_buffer(&mut buffer, 8000);
+    assert_eq!(restored_buffer, buffer);
+}
+
+#[test]
+/// Test skips jump over the whole F000 NNNN XO-CHIP instruction
+fn test_skip_long_load() {
+    let program = [
+        0x30, 0x00, // SE V0, 0
+        0xF0, 0x00, 0x03, 0x00, // LD I, LONG 0x300
+        0x61, 0x01, // LD V1, 1
+        0xE0, 0xA1, // SKNP V0
+        0xF0, 0x00, 0x04, 0x00, // LD I, LONG 0x400
+        0x62, 0x02, // LD V2, 2
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.pc.inner(), 0x206);
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.pc.inner(), 0x210);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.i.inner(), 0);
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 2);
+
+    // Other profiles only skip the first word, as F000 is not an instruction there
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::SuperChip);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.pc.inner(), 0x204);
+}
+
+#[test]
+/// Test the quirks of the CHIP-48 profile
+fn test_chip48_profile() {
+    let program = [
+        0x61, 0x03, // LD V1, 3
+        0x62, 0x80, // LD V2, 0x80
+        0x81, 0x26, // SHR V1, V2
+        0xA3, 0x00, // LD I, 0x300
+        0xF2, 0x55, // LD [I], V2
+        0xB2, 0x10, // JP V0, 0x210 (JP V2, 0x210 on CHIP-48)
+    ];
+    let run = |profile| {
+        let mut emulator = Emulator::new();
+        emulator.set_profile(profile);
+        assert!(emulator.load_rom_bytes(&program).is_ok());
+        for _ in 0..6 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+        emulator
+    };
+
+    use super::quirks::Profile;
+    let emulator = run(Profile::Chip48);
+    // The shift ignores VY
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 1);
+    assert_eq!(emulator.registers[RegisterIndex::FLAG], 1);
+    // I is incremented by X, not X + 1
+    assert_eq!(emulator.i.inner(), 0x302);
+    // BXNN jumps to XNN + VX
+    assert_eq!(emulator.pc.inner(), 0x290);
+
+    let emulator = run(Profile::Chip8);
+    // I is incremented by X + 1
+    assert_eq!(emulator.i.inner(), 0x303);
+    assert_eq!(emulator.pc.inner(), 0x210);
+}
+
+#[test]
+/// Test the CHIP-8X color and second keypad instructions
+fn test_chip8x() {
+    let program = [
+        0x60, 0x31, // LD V0, 0x31
+        0x61, 0x22, // LD V1, 0x22
+        0x62, 0x05, // LD V2, 5
+        0xB0, 0x20, // COL V0, V2, 0
+        0x02, 0xA0, // BGC
+        0x60, 0x08, // LD V0, 8
+        0x61, 0x00, // LD V1, 0
+        0xB0, 0x25, // COL V0, V2, 5
+        0xE2, 0xF2, // SKP2 V2
+        0x00, 0xE0, // CLS
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::Chip8X);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..5 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    // Columns 1 to 3 and row 2
+    let display = emulator.display();
+    assert_eq!(display.zone_color(8, 8), 5);
+    assert_eq!(display.zone_color(31, 11), 5);
+    assert_eq!(display.zone_color(32, 8), 1);
+    assert_eq!(display.zone_color(8, 12), 1);
+    assert_eq!(display.background(), 1);
+
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    // Column 1, 5 pixels tall from the top
+    let display = emulator.display();
+    assert_eq!(display.zone_color(15, 7), 5);
+    assert_eq!(display.zone_color(15, 8), 5);
+    assert_eq!(display.zone_color(16, 0), 1);
+
+    emulator.press_key2(super::keyboard::Key::K5);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.pc.inner(), 0x214);
+
+    // The same words are SYS and JP V0 on CHIP-8
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..4 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.pc.inner(), 0x020 + 0x31);
+}
+
+#[test]
+/// Test the ETI-660 profile loads at 0x600 and wraps sprites at row 48
+fn test_eti660() {
+    let program = [
+        0x60, 0x2E, // LD V0, 46
+        0xA6, 0x08, // LD I, 0x608
+        0xD0, 0x04, // DRW V0, V0, 4
+        0x16, 0x06, // JP 0x606
+        0xF0, 0x90, 0x90, 0xF0, // Sprite
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_profile(super::quirks::Profile::Eti660);
+    emulator.set_quirks(super::quirks::Quirks {
+        clip_sprites: false,
+        ..emulator.quirks()
+    });
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert_eq!(emulator.pc.inner(), 0x600);
+    for _ in 0..4 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.pc.inner(), 0x606);
+
+    let display = emulator.display();
+    assert_eq!(display.dimensions(), (64, 48));
+    assert!(display.get(46, 46) && display.get(49, 46));
+    assert!(display.get(46, 47) && !display.get(47, 47));
+    // The last rows wrap to the top, instead of drawing rows 48 and 49
+    assert!(display.get(46, 0) && !display.get(47, 0));
+    assert!(display.get(46, 1) && display.get(47, 1));
+    assert!(!display.get(46, 32));
+}
+
+#[test]
+/// Test MEGA-CHIP words run as SYS on the other profiles
+fn test_megachip_sys() {
+    let program = [
+        0x00, 0x11, // MEGAON
+        0x01, 0x00, 0x00, 0x00, // LDHI I, #0
+        0x00, 0x10, // MEGAOFF
+    ];
+    for profile in [
+        super::quirks::Profile::Chip8,
+        super::quirks::Profile::XoChip,
+    ] {
+        let mut emulator = Emulator::with_profile(profile);
+        emulator.set_strict(true);
+        assert!(emulator.load_rom_bytes(&program).is_ok());
+        for _ in 0..4 {
+            assert!(emulator.tick().is_ok());
+        }
+        assert_eq!(emulator.pc.inner(), 0x208);
+        assert_eq!(emulator.i.inner(), 0);
+    }
+    // The ROM is still reported as MEGA-CHIP
+    assert!(super::rom::detect_variant(&program).uses(super::rom::Variant::MegaChip));
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test ROMs larger than the classic memory only load with the XO-CHIP profile
+fn test_rom_size_limit() {
+    let rom = vec![0; 0x1000];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom(&rom[..0xE00]).is_ok());
+    assert!(matches!(
+        emulator.load_rom_bytes(&rom),
+        Err(EmulatorError::RomTooLarge {
+            len: 0x1000,
+            max: 0xE00
+        })
+    ));
+    // One byte over the limit, the emulator is left untouched
+    emulator.pc = super::memory::Address::new(0x204);
+    assert!(matches!(
+        emulator.load_rom(&rom[..0xE01]),
+        Err(EmulatorError::RomTooLarge {
+            len: 0xE01,
+            max: 0xE00
+        })
+    ));
+    assert_eq!(emulator.pc.inner(), 0x204);
+
+    // The limit follows the entry point
+    emulator.set_profile(super::quirks::Profile::Eti660);
+    assert!(emulator.load_rom(&rom[..0xA00]).is_ok());
+    assert!(matches!(
+        emulator.load_rom(&rom[..0xA01]),
+        Err(EmulatorError::RomTooLarge {
+            len: 0xA01,
+            max: 0xA00
+        })
+    ));
+
+    emulator.set_profile(super::quirks::Profile::XoChip);
+    assert!(emulator.load_rom_bytes(&rom).is_ok());
+    let rom = vec![0; 0x10000];
+    assert!(emulator.load_rom(&rom[..0xFE00]).is_ok());
+    assert!(matches!(
+        emulator.load_rom_bytes(&rom),
+        Err(EmulatorError::RomTooLarge {
+            len: 0x10000,
+            max: 0xFE00
+        })
+    ));
+    // Long loads are invalid on plain CHIP-8
+    let mut emulator = initialize_empty_emulator();
+    emulator
+        .memory
+        .read_range(
+            super::memory::Address::ENTRY_POINT,
+            &[0xF0, 0x00, 0x12, 0x34],
+        )
+        .unwrap();
+    emulator.set_strict(true);
+    assert!(matches!(
+        emulator.tick(),
+        Err(EmulatorError::InvalidOpcode { word: 0xF000, .. })
+    ));
+
+    // Even at the end of the memory, where the operand would be out of bounds
+    let end = super::memory::Address::new(0xFFE);
+    assert!(emulator.load_at(end, &[0xF0, 0x00]).is_ok());
+    emulator.pc = end;
+    assert!(emulator.fetch_opcode().is_ok());
+    let result = emulator.tick();
+    let invalid = matches!(
+        result,
+        Err(EmulatorError::InvalidOpcode { word: 0xF000, pc }) if pc == end
+    );
+    assert!(invalid, "{result:?}");
+    assert_eq!(emulator.pc, end);
+}
+
+#[test]
+/// Test empty ROMs are rejected and odd lengths are not
+fn test_rom_length() {
+    let mut emulator = Emulator::new();
+    emulator.pc = super::memory::Address::new(0x204);
+    let result = emulator.load_rom_bytes(&[]);
+    assert!(matches!(result, Err(EmulatorError::EmptyRom)));
+    assert_eq!(emulator.pc.inner(), 0x204);
+    let result = emulator.load_rom_ihex(":00000001FF");
+    assert!(matches!(result, Err(EmulatorError::EmptyRom)));
+
+    assert!(emulator.load_rom_bytes(&[0x12u8, 0x00, 0xFF]).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x203));
+    assert_eq!(emulator.peek(0x202).unwrap(), 0xFF);
+}
+
+#[test]
+/// Test ROMs loaded from a slice, as without `std`
+fn test_load_rom_bytes() {
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&[0x12, 0x00, 0xAB, 0xCD]).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x204));
+
+    // A ROM that does not fit leaves the memory untouched
+    let rom = [0x11; 0xE01];
+    assert!(matches!(
+        emulator.load_rom_bytes(&rom),
+        Err(EmulatorError::RomTooLarge {
+            len: 0xE01,
+            max: 0xE00
+        })
+    ));
+    assert_eq!(
+        emulator.memory_view(0x200..0x204).unwrap(),
+        [0x12, 0x00, 0xAB, 0xCD]
+    );
+
+    // The next ROM clears what the previous one left
+    assert!(emulator.load_rom_bytes(&rom[..0xE00]).is_ok());
+    assert!(emulator.load_rom_bytes(&[0x12, 0x00]).is_ok());
+    assert_eq!(emulator.peek(0x202).unwrap(), 0);
+    assert_eq!(emulator.peek(0xFFF).unwrap(), 0);
+}
+
+#[test]
+/// Test that the emulator takes the configuration of every preset
+fn test_with_profile() {
+    for preset in super::quirks::PRESETS {
+        let mut emulator = Emulator::with_profile(preset.profile);
+        assert_eq!(emulator.profile(), preset.profile);
+        assert_eq!(emulator.quirks(), preset.quirks, "{:?}", preset.profile);
+        assert_eq!(emulator.memory.size(), preset.memory_size);
+        assert_eq!(emulator.display().dimensions(), (64, preset.lores_height));
+        assert!(emulator.load_rom_bytes(&[0x00, 0xE0]).is_ok());
+        assert_eq!(emulator.pc, preset.entry_point);
+    }
+}
+
+#[test]
+/// Test the vf_reset quirk clears VF after 8XY1, 8XY2 and 8XY3
+fn test_vf_reset_quirk() {
+    use super::quirks::Quirks;
+    let run = |vf_reset, operation: u8| {
+        let mut program = [
+            0x60, 0x0C, // LD V0, 0x0C
+            0x61, 0x0A, // LD V1, 0x0A
+            0x6F, 0x05, // LD VF, 5
+            0x80, 0x10, // OR/AND/XOR V0, V1
+            0x8F, 0x10, // OR/AND/XOR VF, V1
+            0x6F, 0x05, // LD VF, 5
+            0x81, 0xF0, // OR/AND/XOR V1, VF
+        ];
+        for index in [7, 9, 13] {
+            program[index] |= operation;
+        }
+        let quirks = Quirks {
+            vf_reset,
+            ..Default::default()
+        };
+        let mut emulator = run_with_quirks(quirks, &program, 4);
+        let v = |emulator: &Emulator, x| emulator.registers[RegisterIndex::new(x)];
+        let first = (v(&emulator, 0), v(&emulator, 0xF));
+        assert!(matches!(emulator.tick(), Ok(())));
+        let second = v(&emulator, 0xF);
+        for _ in 0..2 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+        (first, second, (v(&emulator, 1), v(&emulator, 0xF)))
+    };
+
+    // (operation, V0 op V1, VF op V1, V1 op VF)
+    let cases = [
+        (0x1, 0x0E, 0x0F, 0x0F),
+        (0x2, 0x08, 0x00, 0x00),
+        (0x3, 0x06, 0x0F, 0x0F),
+    ];
+    for (operation, result, flag_result, register_result) in cases {
+        assert_eq!(
+            run(false, operation),
+            ((result, 5), flag_result, (register_result, 5))
+        );
+        // VF is cleared after the operation, even when it is X or Y
+        assert_eq!(run(true, operation), ((result, 0), 0, (register_result, 0)));
+    }
+}
+
+#[test]
+/// Test sprites straddling the right edge, the bottom edge and the corner with the clip_sprites quirk
+fn test_clip_sprites_quirk() {
+    use super::quirks::Quirks;
+    let run = |clip_sprites, x, y| {
+        let program = [
+            0x60, x, // LD V0, x
+            0x61, y, // LD V1, y
+            0xA2, 0x0A, // LD I, 0x20A
+            0xD0, 0x12, // DRW V0, V1, 2
+            0x00, 0x00, // Padding
+            0xFF, 0xFF, // Sprite
+        ];
+        let quirks = Quirks {
+            clip_sprites,
+            ..Default::default()
+        };
+        run_with_quirks(quirks, &program, 4)
+    };
+
+    // (x, y, a pixel inside the display, a pixel past the edge once wrapped)
+    let cases = [
+        (60, 0, (63, 1), (0, 1)),
+        (0, 31, (7, 31), (7, 0)),
+        (60, 31, (63, 31), (0, 0)),
+    ];
+    for (x, y, inside, wrapped) in cases {
+        let emulator = run(false, x, y);
+        assert!(emulator.display().get(inside.0, inside.1));
+        assert!(emulator.display().get(wrapped.0, wrapped.1));
+        let emulator = run(true, x, y);
+        assert!(emulator.display().get(inside.0, inside.1));
+        assert!(!emulator.display().get(wrapped.0, wrapped.1));
+    }
+
+    // Only the position of the sprite wraps, (64 + 60, 32 + 31) is the corner again
+    let emulator = run(true, 124, 63);
+    assert!(emulator.display().get(63, 31) && !emulator.display().get(0, 0));
+}
+
+#[test]
+/// Test the add_i_overflow_vf quirk wraps I and sets VF instead of failing
+fn test_add_i_overflow_vf_quirk() {
+    use super::quirks::Quirks;
+    let run = |add_i_overflow_vf, increment| {
+        let program = [
+            0xAF, 0xFE, // LD I, 0xFFE
+            0x60, increment, // LD V0, increment
+            0x6F, 0x05, // LD VF, 5
+            0xF0, 0x1E, // ADD I, V0
+        ];
+        let quirks = Quirks {
+            add_i_overflow_vf,
+            ..Default::default()
+        };
+        let mut emulator = run_with_quirks(quirks, &program, 3);
+        let result = emulator.tick();
+        let flag = emulator.registers[RegisterIndex::FLAG];
+        (result, emulator.i.inner(), flag)
+    };
+
+    // Exactly at the boundary, VF is left alone without the quirk
+    assert!(matches!(run(false, 1), (Ok(()), 0xFFF, 5)));
+    assert!(matches!(run(true, 1), (Ok(()), 0xFFF, 0)));
+    // Overflow by one
+    assert!(matches!(
+        run(false, 2),
+        (
+            Err(EmulatorError::InvalidAddress {
+                address: 0x1000,
+                ..
+            }),
+            0xFFE,
+            5
+        )
+    ));
+    assert!(matches!(run(true, 2), (Ok(()), 0x000, 1)));
+    assert!(matches!(run(true, 0xFF), (Ok(()), 0x0FD, 1)));
+}
+
+#[test]
+/// Test 8XY6 and 8XYE with and without the shift_uses_vy quirk
+fn test_shift_quirk() {
+    use super::quirks::Quirks;
+    let program = [
+        0x60, 0x81, // LD V0, 0x81
+        0x61, 0x42, // LD V1, 0x42
+        0x82, 0x06, // SHR V2, V0
+        0x83, 0x0E, // SHL V3, V0
+        0x81, 0x16, // SHR V1, V1
+        0x6F, 0x03, // LD VF, 3
+        0x8F, 0x06, // SHR VF, V0
+    ];
+    let v = |emulator: &Emulator, x| emulator.registers[RegisterIndex::new(x)];
+
+    // VX is shifted in place, VY is ignored
+    let emulator = run_with_quirks(Quirks::default(), &program, 3);
+    assert_eq!((v(&emulator, 2), v(&emulator, 0xF)), (0, 0));
+    let emulator = run_with_quirks(Quirks::default(), &program, 4);
+    assert_eq!((v(&emulator, 3), v(&emulator, 0xF)), (0, 0));
+    let emulator = run_with_quirks(Quirks::default(), &program, 5);
+    assert_eq!((v(&emulator, 1), v(&emulator, 0xF)), (0x21, 0));
+    // The flag overwrites the result when X is F
+    let emulator = run_with_quirks(Quirks::default(), &program, 7);
+    assert_eq!(v(&emulator, 0xF), 1);
+
+    let quirks = Quirks {
+        shift_uses_vy: true,
+        ..Default::default()
+    };
+    // VY is shifted into VX, VF takes the bit shifted out of VY
+    let emulator = run_with_quirks(quirks, &program, 3);
+    assert_eq!((v(&emulator, 2), v(&emulator, 0xF)), (0x40, 1));
+    assert_eq!(v(&emulator, 0), 0x81);
+    let emulator = run_with_quirks(quirks, &program, 4);
+    assert_eq!((v(&emulator, 3), v(&emulator, 0xF)), (0x02, 1));
+    // X == Y behaves as the in place shift
+    let emulator = run_with_quirks(quirks, &program, 5);
+    assert_eq!((v(&emulator, 1), v(&emulator, 0xF)), (0x21, 0));
+    let emulator = run_with_quirks(quirks, &program, 7);
+    assert_eq!(v(&emulator, 0xF), 1);
+}
+
+#[test]
+/// Test the final value of I after FX55 and FX65 for every memory_increments_i mode
+fn test_memory_increment_quirk() {
+    use super::quirks::{IndexIncrement, Quirks};
+    let store = [
+        0xA3, 0x00, // LD I, 0x300
+        0xF3, 0x55, // LD [I], V3
+    ];
+    let load = [
+        0xA3, 0x00, // LD I, 0x300
+        0xF3, 0x65, // LD V3, [I]
+    ];
+    let modes = [
+        (IndexIncrement::Unchanged, 0x300),
+        (IndexIncrement::X, 0x303),
+        (IndexIncrement::XPlusOne, 0x304),
+    ];
+    for (memory_increments_i, expected) in modes {
+        let quirks = Quirks {
+            memory_increments_i,
+            ..Default::default()
+        };
+        for program in [store, load] {
+            let emulator = run_with_quirks(quirks, &program, 2);
+            assert_eq!(emulator.i.inner(), expected, "{:?}", memory_increments_i);
+        }
+    }
+
+    // I walks through an array with consecutive loads
+    let program = [
+        0xA3, 0x00, // LD I, 0x300
+        0xF0, 0x65, // LD V0, [I]
+        0xF1, 0x65, // LD V1, [I]
+    ];
+    let quirks = Quirks {
+        memory_increments_i: IndexIncrement::XPlusOne,
+        ..Default::default()
+    };
+    let mut emulator = Emulator::new();
+    emulator.set_quirks(quirks);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    emulator
+        .memory
+        .read_range(super::memory::Address::new(0x300), &[1, 2, 3])
+        .unwrap();
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 3);
+    assert_eq!(emulator.i.inner(), 0x303);
+}
+
+#[test]
+/// Test BNNN as NNN + V0 and as XNN + VX with the jump_uses_vx quirk
+fn test_jump_quirk() {
+    use super::quirks::Quirks;
+    let program = [
+        0x60, 0x10, // LD V0, 0x10
+        0x62, 0x20, // LD V2, 0x20
+        0xB2, 0xF0, // JP V0, 0x2F0 (JP V2, 0x2F0 with the quirk)
+    ];
+    let quirks = Quirks {
+        jump_uses_vx: true,
+        ..Default::default()
+    };
+    let emulator = run_with_quirks(Quirks::default(), &program, 3);
+    assert_eq!(emulator.pc.inner(), 0x300);
+    let emulator = run_with_quirks(quirks, &program, 3);
+    assert_eq!(emulator.pc.inner(), 0x310);
+
+    // Jumping past the memory is an invalid address in both modes
+    let program = [
+        0x60, 0x10, // LD V0, 0x10
+        0x6F, 0x10, // LD VF, 0x10
+        0xBF, 0xF0, // JP V0, 0xFF0 (JP VF, 0xFF0 with the quirk)
+    ];
+    for quirks in [Quirks::default(), quirks] {
+        let mut emulator = run_with_quirks(quirks, &program, 2);
+        assert!(matches!(
+            emulator.tick(),
+            Err(EmulatorError::InvalidAddress {
+                address: 0x1000,
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+/// Test the display_wait quirk draws at most one sprite per frame
+fn test_display_wait_quirk() {
+    use super::emulator::State;
+    use super::quirks::Quirks;
+    let program = [
+        0xD0, 0x01, // DRW V0, V0, 1
+        0x70, 0x01, // ADD V0, 1
+        0x12, 0x00, // JP 0x200
+    ];
+    // Counts the sprites drawn in a number of frames, a frame being one tick
+    let count = |display_wait, frames| {
+        let quirks = Quirks {
+            display_wait,
+            ..Default::default()
+        };
+        let mut emulator = run_with_quirks(quirks, &program, 0);
+        let mut draws = 0;
+        for _ in 0..frames {
+            let pc = emulator.pc.inner();
+            assert!(matches!(emulator.tick(), Ok(())));
+            if pc == 0x200 && emulator.pc.inner() == 0x202 {
+                draws += 1;
+            }
+        }
+        draws
+    };
+    assert_eq!(count(false, 12), 4);
+    // Every DRW costs the tick of the next vertical blank
+    assert_eq!(count(true, 12), 3);
+
+    // Frontends that run several instructions per frame end the wait with tick_timers
+    let quirks = Quirks {
+        display_wait: true,
+        ..Default::default()
+    };
+    let mut emulator = run_with_quirks(quirks, &program, 1);
+    assert!(matches!(emulator.state(), State::WaitingVblank));
+    emulator.tick_timers();
+    assert!(matches!(emulator.state(), State::Running));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.pc.inner(), 0x204);
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test the recommended profile and quirks are applied before loading the ROM
+fn test_load_rom_with_autodetect() {
+    use super::compat::Confidence;
+    use super::quirks::Profile;
+    let program = [
+        0x00, 0xFF, // HIGH
+        0x81, 0x26, // SHR V1, V2
+        0x12, 0x00, // JP 0x200
+    ];
+    let mut emulator = Emulator::new();
+    let recommendation = emulator
+        .load_rom_with_autodetect(program.as_slice())
+        .unwrap();
+    assert_eq!(recommendation.confidence, Confidence::Medium);
+    assert_eq!(emulator.profile(), Profile::SuperChipModern);
+    assert_eq!(emulator.quirks(), recommendation.quirks);
+    assert!(emulator.quirks().shift_uses_vy);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(emulator.display().is_hires());
+}
+
+#[test]
+/// Test DRW sets VF when it erases a pixel
+fn test_draw_collision() {
+    let program = [
+        0xA2, 0x08, // LD I, 0x208
+        0xD0, 0x02, // DRW V0, V0, 2
+        0xD0, 0x02, // DRW V0, V0, 2
+        0x00, 0x00, // Padding
+        0x3C, 0x42, // Sprite
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.registers[RegisterIndex::FLAG], 0);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::FLAG], 1);
+    assert!(emulator.display().pixels().all(|(_, _, pixel)| !pixel));
+}
+
+#[test]
+/// Test DRW never panics, whatever the coordinates, resolution and quirks
+fn test_draw_any_coordinates() {
+    use super::opcode::Opcode;
+    use super::quirks::{Profile, Quirks};
+    let mut emulator = Emulator::with_profile(Profile::SuperChipModern);
+    assert!(emulator.load_rom_bytes(&[0x00, 0xE0]).is_ok());
+    emulator.i = super::memory::Address::new(0x300);
+    let (x, y) = (RegisterIndex::new(0), RegisterIndex::new(1));
+    for hires in [false, true] {
+        emulator.display.set_hires(hires);
+        for clip_sprites in [false, true] {
+            emulator.set_quirks(Quirks {
+                clip_sprites,
+                ..Default::default()
+            });
+            for value in 0..=u16::MAX {
+                let [vx, vy] = value.to_be_bytes();
+                emulator.registers[x] = vx;
+                emulator.registers[y] = vy;
+                emulator.pc = super::memory::Address::ENTRY_POINT;
+                let n = vx & 0xF;
+                assert!(emulator.execute_opcode(Opcode::Drw { x, y, n }).is_ok());
+            }
+        }
+    }
+}
+
+#[test]
+/// Test the rows of a sprite past the bottom edge wrap to the top
+fn test_draw_past_bottom() {
+    let program = [
+        0x60, 0x1E, // LD V0, 30
+        0xA2, 0x08, // LD I, 0x208
+        0xD0, 0x04, // DRW V0, V0, 4
+        0x00, 0x00, // Padding
+        0x80, 0x40, 0x20, 0x10, // Sprite
+    ];
+    let emulator = run_with_quirks(super::quirks::Quirks::default(), &program, 3);
+    let lit: Vec<_> = emulator
+        .display()
+        .pixels()
+        .filter(|&(_, _, pixel)| pixel)
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    assert_eq!(lit, [(32, 0), (33, 1), (30, 30), (31, 31)]);
+}
+
+#[test]
+/// Test DRW marks exactly the rows the sprite covers as dirty
+fn test_draw_dirty_rows() {
+    let program = [
+        0x60, 0x0A, // LD V0, 10
+        0xA2, 0x08, // LD I, 0x208
+        0xD0, 0x03, // DRW V0, V0, 3
+        0x00, 0x00, // Padding
+        0xFF, 0x00, 0xFF, // Sprite
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    emulator.take_dirty_rows();
+    assert!(matches!(emulator.tick(), Ok(())));
+    let dirty = emulator.take_dirty_rows();
+    assert_eq!(dirty.iter().collect::<Vec<_>>(), [10, 11, 12]);
+}
+
+#[test]
+fn test_double_buffering() {
+    use super::opcode::Opcode;
+    let program = [
+        0x60, 0x00, // LD V0, 0
+        0x61, 0x08, // LD V1, 8
+        0xA2, 0x08, // LD I, 0x208
+        0x00, 0x00, // Padding
+        0xFF, // Sprite
+    ];
+    let mut emulator = Emulator::new();
+    emulator.set_double_buffered(true);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    let (v0, v1) = (RegisterIndex::new(0), RegisterIndex::new(1));
+    let left = Opcode::Drw { x: v0, y: v0, n: 1 };
+    let right = Opcode::Drw { x: v1, y: v0, n: 1 };
+    let lit = |emulator: &Emulator| -> Vec<_> {
+        let pixels = emulator.display().pixels();
+        pixels.filter(|pixel| pixel.2).collect()
+    };
+
+    // Two draws within one frame, the display still shows the last presented frame
+    assert!(emulator.execute_opcode(left).is_ok());
+    assert!(lit(&emulator).is_empty());
+    assert!(emulator.execute_opcode(right).is_ok());
+    assert!(lit(&emulator).is_empty());
+
+    // The vertical blank presents both
+    emulator.tick_timers();
+    let pixels = lit(&emulator);
+    assert_eq!(pixels.len(), 16);
+    assert!(pixels.iter().all(|&(x, y, _)| x < 16 && y == 0));
+
+    // Without double buffering the draws show up immediately
+    emulator.set_double_buffered(false);
+    assert!(emulator.execute_opcode(left).is_ok());
+    assert!(!emulator.display().get(0, 0));
+}
+
+#[test]
+fn test_display_version() {
+    let program = [
+        0x00, 0xE0, // CLS
+        0x60, 0x00, // LD V0, 0
+        0xD0, 0x01, // DRW V0, V0, 1
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let (mut renderer, mut recorder) = (0, 0);
+    let mut changes = (0, 0);
+    for tick in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+        let version = emulator.display().version();
+        if version != renderer {
+            renderer = version;
+            changes.0 += 1;
+        }
+        // The recorder only polls every other frame
+        if tick % 2 == 0 && version != recorder {
+            recorder = version;
+            changes.1 += 1;
+        }
+    }
+    // CLS, LD, DRW: the renderer sees both changes, the recorder catches up on the last frame
+    assert_eq!(changes, (2, 2));
+    assert_eq!(renderer, recorder);
+}
+
+#[test]
+/// Test a key tapped between two ticks with the event queue
+fn test_key_events() {
+    use super::keyboard::{Key, KeyEvent};
+
+    let program = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0x9E, // SKP V0
+        0x12, 0x02, // JP 0x202
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x02, // JP 0x202
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.pc.inner(), 0x202);
+    emulator.push_key_event(KeyEvent::Down(Key::K5));
+    emulator.push_key_event(KeyEvent::Up(Key::K5));
+    for _ in 0..20 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    // SKP saw the key once
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 1);
+    assert!(!emulator.keyboard.is_set(0x5));
+    assert_eq!(emulator.frame(), 23);
+
+    let program = [
+        0xF1, 0x0A, // LD V1, K
+        0x72, 0x01, // ADD V2, 1
+        0x12, 0x04, // JP 0x204
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    let waiting = matches!(emulator.state, super::emulator::State::WaitingKey { .. });
+    assert!(waiting);
+    emulator.push_key_event(KeyEvent::Down(Key::K7));
+    emulator.push_key_event(KeyEvent::Up(Key::K7));
+    for _ in 0..5 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    // FX0A got the key and completed once
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 7);
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 1);
+    assert!(!emulator.keyboard.is_set(0x7));
+
+    // Events for a later frame wait for it
+    let frame = emulator.frame();
+    emulator.push_key_event_at(frame + 2, KeyEvent::Down(Key::K1));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(!emulator.keyboard.is_set(0x1));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(emulator.keyboard.is_set(0x1));
+}
+
+#[test]
+/// Test a key tapped between two frames is seen by every instruction of the next frame
+fn test_key_events_run_frame() {
+    use super::keyboard::{Key, KeyEvent};
+
+    let program = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0x9E, // SKP V0
+        0x12, 0x08, // JP 0x208
+        0x61, 0x01, // LD V1, 1
+        0x12, 0x08, // JP 0x208
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    emulator.push_key_event(KeyEvent::Down(Key::K5));
+    emulator.push_key_event(KeyEvent::Up(Key::K5));
+    // The tick runs LD, SKP runs in the first step
+    assert!(emulator.run_frame(12).is_ok());
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 1);
+    assert!(emulator.keyboard.is_set(0x5));
+    assert!(emulator.run_frame(12).is_ok());
+    assert!(!emulator.keyboard.is_set(0x5));
+}
+
+#[test]
+/// Test keys pressed for a number of ticks and frames
+fn test_press_for() {
+    use super::keyboard::Key;
+
+    let program = [
+        0xF1, 0x0A, // LD V1, K
+        0x72, 0x01, // ADD V2, 1
+        0x12, 0x00, // JP 0x200
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(matches!(emulator.tick(), Ok(())));
+    // Released before FX0A runs again
+    emulator.press_for(Key::K5, 3);
+    for _ in 0..10 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 5);
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 1);
+    assert!(!emulator.keyboard.is_set(0x5));
+
+    // Overlapping presses
+    emulator.press_for(Key::K2, 1);
+    emulator.press_for_frames(Key::K8, 3);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 2);
+    assert!(emulator.keyboard.is_set(0x8));
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    assert!(!emulator.keyboard.is_set(0x2) && emulator.keyboard.is_set(0x8));
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(!emulator.keyboard.is_set(0x8));
+}
+
+#[test]
+/// Test a key pressed for a tick stays pressed for the whole frame
+fn test_press_for_run_frame() {
+    use super::keyboard::Key;
+
+    let program = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0xA1, // SKNP V0
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x02, // JP 0x202
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    emulator.press_for(Key::K5, 1);
+    // LD, then SKNP, ADD and JP 3 times and SKNP, ADD once more
+    assert!(emulator.run_frame(12).is_ok());
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 4);
+    assert!(emulator.run_frame(12).is_ok());
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 4);
+    assert!(!emulator.keyboard.is_set(0x5));
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test a scripted session replays to the same frames
+fn test_record_replay() {
+    use super::{
+        compat::crc32,
+        keyboard::{Key, KeyEvent},
+        replay::Recording,
+    };
+
+    let program = [
+        0x63, 0x05, // LD V3, 5
+        0xC0, 0x3F, // RND V0, 0x3F
+        0xC1, 0x1F, // RND V1, 0x1F
+        0xF0, 0x29, // LD F, V0
+        0xE3, 0xA1, // SKNP V3
+        0xD0, 0x15, // DRW V0, V1, 5
+        0x12, 0x02, // JP 0x202
+    ];
+    let frames = |emulator: &mut Emulator, script: bool| {
+        (0..120)
+            .map(|tick| {
+                if script {
+                    match tick {
+                        10 => emulator.press_for(Key::K5, 20),
+                        50 => {
+                            emulator.push_key_event(KeyEvent::Down(Key::K5));
+                            emulator.push_key_event(KeyEvent::Up(Key::K5));
+                        }
+                        80 => emulator.press_key(Key::K5),
+                        90 => emulator.release_key(Key::K5),
+                        _ => {}
+                    }
+                }
+                assert!(matches!(emulator.tick(), Ok(())));
+                crc32(&emulator.display().to_bytes())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    emulator.start_recording();
+    let recorded = frames(&mut emulator, true);
+    let recording = emulator.stop_recording().unwrap();
+    assert_eq!(recording.frames(), 120);
+    let mut bytes = Vec::new();
+    assert!(recording.save(&mut bytes).is_ok());
+
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let recording = Recording::load(bytes.as_slice()).unwrap();
+    assert!(emulator.replay(recording.clone()).is_ok());
+    assert!(emulator.is_replaying());
+    let replayed = frames(&mut emulator, false);
+    assert_eq!(replayed, recorded);
+    // Sprites were drawn while the key was pressed
+    let mut changes = recorded.clone();
+    changes.dedup();
+    assert!(changes.len() > 3);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert!(!emulator.is_replaying());
+
+    // Another ROM
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program[..12]).is_ok());
+    assert!(matches!(
+        emulator.replay(recording),
+        Err(EmulatorError::InvalidRecording(_))
+    ));
+}
+
+#[test]
+/// Test a recording made with run_frame has one keyboard state per frame
+fn test_record_run_frame() {
+    use super::keyboard::Key;
+
+    let program = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0xA1, // SKNP V0
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x02, // JP 0x202
+    ];
+    let run = |emulator: &mut Emulator, script: bool| {
+        for frame in 0..10 {
+            if script && frame == 3 {
+                emulator.press_for(Key::K5, 2);
+            }
+            assert!(emulator.run_frame(12).is_ok());
+        }
+        emulator.registers[RegisterIndex::new(1)]
+    };
+
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    emulator.start_recording();
+    let recorded = run(&mut emulator, true);
+    let recording = emulator.stop_recording().unwrap();
+    assert_eq!(recording.frames(), 10);
+    // The frame and the state of the press and of the release
+    assert_eq!(recording.to_bytes().len(), 29 + 2 * 10);
+
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(emulator.replay(recording).is_ok());
+    assert_eq!(run(&mut emulator, false), recorded);
+    assert!(recorded > 0);
+}
+
+#[test]
+/// Test FX0A takes the lowest pressed key of a whole keyboard state
+fn test_key_mask() {
+    let program = [
+        0xF1, 0x0A, // LD V1, K
+        0x12, 0x00, // JP 0x200
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for (mask, expected) in [(0x0000, 0xFF), (0x8400, 0xA), (0x8001, 0x0), (0x8000, 0xF)] {
+        emulator.registers[RegisterIndex::new(1)] = 0xFF;
+        // Released while FX0A starts waiting
+        emulator.set_key_mask(0);
+        for _ in 0..3 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+        emulator.set_key_mask(mask);
+        assert!(matches!(emulator.tick(), Ok(())));
+        assert_eq!(emulator.registers[RegisterIndex::new(1)], expected);
+        assert_eq!(emulator.keyboard().as_mask(), mask);
+    }
+    let pressed: Vec<_> = emulator.keyboard().pressed().collect();
+    assert_eq!(pressed, [0xF]);
+}
+
+#[test]
+/// Test a state saved while FX0A waits completes the same way once restored
+fn test_save_state_key_wait() {
+    use super::keyboard::{Key, KeyEvent};
+
+    let program = [
+        0xA2, 0x0A, // LD I, 0x20A
+        0xD0, 0x04, // DRW V0, V0, 4
+        0xF1, 0x0A, // LD V1, K
+        0xF1, 0x29, // LD F, V1
+        0xD0, 0x05, // DRW V0, V0, 5
+        0xF0, 0x90, 0x90, 0xF0, // Sprite
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    emulator.set_key_mask(0x0100);
+    for _ in 0..2 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    emulator.set_key_mask(0);
+    assert!(matches!(emulator.tick(), Ok(())));
+    // A tap queued and a key held, both pending when the state is saved
+    emulator.press_for(Key::K9, 2);
+    emulator.push_key_event(KeyEvent::Down(Key::K3));
+    emulator.push_key_event(KeyEvent::Up(Key::K3));
+    let state = emulator.save_state();
+
+    let mut restored = Emulator::new();
+    assert!(restored.load_rom_bytes(&program).is_ok());
+    assert!(restored.load_state(&state).is_ok());
+    assert_eq!(restored.save_state(), state);
+    for emulator in [&mut emulator, &mut restored] {
+        for _ in 0..4 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+    }
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 3);
+    assert_eq!(restored.registers[RegisterIndex::new(1)], 3);
+    assert_eq!(restored.pc.inner(), emulator.pc.inner());
+    assert_eq!(restored.save_state(), emulator.save_state());
+    assert_eq!(restored.display().to_pbm(), emulator.display().to_pbm());
+
+    // Invalid states leave the emulator untouched
+    let before = restored.save_state();
+    let mut truncated = state.clone();
+    truncated.pop();
+    // The profile is the fourth byte from the end
+    let mut profile = state.clone();
+    let index = profile.len() - 4;
+    profile[index] = 0xFF;
+    for data in [&state[..10], &truncated, &profile] {
+        assert!(matches!(
+            restored.load_state(data),
+            Err(EmulatorError::InvalidSaveState(_))
+        ));
+        assert_eq!(restored.save_state(), before);
+    }
+}
+
+#[test]
+/// Test save states restore the profile and the quirks
+fn test_save_state_settings() {
+    use super::quirks::{IndexIncrement, Profile, Quirks};
+
+    let program = [
+        0xF0, 0x00, 0x12, 0x34, // LD I, LONG 0x1234
+        0x12, 0x04, // JP 0x204
+    ];
+    let quirks = Quirks {
+        wait_key_held: true,
+        memory_increments_i: IndexIncrement::X,
+        ..Profile::XoChip.quirks()
+    };
+    let mut emulator = Emulator::with_profile(Profile::XoChip);
+    emulator.set_quirks(quirks);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(matches!(emulator.tick(), Ok(())));
+    let state = emulator.save_state();
+
+    let mut restored = Emulator::new();
+    assert!(restored.load_state(&state).is_ok());
+    assert_eq!(restored.profile(), Profile::XoChip);
+    assert_eq!(restored.quirks(), quirks);
+    assert_eq!(restored.memory.size(), Profile::XoChip.memory_size());
+    assert_eq!(restored.i.inner(), 0x1234);
+    assert_eq!(restored.save_state(), state);
+
+    // And back to the CHIP-8 profile
+    let mut other = Emulator::new();
+    assert!(other.load_rom_bytes(&program[4..]).is_ok());
+    assert!(restored.load_state(&other.save_state()).is_ok());
+    assert_eq!(restored.profile(), Profile::Chip8);
+    assert_eq!(restored.quirks(), other.quirks());
+    assert_eq!(restored.memory.size(), Profile::Chip8.memory_size());
+}
+
+#[test]
+/// Test FX0A waits for a fresh press when a key is held as it runs
+fn test_wait_key_held() {
+    use super::keyboard::Key;
+
+    let program = [
+        0xF1, 0x0A, // LD V1, K
+        0x72, 0x01, // ADD V2, 1
+        0x12, 0x04, // JP 0x204
+    ];
+    let run = |wait_key_held| {
+        let mut emulator = initialize_empty_emulator();
+        emulator.set_quirks(super::quirks::Quirks {
+            wait_key_held,
+            ..emulator.quirks()
+        });
+        assert!(emulator.load_rom_bytes(&program).is_ok());
+        emulator.press_key(Key::K5);
+        for _ in 0..50 {
+            assert!(matches!(emulator.tick(), Ok(())));
+        }
+        emulator
+    };
+
+    let mut emulator = run(false);
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 0);
+    // A key pressed during the wait completes it
+    emulator.press_key(Key::K7);
+    emulator.release_key(Key::K5);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 7);
+
+    // Released and pressed again
+    let mut emulator = run(false);
+    emulator.release_key(Key::K5);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 0);
+    emulator.press_key(Key::K5);
+    assert!(matches!(emulator.tick(), Ok(())));
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 5);
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 1);
+
+    // The held key completes the wait with the quirk
+    let emulator = run(true);
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 5);
+    assert_eq!(emulator.registers[RegisterIndex::new(2)], 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test key events pushed from another thread reach the program
+fn test_shared_keyboard() {
+    use super::keyboard::Key;
+
+    let program = [
+        0x60, 0x05, // LD V0, 5
+        0xE0, 0x9E, // SKP V0
+        0x12, 0x02, // JP 0x202
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x02, // JP 0x202
+    ];
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..3 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    let shared = emulator.shared_keyboard();
+    std::thread::spawn(move || {
+        shared.press(Key::K5);
+        shared.release(Key::K5);
+    })
+    .join()
+    .unwrap();
+    for _ in 0..20 {
+        assert!(matches!(emulator.tick(), Ok(())));
+    }
+    // The tap was seen once
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_memory_io() {
+    use std::io::{ErrorKind, Read, Write};
+
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom(&[0x12u8, 0x34, 0x56][..]).is_ok());
+    let mut reader = emulator.memory_reader(0x200..0x203).unwrap();
+    let mut buf = [0; 2];
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+    assert_eq!(buf, [0x12, 0x34]);
+    assert_eq!(reader.address(), 0x202);
+    // The last read stops at the end of the range
+    assert_eq!(reader.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], 0x56);
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+    let mut bytes = Vec::new();
+    let mut reader = emulator.memory_reader(0xFFC..0x1000).unwrap();
+    assert_eq!(reader.read_to_end(&mut bytes).unwrap(), 4);
+    let result = emulator.memory_reader(0xFFC..0x1001).map(|_| ());
+    let error = result.unwrap_err();
+    assert!(matches!(error, EmulatorError::AddressOutOfRange { address, .. } if address == 0x1000));
+
+    let mut writer = emulator.memory_writer(0xFFE).unwrap();
+    assert_eq!(writer.write(&[1, 2, 3]).unwrap(), 2);
+    assert_eq!(writer.address(), 0x1000);
+    assert_eq!(writer.write(&[3]).unwrap(), 0);
+    let mut writer = emulator.memory_writer(0xFFD).unwrap();
+    let error = writer.write_all(&[4, 5, 6, 7]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WriteZero);
+    assert_eq!(emulator.memory_view(0xFFC..0x1000).unwrap(), [0, 4, 5, 6]);
+    let mut writer = emulator.memory_writer(0x1000).unwrap();
+    assert_eq!(writer.write(&[1]).unwrap(), 0);
+
+    // A ROM streamed out and back in round trips
+    let mut rom = Vec::new();
+    let mut reader = emulator.memory_reader(0x200..0x203).unwrap();
+    std::io::copy(&mut reader, &mut rom).unwrap();
+    let mut writer = emulator.memory_writer(0x300).unwrap();
+    std::io::copy(&mut rom.as_slice(), &mut writer).unwrap();
+    assert_eq!(emulator.memory_view(0x300..0x303).unwrap(), rom);
+
+    emulator.set_write_protection(true);
+    assert!(matches!(
+        emulator.memory_writer(0x1FF),
+        Err(EmulatorError::ProtectedWrite { .. })
+    ));
+    assert!(emulator.memory_writer(0x200).is_ok());
+}
+
+#[test]
+/// Test the edges of a 64KB memory and out of range keys return errors instead of panicking
+fn test_no_panics() {
+    use super::{memory::Address, quirks::Profile};
+
+    let mut emulator = Emulator::with_profile(Profile::XoChip);
+    assert!(emulator.load_rom_bytes(&[0x12u8, 0x00]).is_ok());
+    // F000 NNNN with its address past the end of the memory
+    let last = Address::new_long(0xFFFE);
+    assert!(emulator.load_at(last, &[0xF0, 0x00]).is_ok());
+    emulator.pc = last;
+    let result = emulator.tick();
+    assert!(matches!(
+        result,
+        Err(EmulatorError::OutOfBounds {
+            address: 0xFFFF,
+            ..
+        })
+    ));
+    // An instruction in the last word moves the program counter past the memory
+    assert!(emulator.load_at(last, &[0x60, 0x00]).is_ok());
+    emulator.pc = last;
+    let result = emulator.tick();
+    assert!(matches!(
+        result,
+        Err(EmulatorError::InvalidAddress {
+            address: 0xFFFF,
+            ..
+        })
+    ));
+
+    // Sprites wrap around a 64KB memory, and stop at the end of a 4KB one
+    let draw = super::opcode::Opcode::try_from(0xD012).unwrap();
+    emulator.pc = Address::ENTRY_POINT;
+    emulator.i = Address::new_long(0xFFFF);
+    assert!(emulator.execute_opcode(draw).is_ok());
+    let mut emulator = Emulator::with_profile(Profile::SuperChipModern);
+    emulator.i = Address::new_long(0xFFFF);
+    let result = emulator.execute_opcode(draw);
+    assert!(matches!(
+        result,
+        Err(EmulatorError::InvalidAddress {
+            address: 0xFFFF,
+            ..
+        })
+    ));
+    emulator.i = Address::new(0xFFF);
+    let result = emulator.execute_opcode(draw);
+    assert!(matches!(
+        result,
+        Err(EmulatorError::InvalidAddress {
+            address: 0x1000,
+            ..
+        })
+    ));
+
+    let mut keyboard = super::keyboard::KeyBoard::default();
+    keyboard.set(0x15);
+    assert!(keyboard.is_set(0x5) && keyboard.is_set(0xF5));
+    keyboard.hold(0xFF, 1);
+    keyboard.unset(0x25);
+    assert_eq!(keyboard.as_mask(), 1 << 0xF);
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test loading ROMs without their padding
+fn test_load_rom_normalized() {
+    let mut padded = vec![0x60, 0x01, 0x12, 0x02, 0xAB];
+    padded.resize(0x2000, 0);
+    let mut emulator = Emulator::new();
+    let result = emulator.load_rom(padded.as_slice());
+    assert!(matches!(
+        result,
+        Err(EmulatorError::RomTooLarge { len: 0x2000, .. })
+    ));
+
+    emulator.set_normalize_roms(true);
+    assert!(emulator.load_rom(padded.as_slice()).is_ok());
+    assert_eq!(emulator.loaded_range(), Some(0x200..0x206));
+    assert_eq!(emulator.rom_hash().len, 6);
+    assert_eq!(emulator.memory_view(0x204..0x206).unwrap(), [0xAB, 0x00]);
+}
+
+#[test]
+/// Test the stack depth and the context of stack errors
+fn test_stack_depth() {
+    use super::{memory::Address, opcode::Opcode, quirks::Profile};
+
+    // Recurses forever, CALL 0x200
+    let program = [0x22, 0x00];
+    let mut emulator = Emulator::with_config(EmulatorConfig {
+        profile: Profile::SuperChipModern,
+        stack_depth: 12,
+    });
+    assert_eq!(emulator.stack().depth(), 12);
+    assert_eq!(emulator.profile(), Profile::SuperChipModern);
+    assert_eq!(
+        emulator.quirks(),
+        Emulator::with_profile(Profile::SuperChipModern).quirks()
+    );
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..12 {
+        assert!(emulator.tick().is_ok());
+    }
+    assert_eq!(emulator.stack().len(), 12);
+    let error = emulator.tick().unwrap_err();
+    let message = error.to_string();
+    let EmulatorError::StackOverFlow {
+        depth,
+        pc,
+        word,
+        returns,
+    } = error
+    else {
+        panic!("{error}");
+    };
+    assert_eq!((depth, pc, word), (12, Address::ENTRY_POINT, 0x2200));
+    assert_eq!(returns, [Address::new(0x202); 12]);
+    let expected = "The call 0x2200 (CALL #200) at 0x200 is nested more than 12 deep";
+    assert!(message.contains(expected), "{message}");
+    assert!(message.ends_with("0x202 0x202."), "{message}");
+
+    // The default depth is 16
+    assert_eq!(EmulatorConfig::default().stack_depth, 16);
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..16 {
+        assert!(emulator.tick().is_ok());
+    }
+    let result = emulator.tick();
+    let overflow = matches!(result, Err(EmulatorError::StackOverFlow { depth, .. }) if depth == 16);
+    assert!(overflow);
+    emulator.set_stack_depth(usize::MAX);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..1000 {
+        assert!(emulator.tick().is_ok());
+    }
+
+    let mut emulator = initialize_empty_emulator();
+    emulator.pc = Address::new(0x300);
+    let result = emulator.execute_opcode(Opcode::Ret);
+    let pc = Address::new(0x300);
+    assert!(matches!(result, Err(EmulatorError::StackUnderFlow { pc: p, .. }) if p == pc));
+}
+
+#[test]
+/// Test save states keep the return addresses
+fn test_save_state_stack() {
+    use super::memory::Address;
+
+    let program = [
+        0x22, 0x04, // CALL 0x204
+        0x12, 0x02, // JP 0x202
+        0x22, 0x08, // CALL 0x208
+        0x00, 0xEE, // RET
+        0x00, 0xEE, // RET
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..2 {
+        assert!(emulator.tick().is_ok());
+    }
+    let returns = [Address::new(0x202), Address::new(0x206)];
+    assert_eq!(emulator.stack().as_slice(), returns);
+    let state = emulator.save_state();
+
+    let mut restored = Emulator::new();
+    assert!(restored.load_state(&state).is_ok());
+    assert_eq!(restored.stack(), emulator.stack());
+    // Both RET pop the restored addresses
+    for _ in 0..2 {
+        assert!(restored.tick().is_ok());
+    }
+    assert_eq!(restored.pc(), Address::new(0x202));
+    assert!(restored.stack().is_empty());
+
+    // A shallower stack can not hold the saved one
+    let mut shallow = Emulator::new();
+    shallow.set_stack_depth(1);
+    let result = shallow.load_state(&state);
+    assert!(matches!(result, Err(EmulatorError::InvalidSaveState(_))));
+    assert!(shallow.stack().is_empty());
+}
+
+#[test]
+/// Test RND with seeded and custom random number generators
+fn test_rng_sources() {
+    use super::rand::RngSource;
+
+    struct Counter(u8);
+
+    impl RngSource for Counter {
+        fn next_u8(&mut self) -> u8 {
+            self.0 = self.0.wrapping_add(1);
+            self.0
+        }
+    }
+
+    let program = [
+        0xC0, 0xFF, // RND V0, 0xFF
+        0xC1, 0x0F, // RND V1, 0x0F
+        0x12, 0x00, // JP 0x200
+    ];
+    let run = |emulator: &mut Emulator| {
+        assert!(emulator.load_rom_bytes(&program).is_ok());
+        (0..30)
+            .filter_map(|tick| {
+                assert!(emulator.tick().is_ok());
+                let register = RegisterIndex::new(tick % 3);
+                (tick % 3 != 2).then(|| emulator.v_registers()[register])
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut first = Emulator::new();
+    first.seed_rng(7);
+    let mut second = Emulator::new();
+    second.seed_rng(7);
+    let values = run(&mut first);
+    assert_eq!(run(&mut second), values);
+    assert!(values.iter().skip(1).step_by(2).all(|&value| value <= 0x0F));
+    second.seed_rng(8);
+    assert_ne!(run(&mut second), values);
+
+    let mut emulator = Emulator::new();
+    emulator.set_rng(Box::new(Counter(0)));
+    assert_eq!(run(&mut emulator)[..6], [1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+/// Test a restored save state draws the same random numbers as the unbroken run
+fn test_save_state_rng() {
+    let program = [
+        0xC0, 0xFF, // RND V0, 0xFF
+        0x12, 0x00, // JP 0x200
+    ];
+    let mut emulator = Emulator::new();
+    emulator.seed_rng(11);
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    for _ in 0..50 {
+        assert!(emulator.tick().is_ok());
+    }
+    let state = emulator.save_state();
+    let draw = |emulator: &mut Emulator| {
+        (0..100)
+            .map(|_| {
+                for _ in 0..2 {
+                    assert!(emulator.tick().is_ok());
+                }
+                emulator.v_registers()[RegisterIndex::new(0)]
+            })
+            .collect::<Vec<_>>()
+    };
+    let expected = draw(&mut emulator);
+
+    let mut restored = Emulator::new();
+    restored.seed_rng(12);
+    assert!(restored.load_state(&state).is_ok());
+    assert_eq!(draw(&mut restored), expected);
+
+    // A source without state can not restore it
+    struct Zeros;
+
+    impl super::rand::RngSource for Zeros {
+        fn next_u8(&mut self) -> u8 {
+            0
+        }
+    }
+
+    let mut stateless = Emulator::new();
+    stateless.set_rng(Box::new(Zeros));
+    let result = stateless.load_state(&state);
+    assert!(matches!(result, Err(EmulatorError::InvalidSaveState(_))));
+    assert_eq!(stateless.frame(), 0);
+}
+
+#[test]
+/// Test runtime errors carry the instruction that failed
+fn test_error_context() {
+    use super::{error::Fault, memory::Address};
+
+    // FX65 reading past the end of the memory
+    let program = [
+        0xAF, 0xFE, // LD I, 0xFFE
+        0xF3, 0x65, // LD V3, [I]
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(emulator.tick().is_ok());
+    let error = emulator.tick().unwrap_err();
+    let fault = Fault {
+        pc: Address::new(0x202),
+        word: 0xF365,
+    };
+    assert!(matches!(
+        error,
+        EmulatorError::OutOfBounds { address: 0x1002, fault: Some(f) } if f == fault
+    ));
+    let message = error.to_string();
+    assert!(
+        message.ends_with("in 0xF365 (LD V3, [I]) at 0x202."),
+        "{message}"
+    );
+
+    // A jump past the memory
+    let program = [
+        0x60, 0xFF, // LD V0, 0xFF
+        0xBF, 0xFF, // JP V0, 0xFFF
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    assert!(emulator.tick().is_ok());
+    let result = emulator.tick();
+    assert!(matches!(
+        result,
+        Err(EmulatorError::InvalidAddress {
+            address: 0x10FE,
+            fault: Some(Fault { pc, word: 0xBFFF }),
+        }) if pc == Address::new(0x202)
+    ));
+
+    // RET with nothing to return to
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&[0x00, 0xEE]).is_ok());
+    let error = emulator.tick().unwrap_err();
+    assert!(matches!(
+        error,
+        EmulatorError::StackUnderFlow { pc, word: 0x00EE } if pc == Address::ENTRY_POINT
+    ));
+    let message = error.to_string();
+    assert!(
+        message.contains("The return 0x00EE (RET) at 0x200"),
+        "{message}"
+    );
+
+    // Errors of the memory itself have no instruction
+    let result = emulator.memory.read_u16(Address::new(0xFFF));
+    assert!(matches!(
+        result,
+        Err(EmulatorError::OutOfBounds { fault: None, .. })
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test the run loop with a frontend without a screen or a speaker
+fn test_frontend_run() {
+    use super::frontend::{run, InputState, NullFrontend, RunConfig};
+
+    let program = [
+        0x60, 0x03, // LD V0, 3
+        0xF0, 0x18, // LD ST, V0
+        0xF1, 0x0A, // LD V1, K
+        0xF1, 0x29, // LD F, V1
+        0xD2, 0x25, // DRW V2, V2, 5
+        0x12, 0x0A, // JP 0x20A
+    ];
+    let config = RunConfig {
+        throttle: false,
+        ..RunConfig::default()
+    };
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let mut frontend = NullFrontend {
+        exit_after: Some(8),
+        ..NullFrontend::default()
+    };
+    assert!(run(&mut emulator, &mut frontend, &config).is_ok());
+    // The key wait holds the program until a key is held
+    assert_eq!(frontend.polls, 8);
+    assert_eq!(emulator.pc.inner(), 0x206);
+    assert_eq!(frontend.presents, 1);
+    // The beep lasts while the sound timer runs, and stops when the loop does
+    assert_eq!(frontend.beeps, [true, false]);
+
+    frontend.input = InputState { keys: 1 << 7 };
+    frontend.exit_after = None;
+    let config = RunConfig {
+        max_frames: Some(3),
+        ..config
+    };
+    assert!(run(&mut emulator, &mut frontend, &config).is_ok());
+    assert_eq!(frontend.polls, 11);
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 7);
+    // Each run presents its first frame, then the sprite drawn
+    assert_eq!(frontend.presents, 3);
+    crate::assert_display_eq!(
+        emulator.display(),
+        (0, 0),
+        "
+        ####.
+        ...#.
+        ..#..
+        .#...
+        .#...
+        "
+    );
+
+    // Errors stop the loop
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom(&[0x00, 0xEE][..]).is_ok());
+    let mut frontend = NullFrontend::default();
+    let result = run(&mut emulator, &mut frontend, &config);
+    assert!(matches!(result, Err(EmulatorError::StackUnderFlow { .. })));
+    assert_eq!(frontend.polls, 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+/// Test the run loop decrements the timers once per frame, whatever the ticks per frame
+fn test_frontend_run_timers() {
+    use super::frontend::{run, NullFrontend, RunConfig};
+
+    let program = [
+        0x60, 0x3C, // LD V0, 60
+        0xF0, 0x15, // LD DT, V0
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x04, // JP 0x204
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let mut frontend = NullFrontend::default();
+    let config = RunConfig {
+        ticks_per_frame: 10,
+        throttle: false,
+        max_frames: Some(1),
+        ..RunConfig::default()
+    };
+    assert!(run(&mut emulator, &mut frontend, &config).is_ok());
+    assert_eq!(emulator.delay_timer.get(), 60);
+    assert_eq!(emulator.instructions(), 10);
+
+    let config = RunConfig {
+        max_frames: Some(5),
+        ..config
+    };
+    assert!(run(&mut emulator, &mut frontend, &config).is_ok());
+    assert_eq!(emulator.delay_timer.get(), 55);
+    assert_eq!(emulator.frame(), 6);
+    assert_eq!(emulator.instructions(), 60);
+}
+
+#[test]
+/// Test a frame runs its instructions with a single vertical blank
+fn test_run_frame() {
+    let program = [
+        0x60, 0x0A, // LD V0, 10
+        0xF0, 0x15, // LD DT, V0
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x04, // JP 0x204
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+
+    assert!(emulator.run_frame(10).is_ok());
+    assert_eq!(emulator.frame(), 1);
+    assert_eq!(emulator.delay_timer.get(), 10);
+    // 2 instructions set the timer, then ADD and JP alternate
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 4);
+
+    assert!(emulator.run_frame(10).is_ok());
+    assert_eq!(emulator.frame(), 2);
+    assert_eq!(emulator.delay_timer.get(), 9);
+    assert_eq!(emulator.registers[RegisterIndex::new(1)], 9);
+
+    // A step leaves the timers as they are
+    assert!(emulator.step().is_ok());
+    assert_eq!(emulator.frame(), 2);
+    assert_eq!(emulator.delay_timer.get(), 9);
+}
+
+#[test]
+/// The setters a debugger edits the registers with
+fn test_debug_setters() {
+    let mut emulator = initialize_empty_emulator();
+    assert!(emulator.set_pc(0x300).is_ok());
+    assert_eq!(emulator.pc().inner(), 0x300);
+    assert!(matches!(
+        emulator.set_pc(0x1000),
+        Err(EmulatorError::InvalidAddress {
+            address: 0x1000,
+            ..
+        })
+    ));
+    assert_eq!(emulator.pc().inner(), 0x300);
+
+    emulator.set_i(0xFFFF);
+    assert_eq!(emulator.i().inner(), 0xFFFF);
+    assert!(emulator.set_register(0xF, 7).is_ok());
+    assert_eq!(emulator.v_registers()[RegisterIndex::new(0xF)], 7);
+    assert!(emulator.set_register(0x10, 7).is_err());
+    emulator.set_delay_timer(3);
+    emulator.set_sound_timer(4);
+    assert_eq!((emulator.delay_timer(), emulator.sound_timer()), (3, 4));
+}
+
+#[test]
+/// Runs stop before a breakpoint until stepped past it
+fn test_breakpoints() {
+    let program = [
+        0x71, 0x01, // ADD V1, 1
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x00, // JP 0x200
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+    let breakpoint = super::memory::Address::new(0x202);
+    assert!(emulator.toggle_breakpoint(breakpoint));
+    assert!(emulator.has_breakpoint(breakpoint));
+
+    assert!(matches!(emulator.run_until_breakpoint(10), Ok(true)));
+    assert_eq!(emulator.pc(), breakpoint);
+    assert_eq!(emulator.v_registers()[RegisterIndex::new(1)], 1);
+    // Still at the breakpoint, a step resumes
+    assert!(matches!(emulator.run_until_breakpoint(10), Ok(true)));
+    assert_eq!(emulator.v_registers()[RegisterIndex::new(1)], 1);
+    assert!(emulator.step().is_ok());
+    assert!(matches!(emulator.run_until_breakpoint(10), Ok(true)));
+    assert_eq!(emulator.v_registers()[RegisterIndex::new(1)], 3);
+
+    assert!(!emulator.toggle_breakpoint(breakpoint));
+    assert_eq!(emulator.breakpoints().count(), 0);
+    assert!(matches!(emulator.run_until_breakpoint(10), Ok(false)));
+    assert_eq!(emulator.v_registers()[RegisterIndex::new(1)], 10);
+}
+
+#[test]
+/// A step over runs the whole subroutine, timers included
+fn test_step_over() {
+    let program = [
+        0x22, 0x06, // CALL 0x206
+        0x71, 0x01, // ADD V1, 1
+        0x12, 0x04, // JP 0x204
+        0x60, 0x03, // LD V0, 3
+        0xF0, 0x15, // LD DT, V0
+        0xF0, 0x07, // LD V0, DT
+        0x30, 0x00, // SE V0, 0
+        0x12, 0x0A, // JP 0x20A
+        0x00, 0xEE, // RET
+    ];
+    let mut emulator = Emulator::new();
+    assert!(emulator.load_rom_bytes(&program).is_ok());
+
+    assert!(matches!(emulator.step_over(10, 10), Ok(true)));
+    assert_eq!(emulator.pc().inner(), 0x202);
+    assert!(emulator.stack().is_empty());
+    assert_eq!(emulator.delay_timer(), 0);
+    assert!(emulator.frame() >= 3);
+
+    // Any other instruction is a step
+    assert!(matches!(emulator.step_over(10, 10), Ok(true)));
+    assert_eq!(emulator.pc().inner(), 0x204);
+    assert_eq!(emulator.v_registers()[RegisterIndex::new(1)], 1);
+
+    // A breakpoint in the subroutine stops the step
+    assert!(emulator.set_pc(0x200).is_ok());
+    emulator.toggle_breakpoint(super::memory::Address::new(0x20E));
+    assert!(matches!(emulator.step_over(10, 10), Ok(false)));
+    assert_eq!(emulator.pc().inner(), 0x20E);
+    assert_eq!(emulator.stack().len(), 1);
+
+    // A subroutine that never returns runs out of frames
+    emulator.clear_breakpoints();
+    assert!(emulator.set_pc(0x200).is_ok());
+    assert!(emulator.poke(0x210, 0x12).is_ok());
+    assert!(emulator.poke(0x211, 0x10).is_ok());
+    assert!(matches!(emulator.step_over(10, 5), Ok(false)));
+}
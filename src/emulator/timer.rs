@@ -16,6 +16,7 @@ impl Timer {
     /// # Returns
     ///
     /// * `u8` - The value of the timer.
+    #[inline]
     pub fn get(&self) -> u8 {
         self.0
     }
@@ -25,11 +26,13 @@ impl Timer {
     /// # Arguments
     ///
     /// * `value` - The value to set the timer to.
+    #[inline]
     pub fn set(&mut self, value: u8) {
         self.0 = value;
     }
 
     /// Decrements the timer by 1.
+    #[inline]
     pub fn decrement(&mut self) {
         if self.0 > 0 {
             self.0 -= 1;
@@ -0,0 +1,257 @@
+use crate::opcode::Opcode;
+
+/// Cost of an instruction family on the original COSMAC VIP interpreter.
+///
+/// # Fields
+///
+/// * `pattern` - The opcode pattern of the family, e.g. `"8XYN"`.
+/// * `micros` - The approximate execution time in microseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cost {
+    pub pattern: &'static str,
+    pub micros: u32,
+}
+
+impl Cost {
+    /// Creates a new cost entry.
+    const fn new(pattern: &'static str, micros: u32) -> Self {
+        Self { pattern, micros }
+    }
+}
+
+/// Approximate execution time of every instruction family on the COSMAC VIP.
+///
+/// The values come from <https://jackson-s.me/2019/07/13/Chip-8-Instruction-Scheduling-and-Frequency.html>.
+/// They are averages, instructions whose time depends on their operands
+/// (skips taken or not, `FX55`/`FX65` with different `X`) use the published mean.
+/// `0NNN` runs native machine code and `FX0A` waits for the user, so both are 0.
+pub const VIP_COSTS: [Cost; 27] = [
+    Cost::new("00E0", 109),
+    Cost::new("00EE", 105),
+    Cost::new("0NNN", 0),
+    Cost::new("1NNN", 105),
+    Cost::new("2NNN", 105),
+    Cost::new("3XNN", 55),
+    Cost::new("4XNN", 55),
+    Cost::new("5XY0", 73),
+    Cost::new("6XNN", 27),
+    Cost::new("7XNN", 45),
+    Cost::new("8XYN", 200),
+    Cost::new("9XY0", 73),
+    Cost::new("ANNN", 55),
+    Cost::new("BNNN", 105),
+    Cost::new("CXNN", 164),
+    Cost::new("DXYN", 22734),
+    Cost::new("EX9E", 73),
+    Cost::new("EXA1", 73),
+    Cost::new("FX07", 45),
+    Cost::new("FX0A", 0),
+    Cost::new("FX15", 45),
+    Cost::new("FX18", 45),
+    Cost::new("FX1E", 86),
+    Cost::new("FX29", 91),
+    Cost::new("FX33", 927),
+    Cost::new("FX55", 605),
+    Cost::new("FX65", 605),
+];
+
+/// The machine cycles per second of the COSMAC VIP, its 1.76064 MHz clock divided by the 8
+/// clock cycles of a machine cycle.
+const VIP_MACHINE_CYCLES_PER_SECOND: u64 = 220_080;
+
+/// Time spent by `DXYN` before drawing any row (fetching and decoding the sprite).
+const DRW_SETUP_MICROS: u32 = 68;
+
+/// Time spent by `DXYN` to draw one row that falls on a single display byte.
+const DRW_ROW_ALIGNED_MICROS: u32 = 46;
+
+/// Time spent by `DXYN` to draw one row that spans two display bytes.
+const DRW_ROW_UNALIGNED_MICROS: u32 = 92;
+
+/// Returns the table entry of the family the opcode belongs to.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode to look up.
+///
+/// # Returns
+///
+/// * `Option<Cost>` - The cost entry, or `None` for invalid opcodes.
+pub fn family(opcode: &Opcode) -> Option<Cost> {
+    let index = match opcode {
+        Opcode::Cls => 0,
+        Opcode::Ret => 1,
+        Opcode::Sys { .. } => 2,
+        Opcode::Jp { .. } => 3,
+        Opcode::Call { .. } => 4,
+        Opcode::SeByte { .. } => 5,
+        Opcode::SneByte { .. } => 6,
+        Opcode::SeRegister { .. } => 7,
+        Opcode::LdByte { .. } => 8,
+        Opcode::AddByte { .. } => 9,
+        Opcode::LdRegister { .. }
+        | Opcode::Or { .. }
+        | Opcode::And { .. }
+        | Opcode::Xor { .. }
+        | Opcode::AddRegister { .. }
+        | Opcode::Sub { .. }
+        | Opcode::Shr { .. }
+        | Opcode::Subn { .. }
+        | Opcode::Shl { .. } => 10,
+        Opcode::SneRegister { .. } => 11,
+        Opcode::LdI { .. } => 12,
+        Opcode::JpV0 { .. } => 13,
+        Opcode::Rnd { .. } => 14,
+        Opcode::Drw { .. } => 15,
+        Opcode::Skp { .. } => 16,
+        Opcode::Sknp { .. } => 17,
+        Opcode::LdVxDT { .. } => 18,
+        Opcode::LdVxK { .. } => 19,
+        Opcode::LdDTVx { .. } => 20,
+        Opcode::LdSTVx { .. } => 21,
+        Opcode::AddIVx { .. } => 22,
+        Opcode::LdFVx { .. } => 23,
+        Opcode::LdBVx { .. } => 24,
+        Opcode::LdIVx { .. } => 25,
+        Opcode::LdVxI { .. } => 26,
+        Opcode::Scd { .. }
+        | Opcode::Scr
+        | Opcode::Scl
+        | Opcode::Low
+        | Opcode::High
+        | Opcode::LdHfVx { .. }
+        | Opcode::LdRVx { .. }
+        | Opcode::LdVxR { .. }
+        | Opcode::Plane { .. }
+        | Opcode::LdAudioI
+        | Opcode::LdPitchVx { .. }
+        | Opcode::LdILong { .. }
+        | Opcode::Skp2 { .. }
+        | Opcode::Sknp2 { .. }
+        | Opcode::Bgc
+        | Opcode::Col { .. }
+        | Opcode::MegaOff
+        | Opcode::MegaOn
+        | Opcode::LdHi { .. }
+        | Opcode::LdPal { .. }
+        | Opcode::SprW { .. }
+        | Opcode::SprH { .. }
+        | Opcode::Alpha { .. }
+        | Opcode::DigiSnd { .. }
+        | Opcode::StopSnd
+        | Opcode::BMode { .. }
+        | Opcode::CCol { .. }
+        | Opcode::Invalid(_) => return None,
+    };
+    Some(VIP_COSTS[index])
+}
+
+/// Returns the approximate cost of an opcode on the COSMAC VIP in microseconds.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode to get the cost of.
+///
+/// # Returns
+///
+/// * `u32` - The cost in microseconds, 0 for invalid opcodes and opcodes the VIP does not have.
+///
+/// # Notes
+///
+/// * `DXYN` uses the published average, which includes the wait for the vertical blank.
+///   Use [`draw_cost`] when the sprite height and position are known.
+pub fn cost(opcode: &Opcode) -> u32 {
+    family(opcode).map_or(0, |cost| cost.micros)
+}
+
+/// Returns the approximate time needed by `DXYN` to draw a sprite, without the vertical blank wait.
+///
+/// # Arguments
+///
+/// * `n` - The height of the sprite in rows.
+/// * `x` - The horizontal position of the sprite, sprites not aligned to a byte need two writes per row.
+///
+/// # Returns
+///
+/// * `u32` - The cost in microseconds.
+pub fn draw_cost(n: u8, x: u8) -> u32 {
+    let row = if x.is_multiple_of(8) {
+        DRW_ROW_ALIGNED_MICROS
+    } else {
+        DRW_ROW_UNALIGNED_MICROS
+    };
+    DRW_SETUP_MICROS + n as u32 * row
+}
+
+impl Opcode {
+    /// Returns the approximate cost of the opcode on the COSMAC VIP in machine cycles.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The cost of [`cost`] converted to machine cycles of about 4.54 microseconds,
+    ///   rounded to the nearest.
+    pub fn cycles(&self) -> u32 {
+        let micros = cost(self) as u64;
+        ((micros * VIP_MACHINE_CYCLES_PER_SECOND + 500_000) / 1_000_000) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(raw: u16) -> Opcode {
+        Opcode::try_from(raw).unwrap()
+    }
+
+    #[test]
+    fn test_documented_costs() {
+        assert_eq!(cost(&op(0x00E0)), 109);
+        assert_eq!(cost(&op(0x00EE)), 105);
+        assert_eq!(cost(&op(0x6A12)), 27);
+        assert_eq!(cost(&op(0x8124)), 200);
+        assert_eq!(cost(&op(0xC0FF)), 164);
+        assert_eq!(cost(&op(0xD125)), 22734);
+        assert_eq!(cost(&op(0xF033)), 927);
+        assert_eq!(cost(&op(0xF065)), 605);
+        assert_eq!(cost(&op(0xFFFF)), 0);
+    }
+
+    #[test]
+    fn test_cycles() {
+        // 6XNN, 7XNN and 00E0 take 6, 10 and 24 machine cycles
+        assert_eq!(op(0x6A12).cycles(), 6);
+        assert_eq!(op(0x7A12).cycles(), 10);
+        assert_eq!(op(0x00E0).cycles(), 24);
+        assert_eq!(op(0xF065).cycles(), 133);
+        assert_eq!(op(0xFFFF).cycles(), 0);
+    }
+
+    #[test]
+    fn test_family_patterns_match_opcodes() {
+        // Every pattern must describe the opcodes that map to it.
+        for raw in 0..=u16::MAX {
+            let Some(family) = family(&op(raw)) else {
+                continue;
+            };
+            let hex = format!("{:04X}", raw);
+            let matches = family
+                .pattern
+                .chars()
+                .zip(hex.chars())
+                .all(|(p, h)| "XYN".contains(p) || p == h);
+            assert!(matches, "{} is not a {}", hex, family.pattern);
+        }
+    }
+
+    #[test]
+    fn test_draw_cost() {
+        // 68 microseconds of setup, then 46 per aligned row and 92 per unaligned row
+        assert_eq!(draw_cost(0, 0), 68);
+        assert_eq!(draw_cost(1, 0), 114);
+        assert_eq!(draw_cost(5, 8), 298);
+        assert_eq!(draw_cost(5, 9), 528);
+        assert_eq!(draw_cost(15, 0), 758);
+        assert_eq!(draw_cost(15, 63), 1448);
+    }
+}
@@ -0,0 +1,114 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    constants::{Palette, MONOCHROME_PALETTE},
+    emulator::Emulator,
+    error::EmulatorError,
+    keyboard::Key,
+};
+
+/// Converts an error to a JavaScript `Error`, with its message and its `code`.
+fn to_js(error: EmulatorError) -> JsValue {
+    let value = JsValue::from(js_sys::Error::new(&error.to_string()));
+    // Setting a property of a new Error can not fail
+    let _ = js_sys::Reflect::set(&value, &"code".into(), &error.code().into());
+    value
+}
+
+/// The emulator for JavaScript, built with the `wasm` feature.
+///
+/// # Fields
+///
+/// * `emulator` - The emulator.
+/// * `palette` - The colors of `framebuffer`.
+/// * `ticks_per_frame` - The instructions run by `run_frame`.
+///
+/// # Notes
+///
+/// Errors are thrown as `Error`s with the message and the code of the `EmulatorError`, see
+/// `EmulatorError::code`.
+#[wasm_bindgen]
+pub struct WasmEmulator {
+    emulator: Emulator,
+    palette: Palette,
+    ticks_per_frame: u32,
+}
+
+#[wasm_bindgen]
+impl WasmEmulator {
+    /// Creates an emulator running one instruction per frame, as `RunConfig` does.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmEmulator {
+        WasmEmulator {
+            emulator: Emulator::new(),
+            palette: MONOCHROME_PALETTE,
+            ticks_per_frame: 1,
+        }
+    }
+
+    /// Loads a ROM from a `Uint8Array`, see `Emulator::load_rom`.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsValue> {
+        self.emulator.load_rom_bytes(rom).map_err(to_js)
+    }
+
+    /// Sets the instructions run by each `run_frame`, at least 1.
+    pub fn set_ticks_per_frame(&mut self, ticks: u32) {
+        self.ticks_per_frame = ticks.max(1);
+    }
+
+    /// Runs a frame, call it 60 times per second, e.g. from `requestAnimationFrame`. The
+    /// timers are decremented once per frame, see `Emulator::run_frame`.
+    pub fn run_frame(&mut self) -> Result<(), JsValue> {
+        self.emulator.run_frame(self.ticks_per_frame).map_err(to_js)
+    }
+
+    /// Returns the width of `framebuffer` in pixels.
+    pub fn width(&self) -> usize {
+        self.emulator.display().dimensions().0
+    }
+
+    /// Returns the height of `framebuffer` in pixels.
+    pub fn height(&self) -> usize {
+        self.emulator.display().dimensions().1
+    }
+
+    /// Returns the display as a `Uint8Array` of RGBA pixels, for an `ImageData`.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.emulator.display().to_rgba8(&self.palette)
+    }
+
+    /// Presses a key, only its low nibble is used.
+    pub fn key_down(&mut self, key: u8) {
+        if let Some(&key) = Key::all().nth((key & 0xF) as usize) {
+            self.emulator.press_key(key);
+        }
+    }
+
+    /// Releases a key, only its low nibble is used.
+    pub fn key_up(&mut self, key: u8) {
+        if let Some(&key) = Key::all().nth((key & 0xF) as usize) {
+            self.emulator.release_key(key);
+        }
+    }
+
+    /// Returns true while the sound timer runs.
+    pub fn beep_active(&self) -> bool {
+        self.emulator.sound_timer() > 0
+    }
+
+    /// Saves the state as a `Uint8Array`, see `Emulator::save_state`.
+    pub fn save_state(&self) -> Vec<u8> {
+        self.emulator.save_state()
+    }
+
+    /// Restores a state saved by `save_state`, see `Emulator::load_state`.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), JsValue> {
+        self.emulator.load_state(state).map_err(to_js)
+    }
+}
+
+impl Default for WasmEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -40,8 +40,13 @@ fn init_display(mut commands: Commands) {
     }
 }
 
-fn update_screen_system(r8: Res<Emulator>, mut query: Query<(&mut Sprite, &Pixel)>) {
-    if r8.0.display().updated {
+fn update_screen_system(
+    r8: Res<Emulator>,
+    mut drawn: Local<u64>,
+    mut query: Query<(&mut Sprite, &Pixel)>,
+) {
+    if r8.0.display().version() != *drawn {
+        *drawn = r8.0.display().version();
         for (mut sprite, pixel) in &mut query {
             sprite.color = if r8.0.display().get(pixel.0, pixel.1) {
                 Color::WHITE
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use crossterm::{style::Stylize, ExecutableCommand};
-use r8::{emulator, keyboard::Key};
+use r8::{emulator, keyboard::Key, keymap::KeyMap};
 
 // Clap
 #[derive(Parser)]
@@ -53,6 +53,10 @@ fn main() {
 
     let frame_duration = std::time::Duration::from_secs_f32(1.0 / 60.0);
 
+    // The version of the display last drawn
+    let mut drawn = 0;
+    let keymap = KeyMap::qwerty();
+
     loop {
         let frame_start = std::time::Instant::now();
 
@@ -65,7 +69,8 @@ fn main() {
                             break;
                         }
                         crossterm::event::KeyCode::Char(key) => {
-                            if let Some(key) = map_key(key) {
+                            let key = keymap.map(key).and_then(|key| Key::all().nth(key as usize));
+                            if let Some(&key) = key {
                                 emu.press_key(key);
                             }
                         }
@@ -83,14 +88,16 @@ fn main() {
             log_and_exit!("Fatal emulator error: {}", err);
         }
 
-        if emu.display().updated {
+        if emu.display().version() != drawn {
+            drawn = emu.display().version();
             if let Err(err) = stdout.execute(crossterm::terminal::Clear(
                 crossterm::terminal::ClearType::All,
             )) {
                 log_and_exit!("Failed to clear terminal: {}", err);
             }
-            for x in 0..r8::constants::WIDTH {
-                for y in 0..r8::constants::HEIGHT {
+            let (width, height) = emu.display().dimensions();
+            for x in 0..width {
+                for y in 0..height {
                     if emu.display()[(x,y)] {
                         if let Err(err) =
                             stdout.execute(crossterm::cursor::MoveTo(x as u16, y as u16))
@@ -117,43 +124,6 @@ fn main() {
     crossterm::terminal::disable_raw_mode().unwrap();
 }
 
-/// The original implementation of the Chip8 system had a 16-key hexadecimal keypad with the following layout:
-///
-/// | 1 | 2 | 3 | C |
-/// |---|---|---|---|
-/// | 4 | 5 | 6 | D |
-/// | 7 | 8 | 9 | E |
-/// | A | 0 | B | F |
-///
-/// The keys are mapped to the following indexes:
-///
-/// | 1 | 2 | 3 | 4 |
-/// |---|---|---|---|
-/// | Q | W | E | R |
-/// | A | S | D | F |
-/// | Z | X | C | V |
-fn map_key(key: char) -> Option<Key> {
-    match key {
-        '1' => Some(Key::K1),
-        '2' => Some(Key::K2),
-        '3' => Some(Key::K3),
-        '4' => Some(Key::KC),
-        'Q' | 'q' => Some(Key::K4),
-        'W' | 'w' => Some(Key::K5),
-        'E' | 'e' => Some(Key::K6),
-        'R' | 'r' => Some(Key::KD),
-        'A' | 'a' => Some(Key::K7),
-        'S' | 's' => Some(Key::K8),
-        'D' | 'd' => Some(Key::K9),
-        'F' | 'f' => Some(Key::KE),
-        'Z' | 'z' => Some(Key::KA),
-        'X' | 'x' => Some(Key::K0),
-        'C' | 'c' => Some(Key::KB),
-        'V' | 'v' => Some(Key::KF),
-        _ => None,
-    }
-}
-
 /// Loads the ROM or the assembly file.
 fn load_rom(args: R8, emu: &mut emulator::Emulator) {
     match (args.rom, args.asm) {
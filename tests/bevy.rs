@@ -0,0 +1,87 @@
+//! Tests of the Bevy plugin in a headless app, run with `cargo test --features bevy`.
+#![cfg(feature = "bevy")]
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use r8::{
+    bevy::{EmulatorComponent, EmulatorKeyMap, InputFocus, R8Plugin},
+    emulator::Emulator,
+};
+
+/// Draws 0 in the top-left corner and waits for a key, then draws 1 below it.
+const ROM: [u8; 12] = [
+    0xD1, 0x15, // DRW V1, V1, 5
+    0xF2, 0x0A, // LD V2, K
+    0xF2, 0x29, // LD F, V2
+    0x60, 0x08, // LD V0, 8
+    0xD1, 0x05, // DRW V1, V0, 5
+    0x12, 0x0A, // JP 0x20A
+];
+
+/// An app without a window, each update is a 60Hz frame.
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Image>()
+        .init_resource::<ButtonInput<KeyCode>>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Duration::from_secs(1) / 60,
+        ))
+        .add_plugins(R8Plugin);
+    app
+}
+
+/// Spawns an emulator running `ROM`.
+fn spawn(app: &mut App) -> Entity {
+    let mut emulator = Emulator::new();
+    emulator.load_rom_bytes(&ROM).unwrap();
+    let mut images = app.world.resource_mut::<Assets<Image>>();
+    let screen = EmulatorComponent::new(emulator, &mut images);
+    app.world.spawn(screen).id()
+}
+
+/// Returns the pixels of the image of an emulator.
+fn image(app: &App, entity: Entity) -> Vec<u8> {
+    let screen = app.world.get::<EmulatorComponent>(entity).unwrap();
+    let images = app.world.resource::<Assets<Image>>();
+    images.get(&screen.image).unwrap().data.clone()
+}
+
+/// Returns true if the pixel of an RGBA image of 64 pixels per row is lit.
+fn lit(pixels: &[u8], x: usize, y: usize) -> bool {
+    pixels[(y * 64 + x) * 4] != 0
+}
+
+#[test]
+/// Two emulators run side by side, only the focused one gets the keys
+fn run_frames() {
+    let mut app = app();
+    let focused = spawn(&mut app);
+    let other = spawn(&mut app);
+    app.world.entity_mut(focused).insert(InputFocus);
+    for _ in 0..5 {
+        app.update();
+    }
+
+    for entity in [focused, other] {
+        let screen = app.world.get::<EmulatorComponent>(entity).unwrap();
+        assert!(screen.emulator.frame() >= 3);
+        assert!(!screen.halted);
+        let pixels = image(&app, entity);
+        assert_eq!(pixels.len(), 64 * 32 * 4);
+        assert!(lit(&pixels, 0, 0) && !lit(&pixels, 4, 0));
+    }
+
+    // W is the key 5 on QWERTY, the focused emulator draws 5 below the 0
+    let key = app.world.resource::<EmulatorKeyMap>().0.host_key(0x5);
+    assert_eq!(key, Some('w'));
+    app.world
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyW);
+    for _ in 0..3 {
+        app.update();
+    }
+    assert!(lit(&image(&app, focused), 0, 8));
+    assert!(!lit(&image(&app, other), 0, 8));
+}
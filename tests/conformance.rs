@@ -0,0 +1,205 @@
+//! Conformance checks in the spirit of the corax89 opcode test and the Timendus flags and
+//! quirks tests, run headless with `r8::testing::run_headless`.
+//!
+//! Each check runs a few instructions, then lights the pixel `(2 * n, 0)` if a register has
+//! the expected value, so the first row of the final display is the report: `#.` for a
+//! passed check and `..` for a failed one.
+//!
+//! The ROMs themselves run in `tests/suite.rs`, these checks are extra coverage that does
+//! not need them.
+
+use r8::{
+    assert_display_eq,
+    quirks::Profile,
+    testing::{run_headless, HarnessOptions},
+};
+
+/// The address of the one pixel sprite, after the jump over it.
+const PIXEL: u16 = 0x202;
+
+/// A ROM of checks, see the module documentation.
+struct Checks {
+    rom: Vec<u8>,
+    count: u8,
+}
+
+impl Checks {
+    fn new() -> Self {
+        let mut checks = Self {
+            rom: Vec::new(),
+            count: 0,
+        };
+        // Jump over the sprite
+        checks.emit(&[0x1204, 0x8000]);
+        checks
+    }
+
+    /// Returns the address of the next instruction.
+    fn here(&self) -> u16 {
+        0x200 + self.rom.len() as u16
+    }
+
+    fn emit(&mut self, words: &[u16]) {
+        for word in words {
+            self.rom.extend(word.to_be_bytes());
+        }
+    }
+
+    /// Runs `setup`, then checks that `Vx` is `expected`.
+    fn check(&mut self, setup: &[u16], x: u16, expected: u8) -> &mut Self {
+        self.emit(setup);
+        // SE Vx, expected, else JP past the pixel
+        let skip = self.here() + 4 + 8;
+        self.emit(&[0x3000 | x << 8 | expected as u16, 0x1000 | skip]);
+        let column = 2 * self.count as u16;
+        self.emit(&[0x6E00 | column, 0x6D00, 0xA000 | PIXEL, 0xDED1]);
+        self.count += 1;
+        self
+    }
+
+    /// Ends the ROM with an infinite loop, and returns it with the report of all checks
+    /// passing.
+    fn finish(&mut self) -> (Vec<u8>, String) {
+        let here = self.here();
+        self.emit(&[0x1000 | here]);
+        (self.rom.clone(), "#.".repeat(self.count as usize))
+    }
+}
+
+/// Runs the checks and asserts the report.
+fn assert_checks(checks: &mut Checks, profile: Profile) {
+    let (rom, report) = checks.finish();
+    let options = HarnessOptions {
+        profile,
+        ..HarnessOptions::default()
+    };
+    let result = run_headless(&rom, 60, options);
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+    assert_display_eq!(result.display, (0, 0), &report);
+}
+
+#[test]
+/// The arithmetic of 8XY4, 8XY5 and 8XY7, with the carry and borrow flags
+fn arithmetic_flags() {
+    let mut checks = Checks::new();
+    checks
+        // ADD without and with a carry
+        .check(&[0x6110, 0x6220, 0x8124], 0x1, 0x30)
+        .check(&[0x6110, 0x6220, 0x8124], 0xF, 0)
+        .check(&[0x61FF, 0x6202, 0x8124], 0x1, 0x01)
+        .check(&[0x61FF, 0x6202, 0x8124], 0xF, 1)
+        // SUB, equal values do not borrow
+        .check(&[0x6105, 0x6203, 0x8125], 0x1, 0x02)
+        .check(&[0x6105, 0x6203, 0x8125], 0xF, 1)
+        .check(&[0x6105, 0x6205, 0x8125], 0x1, 0x00)
+        .check(&[0x6105, 0x6205, 0x8125], 0xF, 1)
+        .check(&[0x6103, 0x6205, 0x8125], 0x1, 0xFE)
+        .check(&[0x6103, 0x6205, 0x8125], 0xF, 0)
+        // SUBN, equal values do not borrow
+        .check(&[0x6103, 0x6205, 0x8127], 0x1, 0x02)
+        .check(&[0x6103, 0x6205, 0x8127], 0xF, 1)
+        .check(&[0x6105, 0x6205, 0x8127], 0xF, 1)
+        .check(&[0x6105, 0x6203, 0x8127], 0x1, 0xFE)
+        .check(&[0x6105, 0x6203, 0x8127], 0xF, 0);
+    assert_checks(&mut checks, Profile::Chip8);
+}
+
+#[test]
+/// With VF as the destination the flag is written last and wins
+fn flag_register_destination() {
+    let mut checks = Checks::new();
+    checks
+        // ADD VF, V2 without and with a carry
+        .check(&[0x6F10, 0x6220, 0x8F24], 0xF, 0)
+        .check(&[0x6FFF, 0x6202, 0x8F24], 0xF, 1)
+        // SUB VF, V2 and SUBN VF, V2
+        .check(&[0x6F05, 0x6203, 0x8F25], 0xF, 1)
+        .check(&[0x6F03, 0x6205, 0x8F25], 0xF, 0)
+        .check(&[0x6F03, 0x6205, 0x8F27], 0xF, 1)
+        .check(&[0x6F05, 0x6203, 0x8F27], 0xF, 0)
+        // SHR VF and SHL VF, the shifted out bit
+        .check(&[0x6F02, 0x8FF6], 0xF, 0)
+        .check(&[0x6F03, 0x8FF6], 0xF, 1)
+        .check(&[0x6F81, 0x8FFE], 0xF, 1)
+        .check(&[0x6F41, 0x8FFE], 0xF, 0);
+    assert_checks(&mut checks, Profile::SuperChipModern);
+}
+
+#[test]
+/// DXYN sets VF on a collision, and reads VF as a coordinate before clearing it
+fn draw_collision() {
+    let mut checks = Checks::new();
+    let draw = |x: u16, y: u16| 0xD001 | x << 8 | y << 4;
+    checks
+        // The same pixel twice, the second draw turns it off
+        .check(&[0x6328, 0x640A, 0xA000 | PIXEL, draw(3, 4)], 0xF, 0)
+        .check(&[0xA000 | PIXEL, draw(3, 4)], 0xF, 1)
+        // Drawn at (VF, V4), then erased from (V3, V4) with the same coordinates
+        .check(&[0x6F30, 0x6330, 0xA000 | PIXEL, draw(0xF, 4)], 0xF, 0)
+        .check(&[0xA000 | PIXEL, draw(3, 4)], 0xF, 1);
+    assert_checks(&mut checks, Profile::Chip8);
+}
+
+/// The checks of the quirks that differ between CHIP-8 and SUPER-CHIP.
+///
+/// # Arguments
+///
+/// * `vy` - Whether the shifts read VY, as on the COSMAC VIP.
+/// * `vf_reset` - Whether the logic instructions clear VF.
+/// * `bxnn` - Whether BNNN jumps to NNN + VX, X being the highest nibble of NNN.
+fn quirk_checks(vy: bool, vf_reset: bool, bxnn: bool) -> Checks {
+    let mut checks = Checks::new();
+    // SHR V1, V2 and SHL V1, V2
+    checks
+        .check(&[0x6110, 0x6203, 0x8126], 0x1, if vy { 0x01 } else { 0x08 })
+        .check(&[0x6110, 0x6203, 0x8126], 0xF, if vy { 1 } else { 0 })
+        .check(&[0x6181, 0x6241, 0x812E], 0x1, if vy { 0x82 } else { 0x02 })
+        .check(&[0x6181, 0x6241, 0x812E], 0xF, if vy { 0 } else { 1 });
+    // OR, AND and XOR with VF set
+    for logic in [0x8121, 0x8122, 0x8123] {
+        checks.check(
+            &[0x6F07, 0x6103, 0x6205, logic],
+            0xF,
+            if vf_reset { 0 } else { 7 },
+        );
+    }
+    // B2NN: V0 is 4 and V2 is 8, the jump lands on one of the two markers
+    let start = checks.here();
+    let target = start + 8;
+    checks.check(
+        &[
+            0x6104,
+            0x6004,
+            0x6208,
+            0xB000 | target,
+            // start + 8, skipped by V0 = 4 to start + 12
+            0x1000 | (start + 20),
+            0x1000 | (start + 20),
+            0x6101,
+            0x1000 | (start + 22),
+            // start + 16, reached with V2 = 8
+            0x6102,
+            0x1000 | (start + 22),
+            // start + 20, not a jump target
+            0x6100,
+        ],
+        0x1,
+        if bxnn { 2 } else { 1 },
+    );
+    checks
+}
+
+#[test]
+/// The quirks of the COSMAC VIP CHIP-8
+fn chip8_quirks() {
+    assert_checks(&mut quirk_checks(true, true, false), Profile::Chip8);
+}
+
+#[test]
+/// The quirks of SUPER-CHIP
+fn superchip_quirks() {
+    assert_checks(
+        &mut quirk_checks(false, false, true),
+        Profile::SuperChipModern,
+    );
+}
@@ -0,0 +1,54 @@
+/*
+ * A C program using the C ABI, built with the `ffi` feature:
+ *
+ *   cargo build --release -p r8-bindings --features ffi
+ *   cc -Iinclude tests/ffi.c -Ltarget/release -lr8_bindings -o target/ffi
+ *   LD_LIBRARY_PATH=target/release ./target/ffi
+ */
+#include <stdio.h>
+
+#include "r8.h"
+
+/* Draws 0 in the top-left corner and loops */
+static const uint8_t ROM[] = {0x60, 0x05, 0xF0, 0x18, 0xD1, 0x15, 0x12, 0x06};
+
+static int check(int32_t code, const char *call) {
+  if (code != R8_OK) {
+    fprintf(stderr, "%s failed with %d: %s\n", call, code, r8_last_error_message());
+    return 0;
+  }
+  return 1;
+}
+
+int main(void) {
+  R8 *r8 = r8_new();
+  if (r8 == NULL || !check(r8_load_rom(r8, ROM, sizeof ROM), "r8_load_rom")) {
+    return 1;
+  }
+  for (int frame = 0; frame < 4; frame++) {
+    if (!check(r8_run_frame(r8), "r8_run_frame")) {
+      return 1;
+    }
+  }
+
+  size_t width, height, stride;
+  const uint8_t *pixels = r8_framebuffer(r8, &width, &height, &stride);
+  if (pixels == NULL) {
+    return 1;
+  }
+  /* Print the glyph, the top-left corner of the display */
+  for (size_t y = 0; y < 5; y++) {
+    for (size_t x = 0; x < 4; x++) {
+      putchar(pixels[y * stride + x * 4] ? '#' : '.');
+    }
+    putchar('\n');
+  }
+
+  /* Errors have a code and a message */
+  if (r8_load_rom(r8, NULL, 0) != 402 || r8_last_error_code() != 402) {
+    return 1;
+  }
+  printf("%s\n", r8_last_error_message());
+  r8_free(r8);
+  return 0;
+}
@@ -0,0 +1,141 @@
+//! Round trips through the C ABI, run with `cargo test --features ffi`.
+#![cfg(feature = "ffi")]
+
+use std::{ffi::CStr, ptr, slice};
+
+use r8::ffi::*;
+
+/// Draws 0 in the top-left corner, beeps and waits for a key.
+const ROM: [u8; 10] = [
+    0x60, 0x05, // LD V0, 5
+    0xF0, 0x18, // LD ST, V0
+    0xD1, 0x15, // DRW V1, V1, 5
+    0xF2, 0x0A, // LD V2, K
+    0x12, 0x08, // JP 0x208
+];
+
+/// Returns the message of the last error of the thread.
+fn last_message() -> String {
+    let message = r8_last_error_message();
+    assert!(!message.is_null());
+    unsafe { CStr::from_ptr(message) }
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+/// A few frames of a ROM, the framebuffer, the keys and the save states
+fn run_frames() {
+    unsafe {
+        let r8 = r8_new();
+        assert!(!r8.is_null());
+        assert_eq!(r8_load_rom(r8, ROM.as_ptr(), ROM.len()), R8_OK);
+        assert_eq!(r8_set_ticks_per_frame(r8, 2), R8_OK);
+        for _ in 0..2 {
+            assert_eq!(r8_run_frame(r8), R8_OK);
+        }
+        assert!(r8_beep_active(r8));
+
+        let (mut width, mut height, mut stride) = (0, 0, 0);
+        let pixels = r8_framebuffer(r8, &mut width, &mut height, &mut stride);
+        assert!(!pixels.is_null());
+        assert_eq!((width, height, stride), (64, 32, 64 * 4));
+        let pixels = slice::from_raw_parts(pixels, height * stride);
+        // The top-left pixel of 0 is lit, the one next to the glyph is not
+        assert_ne!(pixels[..4], pixels[4 * 4..5 * 4]);
+        // The outputs are optional
+        let null = ptr::null_mut();
+        assert!(!r8_framebuffer(r8, null, null, null).is_null());
+
+        // The size of the state is queried first
+        let mut len = 0;
+        let code = r8_save_state(r8, ptr::null_mut(), 0, &mut len);
+        assert_eq!(code, 501);
+        assert_eq!(r8_last_error_code(), 501);
+        let mut state = vec![0; len];
+        assert_eq!(
+            r8_save_state(r8, state.as_mut_ptr(), len, ptr::null_mut()),
+            R8_OK
+        );
+
+        // The key wait ends with a key, the state restores it
+        assert_eq!(r8_key_event(r8, 0xA, true), R8_OK);
+        assert_eq!(r8_run_frame(r8), R8_OK);
+        assert_eq!(r8_key_event(r8, 0xA, false), R8_OK);
+        assert_eq!(r8_load_state(r8, state.as_ptr(), state.len()), R8_OK);
+        let mut restored = vec![0; len];
+        assert_eq!(
+            r8_save_state(r8, restored.as_mut_ptr(), len, ptr::null_mut()),
+            R8_OK
+        );
+        assert_eq!(restored, state);
+        r8_free(r8);
+    }
+}
+
+#[test]
+/// Errors are returned with their code and message instead of panicking
+fn errors() {
+    unsafe {
+        let r8 = r8_new();
+        assert_eq!(r8_load_rom(r8, ptr::null(), 0), 402);
+        assert_eq!(r8_last_error_code(), 402);
+        assert!(
+            last_message().starts_with("Empty ROM"),
+            "{}",
+            last_message()
+        );
+
+        assert_eq!(r8_load_state(r8, b"R8SS".as_ptr(), 4), 705);
+        assert_eq!(r8_load_rom(r8, ptr::null(), 2), R8_ERROR_NULL);
+        assert_eq!(r8_run_frame(ptr::null_mut()), R8_ERROR_NULL);
+        assert_eq!(r8_last_error_code(), R8_ERROR_NULL);
+        assert!(r8_framebuffer(
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut()
+        )
+        .is_null());
+        assert!(!r8_beep_active(ptr::null()));
+
+        // A RET with an empty stack
+        assert_eq!(r8_load_rom(r8, [0x00, 0xEE].as_ptr(), 2), R8_OK);
+        assert_eq!(r8_run_frame(r8), 202);
+        assert!(last_message().contains("0x00EE"), "{}", last_message());
+        r8_free(r8);
+        r8_free(ptr::null_mut());
+    }
+}
+
+#[test]
+/// The last error is kept per thread
+fn last_error_per_thread() {
+    unsafe {
+        assert_eq!(r8_run_frame(ptr::null_mut()), R8_ERROR_NULL);
+    }
+    let other = std::thread::spawn(|| (r8_last_error_code(), r8_last_error_message().is_null()));
+    assert_eq!(other.join().unwrap(), (R8_OK, true));
+    assert_eq!(r8_last_error_code(), R8_ERROR_NULL);
+}
+
+#[test]
+/// The header declares every function of the ABI, see scripts/generate-header.sh
+fn header_declares_functions() {
+    let source = include_str!("../src/emulator/ffi.rs");
+    let header = include_str!("../include/r8.h");
+    let functions = source
+        .lines()
+        .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+        .filter_map(|rest| rest.split('(').next());
+    let mut count = 0;
+    for function in functions {
+        assert!(
+            header.contains(&format!(" {function}(")) || header.contains(&format!("*{function}(")),
+            "include/r8.h does not declare {function}, regenerate it"
+        );
+        count += 1;
+    }
+    assert!(count > 0);
+}
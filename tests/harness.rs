@@ -0,0 +1,63 @@
+//! Runs the ROMs of `roms/` headless, see `r8::testing::run_headless`.
+
+use r8::{
+    assert_rom_frame, assert_rom_hash,
+    testing::{run_headless, HarnessOptions},
+};
+
+const IBM_LOGO: &[u8] = include_bytes!("../roms/IBM Logo.ch8");
+
+#[test]
+/// The logo is drawn within a second
+fn ibm_logo_frame() {
+    assert_rom_frame!(
+        IBM_LOGO,
+        60,
+        "
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ............########.#########...#####.........#####............
+        ................................................................
+        ............########.###########.######.......######............
+        ................................................................
+        ..............####.....###...###...#####.....#####..............
+        ................................................................
+        ..............####.....#######.....#######.#######..............
+        ................................................................
+        ..............####.....#######.....###.#######.###..............
+        ................................................................
+        ..............####.....###...###...###..#####..###..............
+        ................................................................
+        ............########.###########.#####...###...#####............
+        ................................................................
+        ............########.#########...#####....#....#####............
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        "
+    );
+}
+
+#[test]
+/// The logo stays on screen, the same on every run
+fn ibm_logo_hash() {
+    assert_rom_hash!(IBM_LOGO, 60, 0xEA11530B);
+    assert_rom_hash!(IBM_LOGO, 600, HarnessOptions::default(), 0xEA11530B);
+
+    let result = run_headless(IBM_LOGO, 60, HarnessOptions::default());
+    assert_eq!(result.frames, 60);
+    assert!(result.instructions > 60);
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+}
@@ -0,0 +1,108 @@
+//! Property tests tying together opcode decoding, encoding, formatting and parsing.
+
+use proptest::prelude::*;
+use r8::{
+    emulator::Emulator,
+    memory::Address,
+    opcode::Opcode,
+    quirks::{Profile, PRESETS},
+};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    /// Decoding then encoding a word gives back the same word
+    fn decode_encode_round_trip(word in any::<u16>()) {
+        let opcode = Opcode::try_from(word).unwrap();
+        if !matches!(opcode, Opcode::Invalid(_)) {
+            prop_assert_eq!(opcode.encode(), word);
+        }
+    }
+
+    #[test]
+    /// Formatting then parsing a valid opcode gives back the same opcode
+    fn display_from_str_round_trip(word in any::<u16>()) {
+        let opcode = Opcode::try_from(word).unwrap();
+        prop_assume!(!matches!(opcode, Opcode::Invalid(_)));
+        let parsed: Opcode = opcode.to_string().parse().unwrap();
+        prop_assert_eq!(parsed, opcode);
+    }
+
+    #[test]
+    /// The listing formatter handles every word at every address
+    fn format_at_never_panics(word in any::<u16>(), address in 0u16..=0xFFF) {
+        let opcode = Opcode::try_from(word).unwrap();
+        let line = opcode.format_at(Address::new(address));
+        let [high, low] = word.to_be_bytes();
+        let prefix = format!("0x{:04X}  {:02X} {:02X}  ", address, high, low);
+        prop_assert!(line.starts_with(&prefix), "{} does not start with {}", line, prefix);
+    }
+
+    #[test]
+    /// Drawing a sprite never panics, whatever the registers hold
+    fn draw_never_panics(x in any::<u8>(), y in any::<u8>(), n in 0u8..=0xF, hires in any::<bool>()) {
+        let program = [
+            0x60, x, // LD V0, x
+            0x61, y, // LD V1, y
+            0xA3, 0x00, // LD I, 0x300
+            0x00, 0xFE | hires as u8, // LOW or HIGH
+            0xD0, 0x10 | n, // DRW V0, V1, n
+        ];
+        let mut emulator = Emulator::with_profile(Profile::SuperChipModern);
+        prop_assert!(emulator.load_rom_bytes(&program).is_ok());
+        for _ in 0..5 {
+            emulator.tick().unwrap();
+        }
+    }
+
+    #[test]
+    /// Reading any pixel never panics, and only pixels on the display are reported
+    fn display_reads_never_panic(x in any::<usize>(), y in any::<usize>(), hires in any::<bool>()) {
+        let program = [0x00, 0xFE | hires as u8]; // LOW or HIGH
+        let mut emulator = Emulator::with_profile(Profile::SuperChipModern);
+        prop_assert!(emulator.load_rom_bytes(&program).is_ok());
+        emulator.tick().unwrap();
+        let display = emulator.display();
+        let (width, height) = display.dimensions();
+        prop_assert_eq!(display.try_get(x, y).is_some(), x < width && y < height);
+        prop_assert_eq!(display.try_color(x, y).is_some(), x < width && y < height);
+        prop_assert!(!display.get(x, y) && !display[(x, y)]);
+        prop_assert_eq!(display.color(x, y), 0);
+        display.zone_color(x, y);
+        display.chip8x_rgb(x, y);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    /// Random programs on random machine states return errors instead of panicking,
+    /// 4096 instructions per case
+    fn random_programs_never_panic(seed in any::<u64>(), preset in 0..PRESETS.len(), strict in any::<bool>()) {
+        // xorshift, generating the memory with proptest would be too slow for 64KB
+        let mut state = seed | 1;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let profile = PRESETS[preset].profile;
+        let mut emulator = Emulator::with_profile(profile);
+        emulator.set_strict(strict);
+        prop_assert!(emulator.load_rom_bytes(&[0x12, 0x00]).is_ok());
+        let memory: Vec<_> = (0..profile.memory_size()).map(|_| next() as u8).collect();
+        prop_assert!(emulator.load_at(Address::new(0), &memory).is_ok());
+        for _ in 0..4096 {
+            // Random opcodes also randomize the registers, I and the program counter
+            let word = next() as u16;
+            let _ = match next() % 4 {
+                0 => emulator.execute_opcode(Opcode::try_from(word).unwrap()),
+                _ => emulator.tick(),
+            };
+            emulator.set_key_mask(next() as u16);
+        }
+    }
+}
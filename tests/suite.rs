@@ -0,0 +1,90 @@
+//! Runs the corax89 opcode test and the Timendus test suite and compares their final display
+//! with the snapshots of `tests/roms/`.
+//!
+//! The ROMs are not committed, `scripts/fetch-test-roms.sh` downloads them into a cache,
+//! `target/test-roms/` or the directory of `R8_TEST_ROMS`, see `tests/roms/README.md`.
+//! The tests are ignored until the snapshots are committed, `tests/conformance.rs` covers
+//! the same behaviors with small synthetic ROMs.
+
+use std::{env, fs, path::PathBuf};
+
+use r8::{
+    assert_rom_frame,
+    keyboard::Key,
+    quirks::Profile,
+    testing::{run_headless, HarnessOptions},
+};
+
+const MISSING: &str = "run scripts/fetch-test-roms.sh, see tests/roms/README.md";
+
+/// The directory the fetch script downloads the ROMs into.
+fn cache_dir() -> PathBuf {
+    env::var_os("R8_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/test-roms"))
+}
+
+/// Runs the cached `<name>.ch8` and asserts it shows `tests/roms/<name>.frame` at the end.
+///
+/// With `R8_UPDATE_SNAPSHOTS` set the snapshot is written instead.
+fn assert_snapshot(name: &str, frames: u32, options: HarnessOptions) {
+    let rom = fs::read(cache_dir().join(format!("{name}.ch8")))
+        .unwrap_or_else(|err| panic!("Can not read {name}.ch8, {MISSING}: {err}"));
+    let snapshot = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/roms")
+        .join(format!("{name}.frame"));
+
+    if env::var_os("R8_UPDATE_SNAPSHOTS").is_some() {
+        let result = run_headless(&rom, frames, options);
+        assert!(result.error().is_none(), "{:?}", result.diagnostics);
+        fs::write(&snapshot, &result.text).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&snapshot)
+        .unwrap_or_else(|err| panic!("Can not read {name}.frame, {MISSING}: {err}"));
+    assert_rom_frame!(&rom, frames, options, &expected);
+}
+
+/// The options of a CHIP-8 run that presses `key` once, to pick an entry of a menu.
+fn chip8_with_key(key: Key) -> HarnessOptions {
+    HarnessOptions {
+        profile: Profile::Chip8,
+        ..HarnessOptions::default()
+    }
+    .press(30, key, 5)
+}
+
+#[test]
+#[ignore = "needs the snapshots of tests/roms/, see tests/roms/README.md"]
+/// The opcode test of corax89, an OK per instruction
+fn corax89_test_opcode() {
+    assert_snapshot("test_opcode", 60, HarnessOptions::default());
+}
+
+#[test]
+#[ignore = "needs the snapshots of tests/roms/, see tests/roms/README.md"]
+/// The splash screen of the Timendus suite
+fn timendus_chip8_logo() {
+    assert_snapshot("1-chip8-logo", 60, HarnessOptions::default());
+}
+
+#[test]
+#[ignore = "needs the snapshots of tests/roms/, see tests/roms/README.md"]
+/// The Timendus version of the corax89 opcode test, with more instructions
+fn timendus_corax_plus() {
+    assert_snapshot("3-corax+", 60, HarnessOptions::default());
+}
+
+#[test]
+#[ignore = "needs the snapshots of tests/roms/, see tests/roms/README.md"]
+/// The carry, borrow and shifted out flags, VF as the destination included
+fn timendus_flags() {
+    assert_snapshot("4-flags", 120, HarnessOptions::default());
+}
+
+#[test]
+#[ignore = "needs the snapshots of tests/roms/, see tests/roms/README.md"]
+/// The quirks of CHIP-8, picked with the key 1 in the menu
+fn timendus_quirks_chip8() {
+    assert_snapshot("5-quirks", 600, chip8_with_key(Key::K1));
+}
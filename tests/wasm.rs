@@ -0,0 +1,52 @@
+//! Tests of the browser bindings, run with `wasm-pack test --node --features wasm`.
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use r8::wasm::WasmEmulator;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// Draws 0 in the top-left corner, beeps and waits for a key.
+const ROM: [u8; 10] = [
+    0x60, 0x05, // LD V0, 5
+    0xF0, 0x18, // LD ST, V0
+    0xD1, 0x15, // DRW V1, V1, 5
+    0xF2, 0x0A, // LD V2, K
+    0x12, 0x08, // JP 0x208
+];
+
+#[wasm_bindgen_test]
+/// A few frames of a ROM, the framebuffer and the beep
+fn run_frames() {
+    let mut emulator = WasmEmulator::new();
+    assert!(emulator.load_rom(&ROM).is_ok());
+    for _ in 0..4 {
+        assert!(emulator.run_frame().is_ok());
+    }
+    let (width, height) = (emulator.width(), emulator.height());
+    let framebuffer = emulator.framebuffer();
+    assert_eq!(framebuffer.len(), width * height * 4);
+    // The top-left pixel of 0 is lit, the one next to the glyph is not
+    let lit = &framebuffer[..4];
+    let unlit = &framebuffer[4 * 4..5 * 4];
+    assert_ne!(lit, unlit);
+    assert!(emulator.beep_active());
+
+    // The key wait ends with a key
+    let state = emulator.save_state();
+    emulator.key_down(0xA);
+    for _ in 0..2 {
+        assert!(emulator.run_frame().is_ok());
+    }
+    emulator.key_up(0xA);
+    assert!(emulator.load_state(&state).is_ok());
+    assert_eq!(emulator.save_state(), state);
+}
+
+#[wasm_bindgen_test]
+/// Errors are thrown instead of panicking
+fn errors() {
+    let mut emulator = WasmEmulator::new();
+    assert!(emulator.load_rom(&[]).is_err());
+    assert!(emulator.load_state(b"R8SS").is_err());
+    assert!(emulator.load_rom(&[0x00, 0xEE]).is_ok());
+    assert!(emulator.run_frame().is_err());
+}